@@ -0,0 +1,382 @@
+//! Async client for the author-comparer HTTP API.
+
+pub mod types;
+
+use author_comparer::clustering::DendrogramFormat;
+use author_comparer::datasets::LabeledText;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use types::*;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The request could not be sent or the response body not read.
+    Http(reqwest::Error),
+    /// The server answered with an error status; `message` is its response body.
+    Status { status: u16, message: String },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Http(e) => write!(f, "request failed: {}", e),
+            Error::Status { status, message } => write!(f, "server returned {}: {}", status, message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Error {
+        Error::Http(e)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Serialize)]
+struct ClusterRequest<'a> {
+    texts: &'a [LabeledText],
+    format: DendrogramFormat,
+}
+
+#[derive(Deserialize)]
+struct ClusterTree {
+    tree: Dendrogram,
+}
+
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    base_url: String,
+}
+
+impl Client {
+    /// `base_url` is the server's root, e.g. `http://127.0.0.1:8000`.
+    pub fn new(base_url: impl Into<String>) -> Client {
+        Client::with_http_client(base_url, reqwest::Client::new())
+    }
+
+    /// Uses a preconfigured `reqwest` client, e.g. one with timeouts or extra headers.
+    pub fn with_http_client(base_url: impl Into<String>, http: reqwest::Client) -> Client {
+        let base_url = base_url.into().trim_end_matches('/').to_string();
+        Client { http, base_url }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        let response = request.send().await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let message = response.text().await.unwrap_or_default();
+        Err(Error::Status {
+            status: status.as_u16(),
+            message,
+        })
+    }
+
+    async fn post<B: Serialize + ?Sized, T: DeserializeOwned>(&self, path: &str, body: &B) -> Result<T> {
+        let response = self.send(self.http.post(self.url(path)).json(body)).await?;
+        Ok(response.json().await?)
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        let response = self.send(self.http.get(self.url(path))).await?;
+        Ok(response.json().await?)
+    }
+
+    pub async fn compare(&self, request: &CompareRequest) -> Result<Analysis> {
+        self.post("/compare", request).await
+    }
+
+    pub async fn compare_batch(&self, request: &BatchRequest) -> Result<BatchResponse> {
+        self.post("/compare/batch", request).await
+    }
+
+    pub async fn explain(&self, request: &ExplainRequest) -> Result<Explanation> {
+        self.post("/compare/explain", request).await
+    }
+
+    pub async fn preprocess(&self, request: &PreprocessRequest) -> Result<PreprocessOutput> {
+        self.post("/preprocess", request).await
+    }
+
+    pub async fn overlap(&self, request: &OverlapRequest) -> Result<Overlap> {
+        self.post("/overlap", request).await
+    }
+
+    pub async fn anonymize(&self, request: &AnonymizeRequest) -> Result<AnonymizationReport> {
+        self.post("/anonymize", request).await
+    }
+
+    pub async fn anomaly(&self, request: &AnomalyRequest) -> Result<AnomalyReport> {
+        self.post("/anomaly", request).await
+    }
+
+    /// Stores a profile built from the author's texts, replacing any earlier one.
+    pub async fn register_author(&self, request: &RegisterAuthorRequest) -> Result<RegisteredAuthor> {
+        self.post("/authors", request).await
+    }
+
+    pub async fn authors(&self) -> Result<Vec<RegisteredAuthor>> {
+        let registered: RegisteredAuthors = self.get("/authors").await?;
+        Ok(registered.authors)
+    }
+
+    /// Registered authors ranked by their confidence of having written the text.
+    pub async fn identify(&self, request: &IdentifyRequest) -> Result<Identification> {
+        self.post("/identify", request).await
+    }
+
+    /// The feature table in the requested format, as the raw file contents.
+    pub async fn export(&self, request: &ExportRequest) -> Result<Vec<u8>> {
+        let response = self.send(self.http.post(self.url("/export")).json(request)).await?;
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    pub async fn projection(&self, request: &ProjectionRequest) -> Result<Projection> {
+        self.post("/projection", request).await
+    }
+
+    pub async fn cluster(&self, texts: &[LabeledText]) -> Result<Dendrogram> {
+        let request = ClusterRequest {
+            texts,
+            format: DendrogramFormat::Json,
+        };
+        let tree: ClusterTree = self.post("/cluster", &request).await?;
+        Ok(tree.tree)
+    }
+
+    pub async fn cluster_newick(&self, texts: &[LabeledText]) -> Result<String> {
+        let request = ClusterRequest {
+            texts,
+            format: DendrogramFormat::Newick,
+        };
+        let response = self.send(self.http.post(self.url("/cluster")).json(&request)).await?;
+        Ok(response.text().await?)
+    }
+
+    /// Checks a stored analysis against the server's signing key.
+    pub async fn verify(&self, analysis: &Analysis) -> Result<Verification> {
+        self.post("/verify", analysis).await
+    }
+
+    pub async fn config_snapshot(&self, id: &str) -> Result<ConfigSnapshot> {
+        self.get(&format!("/config/snapshots/{}", id)).await
+    }
+
+    /// Stored `/compare` results, newest first, older than the id `before`
+    /// when given; the server caps `limit` at 500.
+    pub async fn comparisons(&self, limit: Option<usize>, before: Option<i64>) -> Result<Vec<ComparisonSummary>> {
+        let mut query = Vec::new();
+        if let Some(limit) = limit {
+            query.push(("limit", limit.to_string()));
+        }
+        if let Some(before) = before {
+            query.push(("before", before.to_string()));
+        }
+        let response = self.send(self.http.get(self.url("/comparisons")).query(&query)).await?;
+        let list: ComparisonList = response.json().await?;
+        Ok(list.comparisons)
+    }
+
+    pub async fn comparison(&self, id: i64) -> Result<StoredComparison> {
+        self.get(&format!("/comparisons/{}", id)).await
+    }
+
+    pub async fn explain_comparison(&self, id: i64) -> Result<Explanation> {
+        self.get(&format!("/comparisons/{}/explain", id)).await
+    }
+
+    /// The zipped report bundle of every comparison filed under `case`.
+    pub async fn case_report(&self, case: &str) -> Result<Vec<u8>> {
+        let response = self.send(self.http.get(self.url(&format!("/cases/{}/report", case)))).await?;
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    pub async fn capabilities(&self) -> Result<Capabilities> {
+        self.get("/capabilities").await
+    }
+
+    /// Aggregates of the comparisons served, when the server has telemetry
+    /// enabled; `token` is its admin bearer token, if one is configured.
+    pub async fn telemetry(&self, token: Option<&str>) -> Result<TelemetryReport> {
+        let mut request = self.http.get(self.url("/admin/telemetry"));
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        Ok(self.send(request).await?.json().await?)
+    }
+
+    /// Outcomes per arm of the server's decision-profile experiments. The
+    /// arm follows the `X-Api-Key` header, which a client built
+    /// `with_http_client` can send by default.
+    pub async fn experiments(&self) -> Result<ExperimentReport> {
+        self.get("/experiments").await
+    }
+
+    /// Uploads a WASM feature extractor for the tenant of `api_key`,
+    /// replacing its module of the same name.
+    pub async fn upload_plugin(&self, api_key: &str, name: &str, module: Vec<u8>) -> Result<PluginInfo> {
+        let request = self.http.post(self.url(&format!("/plugins/{}", name))).header("x-api-key", api_key).body(module);
+        Ok(self.send(request).await?.json().await?)
+    }
+
+    pub async fn plugins(&self, api_key: &str) -> Result<Vec<PluginInfo>> {
+        let response = self.send(self.http.get(self.url("/plugins")).header("x-api-key", api_key)).await?;
+        let list: PluginList = response.json().await?;
+        Ok(list.plugins)
+    }
+
+    /// The server's scheduled `[[jobs]]` and how their last runs went.
+    pub async fn jobs(&self) -> Result<Vec<JobStatus>> {
+        let list: JobList = self.get("/jobs").await?;
+        Ok(list.jobs)
+    }
+
+    /// JSON Schemas of every response, as served at `/schema`.
+    pub async fn schema(&self) -> Result<serde_json::Value> {
+        self.get("/schema").await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use author_comparer::config::Config;
+    use std::net::TcpListener;
+
+    fn spawn_server() -> Client {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = author_comparer::server::serve(Config::default(), listener).unwrap();
+        tokio::spawn(server);
+        Client::new(format!("http://{}", address))
+    }
+
+    fn texts() -> Vec<LabeledText> {
+        ["私は今日公園に行きました。", "私は昨日公園で遊びました。", "猫が好きだ！本当に可愛い。"]
+            .iter()
+            .enumerate()
+            .map(|(i, text)| LabeledText {
+                author: format!("author{}", i),
+                text: text.to_string(),
+                genre: None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_client_against_server() {
+        let client = spawn_server();
+        let (text1, text2) = ("私は今日公園に行きました。とても楽しかったです。", "猫が好きだ！本当に可愛いから、毎日見ている。");
+
+        let request = CompareRequest {
+            debug: true,
+            verbose: true,
+            vocabulary: Some(3),
+            robustness: true,
+            ..CompareRequest::new(text1, text2)
+        };
+        let analysis = client.compare(&request).await.unwrap();
+        assert!(!analysis.detailed_analysis.is_empty());
+        assert!(analysis.debug.is_some());
+        let snapshot = client.config_snapshot(analysis.config_snapshot.as_deref().unwrap()).await.unwrap();
+        assert_eq!(snapshot.model_version, author_comparer::MODEL_VERSION);
+
+        let batch = BatchRequest {
+            pairs: vec![BatchPair {
+                id: Some("a".to_string()),
+                text1: text1.to_string(),
+                text2: text2.to_string(),
+            }],
+            ..BatchRequest::default()
+        };
+        let batch = client.compare_batch(&batch).await.unwrap();
+        assert_eq!(batch.results[0].id.as_deref(), Some("a"));
+
+        let explanation = client
+            .explain(&ExplainRequest {
+                text1: text1.to_string(),
+                text2: text2.to_string(),
+                preprocessing: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(explanation.aspects[0].aspect, "Word Usage");
+
+        let preprocessed = client
+            .preprocess(&PreprocessRequest {
+                text: text1.to_string(),
+                preprocessing: None,
+            })
+            .await
+            .unwrap();
+        assert!(!preprocessed.tokens.is_empty());
+
+        let overlap = client
+            .overlap(&OverlapRequest {
+                text1: "本日の会議にて決定致しました。".to_string(),
+                text2: "本日の会議にて決定致しました".to_string(),
+                min_length: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(overlap.spans.len(), 1);
+
+        let tokenizer = author_comparer::tokenizer::shared_tokenizer().unwrap();
+        let profile = author_comparer::build_profile(&["とても楽しかった。とても笑った。"], tokenizer).unwrap();
+        let report = client
+            .anonymize(&AnonymizeRequest {
+                text: "とても眠い。".to_string(),
+                profile: Some(profile),
+                author: None,
+            })
+            .await
+            .unwrap();
+        assert!(report.identifying_features.iter().any(|f| f.feature == "とても"));
+
+        let export = client
+            .export(&ExportRequest {
+                texts: texts(),
+                format: author_comparer::export::ExportFormat::Arff,
+                vocabulary: Some(5),
+            })
+            .await
+            .unwrap();
+        assert!(String::from_utf8(export).unwrap().contains("@RELATION"));
+
+        let projection = client
+            .projection(&ProjectionRequest {
+                texts: texts(),
+                vocabulary: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(projection.points.len(), 3);
+
+        assert!(matches!(client.cluster(&texts()).await.unwrap(), Dendrogram::Merge { size: 3, .. }));
+        assert!(client.cluster_newick(&texts()).await.unwrap().ends_with(';'));
+
+        let capabilities = client.capabilities().await.unwrap();
+        assert!(capabilities.aspects.iter().any(|a| a.name == "Word Usage"));
+        assert!(client.schema().await.unwrap()["responses"]["POST /compare"].is_object());
+        assert!(client.jobs().await.unwrap().is_empty());
+        // Telemetry is off by default
+        let error = client.telemetry(None).await.unwrap_err();
+        assert!(matches!(error, Error::Status { status: 404, .. }));
+        let error = client.experiments().await.unwrap_err();
+        assert!(matches!(error, Error::Status { status: 404, .. }));
+
+        // Signing isn't configured, so the server refuses to verify
+        let error = client.verify(&analysis).await.unwrap_err();
+        assert!(matches!(error, Error::Status { status: 400, .. }));
+    }
+}
@@ -0,0 +1,413 @@
+//! Request and response bodies of the HTTP API. Responses the library
+//! already models (analyses, explanations, overlaps, ...) are re-exported
+//! from it; the rest mirror the server's own structs field for field.
+
+use author_comparer::aspects::{Dimension, Evidence, Transform};
+use author_comparer::datasets::LabeledText;
+use author_comparer::export::ExportFormat;
+use author_comparer::features::TextFeatures;
+use author_comparer::profile::AuthorProfile;
+use author_comparer::scoring::Backend;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::num::NonZeroUsize;
+
+pub use author_comparer::anomaly::AnomalyReport;
+pub use author_comparer::anonymize::AnonymizationReport;
+pub use author_comparer::attribution::{AttributionMode, Decisiveness, RankMargin};
+pub use author_comparer::clustering::Dendrogram;
+pub use author_comparer::comparison::{Analysis, Verdict};
+pub use author_comparer::config::{Config, DecisionProfile, WordUsageConfig};
+pub use author_comparer::explain::Explanation;
+pub use author_comparer::history::{ComparisonSummary, StoredComparison};
+pub use author_comparer::overlap::Overlap;
+pub use author_comparer::plugins::PluginInfo;
+pub use author_comparer::projection::Projection;
+pub use author_comparer::token_cache::CacheStats;
+pub use author_comparer::tokenizer::TokenInfo;
+
+/// Body of `POST /compare`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CompareRequest {
+    /// May be left empty when `features` is given.
+    #[serde(default)]
+    pub text1: String,
+    #[serde(default)]
+    pub text2: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub features: Option<ImportedFeatures>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preprocessing: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dictionary: Option<String>,
+    #[serde(default)]
+    pub debug: bool,
+    #[serde(default)]
+    pub verbose: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vocabulary: Option<usize>,
+    #[serde(default)]
+    pub robustness: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mfw: Option<NonZeroUsize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scoring: Option<Backend>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub genres: Option<[String; 2]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weights: Option<BTreeMap<String, f64>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub same_author_above: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub case: Option<String>,
+}
+
+impl CompareRequest {
+    pub fn new(text1: impl Into<String>, text2: impl Into<String>) -> CompareRequest {
+        CompareRequest {
+            text1: text1.into(),
+            text2: text2.into(),
+            ..CompareRequest::default()
+        }
+    }
+}
+
+/// Feature vectors extracted elsewhere, compared instead of texts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportedFeatures {
+    pub feature_schema_version: u32,
+    pub text1: TextFeatures,
+    pub text2: TextFeatures,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchPair {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub text1: String,
+    pub text2: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchText {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub text: String,
+}
+
+/// Body of `POST /compare/batch`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BatchRequest {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pairs: Vec<BatchPair>,
+    /// Compares every two of these instead of `pairs`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub texts: Vec<BatchText>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_duration_ms: Option<u64>,
+    /// Token from a previous partial response for the same pairs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub continuation: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preprocessing: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dictionary: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scoring: Option<Backend>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BatchResult {
+    pub index: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub texts: Option<[usize; 2]>,
+    pub analysis: Analysis,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BatchResponse {
+    pub results: Vec<BatchResult>,
+    /// Present when pairs remain; resend the same request with it to resume.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub continuation: Option<String>,
+    pub remaining: usize,
+    /// Confidence of every two `texts`; null where not compared yet.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matrix: Option<Vec<Vec<Option<f64>>>>,
+}
+
+/// Body of `POST /compare/explain`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExplainRequest {
+    pub text1: String,
+    pub text2: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preprocessing: Option<Vec<String>>,
+}
+
+/// Body of `POST /preprocess`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PreprocessRequest {
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preprocessing: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreprocessOutput {
+    pub steps: Vec<String>,
+    pub text: String,
+    pub tokens: Vec<TokenInfo>,
+}
+
+/// Body of `POST /overlap`; the server's default `min_length` applies when unset.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OverlapRequest {
+    pub text1: String,
+    pub text2: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_length: Option<usize>,
+}
+
+/// Body of `POST /anonymize`; give either `profile` or the `author` of a stored one.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AnonymizeRequest {
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<AuthorProfile>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+}
+
+/// Body of `POST /anomaly`; without `profile` or `author` the text is
+/// scored against the server's reference corpus.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AnomalyRequest {
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<AuthorProfile>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preprocessing: Option<Vec<String>>,
+}
+
+/// Body of `POST /authors`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RegisterAuthorRequest {
+    pub author: String,
+    pub texts: Vec<String>,
+    #[serde(default)]
+    pub keep_samples: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preprocessing: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegisteredAuthor {
+    pub author: String,
+    pub sample_count: usize,
+    pub token_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegisteredAuthors {
+    pub authors: Vec<RegisteredAuthor>,
+}
+
+/// Body of `POST /identify`; without `authors`, every registered author is a
+/// candidate.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IdentifyRequest {
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub authors: Option<Vec<String>>,
+    #[serde(default)]
+    pub mode: AttributionMode,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reject_below: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preprocessing: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Candidate {
+    pub author: String,
+    pub confidence: f64,
+    pub verdict: Verdict,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Identification {
+    pub scoring: Backend,
+    pub candidates: Vec<Candidate>,
+    #[serde(default)]
+    pub mode: AttributionMode,
+    #[serde(default)]
+    pub reject_below: Option<f64>,
+    /// None in an open set when no candidate matches.
+    pub best_match: Option<String>,
+    #[serde(default)]
+    pub rank_margin: Option<RankMargin>,
+}
+
+/// Body of `POST /export`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportRequest {
+    pub texts: Vec<LabeledText>,
+    pub format: ExportFormat,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vocabulary: Option<usize>,
+}
+
+/// Body of `POST /projection`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProjectionRequest {
+    pub texts: Vec<LabeledText>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vocabulary: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Verification {
+    pub valid: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComparisonList {
+    pub comparisons: Vec<ComparisonSummary>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginList {
+    pub plugins: Vec<PluginInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobRun {
+    pub started_at: u64,
+    pub duration_ms: u64,
+    pub succeeded: bool,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobStatus {
+    pub name: String,
+    pub task: String,
+    pub every_minutes: u64,
+    pub running: bool,
+    pub runs: u64,
+    pub next_run_at: u64,
+    pub last_run: Option<JobRun>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobList {
+    pub jobs: Vec<JobStatus>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigSnapshot {
+    pub id: String,
+    pub model_version: String,
+    pub config: ScoringSettings,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScoringSettings {
+    pub preprocessing: Vec<String>,
+    pub backend: Backend,
+    pub transforms: BTreeMap<String, Transform>,
+    pub weights: BTreeMap<String, f64>,
+    pub decision: DecisionProfile,
+    pub word_usage: WordUsageConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Histogram {
+    pub upper_bounds: Vec<f64>,
+    pub counts: Vec<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerdictCounts {
+    pub same_author: u64,
+    pub different_author: u64,
+    pub insufficient_data: u64,
+    #[serde(default)]
+    pub identical_text: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TelemetryWindow {
+    pub start: u64,
+    pub comparisons: u64,
+    pub verdicts: VerdictCounts,
+    pub mean_confidence: Option<f64>,
+    pub confidence: Histogram,
+    pub text_length: Histogram,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TelemetryReport {
+    pub window_seconds: u64,
+    pub windows: Vec<TelemetryWindow>,
+    pub total: TelemetryWindow,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArmStats {
+    pub fraction: f64,
+    pub decision: DecisionProfile,
+    pub requests: u64,
+    pub by_api_key: u64,
+    pub verdicts: VerdictCounts,
+    pub mean_confidence: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExperimentReport {
+    pub arms: BTreeMap<String, ArmStats>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AspectInfo {
+    pub name: String,
+    pub description: String,
+    pub dimension: Dimension,
+    pub default_weight: f64,
+    pub default_transform: Transform,
+    pub evidence: Evidence,
+    pub half_reliability_at: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LanguageSupport {
+    pub code: String,
+    pub name: String,
+    pub dictionary: Option<String>,
+    pub pos_tagging: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Limits {
+    pub max_payload_bytes: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Capabilities {
+    pub aspects: Vec<AspectInfo>,
+    pub languages: Vec<LanguageSupport>,
+    pub model_version: String,
+    pub feature_schema_version: u32,
+    pub oldest_feature_schema_version: u32,
+    pub scoring_backends: Vec<Backend>,
+    pub limits: Limits,
+    pub dictionaries: Vec<String>,
+    pub token_cache: Option<CacheStats>,
+}
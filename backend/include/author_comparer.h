@@ -0,0 +1,30 @@
+#ifndef AUTHOR_COMPARER_H
+#define AUTHOR_COMPARER_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/*
+ * Compares two UTF-8 buffers (not necessarily NUL-terminated) and returns the
+ * analysis as a NUL-terminated JSON string, in the same shape as `POST /compare`.
+ * Failures are reported as {"error": "..."}.
+ *
+ * The returned string is owned by the library and must be released with
+ * author_comparer_free_string. The first call loads the dictionary and is slow;
+ * later calls reuse it and are safe from multiple threads.
+ */
+char *author_comparer_compare(const uint8_t *text1, size_t text1_len,
+                              const uint8_t *text2, size_t text2_len);
+
+/* Releases a string returned by author_comparer_compare. NULL is a no-op. */
+void author_comparer_free_string(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* AUTHOR_COMPARER_H */
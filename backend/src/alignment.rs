@@ -0,0 +1,89 @@
+use crate::comparison::{calculate_confidence, compare_features};
+use crate::features::extract_features;
+use crate::language;
+use crate::tokenizer::{LinderaResult, Tokenizer};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+// Pairs grow quadratically, so only the leading sentences of long texts are aligned
+const MAX_SENTENCES: usize = 50;
+const TOP_PAIRS: usize = 3;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SentencePair {
+    pub text1_index: usize,
+    pub text1: String,
+    pub text2_index: usize,
+    pub text2: String,
+    // Same 0–1 scale as the overall confidence
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SentenceAlignment {
+    pub most_similar: Vec<SentencePair>,
+    pub most_different: Vec<SentencePair>,
+}
+
+fn sentences(text: &str) -> Vec<&str> {
+    language::segment(text)
+        .into_iter()
+        .map(|s| s.text.trim())
+        .filter(|s| !s.is_empty())
+        .take(MAX_SENTENCES)
+        .collect()
+}
+
+/// Scores every sentence of `text1` against every sentence of `text2` with the
+/// same aspects as the whole-text comparison.
+pub fn align_sentences(text1: &str, text2: &str, tokenizer: &Tokenizer) -> LinderaResult<SentenceAlignment> {
+    let sentences1 = sentences(text1);
+    let sentences2 = sentences(text2);
+    let features1 = sentences1.iter().map(|s| extract_features(s, tokenizer)).collect::<LinderaResult<Vec<_>>>()?;
+    let features2 = sentences2.iter().map(|s| extract_features(s, tokenizer)).collect::<LinderaResult<Vec<_>>>()?;
+
+    let mut pairs = Vec::with_capacity(sentences1.len() * sentences2.len());
+    for (i, f1) in features1.iter().enumerate() {
+        for (j, f2) in features2.iter().enumerate() {
+            pairs.push(SentencePair {
+                text1_index: i,
+                text1: sentences1[i].to_string(),
+                text2_index: j,
+                text2: sentences2[j].to_string(),
+                score: calculate_confidence(&compare_features(f1, f2)),
+            });
+        }
+    }
+
+    pairs.sort_by(|a, b| b.score.total_cmp(&a.score));
+    let most_similar = pairs.iter().take(TOP_PAIRS).cloned().collect();
+    let most_different = pairs.iter().rev().take(TOP_PAIRS).cloned().collect();
+
+    Ok(SentenceAlignment {
+        most_similar,
+        most_different,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::new_tokenizer;
+
+    #[test]
+    fn test_align_sentences() {
+        let tokenizer = new_tokenizer().unwrap();
+        let alignment = align_sentences(
+            "本日の会議にて、以下の事項が決定致しました。やっほー！",
+            "明日の会議にて、以下の事項を決定致します。ご確認ください。",
+            &tokenizer,
+        )
+        .unwrap();
+
+        let best = &alignment.most_similar[0];
+        assert_eq!((best.text1_index, best.text2_index), (0, 0));
+        assert!(alignment.most_different[0].score <= best.score);
+        assert!(alignment.most_similar.windows(2).all(|w| w[0].score >= w[1].score));
+        assert!(alignment.most_different.windows(2).all(|w| w[0].score <= w[1].score));
+    }
+}
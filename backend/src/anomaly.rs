@@ -0,0 +1,122 @@
+use crate::features::TextFeatures;
+use crate::numerics::safe_div;
+use crate::scoring::{feature_vector, ReferenceModel, MAHALANOBIS_FEATURES};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+// Features further than this many standard deviations from expected are named as unusual
+const UNUSUAL_Z: f64 = 2.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct FeatureDeviation {
+    pub feature: String,
+    pub value: f64,
+    /// The reference samples' mean, or the author profile's value.
+    pub expected: f64,
+    pub std_dev: f64,
+    pub z_score: f64,
+}
+
+/// How unusual one text is next to a reference, without a second text to
+/// compare it with.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AnomalyReport {
+    /// Samples the spread of each feature was estimated from.
+    pub reference_samples: usize,
+    /// Largest absolute z-score first.
+    pub features: Vec<FeatureDeviation>,
+    /// Mahalanobis distance from the expected values, which accounts for
+    /// features that move together.
+    pub distance: f64,
+    /// From 0 toward 1; a text as far out as a typical reference sample
+    /// scores 0.5.
+    pub anomaly_score: f64,
+    /// Features more than two standard deviations from expected.
+    pub unusual: Vec<String>,
+}
+
+/// Scores `features` against the spread of `model`'s samples, centered on
+/// `expected` (e.g. an author profile's features) or on the samples' mean.
+pub fn anomaly(features: &TextFeatures, model: &ReferenceModel, expected: Option<&TextFeatures>) -> AnomalyReport {
+    let value = feature_vector(features);
+    let expected = expected.map_or_else(|| model.mean().clone(), feature_vector);
+    let mut deviations: Vec<FeatureDeviation> = MAHALANOBIS_FEATURES
+        .iter()
+        .enumerate()
+        .map(|(i, feature)| FeatureDeviation {
+            feature: feature.to_string(),
+            value: value[i],
+            expected: expected[i],
+            std_dev: model.std_devs()[i],
+            z_score: safe_div(value[i] - expected[i], model.std_devs()[i], 0.0),
+        })
+        .collect();
+    deviations.sort_by(|a, b| b.z_score.abs().total_cmp(&a.z_score.abs()));
+
+    // A sample is k from its mean in squared distance on average (k features)
+    let squared = model.squared_distance_between(&value, &expected);
+    let expected_squared = MAHALANOBIS_FEATURES.len() as f64;
+    AnomalyReport {
+        reference_samples: model.sample_count(),
+        unusual: deviations
+            .iter()
+            .filter(|d| d.z_score.abs() > UNUSUAL_Z)
+            .map(|d| d.feature.clone())
+            .collect(),
+        features: deviations,
+        distance: squared.sqrt(),
+        anomaly_score: squared / (squared + expected_squared),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::features::PosCounts;
+    use std::collections::HashMap;
+
+    fn features(particle_ratio: f64, avg_sentence_length: f64) -> TextFeatures {
+        TextFeatures {
+            word_frequencies: HashMap::new(),
+            particle_ratio,
+            verb_ratio: 0.15,
+            adjective_ratio: 0.1,
+            unique_words_ratio: 0.8,
+            avg_sentence_length,
+            punctuation_ratio: 0.1,
+            english_segment_ratio: 0.0,
+            symbol_ratio: 0.0,
+            unusual_whitespace_ratio: 0.0,
+            token_count: 50,
+            sentence_count: 5,
+            pos_counts: PosCounts::default(),
+            pos_distribution: Default::default(),
+            char_ngrams: Default::default(),
+            pos_ngrams: Default::default(),
+            particle_distribution: Default::default(),
+            register: None,
+            script_ratios: None,
+        }
+    }
+
+    #[test]
+    fn test_anomaly() {
+        let corpus: Vec<TextFeatures> = (0..20)
+            .map(|i| features(0.25 + (i % 5) as f64 * 0.01, 15.0 + (i % 4) as f64))
+            .collect();
+        let model = ReferenceModel::fit(&corpus).unwrap();
+
+        let typical = anomaly(&features(0.27, 16.5), &model, None);
+        assert!(typical.anomaly_score < 0.5, "{}", typical.anomaly_score);
+        assert!(typical.unusual.is_empty());
+
+        let ghostwritten = anomaly(&features(0.45, 16.5), &model, None);
+        assert!(ghostwritten.anomaly_score > 0.9);
+        assert_eq!(ghostwritten.features[0].feature, "particle_ratio");
+        assert_eq!(ghostwritten.unusual, ["particle_ratio"]);
+
+        // Centered on an author's own values instead of the corpus mean
+        let author = features(0.45, 16.5);
+        assert!(anomaly(&features(0.45, 16.5), &model, Some(&author)).distance < 1e-9);
+    }
+}
@@ -0,0 +1,200 @@
+use crate::features::{PosPrior, ADJECTIVE_PRIOR, PARTICLE_PRIOR, VERB_PRIOR};
+use crate::pos::Pos;
+use crate::profile::AuthorProfile;
+use crate::tokenizer::{tokenize_with_pos, LinderaResult, TokenInfo, Tokenizer};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Rough rates in contemporary written Japanese, used as the "neutral" reference
+const TYPICAL_WORD_RATE: f64 = 0.005;
+const TYPICAL_ENDING_RATE: f64 = 0.01;
+const TYPICAL_COMMA_RATE: f64 = 0.06;
+// How far from typical (as a ratio either way) a habit must be to be reported
+const MIN_DISTINCTIVENESS: f64 = 1.5;
+
+// Colloquial or emphatic words with a plainer equivalent
+const NEUTRAL_ALTERNATIVES: &[(&str, &str)] = &[
+    ("とても", "かなり"),
+    ("本当に", "実に"),
+    ("めっちゃ", "とても"),
+    ("すごく", "とても"),
+    ("やっぱり", "やはり"),
+    ("ちょっと", "少し"),
+    ("結構", "かなり"),
+    ("なんか", ""),
+    ("まあ", ""),
+    ("マジ", "本当に"),
+];
+
+const COMMAS: &[&str] = &["、", ","];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FeatureKind {
+    PetWord,
+    EndingParticle,
+    CommaHabit,
+    PosRatio,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct IdentifyingFeature {
+    pub kind: FeatureKind,
+    pub feature: String,
+    // The author's rate per token, from the profile
+    pub author_rate: f64,
+    pub typical_rate: f64,
+    // How many times the text at hand uses it
+    pub occurrences: usize,
+    pub suggestion: String,
+    // Ratio to the typical rate, folded so that 2.0 means twice or half as often
+    pub distinctiveness: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AnonymizationReport {
+    pub identifying_features: Vec<IdentifyingFeature>,
+}
+
+fn distinctiveness(author_rate: f64, typical_rate: f64) -> f64 {
+    if author_rate <= 0.0 || typical_rate <= 0.0 {
+        return 0.0;
+    }
+    let ratio = author_rate / typical_rate;
+    ratio.max(1.0 / ratio)
+}
+
+fn is_ending_particle(token: &TokenInfo) -> bool {
+    Pos::of(&token.pos) == Pos::Particle && token.pos.get(1).map(String::as_str) == Some("終助詞")
+}
+
+fn is_content_word(token: &TokenInfo) -> bool {
+    matches!(
+        Pos::of(&token.pos),
+        Pos::Noun | Pos::Adverb | Pos::Adjective | Pos::Interjection | Pos::Prenominal
+    )
+}
+
+/// Lists the habits in `text` that most strongly match the author's profile,
+/// most distinctive first, each with a suggestion for masking it.
+pub fn suggest(text: &str, profile: &AuthorProfile, tokenizer: &Tokenizer) -> LinderaResult<AnonymizationReport> {
+    let tokens = tokenize_with_pos(text, tokenizer)?;
+    let author_rate = |word: &str| *profile.features.word_frequencies.get(word).unwrap_or(&0.0);
+
+    let mut occurrences: HashMap<&str, (usize, &TokenInfo)> = HashMap::new();
+    for token in &tokens {
+        occurrences.entry(token.text.as_str()).or_insert((0, token)).0 += 1;
+    }
+
+    let mut features = Vec::new();
+    for (word, (count, token)) in &occurrences {
+        let (kind, typical_rate) = if is_ending_particle(token) {
+            (FeatureKind::EndingParticle, TYPICAL_ENDING_RATE)
+        } else if is_content_word(token) {
+            (FeatureKind::PetWord, TYPICAL_WORD_RATE)
+        } else {
+            continue;
+        };
+        let rate = author_rate(word);
+        // Only habits, not rarities: the author must use it more than usual
+        if rate < typical_rate * MIN_DISTINCTIVENESS {
+            continue;
+        }
+
+        let suggestion = match kind {
+            FeatureKind::EndingParticle => format!("end sentences without 「{}」, e.g. with plain です/ます", word),
+            _ => match NEUTRAL_ALTERNATIVES.iter().find(|(w, _)| w == word) {
+                Some((_, "")) => format!("drop 「{}」", word),
+                Some((_, alternative)) => format!("replace 「{}」 with 「{}」", word, alternative),
+                None => format!("use a synonym for 「{}」 or rephrase", word),
+            },
+        };
+        features.push(IdentifyingFeature {
+            kind,
+            feature: word.to_string(),
+            author_rate: rate,
+            typical_rate,
+            occurrences: *count,
+            suggestion,
+            distinctiveness: distinctiveness(rate, typical_rate),
+        });
+    }
+
+    let comma_rate: f64 = COMMAS.iter().map(|c| author_rate(c)).sum();
+    let comma_distinctiveness = distinctiveness(comma_rate, TYPICAL_COMMA_RATE);
+    if comma_distinctiveness >= MIN_DISTINCTIVENESS {
+        let suggestion = if comma_rate > TYPICAL_COMMA_RATE {
+            "use fewer commas; merge short clauses"
+        } else {
+            "add commas between clauses of long sentences"
+        };
+        features.push(IdentifyingFeature {
+            kind: FeatureKind::CommaHabit,
+            feature: "、".to_string(),
+            author_rate: comma_rate,
+            typical_rate: TYPICAL_COMMA_RATE,
+            occurrences: tokens.iter().filter(|t| COMMAS.contains(&t.text.as_str())).count(),
+            suggestion: suggestion.to_string(),
+            distinctiveness: comma_distinctiveness,
+        });
+    }
+
+    let pos_ratios: [(&str, Pos, &str, f64, PosPrior); 3] = [
+        ("助詞", Pos::Particle, "particles", profile.features.particle_ratio, PARTICLE_PRIOR),
+        ("動詞", Pos::Verb, "verbs", profile.features.verb_ratio, VERB_PRIOR),
+        ("形容詞", Pos::Adjective, "adjectives", profile.features.adjective_ratio, ADJECTIVE_PRIOR),
+    ];
+    for (tag, pos, label, rate, prior) in pos_ratios {
+        let score = distinctiveness(rate, prior.mean);
+        if score < MIN_DISTINCTIVENESS {
+            continue;
+        }
+        let direction = if rate > prior.mean { "fewer" } else { "more" };
+        features.push(IdentifyingFeature {
+            kind: FeatureKind::PosRatio,
+            feature: tag.to_string(),
+            author_rate: rate,
+            typical_rate: prior.mean,
+            occurrences: tokens.iter().filter(|t| Pos::of(&t.pos) == pos).count(),
+            suggestion: format!("use {} {} than usual", direction, label),
+            distinctiveness: score,
+        });
+    }
+
+    features.sort_by(|a, b| b.distinctiveness.total_cmp(&a.distinctiveness).then_with(|| a.feature.cmp(&b.feature)));
+    Ok(AnonymizationReport {
+        identifying_features: features,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::build_profile;
+    use crate::tokenizer::new_tokenizer;
+
+    #[test]
+    fn test_suggest() {
+        let tokenizer = new_tokenizer().unwrap();
+        let profile = build_profile(
+            &[
+                "今日はとても楽しかったよね。とても笑ったよね。",
+                "昨日もとても遊んだよね。また行こうね。",
+            ],
+            &tokenizer,
+        )
+        .unwrap();
+
+        let report = suggest("明日もとても晴れるよね。", &profile, &tokenizer).unwrap();
+        let features = &report.identifying_features;
+
+        let pet = features.iter().find(|f| f.feature == "とても").unwrap();
+        assert_eq!(pet.kind, FeatureKind::PetWord);
+        assert_eq!(pet.suggestion, "replace 「とても」 with 「かなり」");
+        assert!(features.iter().any(|f| f.kind == FeatureKind::EndingParticle && f.feature == "ね"));
+        assert!(features.windows(2).all(|w| w[0].distinctiveness >= w[1].distinctiveness));
+        // Words the text doesn't use can't be suggested away
+        assert!(features.iter().all(|f| f.feature != "また"));
+    }
+}
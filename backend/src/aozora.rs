@@ -0,0 +1,205 @@
+use std::collections::HashSet;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// One work from Aozora Bunko, with ruby and editorial markup removed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AozoraWork {
+    pub title: String,
+    /// The translator for translated works, since the Japanese text is theirs.
+    pub author: String,
+    pub text: String,
+}
+
+// Drops ruby (《よみ》 and the ｜ marking where it starts), ［＃...］ notes
+// and the ※ standing in for a character described by the note after it
+fn strip_markup(line: &str) -> String {
+    let mut plain = String::new();
+    let mut chars = line.chars().peekable();
+    let mut ruby = false;
+    let mut note = 0;
+    while let Some(c) = chars.next() {
+        match c {
+            '《' if note == 0 => ruby = true,
+            '》' if ruby => ruby = false,
+            '［' if chars.peek() == Some(&'＃') || note > 0 => note += 1,
+            '］' if note > 0 => note -= 1,
+            '※' if chars.peek() == Some(&'［') => {}
+            '｜' => {}
+            _ if ruby || note > 0 => {}
+            _ => plain.push(c),
+        }
+    }
+    plain
+}
+
+/// Parses a decoded Aozora Bunko text file: the title and author header,
+/// the optional block explaining the markup, the body and the colophon
+/// starting at `底本：`, which is dropped. None without a title and author.
+pub fn parse_aozora(source: &str) -> Option<AozoraWork> {
+    let source = source.trim_start_matches('\u{FEFF}').replace("\r\n", "\n");
+    let mut lines = source.lines().peekable();
+    let header: Vec<&str> = lines.by_ref().map(str::trim).take_while(|line| !line.is_empty()).collect();
+    let (title, author) = match header.as_slice() {
+        [title, .., author] => (*title, *author),
+        _ => return None,
+    };
+    let author = author.strip_suffix("訳").unwrap_or(author).trim();
+
+    while lines.peek().is_some_and(|line| line.trim().is_empty()) {
+        lines.next();
+    }
+    if lines.peek().is_some_and(|line| line.starts_with("---")) {
+        lines.next();
+        lines.by_ref().find(|line| line.starts_with("---"));
+    }
+
+    let mut body = Vec::new();
+    for line in lines.take_while(|line| !line.starts_with("底本：")) {
+        let plain = strip_markup(line);
+        // Lines holding nothing but a note, like ［＃改ページ］, go entirely
+        if plain.trim().is_empty() && !line.trim().is_empty() {
+            continue;
+        }
+        body.push(plain);
+    }
+    let text = body.join("\n").trim_matches('\n').to_string();
+    Some(AozoraWork {
+        title: title.to_string(),
+        author: author.to_string(),
+        text,
+    })
+}
+
+/// Aozora Bunko files are Shift_JIS; UTF-8 ones, as in some mirrors, are kept as they are.
+#[cfg(feature = "aozora")]
+pub fn decode(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => encoding_rs::SHIFT_JIS.decode(bytes).0.into_owned(),
+    }
+}
+
+#[cfg(feature = "aozora")]
+fn parse_bytes(bytes: &[u8], name: &str) -> io::Result<AozoraWork> {
+    parse_aozora(&decode(bytes))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("{} is not an Aozora Bunko text", name)))
+}
+
+/// Reads the works in a `.zip` as distributed by Aozora Bunko, or in a
+/// plain `.txt`, from a file or an `http(s)://` URL.
+#[cfg(feature = "aozora")]
+pub fn read_source(source: &str) -> io::Result<Vec<AozoraWork>> {
+    use std::io::Read;
+
+    let bytes = if source.starts_with("http://") || source.starts_with("https://") {
+        let response = ureq::get(source).call().map_err(io::Error::other)?;
+        let mut bytes = Vec::new();
+        response.into_reader().read_to_end(&mut bytes)?;
+        bytes
+    } else {
+        std::fs::read(source)?
+    };
+    if !source.to_ascii_lowercase().ends_with(".zip") {
+        return Ok(vec![parse_bytes(&bytes, source)?]);
+    }
+
+    let mut archive = zip::ZipArchive::new(io::Cursor::new(bytes)).map_err(io::Error::other)?;
+    let mut works = Vec::new();
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i).map_err(io::Error::other)?;
+        if !file.name().to_ascii_lowercase().ends_with(".txt") {
+            continue;
+        }
+        let name = format!("{}/{}", source, file.name());
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        works.push(parse_bytes(&bytes, &name)?);
+    }
+    Ok(works)
+}
+
+/// `.zip` and `.txt` files anywhere below `dir`, e.g. a mirror's `cards/` folder, sorted.
+pub fn source_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if path.extension().is_some_and(|ext| ext == "zip" || ext == "txt") {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+// Keeps titles usable as file names on every platform
+fn file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') { '_' } else { c })
+        .collect()
+}
+
+/// Writes works as a corpus of one folder per author holding `<title>.txt`
+/// files, the layout `evaluate` and `scoring.reference_corpus` read.
+/// Titles repeated within an author get a numbered suffix.
+pub fn write_corpus(works: &[AozoraWork], output: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut written = Vec::new();
+    let mut taken = HashSet::new();
+    for work in works {
+        let dir = output.join(file_name(&work.author));
+        std::fs::create_dir_all(&dir)?;
+        let title = file_name(&work.title);
+        let mut path = dir.join(format!("{}.txt", title));
+        for n in 2.. {
+            if taken.insert(path.clone()) {
+                break;
+            }
+            path = dir.join(format!("{} ({}).txt", title, n));
+        }
+        std::fs::write(&path, &work.text)?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_aozora() {
+        let source = "\u{FEFF}羅生門\r\n芥川龍之介\r\n\r\n-------------------------------------------------------\r\n【テキスト中に現れる記号について】\r\n\r\n《》：ルビ\r\n（例）下人《げにん》\r\n-------------------------------------------------------\r\n\r\n［＃８字下げ］一［＃「一」は中見出し］\r\n\r\n　ある日の暮方の事である。一人の｜下人《げにん》が、羅生門の下で雨やみを待っていた。\r\n［＃改ページ］\r\n　※［＃「木＋吶のつくり」、第3水準1-85-54］の木が見えた。\r\n\r\n\r\n底本：「芥川龍之介全集1」ちくま文庫、筑摩書房\r\n入力：j.utiyama\r\n";
+        let work = parse_aozora(source).unwrap();
+        assert_eq!(work.title, "羅生門");
+        assert_eq!(work.author, "芥川龍之介");
+        assert_eq!(work.text, "一\n\n　ある日の暮方の事である。一人の下人が、羅生門の下で雨やみを待っていた。\n　の木が見えた。");
+
+        let translated = parse_aozora("ファウスト\n第一部\nゲーテ\n森鴎外訳\n\n本文").unwrap();
+        assert_eq!((translated.title.as_str(), translated.author.as_str()), ("ファウスト", "森鴎外"));
+        assert!(parse_aozora("本文だけの行\n\n続き").is_none());
+    }
+
+    #[test]
+    fn test_write_corpus() {
+        let dir = std::env::temp_dir().join(format!("author-comparer-aozora-{}", std::process::id()));
+        let work = |title: &str, text: &str| AozoraWork {
+            title: title.to_string(),
+            author: "夏目漱石".to_string(),
+            text: text.to_string(),
+        };
+        let works = [work("こころ", "上"), work("こころ", "下"), work("a/b", "c")];
+        let written = write_corpus(&works, &dir).unwrap();
+        let names: Vec<String> = written.iter().map(|p| p.file_name().unwrap().to_string_lossy().into_owned()).collect();
+        assert_eq!(names, ["こころ.txt", "こころ (2).txt", "a_b.txt"]);
+
+        let corpus = crate::datasets::load_author_dirs(&dir).unwrap();
+        assert_eq!(corpus.len(), 3);
+        assert!(corpus.iter().all(|text| text.author == "夏目漱石"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,312 @@
+use crate::features::TextFeatures;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// What an aspect's estimate is built from, which decides how its reliability
+/// grows with text length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Evidence {
+    Tokens,
+    Sentences,
+}
+
+/// Style dimension an aspect belongs to, so results can be read a level above
+/// the individual aspects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Dimension {
+    Lexical,
+    Syntactic,
+    Orthographic,
+    // Line breaks, spacing and layout
+    Formatting,
+    Register,
+}
+
+impl Dimension {
+    pub const ALL: [Dimension; 5] = [
+        Dimension::Lexical,
+        Dimension::Syntactic,
+        Dimension::Orthographic,
+        Dimension::Formatting,
+        Dimension::Register,
+    ];
+
+    /// As serialized, e.g. `formatting`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Dimension::Lexical => "lexical",
+            Dimension::Syntactic => "syntactic",
+            Dimension::Orthographic => "orthographic",
+            Dimension::Formatting => "formatting",
+            Dimension::Register => "register",
+        }
+    }
+}
+
+/// Default cap on ratio-based aspects, so one outlier can't dominate the score.
+pub const DIFFERENCE_CAP: f64 = 0.5;
+
+/// How an aspect's raw difference is turned into the reported one.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Transform {
+    Identity,
+    /// Clips the difference at `cap`.
+    Capped { cap: f64 },
+    /// S-curve through `midpoint`, rescaled so no difference stays 0 and
+    /// the result approaches 1.
+    Logistic { midpoint: f64, steepness: f64 },
+}
+
+impl Transform {
+    /// The transformed difference, and whether the cap truncated it.
+    pub fn apply(&self, raw: f64) -> (f64, bool) {
+        match *self {
+            Transform::Identity => (raw, false),
+            Transform::Capped { cap } if raw > cap => (cap, true),
+            Transform::Capped { .. } => (raw, false),
+            Transform::Logistic { midpoint, steepness } => {
+                let sigmoid = |x: f64| 1.0 / (1.0 + (-steepness * (x - midpoint)).exp());
+                let floor = sigmoid(0.0);
+                ((sigmoid(raw) - floor) / (1.0 - floor), false)
+            }
+        }
+    }
+
+    /// Largest difference the transform lets through, for comparing aspects
+    /// on a common 0–1 scale.
+    pub fn max(&self) -> f64 {
+        match *self {
+            Transform::Capped { cap } => cap,
+            Transform::Identity | Transform::Logistic { .. } => 1.0,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        match *self {
+            Transform::Capped { cap } if cap.is_nan() || cap <= 0.0 => Err(format!("cap must be positive, got {}", cap)),
+            Transform::Logistic { steepness, .. } if steepness.is_nan() || steepness <= 0.0 => {
+                Err(format!("steepness must be positive, got {}", steepness))
+            }
+            Transform::Logistic { midpoint, .. } if !midpoint.is_finite() => {
+                Err(format!("midpoint must be finite, got {}", midpoint))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, JsonSchema)]
+pub struct AspectInfo {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub dimension: Dimension,
+    pub default_weight: f64,
+    pub default_transform: Transform,
+    pub evidence: Evidence,
+    // Amount of evidence in the shorter text at which reliability reaches 0.5
+    pub half_reliability_at: f64,
+}
+
+pub const ASPECTS: &[AspectInfo] = &[
+    AspectInfo {
+        name: "Word Usage",
+        description: "Difference in word choice and frequency",
+        dimension: Dimension::Lexical,
+        default_weight: 3.0, // Increase weight of word usage
+        default_transform: Transform::Identity,
+        evidence: Evidence::Tokens,
+        half_reliability_at: 50.0,
+    },
+    AspectInfo {
+        name: "Sentence Length",
+        description: "Difference in average sentence length",
+        dimension: Dimension::Syntactic,
+        default_weight: 1.5,
+        default_transform: Transform::Capped { cap: DIFFERENCE_CAP },
+        evidence: Evidence::Sentences,
+        half_reliability_at: 3.0,
+    },
+    AspectInfo {
+        name: "Particle Usage",
+        description: "Difference in particle usage",
+        dimension: Dimension::Syntactic,
+        default_weight: 1.5,
+        default_transform: Transform::Capped { cap: DIFFERENCE_CAP },
+        evidence: Evidence::Tokens,
+        half_reliability_at: 20.0,
+    },
+    AspectInfo {
+        name: "Verb Usage",
+        description: "Difference in verb usage",
+        dimension: Dimension::Syntactic,
+        default_weight: 1.2,
+        default_transform: Transform::Capped { cap: DIFFERENCE_CAP },
+        evidence: Evidence::Tokens,
+        half_reliability_at: 20.0,
+    },
+    AspectInfo {
+        name: "Adjective Usage",
+        description: "Difference in adjective usage",
+        dimension: Dimension::Lexical,
+        default_weight: 1.2,
+        default_transform: Transform::Capped { cap: DIFFERENCE_CAP },
+        evidence: Evidence::Tokens,
+        half_reliability_at: 40.0,
+    },
+    AspectInfo {
+        name: "POS Distribution",
+        description: "Divergence between the part-of-speech distributions",
+        dimension: Dimension::Syntactic,
+        default_weight: 1.5,
+        default_transform: Transform::Identity,
+        evidence: Evidence::Tokens,
+        half_reliability_at: 30.0,
+    },
+    AspectInfo {
+        name: "Syntactic Pattern",
+        description: "Difference in part-of-speech bigrams and trigrams",
+        dimension: Dimension::Syntactic,
+        default_weight: 1.5,
+        default_transform: Transform::Capped { cap: DIFFERENCE_CAP },
+        evidence: Evidence::Tokens,
+        half_reliability_at: 50.0,
+    },
+    AspectInfo {
+        name: "Particle Preference",
+        description: "Difference in which particles are used",
+        dimension: Dimension::Syntactic,
+        default_weight: 2.0,
+        default_transform: Transform::Identity,
+        evidence: Evidence::Tokens,
+        half_reliability_at: 30.0,
+    },
+    AspectInfo {
+        name: "Punctuation",
+        description: "Difference in punctuation",
+        dimension: Dimension::Orthographic,
+        default_weight: 1.0,
+        default_transform: Transform::Capped { cap: DIFFERENCE_CAP },
+        evidence: Evidence::Tokens,
+        half_reliability_at: 20.0,
+    },
+    AspectInfo {
+        name: "Script Composition",
+        description: "Difference in the shares of kanji, hiragana, katakana, Latin letters and digits",
+        dimension: Dimension::Orthographic,
+        default_weight: 1.0,
+        default_transform: Transform::Capped { cap: DIFFERENCE_CAP },
+        evidence: Evidence::Tokens,
+        half_reliability_at: 5.0,
+    },
+    AspectInfo {
+        name: "Vocabulary Richness",
+        description: "Difference in vocabulary diversity",
+        dimension: Dimension::Lexical,
+        default_weight: 1.5,
+        default_transform: Transform::Capped { cap: DIFFERENCE_CAP },
+        evidence: Evidence::Tokens,
+        half_reliability_at: 30.0,
+    },
+    AspectInfo {
+        name: "Register / Politeness",
+        description: "Difference in sentence-final register and keigo use",
+        dimension: Dimension::Register,
+        default_weight: 2.0,
+        default_transform: Transform::Identity,
+        evidence: Evidence::Sentences,
+        half_reliability_at: 5.0,
+    },
+    AspectInfo {
+        name: "Code Switching",
+        description: "Difference in the share of English sentences",
+        dimension: Dimension::Register,
+        default_weight: 1.0,
+        default_transform: Transform::Capped { cap: DIFFERENCE_CAP },
+        evidence: Evidence::Sentences,
+        half_reliability_at: 3.0,
+    },
+    AspectInfo {
+        name: "Unusual Whitespace",
+        description: "Difference in the use of full-width, typographic and zero-width spaces",
+        dimension: Dimension::Formatting,
+        default_weight: 0.5,
+        default_transform: Transform::Identity,
+        evidence: Evidence::Tokens,
+        half_reliability_at: 30.0,
+    },
+    // Only present when an embedding model is configured
+    AspectInfo {
+        name: "Semantic Style",
+        description: "Difference in sentence-embedding style",
+        dimension: Dimension::Register,
+        default_weight: 1.0,
+        default_transform: Transform::Identity,
+        evidence: Evidence::Sentences,
+        half_reliability_at: 3.0,
+    },
+    // Compared on every pair; in short-text mode, with the two below and Script Composition, in place of the rest
+    AspectInfo {
+        name: "Character N-grams",
+        description: "Difference in character bigrams and trigrams",
+        dimension: Dimension::Lexical,
+        default_weight: 2.0,
+        default_transform: Transform::Capped { cap: DIFFERENCE_CAP },
+        evidence: Evidence::Tokens,
+        half_reliability_at: 5.0,
+    },
+    AspectInfo {
+        name: "Sentence Endings",
+        description: "Difference in the sentence-final particles used",
+        dimension: Dimension::Register,
+        default_weight: 1.5,
+        default_transform: Transform::Identity,
+        evidence: Evidence::Sentences,
+        half_reliability_at: 1.0,
+    },
+    AspectInfo {
+        name: "Emoji",
+        description: "Difference in the emoji used",
+        dimension: Dimension::Orthographic,
+        default_weight: 1.0,
+        default_transform: Transform::Identity,
+        evidence: Evidence::Tokens,
+        half_reliability_at: 5.0,
+    },
+];
+
+fn info(aspect: &str) -> Option<&'static AspectInfo> {
+    ASPECTS.iter().find(|info| info.name == aspect)
+}
+
+pub fn description(aspect: &str) -> Option<&'static str> {
+    info(aspect).map(|info| info.description)
+}
+
+pub fn dimension(aspect: &str) -> Option<Dimension> {
+    info(aspect).map(|info| info.dimension)
+}
+
+pub fn default_transform(aspect: &str) -> Transform {
+    info(aspect).map_or(Transform::Identity, |info| info.default_transform)
+}
+
+pub fn default_weight(aspect: &str) -> f64 {
+    info(aspect).map_or(1.0, |info| info.default_weight)
+}
+
+/// Rough 0–1 estimate of how trustworthy an aspect's difference is, given how
+/// much evidence the shorter of the two texts provides.
+pub fn reliability(aspect: &str, features1: &TextFeatures, features2: &TextFeatures) -> f64 {
+    let Some(info) = info(aspect) else {
+        return 0.5;
+    };
+    let evidence = match info.evidence {
+        Evidence::Tokens => features1.token_count.min(features2.token_count),
+        Evidence::Sentences => features1.sentence_count.min(features2.sentence_count),
+    } as f64;
+
+    evidence / (evidence + info.half_reliability_at)
+}
@@ -0,0 +1,322 @@
+use crate::comparison::{compare_extracted, DetailedResult, Verdict};
+use crate::config::{AttributionConfig, Config};
+use crate::datasets::LabeledText;
+use crate::features::{extract_features, TextFeatures};
+use crate::profile::mean_features;
+use crate::scoring::Scorer;
+use crate::tokenizer::{LinderaResult, Tokenizer};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CandidateScore {
+    pub author: String,
+    pub confidence: f64,
+    pub verdict: Verdict,
+}
+
+/// Whether the true author must be among the candidates. In an open set
+/// the top candidate is only named when it reaches the rejection threshold,
+/// so the answer can be that none of them matches.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AttributionMode {
+    #[default]
+    Open,
+    Closed,
+}
+
+impl AttributionMode {
+    /// The candidate to name from a ranking, best first.
+    pub fn best_match(self, ranked: &[CandidateScore], reject_below: f64) -> Option<&CandidateScore> {
+        let top = ranked.first()?;
+        match self {
+            AttributionMode::Closed => Some(top),
+            AttributionMode::Open => (top.confidence >= reject_below).then_some(top),
+        }
+    }
+}
+
+/// Confidence the top candidate needs in an open set.
+pub fn rejection_threshold(config: &Config) -> f64 {
+    config.attribution.reject_below.unwrap_or(config.decision.same_author_above)
+}
+
+/// How clearly the top candidate beats the runner-up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Decisiveness {
+    Clear,
+    Probable,
+    NearTie,
+}
+
+/// The top candidate's confidence lead over the runner-up, rated against
+/// the configured margins.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct RankMargin {
+    pub margin: f64,
+    pub decisiveness: Decisiveness,
+}
+
+impl RankMargin {
+    /// `ranked` holds the candidates' confidences, best first; `None` with
+    /// fewer than two.
+    pub fn of(ranked: &[f64], config: &AttributionConfig) -> Option<RankMargin> {
+        let [first, second, ..] = ranked else {
+            return None;
+        };
+        let margin = first - second;
+        let decisiveness = if margin >= config.clear_margin {
+            Decisiveness::Clear
+        } else if margin < config.near_tie_margin {
+            Decisiveness::NearTie
+        } else {
+            Decisiveness::Probable
+        };
+        Some(RankMargin { margin, decisiveness })
+    }
+}
+
+/// Every known author's score for one disputed document, best first, with
+/// the aspects behind the top candidate's score.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentAttribution {
+    pub document: String,
+    pub candidates: Vec<CandidateScore>,
+    /// None when no candidate reaches the rejection threshold of an open set.
+    pub best_match: Option<String>,
+    pub rank_margin: Option<RankMargin>,
+    pub top_details: Vec<DetailedResult>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Csv,
+    Html,
+}
+
+/// Profiles every known author from their texts, each text counting
+/// equally, and scores each disputed `(name, text)` against all of them.
+pub fn attribute(
+    known: &[LabeledText],
+    disputed: &[(String, String)],
+    tokenizer: &Tokenizer,
+    config: &Config,
+    scorer: &dyn Scorer,
+    mode: AttributionMode,
+) -> LinderaResult<Vec<DocumentAttribution>> {
+    let reject_below = rejection_threshold(config);
+    let mut samples: BTreeMap<&str, Vec<TextFeatures>> = BTreeMap::new();
+    for text in known {
+        samples.entry(&text.author).or_default().push(extract_features(&text.text, tokenizer)?);
+    }
+    let profiles: Vec<(&str, TextFeatures)> =
+        samples.iter().map(|(author, features)| (*author, mean_features(features))).collect();
+
+    disputed
+        .iter()
+        .map(|(document, text)| {
+            let features = extract_features(text, tokenizer)?;
+            let mut scored: Vec<(CandidateScore, Vec<DetailedResult>)> = profiles
+                .iter()
+                .map(|(author, profile)| {
+                    let analysis = compare_extracted(&features, profile, config, scorer);
+                    let candidate = CandidateScore {
+                        author: author.to_string(),
+                        confidence: analysis.confidence,
+                        verdict: analysis.verdict,
+                    };
+                    (candidate, analysis.detailed_analysis)
+                })
+                .collect();
+            scored.sort_by(|a, b| b.0.confidence.total_cmp(&a.0.confidence));
+            let top_details = scored.first().map(|(_, details)| details.clone()).unwrap_or_default();
+            let candidates: Vec<CandidateScore> = scored.into_iter().map(|(candidate, _)| candidate).collect();
+            let confidences: Vec<f64> = candidates.iter().map(|c| c.confidence).collect();
+            Ok(DocumentAttribution {
+                document: document.clone(),
+                best_match: mode.best_match(&candidates, reject_below).map(|c| c.author.clone()),
+                rank_margin: RankMargin::of(&confidences, &config.attribution),
+                candidates,
+                top_details,
+            })
+        })
+        .collect()
+}
+
+pub fn write_report(results: &[DocumentAttribution], format: ReportFormat, out: &mut impl Write) -> io::Result<()> {
+    match format {
+        ReportFormat::Csv => write_csv(results, out),
+        ReportFormat::Html => write_html(results, out),
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn name_of(value: impl Serialize) -> String {
+    serde_json::to_value(value).ok().and_then(|v| v.as_str().map(str::to_string)).unwrap_or_default()
+}
+
+/// One row per document and candidate, ranked within each document; the
+/// document's best match (empty for none), margin and decisiveness repeat
+/// on each of its rows.
+pub fn write_csv(results: &[DocumentAttribution], out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "document,rank,author,confidence,verdict,best_match,margin,decisiveness")?;
+    for result in results {
+        let (margin, decisiveness) = match result.rank_margin {
+            Some(m) => (format!("{:.4}", m.margin), name_of(m.decisiveness)),
+            None => (String::new(), String::new()),
+        };
+        for (rank, candidate) in result.candidates.iter().enumerate() {
+            writeln!(
+                out,
+                "{},{},{},{:.4},{},{},{},{}",
+                csv_field(&result.document),
+                rank + 1,
+                csv_field(&candidate.author),
+                candidate.confidence,
+                name_of(candidate.verdict),
+                csv_field(result.best_match.as_deref().unwrap_or_default()),
+                margin,
+                decisiveness
+            )?;
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn escape_html(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// A self-contained page: a summary table of the top candidate per document,
+/// then each document's full ranking and the aspects behind its top score.
+pub fn write_html(results: &[DocumentAttribution], out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, "<html lang=\"ja\"><head><meta charset=\"utf-8\"><title>Attribution report</title>")?;
+    writeln!(out, "<style>body{{font-family:sans-serif}}table{{border-collapse:collapse;margin-bottom:1em}}td,th{{border:1px solid #ccc;padding:2px 8px;text-align:left}}</style>")?;
+    writeln!(out, "</head><body>")?;
+    writeln!(out, "<h1>Attribution report</h1>")?;
+    writeln!(out, "<table><tr><th>document</th><th>attributed to</th><th>top candidate</th><th>confidence</th><th>verdict</th><th>margin</th><th>decisiveness</th></tr>")?;
+    for (i, result) in results.iter().enumerate() {
+        let Some(top) = result.candidates.first() else {
+            continue;
+        };
+        writeln!(
+            out,
+            "<tr><td><a href=\"#doc{}\">{}</a></td><td>{}</td><td>{}</td><td>{:.3}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            i,
+            escape_html(&result.document),
+            result.best_match.as_deref().map_or_else(|| "<em>none of the candidates</em>".to_string(), escape_html),
+            escape_html(&top.author),
+            top.confidence,
+            name_of(top.verdict),
+            result.rank_margin.map_or_else(|| "-".to_string(), |m| format!("{:.3}", m.margin)),
+            result.rank_margin.map_or_else(|| "-".to_string(), |m| name_of(m.decisiveness))
+        )?;
+    }
+    writeln!(out, "</table>")?;
+
+    for (i, result) in results.iter().enumerate() {
+        writeln!(out, "<h2 id=\"doc{}\">{}</h2>", i, escape_html(&result.document))?;
+        writeln!(out, "<table><tr><th>rank</th><th>author</th><th>confidence</th><th>verdict</th></tr>")?;
+        for (rank, candidate) in result.candidates.iter().enumerate() {
+            writeln!(
+                out,
+                "<tr><td>{}</td><td>{}</td><td>{:.3}</td><td>{}</td></tr>",
+                rank + 1,
+                escape_html(&candidate.author),
+                candidate.confidence,
+                name_of(candidate.verdict)
+            )?;
+        }
+        writeln!(out, "</table>")?;
+        if let Some(top) = result.candidates.first() {
+            writeln!(out, "<p>aspects against {}:</p>", escape_html(&top.author))?;
+            writeln!(out, "<table><tr><th>aspect</th><th>difference</th></tr>")?;
+            for detail in &result.top_details {
+                let difference = detail.difference.map_or_else(|| "-".to_string(), |d| format!("{:.3}", d));
+                writeln!(out, "<tr><td>{}</td><td>{}</td></tr>", escape_html(&detail.aspect), difference)?;
+            }
+            writeln!(out, "</table>")?;
+        }
+    }
+    writeln!(out, "</body></html>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reports() {
+        let results = vec![DocumentAttribution {
+            document: "letter, 1910.txt".to_string(),
+            candidates: vec![
+                CandidateScore {
+                    author: "natsume".to_string(),
+                    confidence: 0.8,
+                    verdict: Verdict::SameAuthor,
+                },
+                CandidateScore {
+                    author: "<mori>".to_string(),
+                    confidence: 0.55,
+                    verdict: Verdict::DifferentAuthor,
+                },
+            ],
+            best_match: Some("natsume".to_string()),
+            rank_margin: RankMargin::of(&[0.8, 0.55], &AttributionConfig::default()),
+            top_details: Vec::new(),
+        }];
+        assert!((results[0].rank_margin.unwrap().margin - 0.25).abs() < 1e-9);
+
+        let mut csv = Vec::new();
+        write_csv(&results, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(csv.lines().nth(1), Some("\"letter, 1910.txt\",1,natsume,0.8000,same_author,natsume,0.2500,clear"));
+        assert_eq!(csv.lines().count(), 3);
+
+        let mut html = Vec::new();
+        write_html(&results, &mut html).unwrap();
+        let html = String::from_utf8(html).unwrap();
+        assert!(html.contains("&lt;mori&gt;") && !html.contains("<mori>"));
+        assert!(html.contains("<td>0.250</td><td>clear</td>"));
+    }
+
+    #[test]
+    fn test_open_set() {
+        let candidate = |author: &str, confidence: f64| CandidateScore {
+            author: author.to_string(),
+            confidence,
+            verdict: Verdict::DifferentAuthor,
+        };
+        let ranked = [candidate("natsume", 0.45), candidate("mori", 0.4)];
+        assert!(AttributionMode::Open.best_match(&ranked, 0.6).is_none());
+        assert_eq!(AttributionMode::Closed.best_match(&ranked, 0.6).unwrap().author, "natsume");
+        assert_eq!(AttributionMode::Open.best_match(&ranked, 0.45).unwrap().author, "natsume");
+        assert!(AttributionMode::Closed.best_match(&[], 0.6).is_none());
+
+        let mut config = Config::default();
+        assert_eq!(rejection_threshold(&config), config.decision.same_author_above);
+        config.attribution.reject_below = Some(0.5);
+        assert_eq!(rejection_threshold(&config), 0.5);
+    }
+
+    #[test]
+    fn test_rank_margin() {
+        let config = AttributionConfig::default();
+        assert_eq!(RankMargin::of(&[0.9], &config), None);
+        assert_eq!(RankMargin::of(&[0.62, 0.6, 0.3], &config).unwrap().decisiveness, Decisiveness::NearTie);
+        assert_eq!(RankMargin::of(&[0.7, 0.6], &config).unwrap().decisiveness, Decisiveness::Probable);
+        assert_eq!(RankMargin::of(&[0.9, 0.6], &config).unwrap().decisiveness, Decisiveness::Clear);
+    }
+}
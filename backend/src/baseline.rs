@@ -0,0 +1,126 @@
+use crate::comparison::{compare_features_with_config, DetailedResult};
+use crate::config::{Config, ConfigError};
+use crate::datasets::{load_author_dirs, LabeledText};
+use crate::features::extract_features_filtered;
+use crate::synthetic::{generate, SyntheticOptions};
+use crate::tokenizer::{LinderaResult, Tokenizer};
+use std::collections::HashMap;
+
+/// Per-aspect differences among random different-author pairs, so an observed
+/// difference can be read as a percentile rather than an absolute number.
+#[derive(Debug, Clone, Default)]
+pub struct Baseline {
+    // Sorted ascending
+    differences: HashMap<String, Vec<f64>>,
+    pair_count: usize,
+}
+
+impl Baseline {
+    /// Builds the baseline from `baseline.corpus`, or returns None when unset.
+    pub fn from_config(config: &Config, tokenizer: &Tokenizer) -> Result<Option<Baseline>, ConfigError> {
+        let Some(dir) = &config.baseline.corpus else {
+            return Ok(None);
+        };
+        let texts = load_author_dirs(dir).map_err(ConfigError::Io)?;
+        let baseline = Baseline::build(&texts, config, tokenizer)
+            .map_err(|e| ConfigError::Reference(format!("{}: {}", dir.display(), e)))?;
+        if baseline.pair_count == 0 {
+            return Err(ConfigError::Reference(format!(
+                "no different-author pairs could be drawn from {}",
+                dir.display()
+            )));
+        }
+        Ok(Some(baseline))
+    }
+
+    /// Compares `baseline.pairs` random excerpts of texts by different authors.
+    pub fn build(texts: &[LabeledText], config: &Config, tokenizer: &Tokenizer) -> LinderaResult<Baseline> {
+        let options = SyntheticOptions {
+            pairs: config.baseline.pairs,
+            same_author_share: 0.0,
+            mix_genres: true,
+            seed: config.baseline.seed,
+            ..SyntheticOptions::default()
+        };
+        let filters = config.token_filters().unwrap_or_default();
+        let details = generate(texts, &options)
+            .into_iter()
+            .map(|pair| {
+                let features1 = extract_features_filtered(&pair.text1, tokenizer, &filters)?;
+                let features2 = extract_features_filtered(&pair.text2, tokenizer, &filters)?;
+                Ok(compare_features_with_config(&features1, &features2, config))
+            })
+            .collect::<LinderaResult<Vec<_>>>()?;
+        Ok(Baseline::from_details(details))
+    }
+
+    pub fn from_details(pairs: impl IntoIterator<Item = Vec<DetailedResult>>) -> Baseline {
+        let mut baseline = Baseline::default();
+        for details in pairs {
+            baseline.pair_count += 1;
+            for detail in details {
+                if let Some(difference) = detail.difference {
+                    baseline.differences.entry(detail.aspect).or_default().push(difference);
+                }
+            }
+        }
+        for differences in baseline.differences.values_mut() {
+            differences.sort_by(f64::total_cmp);
+        }
+        baseline
+    }
+
+    pub fn pair_count(&self) -> usize {
+        self.pair_count
+    }
+
+    /// Share of baseline pairs whose difference for `aspect` is below
+    /// `difference`, counting ties as half.
+    pub fn percentile(&self, aspect: &str, difference: f64) -> Option<f64> {
+        let differences = self.differences.get(aspect).filter(|d| !d.is_empty())?;
+        let below = differences.partition_point(|d| *d < difference);
+        let ties = differences[below..].partition_point(|d| *d <= difference);
+        Some((below as f64 + ties as f64 / 2.0) / differences.len() as f64)
+    }
+
+    pub fn annotate(&self, details: &mut [DetailedResult]) {
+        for detail in details {
+            detail.percentile = detail.difference.and_then(|d| self.percentile(&detail.aspect, d));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detail(aspect: &str, difference: Option<f64>) -> DetailedResult {
+        DetailedResult {
+            aspect: aspect.to_string(),
+            difference,
+            explanation: String::new(),
+            weight: 1.0,
+            capped: false,
+            reliability: 1.0,
+            missing_reason: None,
+            percentile: None,
+        }
+    }
+
+    #[test]
+    fn test_percentile() {
+        let baseline = Baseline::from_details(
+            [0.4, 0.1, 0.3, 0.3].map(|d| vec![detail("Punctuation", Some(d)), detail("Verb Usage", None)]),
+        );
+        assert_eq!(baseline.pair_count(), 4);
+        assert_eq!(baseline.percentile("Punctuation", 0.0), Some(0.0));
+        assert_eq!(baseline.percentile("Punctuation", 0.3), Some(0.5));
+        assert_eq!(baseline.percentile("Punctuation", 0.5), Some(1.0));
+        assert_eq!(baseline.percentile("Verb Usage", 0.1), None);
+
+        let mut details = vec![detail("Punctuation", Some(0.2)), detail("Word Usage", Some(0.2))];
+        baseline.annotate(&mut details);
+        assert_eq!(details[0].percentile, Some(0.25));
+        assert_eq!(details[1].percentile, None);
+    }
+}
@@ -0,0 +1,194 @@
+use crate::comparison::compare_extracted;
+use crate::config::{Config, ConfigError};
+use crate::datasets::Dataset;
+use crate::evaluation::fold_pairs;
+use crate::features::{extract_features_filtered, TextFeatures};
+use crate::logistic::{train, TrainOptions, TrainingPair};
+use crate::scoring::{Backend, Scorer, ScoringInput};
+use crate::tokenizer::{LinderaResult, Tokenizer};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+// Fewer labeled pairs than this in a length range leave its scores uncalibrated
+const MIN_BUCKET_PAIRS: usize = 8;
+
+/// Platt scaling fitted on the pairs whose shorter text has between
+/// `min_tokens` and `max_tokens` tokens: sigmoid(slope * score + intercept).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LengthBucket {
+    pub min_tokens: usize,
+    /// Exclusive; the last bucket has no upper end.
+    pub max_tokens: Option<usize>,
+    pub slope: f64,
+    pub intercept: f64,
+    /// Labeled pairs the bucket was fitted on.
+    pub pairs: usize,
+}
+
+impl LengthBucket {
+    fn contains(&self, length: usize) -> bool {
+        length >= self.min_tokens && self.max_tokens.is_none_or(|max| length < max)
+    }
+}
+
+/// Maps one backend's scores to same-author probabilities separately by
+/// text length, as written by `calibrate`: the same score means more on
+/// two long texts than on two short ones.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Calibration {
+    pub backend: Backend,
+    pub buckets: Vec<LengthBucket>,
+}
+
+impl Calibration {
+    pub fn load(path: &Path) -> Result<Calibration, ConfigError> {
+        let json = fs::read_to_string(path).map_err(|e| ConfigError::Scoring(format!("{}: {}", path.display(), e)))?;
+        serde_json::from_str(&json).map_err(|e| ConfigError::Scoring(format!("{}: {}", path.display(), e)))
+    }
+
+    /// Fits one bucket per length range between consecutive `bounds` (and
+    /// below the first and above the last) from `(score, length, same_author)`
+    /// triples. Ranges with too few pairs, or only one label, get no bucket.
+    pub fn fit(backend: Backend, scored: &[(f64, usize, bool)], bounds: &[usize]) -> Calibration {
+        let mut bounds = bounds.to_vec();
+        bounds.sort_unstable();
+        bounds.dedup();
+        let ranges = std::iter::once(0)
+            .chain(bounds.iter().copied())
+            .zip(bounds.iter().copied().map(Some).chain(std::iter::once(None)));
+
+        let mut buckets = Vec::new();
+        for (min_tokens, max_tokens) in ranges {
+            let mut bucket = LengthBucket {
+                min_tokens,
+                max_tokens,
+                slope: 0.0,
+                intercept: 0.0,
+                pairs: 0,
+            };
+            let pairs: Vec<TrainingPair> = scored
+                .iter()
+                .filter(|(_, length, _)| bucket.contains(*length))
+                .map(|(score, _, same_author)| {
+                    TrainingPair::untagged(BTreeMap::from([("score".to_string(), *score)]), *same_author)
+                })
+                .collect();
+            let same = pairs.iter().filter(|p| p.same_author).count();
+            if pairs.len() < MIN_BUCKET_PAIRS || same == 0 || same == pairs.len() {
+                continue;
+            }
+            let model = train(&pairs, &TrainOptions::default());
+            bucket.slope = model.coefficients.get("score").copied().unwrap_or(0.0);
+            bucket.intercept = model.intercept;
+            bucket.pairs = pairs.len();
+            buckets.push(bucket);
+        }
+        Calibration { backend, buckets }
+    }
+
+    /// The bucket covering pairs whose shorter text has `length` tokens.
+    pub fn bucket(&self, length: usize) -> Option<&LengthBucket> {
+        self.buckets.iter().find(|bucket| bucket.contains(length))
+    }
+
+    /// The calibrated probability, or the score itself for lengths without a bucket.
+    pub fn apply(&self, score: f64, length: usize) -> f64 {
+        match self.bucket(length) {
+            Some(bucket) => 1.0 / (1.0 + (-(bucket.slope * score + bucket.intercept)).exp()),
+            None => score,
+        }
+    }
+}
+
+/// Length a pair is calibrated by: the shorter text's tokens, since it
+/// limits how much evidence there is.
+pub fn pair_length(features1: &TextFeatures, features2: &TextFeatures) -> usize {
+    features1.token_count.min(features2.token_count)
+}
+
+/// A backend whose scores go through a calibration.
+#[derive(Debug)]
+pub struct Calibrated {
+    pub scorer: Box<dyn Scorer>,
+    pub calibration: Calibration,
+}
+
+impl Scorer for Calibrated {
+    fn backend(&self) -> Backend {
+        self.scorer.backend()
+    }
+
+    fn score(&self, input: &ScoringInput) -> Result<f64, String> {
+        let length = pair_length(input.features1, input.features2);
+        self.scorer.score(input).map(|score| self.calibration.apply(score, length))
+    }
+}
+
+/// `(score, length, same_author)` of every pair in a dataset the scorer
+/// could judge: every two labeled texts, or the ready-made pairs.
+pub fn scored_pairs(
+    dataset: &Dataset,
+    tokenizer: &Tokenizer,
+    config: &Config,
+    scorer: &dyn Scorer,
+) -> LinderaResult<Vec<(f64, usize, bool)>> {
+    let filters = config.token_filters().unwrap_or_default();
+    let score = |features1: &TextFeatures, features2: &TextFeatures, same_author: bool| {
+        let analysis = compare_extracted(features1, features2, config, scorer);
+        analysis
+            .verdict
+            .is_scored()
+            .then(|| (analysis.confidence, pair_length(features1, features2), same_author))
+    };
+    match dataset {
+        Dataset::Texts(texts) => {
+            let features = texts
+                .iter()
+                .map(|t| extract_features_filtered(&t.text, tokenizer, &filters))
+                .collect::<LinderaResult<Vec<TextFeatures>>>()?;
+            Ok(fold_pairs(texts, 1)
+                .into_iter()
+                .flatten()
+                .filter_map(|(i, j, same_author)| score(&features[i], &features[j], same_author))
+                .collect())
+        }
+        Dataset::Pairs(pairs) => {
+            let mut scored = Vec::new();
+            for pair in pairs {
+                let features1 = extract_features_filtered(&pair.text1, tokenizer, &filters)?;
+                let features2 = extract_features_filtered(&pair.text2, tokenizer, &filters)?;
+                scored.extend(score(&features1, &features2, pair.same_author));
+            }
+            Ok(scored)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_length_buckets() {
+        // Short pairs: the score says nothing. Long pairs: it separates the authors.
+        let mut scored = Vec::new();
+        for score in [0.2, 0.4, 0.6, 0.8] {
+            scored.push((score, 50, true));
+            scored.push((score, 50, false));
+        }
+        for _ in 0..3 {
+            scored.extend([(0.2, 500, false), (0.3, 500, false), (0.7, 500, true), (0.8, 500, true)]);
+        }
+        let calibration = Calibration::fit(Backend::Weighted, &scored, &[100, 1000]);
+
+        assert_eq!(calibration.buckets.len(), 2);
+        assert_eq!(calibration.bucket(50).unwrap().pairs, 8);
+        let (short, long) = (calibration.apply(0.7, 50), calibration.apply(0.7, 500));
+        assert!((short - 0.5).abs() < 0.05, "{}", short);
+        assert!(long > short + 0.2, "{} vs {}", long, short);
+        // Nothing above 1000 tokens to fit on
+        assert_eq!(calibration.apply(0.7, 2000), 0.7);
+    }
+}
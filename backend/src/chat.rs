@@ -0,0 +1,335 @@
+use crate::comparison::{compare_extracted, Verdict};
+use crate::config::Config;
+use crate::datasets::{parse_csv, DatasetError};
+use crate::profile::{build_profile, AuthorProfile};
+use crate::scoring::Scorer;
+use crate::tokenizer::{LinderaResult, Tokenizer};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatFormat {
+    /// LINE's "send chat history" text export.
+    Line,
+    /// A Slack export: one channel's day files, or a single one of them.
+    Slack,
+    /// CSV with a header naming a speaker and a message column.
+    Csv,
+}
+
+impl ChatFormat {
+    /// Guesses the format from the file extension; a directory is taken for a Slack channel.
+    pub fn from_path(path: &Path) -> Option<ChatFormat> {
+        if path.is_dir() {
+            return Some(ChatFormat::Slack);
+        }
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "txt" => Some(ChatFormat::Line),
+            "json" => Some(ChatFormat::Slack),
+            "csv" => Some(ChatFormat::Csv),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub speaker: String,
+    pub text: String,
+}
+
+// Accepted column names, in order of preference
+const SPEAKER_COLUMNS: &[&str] = &["speaker", "user", "author", "sender", "name", "from"];
+const MESSAGE_COLUMNS: &[&str] = &["text", "message", "content", "body"];
+
+pub fn load_chat(path: &Path, format: ChatFormat) -> Result<Vec<ChatMessage>, DatasetError> {
+    match format {
+        ChatFormat::Line => Ok(parse_line(&std::fs::read_to_string(path)?)),
+        ChatFormat::Slack if path.is_dir() => {
+            let mut files: Vec<_> = std::fs::read_dir(path)?
+                .map(|entry| entry.map(|e| e.path()))
+                .collect::<std::io::Result<_>>()?;
+            files.retain(|file| file.extension().is_some_and(|ext| ext == "json"));
+            // Day files are named by date, so this is chronological
+            files.sort();
+            let mut messages = Vec::new();
+            for file in files {
+                messages.extend(parse_slack(&std::fs::read_to_string(file)?)?);
+            }
+            Ok(messages)
+        }
+        ChatFormat::Slack => parse_slack(&std::fs::read_to_string(path)?),
+        ChatFormat::Csv => parse_chat_csv(&std::fs::read_to_string(path)?),
+    }
+}
+
+// "12:34", or "午後1:23" in older exports
+fn is_time(field: &str) -> bool {
+    let digits = field.trim_start_matches(|c: char| !c.is_ascii_digit());
+    let Some((hours, minutes)) = digits.split_once(':') else {
+        return false;
+    };
+    (1..=2).contains(&hours.len())
+        && minutes.len() == 2
+        && hours.chars().chain(minutes.chars()).all(|c| c.is_ascii_digit())
+}
+
+/// Parses a LINE history export: `time<TAB>speaker<TAB>message` lines under
+/// date headers, with multi-line messages wrapped in double quotes.
+/// Stickers, photos and other `[...]` placeholders and system notices are dropped.
+pub fn parse_line(export: &str) -> Vec<ChatMessage> {
+    let mut messages = Vec::new();
+    let mut open: Option<ChatMessage> = None;
+    for line in export.trim_start_matches('\u{FEFF}').lines() {
+        let line = line.trim_end_matches('\r');
+        if let Some(message) = open.as_mut() {
+            match line.strip_suffix('"') {
+                Some(last) => {
+                    message.text.push('\n');
+                    message.text.push_str(last);
+                    messages.extend(open.take());
+                }
+                None => {
+                    message.text.push('\n');
+                    message.text.push_str(line);
+                }
+            }
+            continue;
+        }
+        let mut fields = line.splitn(3, '\t');
+        let (Some(time), Some(speaker), Some(text)) = (fields.next(), fields.next(), fields.next()) else {
+            continue;
+        };
+        if !is_time(time) || speaker.is_empty() {
+            continue;
+        }
+        let message = ChatMessage {
+            speaker: speaker.to_string(),
+            text: text.to_string(),
+        };
+        match text.strip_prefix('"') {
+            Some(rest) if !rest.ends_with('"') => {
+                open = Some(ChatMessage {
+                    text: rest.to_string(),
+                    ..message
+                })
+            }
+            _ => messages.push(message),
+        }
+    }
+    messages.extend(open);
+    let placeholder = |text: &str| text.starts_with('[') && text.ends_with(']');
+    messages.retain(|m| !m.text.trim().is_empty() && !placeholder(&m.text));
+    messages
+}
+
+#[derive(Deserialize)]
+struct SlackMessage {
+    #[serde(default)]
+    subtype: Option<String>,
+    #[serde(default)]
+    user: Option<String>,
+    #[serde(default)]
+    user_profile: Option<SlackProfile>,
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct SlackProfile {
+    #[serde(default)]
+    display_name: String,
+    #[serde(default)]
+    real_name: String,
+}
+
+// Mentions and channel links carry no style; links keep only their label
+fn slack_plain_text(text: &str) -> String {
+    let mut plain = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        plain.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('>') else {
+            rest = &rest[start..];
+            break;
+        };
+        let inner = &rest[start + 1..start + end];
+        if !inner.starts_with(['@', '#', '!']) {
+            if let Some((_, label)) = inner.split_once('|') {
+                plain.push_str(label);
+            }
+        }
+        rest = &rest[start + end + 1..];
+    }
+    plain.push_str(rest);
+    plain.replace("&lt;", "<").replace("&gt;", ">").replace("&amp;", "&")
+}
+
+/// Parses one Slack export day file. Speakers are named by their display
+/// name, falling back to the real name and then the user ID; joins, bots
+/// and other non-message events are dropped.
+pub fn parse_slack(export: &str) -> Result<Vec<ChatMessage>, DatasetError> {
+    let events: Vec<SlackMessage> = serde_json::from_str(export).map_err(|e| DatasetError::Parse {
+        line: e.line(),
+        message: e.to_string(),
+    })?;
+    Ok(events
+        .into_iter()
+        .filter(|event| matches!(event.subtype.as_deref(), None | Some("thread_broadcast")))
+        .filter_map(|event| {
+            let profile_name = event.user_profile.and_then(|profile| {
+                [profile.display_name, profile.real_name].into_iter().find(|name| !name.is_empty())
+            });
+            let speaker = profile_name.or(event.user)?;
+            let text = slack_plain_text(&event.text).trim().to_string();
+            (!text.is_empty()).then_some(ChatMessage { speaker, text })
+        })
+        .collect())
+}
+
+/// Parses a CSV export whose header names a speaker column (`speaker`,
+/// `user`, `author`, ...) and a message column (`text`, `message`, ...).
+pub fn parse_chat_csv(export: &str) -> Result<Vec<ChatMessage>, DatasetError> {
+    let mut records = parse_csv(export.trim_start_matches('\u{FEFF}')).into_iter();
+    let Some((_, header)) = records.next() else {
+        return Ok(Vec::new());
+    };
+    let column = |names: &[&str]| {
+        names
+            .iter()
+            .find_map(|name| header.iter().position(|h| h.trim().eq_ignore_ascii_case(name)))
+            .ok_or_else(|| DatasetError::MissingColumn(names.join("' or '")))
+    };
+    let speaker_column = column(SPEAKER_COLUMNS)?;
+    let message_column = column(MESSAGE_COLUMNS)?;
+
+    let mut messages = Vec::new();
+    for (line, record) in records {
+        let (Some(speaker), Some(text)) = (record.get(speaker_column), record.get(message_column)) else {
+            return Err(DatasetError::Parse {
+                line,
+                message: format!("expected {} fields, found {}", header.len(), record.len()),
+            });
+        };
+        if !speaker.is_empty() && !text.trim().is_empty() {
+            messages.push(ChatMessage {
+                speaker: speaker.clone(),
+                text: text.clone(),
+            });
+        }
+    }
+    Ok(messages)
+}
+
+/// Builds one profile per speaker, each message a sample, sorted by speaker.
+/// Speakers with fewer than `min_messages` messages are left out.
+pub fn speaker_profiles(
+    messages: &[ChatMessage],
+    tokenizer: &Tokenizer,
+    min_messages: usize,
+) -> LinderaResult<Vec<(String, AuthorProfile)>> {
+    let mut by_speaker: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for message in messages {
+        by_speaker.entry(&message.speaker).or_default().push(&message.text);
+    }
+    by_speaker
+        .into_iter()
+        .filter(|(_, texts)| texts.len() >= min_messages)
+        .map(|(speaker, texts)| Ok((speaker.to_string(), build_profile(&texts, tokenizer)?)))
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpeakerPair {
+    pub speaker1: String,
+    pub speaker2: String,
+    pub verdict: Verdict,
+    pub confidence: f64,
+}
+
+/// Compares every pair of speakers, most alike first.
+pub fn compare_speakers(profiles: &[(String, AuthorProfile)], config: &Config, scorer: &dyn Scorer) -> Vec<SpeakerPair> {
+    let mut pairs = Vec::new();
+    for (i, (speaker1, profile1)) in profiles.iter().enumerate() {
+        for (speaker2, profile2) in &profiles[i + 1..] {
+            let analysis = compare_extracted(&profile1.features, &profile2.features, config, scorer);
+            pairs.push(SpeakerPair {
+                speaker1: speaker1.clone(),
+                speaker2: speaker2.clone(),
+                verdict: analysis.verdict,
+                confidence: analysis.confidence,
+            });
+        }
+    }
+    pairs.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scoring::WeightedScorer;
+
+    fn message(speaker: &str, text: &str) -> ChatMessage {
+        ChatMessage {
+            speaker: speaker.to_string(),
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_exports() {
+        let line = "\u{FEFF}[LINE] 夏目とのトーク履歴\r\n保存日時：2024/05/01 10:00\r\n\r\n2024/04/30(火)\r\n12:01\t夏目\t今日は公園に行きました。\r\n12:02\t森\t[スタンプ]\r\n12:03\t森\t\"楽しかった？\r\nまた行こう\"\r\n12:04\t\t森がグループに参加しました。\r\n午後1:05\t夏目\tうん\r\n";
+        assert_eq!(
+            parse_line(line),
+            [
+                message("夏目", "今日は公園に行きました。"),
+                message("森", "楽しかった？\nまた行こう"),
+                message("夏目", "うん"),
+            ]
+        );
+
+        let slack = r#"[
+            {"type": "message", "user": "U1", "user_profile": {"display_name": "", "real_name": "Natsume"}, "text": "<@U2> 見ました &amp; <https://example.com|資料>を読みました"},
+            {"type": "message", "subtype": "channel_join", "user": "U2", "text": "<@U2> has joined the channel"},
+            {"type": "message", "user": "U2", "text": "了解です"}
+        ]"#;
+        assert_eq!(
+            parse_slack(slack).unwrap(),
+            [message("Natsume", "見ました & 資料を読みました"), message("U2", "了解です")]
+        );
+        assert!(matches!(parse_slack("{"), Err(DatasetError::Parse { .. })));
+
+        let csv = "timestamp,Sender,Message\n1,夏目,\"こんにちは、世界\"\n2,森,\n3,森,元気？\n";
+        assert_eq!(
+            parse_chat_csv(csv).unwrap(),
+            [message("夏目", "こんにちは、世界"), message("森", "元気？")]
+        );
+        assert!(matches!(parse_chat_csv("who,what\na,b\n"), Err(DatasetError::MissingColumn(_))));
+    }
+
+    #[test]
+    fn test_compare_speakers() {
+        let messages = [
+            message("natsume", "今日は公園に行きました。とても楽しかったです。"),
+            message("mori", "猫が好きだ！本当に可愛い。"),
+            message("natsume", "昨日は図書館に行きました。静かでよかったです。"),
+            message("natsume_alt", "明日は美術館に行きます。きっと楽しいと思います。"),
+            message("mori", "犬も好きだ！毎日見ている。"),
+            message("natsume_alt", "先週は映画館に行きました。面白かったです。"),
+            message("guest", "こんにちは"),
+        ];
+        let tokenizer = crate::tokenizer::new_tokenizer().unwrap();
+        let profiles = speaker_profiles(&messages, &tokenizer, 2).unwrap();
+        let speakers: Vec<&str> = profiles.iter().map(|(s, _)| s.as_str()).collect();
+        assert_eq!(speakers, ["mori", "natsume", "natsume_alt"]);
+        assert_eq!(profiles[1].1.sample_count, 2);
+
+        let pairs = compare_speakers(&profiles, &Config::default(), &WeightedScorer);
+        assert_eq!(pairs.len(), 3);
+        assert_eq!((pairs[0].speaker1.as_str(), pairs[0].speaker2.as_str()), ("natsume", "natsume_alt"));
+        assert!(pairs.windows(2).all(|w| w[0].confidence >= w[1].confidence));
+    }
+}
@@ -0,0 +1,384 @@
+use author_comparer::attribution::ReportFormat;
+use author_comparer::chat::ChatFormat;
+use author_comparer::datasets::{self, Dataset, DatasetError};
+use author_comparer::evaluation::{Confusion, FoldResult};
+use author_comparer::export::ExportFormat;
+use clap::{Parser, Subcommand, ValueEnum};
+use std::path::{Path, PathBuf};
+
+#[derive(Parser)]
+#[command(name = "author-comparer", about = "Compare the writing style of Japanese texts")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Start the HTTP server (the default)
+    Serve,
+    /// Cross-validate over a labeled corpus
+    Evaluate {
+        #[arg(long)]
+        corpus: PathBuf,
+        #[arg(long, value_enum, default_value_t = Format::AuthorDirs)]
+        format: Format,
+        /// Truth file for PAN pairs; defaults to truth.jsonl next to the corpus
+        #[arg(long)]
+        truth: Option<PathBuf>,
+        #[arg(long, default_value_t = 5)]
+        folds: usize,
+    },
+    /// Sample same- and different-author excerpt pairs from a labeled corpus,
+    /// written as PAN pairs.jsonl and truth.jsonl into the output folder
+    Generate {
+        #[arg(long)]
+        corpus: PathBuf,
+        #[arg(long, value_enum, default_value_t = Format::AuthorDirs)]
+        format: Format,
+        #[arg(long)]
+        output: PathBuf,
+        #[arg(long, default_value_t = 100)]
+        pairs: usize,
+        /// Shortest excerpt, in characters
+        #[arg(long, default_value_t = 200)]
+        min_length: usize,
+        /// Longest excerpt, in characters
+        #[arg(long, default_value_t = 2000)]
+        max_length: usize,
+        #[arg(long, default_value_t = 0.5)]
+        same_author_share: f64,
+        /// Allow pairing texts of different genres
+        #[arg(long)]
+        mix_genres: bool,
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+    },
+    /// Write the extracted feature vectors of a labeled corpus for external ML tools
+    Export {
+        #[arg(long)]
+        corpus: PathBuf,
+        #[arg(long, value_enum, default_value_t = Format::AuthorDirs)]
+        format: Format,
+        #[arg(long)]
+        output: PathBuf,
+        #[arg(long, value_enum)]
+        to: ExportTarget,
+        /// Number of word frequency columns, taken from the words most texts use
+        #[arg(long, default_value_t = 100)]
+        vocabulary: usize,
+    },
+    /// Fit the logistic scoring backend on a labeled corpus and write its
+    /// coefficients as JSON, for `scoring.logistic_model`
+    Train {
+        #[arg(long)]
+        corpus: PathBuf,
+        #[arg(long, value_enum, default_value_t = Format::AuthorDirs)]
+        format: Format,
+        /// Truth file for PAN pairs; defaults to truth.jsonl next to the corpus
+        #[arg(long)]
+        truth: Option<PathBuf>,
+        #[arg(long)]
+        output: PathBuf,
+        #[arg(long, default_value_t = 500)]
+        epochs: usize,
+        /// L2 penalty on the standardized coefficients
+        #[arg(long, default_value_t = 0.01)]
+        l2: f64,
+        /// Extra penalty on features that shift between one author's texts
+        /// in different genres (needs a genre column); 0 turns it off
+        #[arg(long, default_value_t = 1.0)]
+        genre_penalty: f64,
+    },
+    /// Fit a length-bucketed mapping from the default backend's scores to
+    /// same-author probabilities on a labeled corpus, for `scoring.calibration`
+    Calibrate {
+        #[arg(long)]
+        corpus: PathBuf,
+        #[arg(long, value_enum, default_value_t = Format::AuthorDirs)]
+        format: Format,
+        /// Truth file for PAN pairs; defaults to truth.jsonl next to the corpus
+        #[arg(long)]
+        truth: Option<PathBuf>,
+        #[arg(long)]
+        output: PathBuf,
+        /// Token counts of the shorter text separating the length buckets
+        #[arg(long, value_delimiter = ',', default_values_t = [100, 300, 1000])]
+        buckets: Vec<usize>,
+    },
+    /// Score every disputed .txt file against each known author and write
+    /// the ranked candidates as a CSV table or an HTML report
+    Attribute {
+        /// Documents of known authorship
+        #[arg(long)]
+        known: PathBuf,
+        #[arg(long, value_enum, default_value_t = Format::AuthorDirs)]
+        format: Format,
+        /// Folder of .txt files to attribute
+        #[arg(long)]
+        disputed: PathBuf,
+        #[arg(long)]
+        output: PathBuf,
+        #[arg(long, value_enum, default_value_t = AttributionReport::Csv)]
+        to: AttributionReport,
+        /// Always name the top candidate, instead of none when it falls
+        /// below the rejection threshold
+        #[arg(long)]
+        closed_set: bool,
+        /// Rejection threshold of the open set; attribution.reject_below
+        /// or the decision threshold when unset
+        #[arg(long)]
+        reject_below: Option<f64>,
+    },
+    /// Bring stored profiles (.json) up to the current feature schema
+    MigrateProfiles {
+        #[arg(long)]
+        input: PathBuf,
+        /// Where migrated profiles are written; defaults to overwriting the input
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Compare two folders of .txt files as two bodies of work, and list the
+    /// files least like the rest of their own folder
+    CompareDirs {
+        dir1: PathBuf,
+        dir2: PathBuf,
+        /// Files listed per folder, least fitting first
+        #[arg(long, default_value_t = 3)]
+        outliers: usize,
+    },
+    /// Flag commits whose message reads unlike the claimed author's other
+    /// commit messages, e.g. from shared credentials
+    AuditCommits {
+        /// Repository to read with `git log`
+        #[arg(default_value = ".")]
+        repo: PathBuf,
+        /// Only the newest this many commits
+        #[arg(long)]
+        limit: Option<usize>,
+        /// Authors with fewer commits are not profiled
+        #[arg(long, default_value_t = 5)]
+        min_commits: usize,
+        /// Flag commits whose same-author confidence is below this
+        #[arg(long, default_value_t = 0.3)]
+        threshold: f64,
+    },
+    /// Import Aozora Bunko works (.zip or .txt files, folders of them, or URLs)
+    /// into a corpus of one folder per author
+    #[cfg(feature = "aozora")]
+    ImportAozora {
+        #[arg(required = true)]
+        sources: Vec<String>,
+        #[arg(long)]
+        output: PathBuf,
+    },
+    /// Build a background corpus from web pages: URLs listed in a file, WARC
+    /// files or JSONL dumps, reduced to their main text
+    #[cfg(feature = "web-import")]
+    ImportWeb {
+        /// File with one URL per line; `#` starts a comment
+        #[arg(long)]
+        urls: Option<PathBuf>,
+        /// WARC file, gzipped when it ends in .gz
+        #[arg(long)]
+        warc: Vec<PathBuf>,
+        /// JSONL dump with one {"url", "title", "text" or "html"} object per line
+        #[arg(long)]
+        jsonl: Vec<PathBuf>,
+        #[arg(long)]
+        output: PathBuf,
+        /// Shorter documents are left out
+        #[arg(long, default_value_t = 200)]
+        min_chars: usize,
+        /// Keep documents detected as English too, not only Japanese ones
+        #[arg(long)]
+        any_language: bool,
+    },
+    /// Group an exported chat log by speaker and compare every pair of speakers,
+    /// e.g. to tell whether two accounts are the same person
+    CompareChat {
+        file: PathBuf,
+        /// Guessed from the extension when left out (.txt LINE, .json or a folder Slack, .csv CSV)
+        #[arg(long, value_enum)]
+        format: Option<ChatExport>,
+        /// Speakers with fewer messages are not compared
+        #[arg(long, default_value_t = 20)]
+        min_messages: usize,
+    },
+    /// Re-compare a draft against a reference text every time the draft is saved
+    Watch {
+        #[arg(long)]
+        reference: PathBuf,
+        draft: PathBuf,
+        /// Smallest change in an aspect's difference worth reporting
+        #[arg(long, default_value_t = 0.01)]
+        threshold: f64,
+    },
+    /// Score an experiment record from `POST /compare/record` again with this
+    /// build and show what changed since it was recorded
+    Replay {
+        record: PathBuf,
+        /// Score under the current config instead of the recorded one
+        #[arg(long)]
+        current_config: bool,
+        /// Smallest change in an aspect's difference worth reporting
+        #[arg(long, default_value_t = 0.01)]
+        threshold: f64,
+    },
+    /// Replay experiment records (.json files, or folders of them) with this
+    /// build and list those whose verdict changed or whose confidence moved
+    /// beyond the tolerance; fails when any did, e.g. to gate an upgrade
+    Regress {
+        #[arg(required = true)]
+        records: Vec<PathBuf>,
+        /// Score under the current config instead of each recorded one
+        #[arg(long)]
+        current_config: bool,
+        /// Largest confidence change still counted as unchanged; also the
+        /// smallest aspect move listed
+        #[arg(long, default_value_t = 0.02)]
+        tolerance: f64,
+    },
+    /// Manage the author profiles in the profile store (`[profiles]` in the config)
+    Profile {
+        #[command(subcommand)]
+        command: ProfileCommand,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ProfileCommand {
+    /// Build an author's profile from a folder of .txt samples, replacing any stored one
+    Build {
+        #[arg(long)]
+        author: String,
+        directory: PathBuf,
+        /// Keep the source texts in the profile, so schema upgrades can re-extract it
+        #[arg(long)]
+        keep_samples: bool,
+    },
+    /// List the stored profiles
+    List,
+    /// Compare a text against an author's stored profile
+    Verify {
+        #[arg(long)]
+        author: String,
+        file: PathBuf,
+    },
+    /// Combine stored profiles into one, weighting each by its sample count
+    Merge {
+        /// Author the merged profile is stored under; may be one of the merged ones
+        #[arg(long)]
+        into: String,
+        #[arg(required = true)]
+        authors: Vec<String>,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ExportTarget {
+    Arff,
+    Libsvm,
+    Parquet,
+}
+
+impl From<ExportTarget> for ExportFormat {
+    fn from(target: ExportTarget) -> Self {
+        match target {
+            ExportTarget::Arff => ExportFormat::Arff,
+            ExportTarget::Libsvm => ExportFormat::Libsvm,
+            ExportTarget::Parquet => ExportFormat::Parquet,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum AttributionReport {
+    /// One row per document and candidate
+    Csv,
+    /// A summary table plus each document's ranking and aspects
+    Html,
+}
+
+impl From<AttributionReport> for ReportFormat {
+    fn from(report: AttributionReport) -> Self {
+        match report {
+            AttributionReport::Csv => ReportFormat::Csv,
+            AttributionReport::Html => ReportFormat::Html,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ChatExport {
+    /// LINE's text history export
+    Line,
+    /// A Slack channel export folder, or one of its day files
+    Slack,
+    /// CSV with a header naming speaker and message columns
+    Csv,
+}
+
+impl From<ChatExport> for ChatFormat {
+    fn from(export: ChatExport) -> Self {
+        match export {
+            ChatExport::Line => ChatFormat::Line,
+            ChatExport::Slack => ChatFormat::Slack,
+            ChatExport::Csv => ChatFormat::Csv,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Format {
+    /// One folder of .txt files per author
+    AuthorDirs,
+    /// CSV with a header naming `text` and `author` (or `label`) columns
+    Csv,
+    /// PAN authorship verification pairs (JSONL)
+    Pan,
+}
+
+pub fn load_dataset(corpus: &Path, format: Format, truth: Option<&Path>) -> Result<Dataset, DatasetError> {
+    Ok(match format {
+        Format::AuthorDirs => Dataset::Texts(datasets::load_author_dirs(corpus)?),
+        Format::Csv => Dataset::Texts(datasets::load_csv(corpus)?),
+        Format::Pan => {
+            let truth = truth.map(Path::to_path_buf).unwrap_or_else(|| corpus.with_file_name("truth.jsonl"));
+            Dataset::Pairs(datasets::load_pan(corpus, &truth)?)
+        }
+    })
+}
+
+fn print_row(label: &str, pairs: usize, confusion: &Confusion) {
+    println!(
+        "{:<10} {:>6} {:>8.3} {:>9.3} {:>6.3} {:>6.3} {:>12}",
+        label,
+        pairs,
+        confusion.accuracy(),
+        confusion.precision(),
+        confusion.recall(),
+        confusion.f1(),
+        confusion.insufficient
+    );
+}
+
+pub fn print_report(results: &[FoldResult]) {
+    println!(
+        "{:<10} {:>6} {:>8} {:>9} {:>6} {:>6} {:>12}",
+        "fold", "pairs", "accuracy", "precision", "recall", "f1", "insufficient"
+    );
+    let mut total = Confusion::default();
+    let mut pairs = 0;
+    for result in results {
+        print_row(&(result.fold + 1).to_string(), result.pairs, &result.confusion);
+        total.merge(&result.confusion);
+        pairs += result.pairs;
+    }
+    print_row("all", pairs, &total);
+
+    println!();
+    println!("{:<18} {:>14} {:>14}", "", "predicted same", "predicted diff");
+    println!("{:<18} {:>14} {:>14}", "actual same", total.true_positives, total.false_negatives);
+    println!("{:<18} {:>14} {:>14}", "actual different", total.false_positives, total.true_negatives);
+}
@@ -0,0 +1,151 @@
+use crate::comparison::compare_extracted;
+use crate::config::Config;
+use crate::features::TextFeatures;
+use crate::scoring::Scorer;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Merge tree of an agglomerative clustering. Serializes as nested JSON: leaves
+/// carry the text's label and input index, merges the distance they joined at.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum Dendrogram {
+    Leaf { index: usize, label: String },
+    Merge { distance: f64, size: usize, children: Box<[Dendrogram; 2]> },
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DendrogramFormat {
+    #[default]
+    Json,
+    Newick,
+}
+
+impl Dendrogram {
+    /// Height of the node; 0 for leaves.
+    pub fn distance(&self) -> f64 {
+        match self {
+            Dendrogram::Leaf { .. } => 0.0,
+            Dendrogram::Merge { distance, .. } => *distance,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        match self {
+            Dendrogram::Leaf { .. } => 1,
+            Dendrogram::Merge { size, .. } => *size,
+        }
+    }
+
+    /// Newick with branch lengths, so each merge sits at its distance, e.g.
+    /// `((a:0.1,b:0.1):0.3,c:0.4);`.
+    pub fn to_newick(&self) -> String {
+        let mut out = String::new();
+        self.write_newick(None, &mut out);
+        out.push(';');
+        out
+    }
+
+    fn write_newick(&self, parent: Option<f64>, out: &mut String) {
+        match self {
+            Dendrogram::Leaf { label, .. } => out.push_str(&newick_label(label)),
+            Dendrogram::Merge { children, .. } => {
+                out.push('(');
+                children[0].write_newick(Some(self.distance()), out);
+                out.push(',');
+                children[1].write_newick(Some(self.distance()), out);
+                out.push(')');
+            }
+        }
+        // The root has no branch above it
+        if let Some(parent) = parent {
+            out.push_str(&format!(":{}", parent - self.distance()));
+        }
+    }
+}
+
+/// Quotes a Newick label holding anything that would break the format.
+fn newick_label(label: &str) -> String {
+    if !label.is_empty() && !label.chars().any(|c| "()[]':;,".contains(c) || c.is_whitespace()) {
+        label.to_string()
+    } else {
+        format!("'{}'", label.replace('\'', "''"))
+    }
+}
+
+/// Average-linkage (UPGMA) clustering over a symmetric distance matrix.
+/// Returns `None` when there is nothing to cluster.
+pub fn cluster(distances: &[Vec<f64>], labels: &[String]) -> Option<Dendrogram> {
+    let mut clusters: Vec<(Dendrogram, Vec<usize>)> = labels
+        .iter()
+        .enumerate()
+        .map(|(index, label)| (Dendrogram::Leaf { index, label: label.clone() }, vec![index]))
+        .collect();
+
+    while clusters.len() > 1 {
+        let linkage = |a: &[usize], b: &[usize]| {
+            let sum: f64 = a.iter().flat_map(|&i| b.iter().map(move |&j| distances[i][j])).sum();
+            sum / (a.len() * b.len()) as f64
+        };
+        // Ties go to the earliest pair, so equal inputs always give the same tree
+        let mut closest = (0, 1, f64::INFINITY);
+        for i in 0..clusters.len() {
+            for j in i + 1..clusters.len() {
+                let distance = linkage(&clusters[i].1, &clusters[j].1);
+                if distance < closest.2 {
+                    closest = (i, j, distance);
+                }
+            }
+        }
+
+        let (i, j, distance) = closest;
+        let (right, right_members) = clusters.remove(j);
+        let (left, mut members) = clusters.remove(i);
+        members.extend(right_members);
+        let merged = Dendrogram::Merge {
+            // Average linkage never shrinks, but guard against rounding
+            distance: distance.max(left.distance()).max(right.distance()),
+            size: left.size() + right.size(),
+            children: Box::new([left, right]),
+        };
+        clusters.insert(i, (merged, members));
+    }
+    clusters.pop().map(|(tree, _)| tree)
+}
+
+/// Pairwise distances as one minus the same-author confidence.
+pub fn comparison_distances(features: &[TextFeatures], config: &Config, scorer: &dyn Scorer) -> Vec<Vec<f64>> {
+    let mut distances = vec![vec![0.0; features.len()]; features.len()];
+    for i in 0..features.len() {
+        for j in i + 1..features.len() {
+            let distance = 1.0 - compare_extracted(&features[i], &features[j], config, scorer).confidence;
+            distances[i][j] = distance;
+            distances[j][i] = distance;
+        }
+    }
+    distances
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cluster() {
+        let labels: Vec<String> = ["a", "b", "c d"].iter().map(|s| s.to_string()).collect();
+        let distances = vec![vec![0.0, 0.2, 0.6], vec![0.2, 0.0, 0.8], vec![0.6, 0.8, 0.0]];
+        let tree = cluster(&distances, &labels).unwrap();
+
+        assert_eq!(tree.size(), 3);
+        assert!((tree.distance() - 0.7).abs() < 1e-9);
+        let Dendrogram::Merge { children, .. } = &tree else {
+            panic!("expected a merge");
+        };
+        assert!((children[0].distance() - 0.2).abs() < 1e-9);
+        assert_eq!(tree.to_newick(), format!("((a:0.2,b:0.2):{},'c d':0.7);", 0.7 - 0.2));
+
+        assert!(cluster(&[], &[]).is_none());
+        assert_eq!(cluster(&[vec![0.0]], &labels[..1]).unwrap().to_newick(), "a;");
+    }
+}
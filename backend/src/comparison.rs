@@ -0,0 +1,1671 @@
+use crate::alignment::{align_sentences, SentenceAlignment};
+use crate::aspects::{description, dimension, reliability, Dimension};
+use crate::baseline::Baseline;
+use crate::config::{Config, DecisionProfile, EvidenceThresholds, TopicOverlapConfig, WordSimilarity};
+use crate::embedding::{embedding_difference, Embedder};
+use crate::features::{extract_features_filtered, extract_features_traced, TextFeatures, TokenTrace};
+use crate::numerics::{clamp, cosine_similarity, js_divergence, magnitude_ratio, safe_div, spearman_correlation};
+use crate::overlap::{find_overlaps, same_normalized, Overlap, DEFAULT_MIN_LENGTH};
+use crate::plugins::{TenantPlugins, ASPECT_PREFIX};
+use crate::pos::Pos;
+use crate::register::register_difference;
+use crate::robustness::{robustness_report, RobustnessReport};
+use crate::script::{script_difference, script_ratios, ScriptRatios};
+use crate::scoring::{Backend, Scorer, ScoringInput, WeightedScorer};
+use crate::short_text;
+use crate::signing::ResultSignature;
+use crate::symbols::{self, TextClass};
+use crate::timeline::{style_timeline, StyleTimeline};
+use crate::tokenizer::{LinderaResult, Tokenizer};
+use crate::vocabulary::{vocabulary_diff, VocabularyDiff};
+use crate::warnings::{Warning, WarningCode};
+use nalgebra::DVector;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    SameAuthor,
+    DifferentAuthor,
+    InsufficientData,
+    /// The texts are the same, or nearly so, after normalization; a style
+    /// comparison would only report a perfect match.
+    IdenticalText,
+    /// A verdict hook judged the evidence inconclusive; the confidence is
+    /// still the scored one.
+    Inconclusive,
+}
+
+impl Verdict {
+    /// Whether the verdict comes from scoring the texts' style.
+    pub fn is_scored(&self) -> bool {
+        matches!(self, Verdict::SameAuthor | Verdict::DifferentAuthor)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Analysis {
+    pub verdict: Verdict,
+    pub same_author: bool,
+    pub confidence: f64,
+    // Which backend produced the confidence
+    #[serde(default)]
+    pub scoring: Backend,
+    pub detailed_analysis: Vec<DetailedResult>,
+    /// The detailed analysis grouped into style dimensions.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dimensions: Vec<DimensionResult>,
+    /// 1.0 when the aspects agree, lower when reliable aspects point in
+    /// opposite directions and the confidence averages them out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stability: Option<f64>,
+    #[serde(default)]
+    pub warnings: Vec<Warning>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub debug: Option<DebugInfo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sentence_pairs: Option<SentenceAlignment>,
+    /// Per-sentence style values of both texts, in verbose mode.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeline: Option<StyleTimeline>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vocabulary: Option<VocabularyDiff>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub robustness: Option<RobustnessReport>,
+    /// Id of the effective configuration, resolvable at `/config/snapshots/{id}`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_snapshot: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<ResultSignature>,
+    /// Set when the server runs decision-profile experiments.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub experiment: Option<ExperimentAssignment>,
+    /// Share of the texts shared verbatim after normalization, taking the
+    /// less-covered one. Only known when texts were compared.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub overlap: Option<f64>,
+    /// Remarks attached by a verdict hook.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
+}
+
+/// The experiment arm whose decision profile produced the verdict.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ExperimentAssignment {
+    /// The experiment's name, or `control` for the default profile.
+    pub arm: String,
+    /// Assigned from the request's API key, which then always gets the
+    /// same arm, rather than at random.
+    pub by_api_key: bool,
+}
+
+/// Per-text token breakdown, only filled in when debugging was requested.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct DebugInfo {
+    pub text1: Vec<TokenTrace>,
+    pub text2: Vec<TokenTrace>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DetailedResult {
+    pub aspect: String,
+    // None when the aspect could not be computed for this pair
+    pub difference: Option<f64>,
+    pub explanation: String,
+    pub weight: f64,
+    // Whether the difference cap truncated the raw value
+    pub capped: bool,
+    pub reliability: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub missing_reason: Option<String>,
+    // Share of random different-author pairs with a smaller difference; only
+    // filled in when a baseline corpus is configured
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub percentile: Option<f64>,
+}
+
+/// Similarity within one style dimension; `aspects` names its entries in
+/// `detailed_analysis`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct DimensionResult {
+    pub dimension: Dimension,
+    // Weighted mean of 1 minus each aspect's normalized difference, None when
+    // none of its aspects could be computed
+    pub score: Option<f64>,
+    // Sum of the computed aspects' weights
+    pub weight: f64,
+    pub aspects: Vec<String>,
+}
+
+/// The aspect's description in `ASPECTS`; plugin aspects name their plugin.
+fn aspect_explanation(aspect: &str) -> String {
+    match aspect.strip_prefix(ASPECT_PREFIX) {
+        Some(plugin) => format!("Difference in the features of the {} plugin", plugin),
+        None => description(aspect).unwrap_or_default().to_string(),
+    }
+}
+
+fn aspect_result(
+    aspect: &str,
+    raw_difference: Result<f64, String>,
+    config: &Config,
+    features1: &TextFeatures,
+    features2: &TextFeatures,
+) -> DetailedResult {
+    let explanation = aspect_explanation(aspect);
+    let raw_difference = match raw_difference {
+        Ok(raw_difference) => raw_difference,
+        Err(reason) => {
+            return DetailedResult {
+                aspect: aspect.to_string(),
+                difference: None,
+                explanation,
+                weight: config.weight(aspect),
+                capped: false,
+                reliability: 0.0,
+                missing_reason: Some(reason),
+                percentile: None,
+            }
+        }
+    };
+    let (difference, capped) = config.transform(aspect).apply(raw_difference);
+
+    DetailedResult {
+        aspect: aspect.to_string(),
+        difference: Some(difference),
+        explanation,
+        weight: config.weight(aspect),
+        capped,
+        reliability: reliability(aspect, features1, features2),
+        missing_reason: None,
+        percentile: None,
+    }
+}
+
+/// Reason an aspect can't be computed when either text fails `has`.
+fn require(
+    features1: &TextFeatures,
+    features2: &TextFeatures,
+    has: impl Fn(&TextFeatures) -> bool,
+    missing: &str,
+) -> Result<(), String> {
+    match (has(features1), has(features2)) {
+        (true, true) => Ok(()),
+        (false, true) => Err(format!("{} in text1", missing)),
+        (true, false) => Err(format!("{} in text2", missing)),
+        (false, false) => Err(format!("{} in either text", missing)),
+    }
+}
+
+// Bounds of the length-aware default for the most-frequent-words cut
+const MIN_MFW: usize = 50;
+const MAX_MFW: usize = 500;
+
+/// Number of most frequent words compared when none is configured: half the
+/// content tokens of the shorter text, so rare words in long texts don't swamp
+/// the function words that carry style.
+pub fn default_mfw(features1: &TextFeatures, features2: &TextFeatures) -> usize {
+    (features1.token_count.min(features2.token_count) / 2).clamp(MIN_MFW, MAX_MFW)
+}
+
+/// The `mfw` words most frequent across both texts.
+fn most_frequent_words<'a>(
+    freq1: &'a HashMap<String, f64>,
+    freq2: &'a HashMap<String, f64>,
+    mfw: usize,
+) -> Vec<&'a String> {
+    let frequency = |word: &str| freq1.get(word).unwrap_or(&0.0) + freq2.get(word).unwrap_or(&0.0);
+    let mut all_words: Vec<&String> = freq1.keys().chain(freq2.keys()).collect();
+    all_words.sort_unstable();
+    all_words.dedup();
+    all_words.sort_by(|a, b| frequency(b).total_cmp(&frequency(a)).then_with(|| a.cmp(b)));
+    all_words.truncate(mfw);
+    all_words
+}
+
+fn calculate_frequency_similarity(
+    freq1: &HashMap<String, f64>,
+    freq2: &HashMap<String, f64>,
+    mfw: usize,
+) -> f64 {
+    let all_words = most_frequent_words(freq1, freq2, mfw);
+    let vec1: Vec<f64> = all_words
+        .iter()
+        .map(|word| *freq1.get(*word).unwrap_or(&0.0))
+        .collect();
+    let vec2: Vec<f64> = all_words
+        .iter()
+        .map(|word| *freq2.get(*word).unwrap_or(&0.0))
+        .collect();
+
+    let v1 = DVector::from_vec(vec1);
+    let v2 = DVector::from_vec(vec2);
+
+    cosine_similarity(&v1, &v2)
+}
+
+// Fewer shared words than this have no meaningful order
+const MIN_SHARED_WORDS: usize = 3;
+
+/// Spearman correlation of the frequencies of words both texts use, so a
+/// longer text's flatter frequencies don't read as a different style. Texts
+/// ordering their words oppositely are as different as ones sharing nothing.
+fn calculate_rank_similarity(
+    freq1: &HashMap<String, f64>,
+    freq2: &HashMap<String, f64>,
+    mfw: usize,
+) -> Result<f64, String> {
+    let (shared1, shared2): (Vec<f64>, Vec<f64>) = most_frequent_words(freq1, freq2, mfw)
+        .into_iter()
+        .filter_map(|word| Some((*freq1.get(word)?, *freq2.get(word)?)))
+        .unzip();
+    if shared1.len() < MIN_SHARED_WORDS {
+        return Err(format!("fewer than {} shared words", MIN_SHARED_WORDS));
+    }
+    Ok(clamp(spearman_correlation(&shared1, &shared2), 0.0, 1.0))
+}
+
+/// Kilgarriff's chi-square by degrees of freedom over the counts of the most
+/// frequent words, mapped from `[0, inf)` onto a similarity as `1 / (1 + cbdf)`.
+fn calculate_chi_square_similarity(
+    features1: &TextFeatures,
+    features2: &TextFeatures,
+    mfw: usize,
+) -> Result<f64, String> {
+    let (freq1, freq2) = (&features1.word_frequencies, &features2.word_frequencies);
+    let words = most_frequent_words(freq1, freq2, mfw);
+    if words.len() < 2 {
+        return Err("fewer than 2 distinct words".to_string());
+    }
+    // Frequencies are relative to the content tokens, so this recovers the counts
+    let (size1, size2) = (features1.token_count as f64, features2.token_count as f64);
+    let share1 = safe_div(size1, size1 + size2, 0.5);
+
+    let chi_square: f64 = words
+        .iter()
+        .map(|word| {
+            let observed1 = freq1.get(*word).unwrap_or(&0.0) * size1;
+            let observed2 = freq2.get(*word).unwrap_or(&0.0) * size2;
+            let expected1 = (observed1 + observed2) * share1;
+            let expected2 = (observed1 + observed2) * (1.0 - share1);
+            safe_div((observed1 - expected1).powi(2), expected1, 0.0) + safe_div((observed2 - expected2).powi(2), expected2, 0.0)
+        })
+        .sum();
+    let by_degrees_of_freedom = chi_square / (words.len() - 1) as f64;
+    Ok(1.0 / (1.0 + by_degrees_of_freedom))
+}
+
+fn calculate_pos_divergence(features1: &TextFeatures, features2: &TextFeatures) -> f64 {
+    let (dist1, dist2) = (&features1.pos_distribution, &features2.pos_distribution);
+    let categories: BTreeSet<&Pos> = dist1.keys().chain(dist2.keys()).collect();
+    let shares = |dist: &BTreeMap<Pos, f64>| -> Vec<f64> {
+        categories.iter().map(|pos| *dist.get(*pos).unwrap_or(&0.0)).collect()
+    };
+    js_divergence(&shares(dist1), &shares(dist2))
+}
+
+/// Jensen-Shannon divergence of the two texts' particle distributions, over
+/// every particle either of them uses.
+pub fn particle_divergence(features1: &TextFeatures, features2: &TextFeatures) -> f64 {
+    let (dist1, dist2) = (&features1.particle_distribution, &features2.particle_distribution);
+    let particles: BTreeSet<&String> = dist1.keys().chain(dist2.keys()).collect();
+    let shares = |dist: &HashMap<String, f64>| -> Vec<f64> {
+        particles.iter().map(|particle| *dist.get(*particle).unwrap_or(&0.0)).collect()
+    };
+    js_divergence(&shares(dist1), &shares(dist2))
+}
+
+// Cosine similarity of two whole frequency maps
+fn frequency_cosine(freq1: &HashMap<String, f64>, freq2: &HashMap<String, f64>) -> f64 {
+    let dot: f64 = freq1.iter().filter_map(|(key, f1)| freq2.get(key).map(|f2| f1 * f2)).sum();
+    let norm = |freq: &HashMap<String, f64>| freq.values().map(|f| f * f).sum::<f64>().sqrt();
+    clamp(safe_div(dot, norm(freq1) * norm(freq2), 0.0), 0.0, 1.0)
+}
+
+pub fn compare_features(features1: &TextFeatures, features2: &TextFeatures) -> Vec<DetailedResult> {
+    compare_features_with_config(features1, features2, &Config::default())
+}
+
+pub fn compare_features_with_config(
+    features1: &TextFeatures,
+    features2: &TextFeatures,
+    config: &Config,
+) -> Vec<DetailedResult> {
+    let mut results = Vec::new();
+    
+    // Compare word frequency distributions
+    let word_usage = require(features1, features2, |f| !f.word_frequencies.is_empty(), "no words").and_then(|_| {
+        let mfw = config.word_usage.mfw.map_or_else(|| default_mfw(features1, features2), |mfw| mfw.get());
+        let (freq1, freq2) = (&features1.word_frequencies, &features2.word_frequencies);
+        let similarity = match config.word_usage.similarity {
+            WordSimilarity::Cosine => calculate_frequency_similarity(freq1, freq2, mfw),
+            WordSimilarity::Spearman => calculate_rank_similarity(freq1, freq2, mfw)?,
+            WordSimilarity::ChiSquare => calculate_chi_square_similarity(features1, features2, mfw)?,
+        };
+        Ok(1.0 - similarity)
+    });
+    results.push(aspect_result(
+        "Word Usage",
+        word_usage,
+        config,
+        features1,
+        features2,
+    ));
+
+    // Compare basic text statistics with tolerance for different text lengths
+    let sentence_length = require(features1, features2, |f| f.sentence_count > 0, "no sentences detected")
+        .map(|_| 1.0 - magnitude_ratio(features1.avg_sentence_length, features2.avg_sentence_length));
+
+    results.push(aspect_result(
+        "Sentence Length",
+        sentence_length,
+        config, // Capped at 0.5 by default to avoid over-penalizing
+        features1,
+        features2,
+    ));
+
+    // Compare writing style markers
+    let style_markers = vec![
+        ("Particle Usage", features1.particle_ratio, features2.particle_ratio),
+        ("Verb Usage", features1.verb_ratio, features2.verb_ratio),
+        ("Adjective Usage", features1.adjective_ratio, features2.adjective_ratio),
+        ("Punctuation", features1.punctuation_ratio, features2.punctuation_ratio),
+    ];
+
+    for (name, ratio1, ratio2) in style_markers {
+        let ratio_diff = style_marker_evidence(name, features1, features2).map(|_| (ratio1 - ratio2).abs());
+        results.push(aspect_result(
+            name,
+            ratio_diff,
+            config,
+            features1,
+            features2,
+        ));
+    }
+
+    // Every part of speech at once, so shifts among the categories the ratios
+    // above don't track (nouns, adverbs, auxiliaries) still show up
+    let pos_divergence = require(
+        features1,
+        features2,
+        |f| !f.pos_distribution.is_empty(),
+        "no part-of-speech distribution",
+    )
+    .map(|_| calculate_pos_divergence(features1, features2));
+    results.push(aspect_result(
+        "POS Distribution",
+        pos_divergence,
+        config,
+        features1,
+        features2,
+    ));
+
+    // Sequences of parts of speech: how sentences are put together, which
+    // the single ratios above can't tell apart
+    let pattern_diff = require(features1, features2, |f| !f.pos_ngrams.is_empty(), "no part-of-speech n-grams")
+        .map(|_| 1.0 - frequency_cosine(&features1.pos_ngrams, &features2.pos_ngrams));
+    results.push(aspect_result(
+        "Syntactic Pattern",
+        pattern_diff,
+        config,
+        features1,
+        features2,
+    ));
+
+    // Which particles carry that share: one author leans on は where another
+    // writes が, at the same overall particle ratio
+    let preference_diff = require(features1, features2, |f| !f.particle_distribution.is_empty(), "no particles")
+        .map(|_| particle_divergence(features1, features2));
+    results.push(aspect_result(
+        "Particle Preference",
+        preference_diff,
+        config,
+        features1,
+        features2,
+    ));
+
+    // Kanji against kana spellings, katakana loanwords, Latin and digits
+    results.push(aspect_result(
+        "Script Composition",
+        script_composition(features1.script_ratios.as_ref(), features2.script_ratios.as_ref()),
+        config,
+        features1,
+        features2,
+    ));
+
+    // Compare vocabulary richness
+    let vocab_diff = require(features1, features2, |f| f.token_count > 0, "no content tokens")
+        .map(|_| (features1.unique_words_ratio - features2.unique_words_ratio).abs());
+    results.push(aspect_result(
+        "Vocabulary Richness",
+        vocab_diff,
+        config,
+        features1,
+        features2,
+    ));
+
+    // Spelling, kana choice and favourite phrases, which still overlap when
+    // short texts share few words
+    results.push(aspect_result(
+        "Character N-grams",
+        short_text::ngram_distance(&features1.char_ngrams, &features2.char_ngrams),
+        config,
+        features1,
+        features2,
+    ));
+
+    // です・ます against だ・である and casual endings, and keigo
+    results.push(aspect_result(
+        "Register / Politeness",
+        profile_difference(
+            features1.register.as_ref(),
+            features2.register.as_ref(),
+            "no Japanese sentences",
+            register_difference,
+        ),
+        config,
+        features1,
+        features2,
+    ));
+
+    // Only report code-switching when at least one text actually mixes in English,
+    // so purely Japanese comparisons keep their existing normalization
+    if features1.english_segment_ratio > 0.0 || features2.english_segment_ratio > 0.0 {
+        let switching_diff = (features1.english_segment_ratio - features2.english_segment_ratio).abs();
+        results.push(aspect_result(
+            "Code Switching",
+            Ok(switching_diff),
+            config,
+            features1,
+            features2,
+        ));
+    }
+
+    // Likewise only when either text has any; a space pasted from a word
+    // processor or a stray BOM is a habit, so one text without any is as
+    // different as it gets
+    if features1.unusual_whitespace_ratio > 0.0 || features2.unusual_whitespace_ratio > 0.0 {
+        let whitespace_diff =
+            1.0 - magnitude_ratio(features1.unusual_whitespace_ratio, features2.unusual_whitespace_ratio);
+        results.push(aspect_result(
+            "Unusual Whitespace",
+            Ok(whitespace_diff),
+            config,
+            features1,
+            features2,
+        ));
+    }
+
+    results
+}
+
+/// POS aspects need tagged Japanese tokens in both texts, and at least one
+/// occurrence of the part of speech somewhere to say anything.
+fn style_marker_evidence(name: &str, features1: &TextFeatures, features2: &TextFeatures) -> Result<(), String> {
+    let (count, label): (fn(&TextFeatures) -> usize, &str) = match name {
+        "Particle Usage" => (|f| f.pos_counts.particles, "no particles"),
+        "Verb Usage" => (|f| f.pos_counts.verbs, "no verbs"),
+        "Adjective Usage" => (|f| f.pos_counts.adjectives, "no adjectives"),
+        _ => return Ok(()),
+    };
+    require(features1, features2, |f| f.pos_counts.tagged > 0, "no Japanese tokens to tag")?;
+    if count(features1) == 0 && count(features2) == 0 {
+        return Err(format!("{} in either text", label));
+    }
+    Ok(())
+}
+
+pub fn calculate_confidence(details: &[DetailedResult]) -> f64 {
+    let weighted_sum: f64 = details
+        .iter()
+        .filter_map(|detail| detail.difference.map(|d| (1.0 - clamp(d, 0.0, 1.0)) * detail.weight))
+        .sum();
+
+    let confidence = safe_div(weighted_sum, confidence_scale(details), 0.0);
+    clamp(confidence, 0.0, 1.0)
+}
+
+/// What `calculate_confidence` divides the weighted sum by: 2 per aspect, the
+/// scale `same_author_above` was set on. Missing aspects, and those weighted
+/// 0, drop out of it, so only their share is renormalized among the rest.
+pub(crate) fn confidence_scale(details: &[DetailedResult]) -> f64 {
+    details.iter().filter(|d| d.difference.is_some() && d.weight != 0.0).count() as f64 * 2.0
+}
+
+pub fn has_enough_evidence(features: &TextFeatures, thresholds: &EvidenceThresholds) -> bool {
+    features.token_count >= thresholds.min_tokens && features.sentence_count >= thresholds.min_sentences
+}
+
+fn short_text_warnings(features1: &TextFeatures, features2: &TextFeatures, thresholds: &EvidenceThresholds) -> Vec<Warning> {
+    [("text1", features1), ("text2", features2)]
+        .iter()
+        .filter(|(_, features)| !has_enough_evidence(features, thresholds))
+        .map(|(label, features)| {
+            Warning::new(
+                WarningCode::ShortText,
+                format!(
+                    "{} has {} tokens in {} sentences; at least {} tokens and {} sentences are needed",
+                    label, features.token_count, features.sentence_count, thresholds.min_tokens, thresholds.min_sentences
+                ),
+            )
+        })
+        .collect()
+}
+
+// Texts on either side of this English share are taken to be in different languages
+const MOSTLY_ENGLISH: f64 = 0.5;
+
+fn language_warning(features1: &TextFeatures, features2: &TextFeatures) -> Option<Warning> {
+    let (english1, english2) = (
+        features1.english_segment_ratio > MOSTLY_ENGLISH,
+        features2.english_segment_ratio > MOSTLY_ENGLISH,
+    );
+    (english1 != english2).then(|| {
+        let (english, japanese) = if english1 { ("text1", "text2") } else { ("text2", "text1") };
+        Warning::new(
+            WarningCode::LanguageMismatch,
+            format!(
+                "{} is mostly English and {} mostly Japanese; style features are not comparable across languages",
+                english, japanese
+            ),
+        )
+    })
+}
+
+fn genre_warning(genres: Option<[&str; 2]>) -> Option<Warning> {
+    let [genre1, genre2] = genres?;
+    (genre1 != genre2).then(|| {
+        Warning::new(
+            WarningCode::GenreMismatch,
+            format!(
+                "text1 is {} and text2 is {}; style differences may come from the genre rather than the author",
+                genre1, genre2
+            ),
+        )
+    })
+}
+
+fn symbol_warnings(text1: &str, text2: &str) -> Vec<Warning> {
+    [("text1", text1), ("text2", text2)]
+        .iter()
+        .filter_map(|(label, text)| {
+            let description = match symbols::classify(text) {
+                TextClass::Prose => return None,
+                TextClass::EmojiHeavy => "mostly emoji",
+                TextClass::AsciiArt => "mostly ASCII art",
+                TextClass::SymbolHeavy => "mostly symbols",
+            };
+            Some(Warning::new(
+                WarningCode::DegenerateFeatures,
+                format!(
+                    "{} is {}; symbol runs were excluded and the remaining ratios may not reflect writing style",
+                    label, description
+                ),
+            ))
+        })
+        .collect()
+}
+
+// Normalized differences below this say "same author", above the other "different"
+const AGREES_BELOW: f64 = 0.2;
+const DISAGREES_ABOVE: f64 = 0.6;
+// Aspects less reliable than this don't take part in conflict detection
+const CONFLICT_MIN_RELIABILITY: f64 = 0.5;
+
+/// Difference as a share of the largest value the aspect's transform lets through.
+pub(crate) fn normalized_difference(detail: &DetailedResult, config: &Config) -> Option<f64> {
+    let max = config.transform(&detail.aspect).max();
+    detail.difference.map(|d| clamp(safe_div(d, max, 1.0), 0.0, 1.0))
+}
+
+/// Groups aspects by dimension, leaving out dimensions without any aspect.
+pub fn group_dimensions(details: &[DetailedResult], config: &Config) -> Vec<DimensionResult> {
+    Dimension::ALL
+        .iter()
+        .filter_map(|&group| {
+            let members: Vec<&DetailedResult> =
+                details.iter().filter(|d| dimension(&d.aspect) == Some(group)).collect();
+            if members.is_empty() {
+                return None;
+            }
+            let computed = members.iter().filter_map(|d| normalized_difference(d, config).map(|n| (1.0 - n, d.weight)));
+            let (weighted_sum, weight) = computed.fold((0.0, 0.0), |(sum, total), (s, w)| (sum + s * w, total + w));
+            Some(DimensionResult {
+                dimension: group,
+                score: (weight > 0.0).then(|| weighted_sum / weight),
+                weight,
+                aspects: members.iter().map(|d| d.aspect.clone()).collect(),
+            })
+        })
+        .collect()
+}
+
+/// Looks for reliable aspects that clearly point at the same author while
+/// others clearly point at different ones. Returns the stability (1.0 minus
+/// twice the weight share of the smaller camp) and, on a conflict, a warning
+/// naming both sides.
+fn aspect_conflict(details: &[DetailedResult], config: &Config) -> (f64, Option<Warning>) {
+    let mut same = Vec::new();
+    let mut different = Vec::new();
+    let (mut same_weight, mut different_weight, mut total_weight) = (0.0, 0.0, 0.0);
+    for detail in details.iter().filter(|d| d.reliability >= CONFLICT_MIN_RELIABILITY) {
+        let Some(difference) = normalized_difference(detail, config) else {
+            continue;
+        };
+        total_weight += detail.weight;
+        if difference < AGREES_BELOW {
+            same.push(detail.aspect.as_str());
+            same_weight += detail.weight;
+        } else if difference > DISAGREES_ABOVE {
+            different.push(detail.aspect.as_str());
+            different_weight += detail.weight;
+        }
+    }
+    if same.is_empty() || different.is_empty() {
+        return (1.0, None);
+    }
+
+    let stability = clamp(1.0 - 2.0 * safe_div(f64::min(same_weight, different_weight), total_weight, 0.0), 0.0, 1.0);
+    let warning = Warning::new(
+        WarningCode::ConflictingAspects,
+        format!(
+            "{} {} the same author but {} {} different authors; the confidence averages contradictory evidence",
+            same.join(", "),
+            if same.len() == 1 { "suggests" } else { "suggest" },
+            different.join(", "),
+            if different.len() == 1 { "suggests" } else { "suggest" },
+        ),
+    );
+    (stability, Some(warning))
+}
+
+// Above this share of copied text, similarity says more about copying than style
+const OVERLAP_WARNING_COVERAGE: f64 = 0.3;
+
+// At or above this share of both texts, they are the same text rather than
+// two samples of a style
+const IDENTICAL_COVERAGE: f64 = 0.95;
+
+fn overlap_warning(overlap: &Overlap) -> Option<Warning> {
+    let coverage = overlap.coverage1.max(overlap.coverage2);
+    (coverage >= OVERLAP_WARNING_COVERAGE).then(|| {
+        Warning::new(
+            WarningCode::HighOverlap,
+            format!(
+                "{:.0}% of the text is shared verbatim; similarity may reflect copying rather than style (see /overlap)",
+                coverage * 100.0
+            ),
+        )
+    })
+}
+
+pub fn compare(text1: &str, text2: &str, tokenizer: &Tokenizer) -> LinderaResult<Analysis> {
+    compare_with_config(text1, text2, tokenizer, &Config::default())
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CompareOptions<'a> {
+    pub scorer: &'a dyn Scorer,
+    /// Attach every token's feature buckets to the analysis.
+    pub debug: bool,
+    /// Attach the most similar and most different sentence pairs, and the
+    /// per-sentence style timeline.
+    pub verbose: bool,
+    /// Attach this many top shared and exclusive content words per list.
+    pub vocabulary: Option<usize>,
+    /// Re-run the comparison on perturbed copies of the second text.
+    pub robustness: bool,
+    /// Adds the "Semantic Style" aspect when a model is loaded.
+    pub embedder: Option<&'a Embedder>,
+    /// Adds a "Plugin: <name>" aspect for each of a tenant's modules.
+    pub plugins: Option<&'a TenantPlugins>,
+    /// Attaches each aspect's percentile among different-author pairs.
+    pub baseline: Option<&'a Baseline>,
+    /// Declared genres of both texts, checked for a mismatch.
+    pub genres: Option<[&'a str; 2]>,
+    /// Names of the preprocessing steps applied to both texts, which
+    /// gating rules may depend on.
+    pub preprocessing: &'a [&'a str],
+}
+
+static WEIGHTED: WeightedScorer = WeightedScorer;
+
+impl Default for CompareOptions<'_> {
+    fn default() -> Self {
+        CompareOptions {
+            scorer: &WEIGHTED,
+            debug: false,
+            verbose: false,
+            vocabulary: None,
+            robustness: false,
+            embedder: None,
+            plugins: None,
+            baseline: None,
+            genres: None,
+            preprocessing: &[],
+        }
+    }
+}
+
+pub fn compare_with_config(text1: &str, text2: &str, tokenizer: &Tokenizer, config: &Config) -> LinderaResult<Analysis> {
+    compare_with_options(text1, text2, tokenizer, config, CompareOptions::default())
+}
+
+/// Same as `compare_with_config`, with every token's feature buckets attached.
+pub fn compare_debug(text1: &str, text2: &str, tokenizer: &Tokenizer, config: &Config) -> LinderaResult<Analysis> {
+    let options = CompareOptions {
+        debug: true,
+        ..CompareOptions::default()
+    };
+    compare_with_options(text1, text2, tokenizer, config, options)
+}
+
+pub fn compare_with_options(
+    text1: &str,
+    text2: &str,
+    tokenizer: &Tokenizer,
+    config: &Config,
+    options: CompareOptions,
+) -> LinderaResult<Analysis> {
+    // Unknown filter names are rejected when the config is loaded
+    let filters = config.token_filters().unwrap_or_default();
+    let mut analysis = if options.debug {
+        let (features1, trace1) = extract_features_traced(text1, tokenizer, &filters)?;
+        let (features2, trace2) = extract_features_traced(text2, tokenizer, &filters)?;
+        let debug = DebugInfo {
+            text1: trace1,
+            text2: trace2,
+        };
+        analyze(text1, text2, tokenizer, &features1, &features2, config, &options, Some(debug))
+    } else {
+        let features1 = extract_features_filtered(text1, tokenizer, &filters)?;
+        let features2 = extract_features_filtered(text2, tokenizer, &filters)?;
+        analyze(text1, text2, tokenizer, &features1, &features2, config, &options, None)
+    };
+
+    if options.verbose && analysis.verdict.is_scored() {
+        analysis.sentence_pairs = Some(align_sentences(text1, text2, tokenizer)?);
+        analysis.timeline = Some(style_timeline(text1, text2));
+    }
+    if let Some(top) = options.vocabulary.filter(|_| analysis.verdict.is_scored()) {
+        analysis.vocabulary = Some(vocabulary_diff(text1, text2, tokenizer, top)?);
+    }
+    if options.robustness && analysis.verdict.is_scored() {
+        let plain = CompareOptions {
+            scorer: options.scorer,
+            embedder: options.embedder,
+            plugins: options.plugins,
+            ..CompareOptions::default()
+        };
+        let report = robustness_report(text1, text2, &analysis, |text1, text2| {
+            compare_with_options(text1, text2, tokenizer, config, plain)
+        })?;
+        analysis.robustness = Some(report);
+    }
+    Ok(analysis)
+}
+
+// The texts, what was extracted from them and how to score it
+#[allow(clippy::too_many_arguments)]
+fn analyze(
+    text1: &str,
+    text2: &str,
+    tokenizer: &Tokenizer,
+    features1: &TextFeatures,
+    features2: &TextFeatures,
+    config: &Config,
+    options: &CompareOptions,
+    debug: Option<DebugInfo>,
+) -> Analysis {
+    let overlap = find_overlaps(text1, text2, DEFAULT_MIN_LENGTH);
+    let ratio = if same_normalized(text1, text2) { 1.0 } else { overlap.coverage1.min(overlap.coverage2) };
+    if ratio >= IDENTICAL_COVERAGE {
+        let mut analysis = unscored(options.scorer.backend());
+        analysis.verdict = Verdict::IdenticalText;
+        analysis.overlap = Some(ratio);
+        analysis.warnings.push(Warning::new(
+            WarningCode::IdenticalText,
+            format!(
+                "{:.0}% of both texts is the same after normalization; they are one text, so their style wasn't compared",
+                ratio * 100.0
+            ),
+        ));
+        analysis.debug = debug;
+        return analysis;
+    }
+
+    let mut analysis = score_features(features1, features2, Some((text1, text2)), Some(tokenizer), config, options);
+    analysis.warnings.extend(symbol_warnings(text1, text2));
+    analysis.warnings.extend(overlap_warning(&overlap));
+    analysis.overlap = Some(ratio);
+    analysis.debug = debug;
+    analysis
+}
+
+/// Scores two already-extracted feature sets. Text-level extras (warnings,
+/// debug tokens, sentence pairs) need the texts and are left empty.
+pub fn compare_extracted(
+    features1: &TextFeatures,
+    features2: &TextFeatures,
+    config: &Config,
+    scorer: &dyn Scorer,
+) -> Analysis {
+    let options = CompareOptions {
+        scorer,
+        ..CompareOptions::default()
+    };
+    score_features(features1, features2, None, None, config, &options)
+}
+
+// Nothing compared yet: no aspects, no confidence and no verdict
+pub(crate) fn unscored(scoring: Backend) -> Analysis {
+    Analysis {
+        verdict: Verdict::InsufficientData,
+        same_author: false,
+        confidence: 0.0,
+        scoring,
+        detailed_analysis: Vec::new(),
+        dimensions: Vec::new(),
+        stability: None,
+        warnings: Vec::new(),
+        debug: None,
+        sentence_pairs: None,
+        timeline: None,
+        vocabulary: None,
+        robustness: None,
+        config_snapshot: None,
+        signature: None,
+        experiment: None,
+        overlap: None,
+        notes: Vec::new(),
+    }
+}
+
+fn score_features(
+    features1: &TextFeatures,
+    features2: &TextFeatures,
+    texts: Option<(&str, &str)>,
+    // What the texts were tokenized with, for plugins to tokenize them alike
+    tokenizer: Option<&Tokenizer>,
+    config: &Config,
+    options: &CompareOptions,
+) -> Analysis {
+    let mut analysis = unscored(options.scorer.backend());
+    analysis.warnings.extend(genre_warning(options.genres));
+    // A single degenerate text is enough to make every aspect meaningless
+    let short = short_text_warnings(features1, features2, &config.evidence);
+    if !short.is_empty() {
+        analysis.warnings.extend(short);
+        return analysis;
+    }
+    analysis.warnings.extend(language_warning(features1, features2));
+
+    // Too little text for the usual features to mean anything
+    let short = texts.filter(|(text1, text2)| short_text::is_short(text1, text2, &config.short_text));
+    if let Some((text1, text2)) = short {
+        analysis.detailed_analysis = short_text_results(text1, text2, config, features1, features2);
+    } else {
+        // Calculate overall similarity score
+        analysis.detailed_analysis = compare_features_with_config(features1, features2, config);
+    }
+    if let (Some(embedder), Some((text1, text2)), None) = (options.embedder, texts, short) {
+        let difference = embedder
+            .style_vector(text1)
+            .and_then(|v1| embedder.style_vector(text2).map(|v2| embedding_difference(&v1, &v2)));
+        analysis.detailed_analysis.push(aspect_result(
+            "Semantic Style",
+            difference,
+            config,
+            features1,
+            features2,
+        ));
+    }
+    if let (Some(plugins), Some((text1, text2)), Some(tokenizer), None) = (options.plugins, texts, tokenizer, short) {
+        for (name, difference) in plugins.differences(text1, text2, tokenizer) {
+            analysis.detailed_analysis.push(aspect_result(
+                &format!("{}{}", ASPECT_PREFIX, name),
+                difference,
+                config,
+                features1,
+                features2,
+            ));
+        }
+    }
+    apply_gating(&mut analysis.detailed_analysis, config, options);
+    if short.is_none() {
+        reweight_topical(&mut analysis.detailed_analysis, features1, features2, &config.topic_overlap);
+    }
+    if let Some(baseline) = options.baseline {
+        baseline.annotate(&mut analysis.detailed_analysis);
+    }
+    analysis.dimensions = group_dimensions(&analysis.detailed_analysis, config);
+    let (stability, conflict) = aspect_conflict(&analysis.detailed_analysis, config);
+    analysis.stability = Some(stability);
+    analysis.warnings.extend(conflict);
+
+    if short.is_some() {
+        analysis.scoring = Backend::ShortText;
+        analysis.confidence = short_text::confidence(&analysis.detailed_analysis, &config.short_text);
+        decide(&mut analysis, &config.decision);
+        return analysis;
+    }
+
+    // The per-aspect details stay as an explanation of where the texts differ,
+    // whichever backend produces the verdict
+    let input = ScoringInput {
+        features1,
+        features2,
+        details: &analysis.detailed_analysis,
+        texts,
+    };
+    let confidence = match options.scorer.score(&input) {
+        Ok(confidence) => confidence,
+        Err(reason) => {
+            analysis.warnings.push(Warning::new(
+                WarningCode::ScoringFallback,
+                format!("{} scoring failed ({}); used weighted instead", options.scorer.backend().name(), reason),
+            ));
+            analysis.scoring = Backend::Weighted;
+            calculate_confidence(&analysis.detailed_analysis)
+        }
+    };
+    analysis.confidence = confidence;
+    set_verdict(&mut analysis, &config.decision);
+    analysis
+}
+
+/// `difference` of two profiles a text may lack, or the reason it can't be
+/// computed, like `require`.
+fn profile_difference<T>(
+    profile1: Option<&T>,
+    profile2: Option<&T>,
+    missing: &str,
+    difference: fn(&T, &T) -> f64,
+) -> Result<f64, String> {
+    match (profile1, profile2) {
+        (Some(profile1), Some(profile2)) => Ok(difference(profile1, profile2)),
+        (None, Some(_)) => Err(format!("{} in text1", missing)),
+        (Some(_), None) => Err(format!("{} in text2", missing)),
+        (None, None) => Err(format!("{} in either text", missing)),
+    }
+}
+
+fn script_composition(ratios1: Option<&ScriptRatios>, ratios2: Option<&ScriptRatios>) -> Result<f64, String> {
+    profile_difference(ratios1, ratios2, "no letters or digits", script_difference)
+}
+
+fn short_text_results(
+    text1: &str,
+    text2: &str,
+    config: &Config,
+    features1: &TextFeatures,
+    features2: &TextFeatures,
+) -> Vec<DetailedResult> {
+    vec![
+        aspect_result(
+            "Character N-grams",
+            short_text::ngram_difference(text1, text2),
+            config,
+            features1,
+            features2,
+        ),
+        aspect_result(
+            "Sentence Endings",
+            short_text::ending_difference(text1, text2),
+            config,
+            features1,
+            features2,
+        ),
+        // Cheap to count and stable over a few words, so short texts get it too
+        aspect_result(
+            "Script Composition",
+            script_composition(script_ratios(text1).as_ref(), script_ratios(text2).as_ref()),
+            config,
+            features1,
+            features2,
+        ),
+        aspect_result(
+            "Emoji",
+            Ok(short_text::emoji_difference(text1, text2)),
+            config,
+            features1,
+            features2,
+        ),
+    ]
+}
+
+// Hiragana-only words are nearly all particles, auxiliaries and endings;
+// words with kanji, katakana or Latin letters carry the content
+fn is_function_form(word: &str) -> bool {
+    !word.is_empty() && word.chars().all(|c| matches!(c, '\u{3041}'..='\u{309F}' | 'ー'))
+}
+
+// Cosine similarity of the texts' function-word or content-word frequencies,
+// or None when either text has no such words
+fn word_class_similarity(features1: &TextFeatures, features2: &TextFeatures, function: bool) -> Option<f64> {
+    let pick = |features: &TextFeatures| -> HashMap<String, f64> {
+        features
+            .word_frequencies
+            .iter()
+            .filter(|(word, _)| is_function_form(word) == function)
+            .map(|(word, frequency)| (word.clone(), *frequency))
+            .collect()
+    };
+    let (words1, words2) = (pick(features1), pick(features2));
+    if words1.is_empty() || words2.is_empty() {
+        return None;
+    }
+    let dot: f64 = words1.iter().filter_map(|(word, f1)| words2.get(word).map(|f2| f1 * f2)).sum();
+    let norm = |words: &HashMap<String, f64>| words.values().map(|f| f * f).sum::<f64>().sqrt();
+    Some(clamp(safe_div(dot, norm(&words1) * norm(&words2), 0.0), 0.0, 1.0))
+}
+
+// Moves part of the lexical aspects' weight onto the syntactic ones when
+// the shared vocabulary looks like a shared topic rather than a shared
+// author, and says so in each adjusted aspect's explanation
+fn reweight_topical(details: &mut [DetailedResult], features1: &TextFeatures, features2: &TextFeatures, config: &TopicOverlapConfig) {
+    let share = clamp(config.lexical_share, 0.0, 1.0);
+    if share >= 1.0 {
+        return;
+    }
+    let (Some(content), Some(function)) = (
+        word_class_similarity(features1, features2, false),
+        word_class_similarity(features1, features2, true),
+    ) else {
+        return;
+    };
+    if content <= config.content_overlap_above || function >= config.function_agreement_below {
+        return;
+    }
+
+    let computed_in = |detail: &DetailedResult, group: Dimension| {
+        detail.difference.is_some() && dimension(&detail.aspect) == Some(group)
+    };
+    let syntactic: f64 = details.iter().filter(|d| computed_in(d, Dimension::Syntactic)).map(|d| d.weight).sum();
+    let moved: f64 = details.iter().filter(|d| computed_in(d, Dimension::Lexical)).map(|d| d.weight * (1.0 - share)).sum();
+    if syntactic == 0.0 || moved == 0.0 {
+        return;
+    }
+    let reason = format!(
+        "shared vocabulary looks topical, with {:.0}% content-word overlap but {:.0}% function-word agreement",
+        content * 100.0,
+        function * 100.0
+    );
+    for detail in details.iter_mut() {
+        let factor = if computed_in(detail, Dimension::Lexical) {
+            share
+        } else if computed_in(detail, Dimension::Syntactic) {
+            1.0 + moved / syntactic
+        } else {
+            continue;
+        };
+        let weight = detail.weight * factor;
+        detail.explanation = format!("{} (weight {:.2} → {:.2}: {})", detail.explanation, detail.weight, weight, reason);
+        detail.weight = weight;
+    }
+}
+
+// Skipped aspects are reported like ones that couldn't be computed, so
+// scoring and the dimensions leave them out the same way
+fn apply_gating(details: &mut [DetailedResult], config: &Config, options: &CompareOptions) {
+    for rule in config.gating.iter().filter(|rule| rule.when.matches(options.genres, options.preprocessing)) {
+        for detail in details.iter_mut().filter(|d| d.difference.is_some() && rule.skips(&d.aspect)) {
+            detail.difference = None;
+            detail.capped = false;
+            detail.reliability = 0.0;
+            detail.missing_reason = Some(rule.when.describe());
+        }
+    }
+}
+
+/// Sets the verdict from the confidence; results without enough evidence,
+/// or of identical texts, keep theirs.
+pub fn decide(analysis: &mut Analysis, profile: &DecisionProfile) {
+    if analysis.verdict.is_scored() {
+        set_verdict(analysis, profile);
+    }
+}
+
+fn set_verdict(analysis: &mut Analysis, profile: &DecisionProfile) {
+    analysis.same_author = analysis.confidence > profile.same_author_above;
+    analysis.verdict = if analysis.same_author { Verdict::SameAuthor } else { Verdict::DifferentAuthor };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aspects::default_weight;
+    use crate::features::PosCounts;
+    use crate::register::RegisterProfile;
+    use proptest::prelude::*;
+
+    fn features(avg_sentence_length: f64, token_count: usize) -> TextFeatures {
+        TextFeatures {
+            word_frequencies: HashMap::from([("公園".to_string(), 1.0)]),
+            particle_ratio: 0.3,
+            verb_ratio: 0.2,
+            adjective_ratio: 0.1,
+            unique_words_ratio: 0.8,
+            avg_sentence_length,
+            punctuation_ratio: 0.1,
+            english_segment_ratio: 0.0,
+            symbol_ratio: 0.0,
+            unusual_whitespace_ratio: 0.0,
+            token_count,
+            sentence_count: 2,
+            pos_counts: PosCounts {
+                tagged: token_count,
+                particles: 3,
+                verbs: 2,
+                adjectives: 1,
+            },
+            pos_distribution: BTreeMap::from([(Pos::Noun, 0.5), (Pos::Particle, 0.3), (Pos::Verb, 0.2)]),
+            char_ngrams: HashMap::from([("公園".to_string(), 1.0)]),
+            pos_ngrams: HashMap::from([("noun particle".to_string(), 0.5), ("particle verb".to_string(), 0.5)]),
+            particle_distribution: HashMap::from([("は".to_string(), 0.6), ("を".to_string(), 0.4)]),
+            register: Some(RegisterProfile {
+                polite: 1.0,
+                plain: 0.0,
+                casual: 0.0,
+                keigo: 0.0,
+            }),
+            script_ratios: Some(ScriptRatios {
+                kanji: 0.4,
+                hiragana: 0.6,
+                ..ScriptRatios::default()
+            }),
+        }
+    }
+
+    #[test]
+    fn test_aspect_metadata() {
+        let short = features(5.0, 10);
+        let long = features(40.0, 200);
+        let details = compare_features(&short, &long);
+
+        let sentence_length = details.iter().find(|d| d.aspect == "Sentence Length").unwrap();
+        assert!(sentence_length.capped);
+        assert_eq!(sentence_length.difference, Some(crate::aspects::DIFFERENCE_CAP));
+        assert_eq!(sentence_length.weight, 1.5);
+
+        let word_usage = details.iter().find(|d| d.aspect == "Word Usage").unwrap();
+        assert!(!word_usage.capped);
+        assert_eq!(word_usage.weight, 3.0);
+        assert!(details.iter().all(|d| Some(d.explanation.as_str()) == description(&d.aspect)));
+
+        // Reliability is driven by the shorter text
+        let longer = compare_features(&features(5.0, 200), &long);
+        for (detail, better) in details.iter().zip(&longer) {
+            assert!(detail.reliability > 0.0 && detail.reliability < 1.0);
+            assert!(better.reliability >= detail.reliability);
+        }
+    }
+
+    #[test]
+    fn test_most_frequent_words() {
+        let mut features1 = features(10.0, 20);
+        let mut features2 = features(10.0, 20);
+        features1.word_frequencies = HashMap::from([("が".to_string(), 0.6), ("猫".to_string(), 0.4)]);
+        features2.word_frequencies = HashMap::from([("が".to_string(), 0.6), ("犬".to_string(), 0.4)]);
+        let word_usage = |config: &Config| compare_features_with_config(&features1, &features2, config)[0].difference.unwrap();
+
+        let full = word_usage(&Config::default());
+        let mut config = Config::default();
+        config.word_usage.mfw = std::num::NonZeroUsize::new(1);
+        // Only the shared function word is left, so the texts look identical
+        assert!(full > 0.1 && word_usage(&config).abs() < 1e-9, "{}", full);
+
+        assert_eq!(default_mfw(&features(10.0, 20), &features(10.0, 4000)), MIN_MFW);
+        assert_eq!(default_mfw(&features(10.0, 3000), &features(10.0, 4000)), MAX_MFW);
+    }
+
+    #[test]
+    fn test_rank_similarity() {
+        let frequencies = |values: &[(&str, f64)]| values.iter().map(|(w, f)| (w.to_string(), *f)).collect();
+        // Same order at a very different scale, plus a word only the longer text uses
+        let short = frequencies(&[("が", 0.4), ("は", 0.3), ("猫", 0.2)]);
+        let long = frequencies(&[("が", 0.1), ("は", 0.05), ("猫", 0.02), ("犬", 0.01)]);
+        assert_eq!(calculate_rank_similarity(&short, &long, 100), Ok(1.0));
+        assert!(calculate_frequency_similarity(&short, &long, 100) < 1.0);
+
+        let reversed = frequencies(&[("が", 0.1), ("は", 0.2), ("猫", 0.3)]);
+        assert_eq!(calculate_rank_similarity(&short, &reversed, 100), Ok(0.0));
+        assert!(calculate_rank_similarity(&short, &frequencies(&[("が", 0.5)]), 100).is_err());
+
+        let mut config = Config::default();
+        config.word_usage.similarity = WordSimilarity::Spearman;
+        let mut features1 = features(10.0, 20);
+        features1.word_frequencies = short;
+        let mut features2 = features(10.0, 20);
+        features2.word_frequencies = long;
+        assert_eq!(compare_features_with_config(&features1, &features2, &config)[0].difference, Some(0.0));
+    }
+
+    #[test]
+    fn test_chi_square_similarity() {
+        let mut features1 = features(10.0, 100);
+        let mut features2 = features(10.0, 300);
+        features1.word_frequencies = HashMap::from([("が".to_string(), 0.5), ("猫".to_string(), 0.5)]);
+        features2.word_frequencies = features1.word_frequencies.clone();
+        // Same proportions at different lengths are a perfect match
+        assert_eq!(calculate_chi_square_similarity(&features1, &features2, 100), Ok(1.0));
+
+        // Counts 50/50 against 75/225: every cell is 18.75 off its expected count
+        // (31.25 and 93.75 for が, 68.75 and 206.25 for 猫), over 1 degree of freedom
+        features2.word_frequencies = HashMap::from([("が".to_string(), 0.25), ("猫".to_string(), 0.75)]);
+        let chi_square = 18.75f64.powi(2) * (1.0 / 31.25 + 1.0 / 93.75 + 1.0 / 68.75 + 1.0 / 206.25);
+        let similarity = calculate_chi_square_similarity(&features1, &features2, 100).unwrap();
+        assert!((similarity - 1.0 / (1.0 + chi_square)).abs() < 1e-9, "{}", similarity);
+
+        features2.word_frequencies = HashMap::from([("が".to_string(), 1.0)]);
+        features1.word_frequencies = features2.word_frequencies.clone();
+        assert!(calculate_chi_square_similarity(&features1, &features2, 100).is_err());
+    }
+
+    fn find_aspect(features1: &TextFeatures, features2: &TextFeatures, aspect: &str) -> DetailedResult {
+        compare_features(features1, features2).into_iter().find(|d| d.aspect == aspect).unwrap()
+    }
+
+    #[test]
+    fn test_pos_divergence() {
+        let nouns = features(10.0, 20);
+        let mut verbs = features(10.0, 20);
+        verbs.pos_distribution = BTreeMap::from([(Pos::Noun, 0.2), (Pos::Verb, 0.5), (Pos::Adverb, 0.3)]);
+
+        assert_eq!(calculate_pos_divergence(&nouns, &nouns), 0.0);
+        let divergence = calculate_pos_divergence(&nouns, &verbs);
+        assert!(divergence > 0.0 && divergence < 1.0, "{}", divergence);
+    }
+
+    #[test]
+    fn test_unusual_whitespace_aspect() {
+        let plain = features(10.0, 20);
+        let find = |details: &[DetailedResult]| details.iter().find(|d| d.aspect == "Unusual Whitespace").cloned();
+        assert!(find(&compare_features(&plain, &plain)).is_none());
+
+        let mut spaced = features(10.0, 20);
+        spaced.unusual_whitespace_ratio = 0.02;
+        assert_eq!(find(&compare_features(&plain, &spaced)).unwrap().difference, Some(1.0));
+        let mut similar = features(10.0, 20);
+        similar.unusual_whitespace_ratio = 0.01;
+        assert_eq!(find(&compare_features(&similar, &spaced)).unwrap().difference, Some(0.5));
+    }
+
+    #[test]
+    fn test_missing_profile_aspects() {
+        // Empty for texts without any, and for vectors migrated from before the field was recorded
+        type Clear = fn(&mut TextFeatures);
+        let cases: [(&str, Clear, &str); 6] = [
+            ("POS Distribution", |f| f.pos_distribution.clear(), "no part-of-speech distribution"),
+            ("Syntactic Pattern", |f| f.pos_ngrams.clear(), "no part-of-speech n-grams"),
+            ("Particle Preference", |f| f.particle_distribution.clear(), "no particles"),
+            ("Script Composition", |f| f.script_ratios = None, "no letters or digits"),
+            ("Character N-grams", |f| f.char_ngrams.clear(), "no character n-grams"),
+            ("Register / Politeness", |f| f.register = None, "no Japanese sentences"),
+        ];
+        let full = features(10.0, 20);
+        for (aspect, clear, missing) in cases {
+            let same = find_aspect(&full, &full, aspect);
+            assert!(same.difference.unwrap().abs() < 1e-9, "{}", aspect);
+
+            let mut empty = features(10.0, 20);
+            clear(&mut empty);
+            for (features1, features2, side) in [(&full, &empty, "text2"), (&empty, &full, "text1"), (&empty, &empty, "either text")] {
+                let detail = find_aspect(features1, features2, aspect);
+                assert_eq!(detail.difference, None, "{}", aspect);
+                assert_eq!(detail.missing_reason, Some(format!("{} in {}", missing, side)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_character_ngrams_aspect() {
+        let with_ngrams = |text: &str| TextFeatures {
+            char_ngrams: crate::features::char_ngrams(text),
+            ..features(10.0, 20)
+        };
+        let (casual1, casual2) = (with_ngrams("今日も雨じゃん、まじ無理"), with_ngrams("明日も雨じゃん、もう無理"));
+        let formal = with_ngrams("拝啓、時下ますますご清栄のこととお慶び申し上げます");
+        let difference = |f1, f2| find_aspect(f1, f2, "Character N-grams").difference.unwrap();
+        assert!(difference(&casual1, &casual2) < difference(&casual1, &formal));
+    }
+
+    #[test]
+    fn test_syntactic_pattern_aspect() {
+        let with_patterns = |patterns: &[(&str, f64)]| TextFeatures {
+            pos_ngrams: patterns.iter().map(|(gram, f)| (gram.to_string(), *f)).collect(),
+            ..features(10.0, 20)
+        };
+        let plain = with_patterns(&[("noun particle", 0.5), ("particle verb", 0.5)]);
+        assert_eq!(find_aspect(&plain, &plain, "Syntactic Pattern").weight, default_weight("Syntactic Pattern"));
+        let nominal = with_patterns(&[("noun noun", 0.5), ("noun auxiliary_verb", 0.5)]);
+        // No pattern in common is the full cap
+        let pattern = find_aspect(&plain, &nominal, "Syntactic Pattern");
+        assert_eq!(pattern.difference, Some(crate::aspects::DIFFERENCE_CAP));
+    }
+
+    #[test]
+    fn test_particle_preference_aspect() {
+        let with_particles = |particles: &[(&str, f64)]| TextFeatures {
+            particle_distribution: particles.iter().map(|(particle, share)| (particle.to_string(), *share)).collect(),
+            ..features(10.0, 20)
+        };
+        let topic = with_particles(&[("は", 0.6), ("を", 0.4)]);
+        // The same particle ratio, but が in place of は
+        let subject = with_particles(&[("が", 0.6), ("を", 0.4)]);
+        let preference = find_aspect(&topic, &subject, "Particle Preference").difference.unwrap();
+        assert!(preference > 0.5 && preference < 1.0, "{}", preference);
+    }
+
+    #[test]
+    fn test_register_aspect() {
+        let with_register = |polite: f64, keigo: f64| TextFeatures {
+            register: Some(RegisterProfile { polite, plain: 1.0 - polite, casual: 0.0, keigo }),
+            ..features(10.0, 20)
+        };
+        let (formal, plain) = (with_register(1.0, 0.5), with_register(0.0, 0.0));
+        let register = find_aspect(&formal, &plain, "Register / Politeness").difference.unwrap();
+        assert!(register > 0.5, "{}", register);
+    }
+
+    #[test]
+    fn test_script_composition_aspect() {
+        let with_scripts = |kanji: f64| TextFeatures {
+            script_ratios: Some(ScriptRatios {
+                kanji,
+                hiragana: 1.0 - kanji,
+                ..ScriptRatios::default()
+            }),
+            ..features(10.0, 20)
+        };
+        let composition = find_aspect(&with_scripts(0.4), &with_scripts(0.2), "Script Composition").difference.unwrap();
+        assert!(composition > 0.1 && composition < crate::aspects::DIFFERENCE_CAP, "{}", composition);
+    }
+
+    #[test]
+    fn test_configured_transform() {
+        let mut config = Config::default();
+        config.transforms.insert("Sentence Length".to_string(), crate::aspects::Transform::Identity);
+        let details = compare_features_with_config(&features(5.0, 10), &features(40.0, 200), &config);
+
+        let sentence_length = details.iter().find(|d| d.aspect == "Sentence Length").unwrap();
+        assert!(!sentence_length.capped);
+        assert_eq!(sentence_length.difference, Some(1.0 - 5.0 / 40.0));
+    }
+
+    #[test]
+    fn test_missing_aspects() {
+        let mut without_adjectives = features(10.0, 20);
+        without_adjectives.pos_counts.adjectives = 0;
+        let mut without_sentences = without_adjectives.clone();
+        without_sentences.sentence_count = 0;
+
+        let details = compare_features(&without_adjectives, &without_sentences);
+        let missing: Vec<(&str, &str)> = details
+            .iter()
+            .filter_map(|d| Some((d.aspect.as_str(), d.missing_reason.as_deref()?)))
+            .collect();
+        assert_eq!(
+            missing,
+            vec![
+                ("Sentence Length", "no sentences detected in text2"),
+                ("Adjective Usage", "no adjectives in either text"),
+            ]
+        );
+        assert!(details.iter().all(|d| d.difference.is_some() == d.missing_reason.is_none()));
+
+        // Missing aspects are left out of both the sum and the normalization
+        let present_weight: f64 = details.iter().filter(|d| d.difference.is_some()).map(|d| d.weight).sum();
+        let present = details.iter().filter(|d| d.difference.is_some()).count() as f64;
+        assert!((calculate_confidence(&details) - present_weight / (present * 2.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_evidence_thresholds() {
+        let thresholds = EvidenceThresholds {
+            min_tokens: 20,
+            min_sentences: 2,
+        };
+
+        assert!(has_enough_evidence(&features(10.0, 20), &thresholds));
+        assert!(!has_enough_evidence(&features(10.0, 19), &thresholds));
+    }
+
+    #[test]
+    fn test_overlap_warning() {
+        let text = "第三四半期の売上実績について報告いたします。";
+
+        let warning = overlap_warning(&find_overlaps(text, text, DEFAULT_MIN_LENGTH)).unwrap();
+        assert_eq!(warning.code, WarningCode::HighOverlap);
+        assert!(warning.message.starts_with("100%"));
+        let different = find_overlaps(text, "今日はとても楽しかったです。", DEFAULT_MIN_LENGTH);
+        assert!(overlap_warning(&different).is_none());
+    }
+
+    #[test]
+    fn test_identical_text() {
+        let (features1, features2) = (features(10.0, 20), features(10.0, 20));
+        let options = CompareOptions::default();
+        let tokenizer = crate::tokenizer::new_tokenizer().unwrap();
+        let text = "第三四半期の売上実績について報告いたします。前年比110%となっております。";
+
+        let copied = text.replace('。', "！");
+        let analysis = analyze(text, &copied, &tokenizer, &features1, &features2, &Config::default(), &options, None);
+        assert_eq!(analysis.verdict, Verdict::IdenticalText);
+        assert!(!analysis.same_author);
+        assert!(analysis.detailed_analysis.is_empty());
+        assert_eq!(analysis.overlap, Some(1.0));
+        assert_eq!(analysis.warnings[0].code, WarningCode::IdenticalText);
+
+        // Quoting the whole of one text in a longer one isn't the same text
+        let quoted = format!("{}今日はとても楽しかったです。", text);
+        let analysis = analyze(text, &quoted, &tokenizer, &features1, &features2, &Config::default(), &options, None);
+        assert!(analysis.verdict.is_scored());
+        assert!(analysis.overlap.unwrap() < 0.95);
+        assert!(analysis.warnings.iter().any(|w| w.code == WarningCode::HighOverlap));
+    }
+
+    #[test]
+    fn test_symbol_warnings() {
+        assert!(symbol_warnings("私は今日公園に行きました。", "今日は雨です。").is_empty());
+
+        let warnings = symbol_warnings("私は今日公園に行きました。", "😀😀😀🎉🎉");
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code, WarningCode::DegenerateFeatures);
+        assert!(warnings[0].message.starts_with("text2 is mostly emoji"));
+    }
+
+    #[test]
+    fn test_warning_codes() {
+        let short = compare_extracted(&features(10.0, 20), &features(10.0, 2), &Config::default(), &WeightedScorer);
+        assert_eq!(short.verdict, Verdict::InsufficientData);
+        let codes: Vec<WarningCode> = short.warnings.iter().map(|w| w.code).collect();
+        assert_eq!(codes, vec![WarningCode::ShortText]);
+        assert!(short.warnings[0].message.starts_with("text2 has 2 tokens"));
+
+        let mut english = features(10.0, 20);
+        english.english_segment_ratio = 0.9;
+        let warning = language_warning(&features(10.0, 20), &english).unwrap();
+        assert_eq!(warning.code, WarningCode::LanguageMismatch);
+        assert!(warning.message.starts_with("text2 is mostly English"));
+        assert!(language_warning(&english, &english).is_none());
+
+        assert_eq!(genre_warning(Some(["blog", "novel"])).unwrap().code, WarningCode::GenreMismatch);
+        assert!(genre_warning(Some(["blog", "blog"])).is_none());
+        assert_eq!(serde_json::to_value(WarningCode::ShortText).unwrap(), "SHORT_TEXT");
+    }
+
+    #[test]
+    fn test_short_text_mode() {
+        // On by default
+        let config = Config::default();
+        let (features1, features2) = (features(10.0, 20), features(12.0, 20));
+        let options = CompareOptions::default();
+
+        let texts = ("今日も雨じゃん😢まじ無理", "今日も雨じゃん😢もう無理");
+        let analysis = score_features(&features1, &features2, Some(texts), None, &config, &options);
+        assert_eq!(analysis.scoring, Backend::ShortText);
+        let aspects: Vec<&str> = analysis.detailed_analysis.iter().map(|d| d.aspect.as_str()).collect();
+        assert_eq!(aspects, ["Character N-grams", "Sentence Endings", "Script Composition", "Emoji"]);
+        let different = score_features(&features1, &features2, Some(("拝啓、お変わりありませんか。", "草www")), None, &config, &options);
+        assert!(analysis.confidence > different.confidence);
+
+        // Without texts, or turned off, the usual aspects are compared
+        let extracted = score_features(&features1, &features2, None, None, &config, &options);
+        assert_eq!(extracted.scoring, Backend::Weighted);
+        let off: Config = toml::from_str("[short_text]\nmax_chars = 0\n").unwrap();
+        let off = score_features(&features1, &features2, Some(texts), None, &off, &options);
+        assert!(off.detailed_analysis.iter().any(|d| d.aspect == "Word Usage"));
+    }
+
+    #[test]
+    fn test_configured_weights() {
+        let (features1, features2) = (features(10.0, 20), features(20.0, 20));
+        let default = compare_features_with_config(&features1, &features2, &Config::default());
+        let mut config = Config::default();
+        config.weights.insert("Sentence Length".to_string(), 0.0);
+        let details = compare_features_with_config(&features1, &features2, &config);
+
+        let weight = |details: &[DetailedResult], aspect: &str| details.iter().find(|d| d.aspect == aspect).unwrap().weight;
+        assert_eq!(weight(&details, "Sentence Length"), 0.0);
+        assert_eq!(weight(&details, "Word Usage"), weight(&default, "Word Usage"));
+        // A weight of 0 leaves the aspect out of the confidence, as if it were missing
+        let without: Vec<DetailedResult> = default.iter().filter(|d| d.aspect != "Sentence Length").cloned().collect();
+        assert!((calculate_confidence(&details) - calculate_confidence(&without)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_topic_overlap() {
+        let with_words = |words: &[(&str, f64)]| {
+            let mut f = features(10.0, 20);
+            f.word_frequencies = words.iter().map(|(w, n)| (w.to_string(), *n)).collect();
+            f
+        };
+        // Same topic words, different particles and endings
+        let features1 = with_words(&[("原発", 5.0), ("再稼働", 3.0), ("は", 6.0), ("が", 1.0), ("です", 4.0)]);
+        let features2 = with_words(&[("原発", 4.0), ("再稼働", 3.0), ("って", 6.0), ("だ", 4.0), ("が", 1.0)]);
+        assert!(is_function_form("です") && !is_function_form("原発") && !is_function_form("スゴい"));
+
+        let config = Config::default();
+        let mut details = compare_features_with_config(&features1, &features2, &config);
+        let before: f64 = details.iter().map(|d| d.weight).sum();
+        reweight_topical(&mut details, &features1, &features2, &config.topic_overlap);
+        let word_usage = details.iter().find(|d| d.aspect == "Word Usage").unwrap();
+        assert!((word_usage.weight - 1.5).abs() < 1e-9);
+        assert!(word_usage.explanation.contains("looks topical"), "{}", word_usage.explanation);
+        let particles = details.iter().find(|d| d.aspect == "Particle Usage").unwrap();
+        assert!(particles.weight > 1.5);
+        assert!((details.iter().map(|d| d.weight).sum::<f64>() - before).abs() < 1e-9);
+
+        // The same function words, or the adjustment turned off, leave the weights alone
+        let mut details = compare_features_with_config(&features1, &features1, &config);
+        reweight_topical(&mut details, &features1, &features1, &config.topic_overlap);
+        assert!(details.iter().all(|d| !d.explanation.contains("topical")));
+        let off = TopicOverlapConfig {
+            lexical_share: 1.0,
+            ..TopicOverlapConfig::default()
+        };
+        let mut details = compare_features_with_config(&features1, &features2, &config);
+        reweight_topical(&mut details, &features1, &features2, &off);
+        assert!(details.iter().all(|d| !d.explanation.contains("topical")));
+    }
+
+    #[test]
+    fn test_gating() {
+        let config: Config = toml::from_str("[[gating]]\nskip = [\"Word Usage\"]\nwhen = { genres_differ = true }\n").unwrap();
+        let (features1, features2) = (features(10.0, 20), features(12.0, 20));
+        let options = |genres| CompareOptions {
+            genres,
+            ..CompareOptions::default()
+        };
+        let word_usage = |analysis: &Analysis| analysis.detailed_analysis.iter().find(|d| d.aspect == "Word Usage").cloned().unwrap();
+
+        let same = score_features(&features1, &features2, None, None, &config, &options(Some(["blog", "blog"])));
+        assert!(word_usage(&same).difference.is_some());
+        let gated = score_features(&features1, &features2, None, None, &config, &options(Some(["blog", "novel"])));
+        let skipped = word_usage(&gated);
+        assert_eq!((skipped.difference, skipped.reliability), (None, 0.0));
+        assert_eq!(skipped.missing_reason.as_deref(), Some("skipped because the genres differ"));
+    }
+
+    #[test]
+    fn test_group_dimensions() {
+        let mut details = compare_features(&features(10.0, 20), &features(20.0, 20));
+        details[0].difference = None;
+        let dimensions = group_dimensions(&details, &Config::default());
+
+        let groups: Vec<Dimension> = dimensions.iter().map(|d| d.dimension).collect();
+        assert_eq!(
+            groups,
+            vec![Dimension::Lexical, Dimension::Syntactic, Dimension::Orthographic, Dimension::Register]
+        );
+        // Word Usage is missing, so Lexical rests on the remaining three aspects
+        let lexical = &dimensions[0];
+        assert_eq!(lexical.aspects, vec!["Word Usage", "Adjective Usage", "Vocabulary Richness", "Character N-grams"]);
+        assert_eq!(
+            lexical.weight,
+            default_weight("Adjective Usage") + default_weight("Vocabulary Richness") + default_weight("Character N-grams")
+        );
+        assert_eq!(lexical.score, Some(1.0));
+        // Sentence lengths of 10 and 20 differ by the full cap
+        let syntactic = dimensions[1].score.unwrap();
+        assert!((syntactic - 7.7 / 9.2).abs() < 1e-9, "{}", syntactic);
+    }
+
+    #[test]
+    fn test_aspect_conflict() {
+        let detail = |aspect: &str, difference: f64| DetailedResult {
+            aspect: aspect.to_string(),
+            difference: Some(difference),
+            explanation: String::new(),
+            weight: default_weight(aspect),
+            capped: false,
+            reliability: 1.0,
+            missing_reason: None,
+            percentile: None,
+        };
+        let agreeing = vec![detail("Word Usage", 0.1), detail("Sentence Length", 0.05)];
+        assert_eq!(aspect_conflict(&agreeing, &Config::default()), (1.0, None));
+
+        // Sentence Length is capped at 0.5, so 0.45 is a strong difference
+        let conflicting = vec![detail("Word Usage", 0.1), detail("Sentence Length", 0.45), detail("Punctuation", 0.4)];
+        let (stability, warning) = aspect_conflict(&conflicting, &Config::default());
+        // Both camps are close in weight (3.0 vs 2.5 of 5.5), so little stability is left
+        assert!((stability - 1.0 / 11.0).abs() < 1e-9, "{}", stability);
+        let warning = warning.unwrap();
+        assert_eq!(warning.code, WarningCode::ConflictingAspects);
+        assert!(warning.message.starts_with("Word Usage suggests the same author but Sentence Length, Punctuation suggest"));
+
+        // Unreliable aspects don't count as evidence either way
+        let mut unreliable = conflicting;
+        unreliable[0].reliability = 0.1;
+        assert_eq!(aspect_conflict(&unreliable, &Config::default()).0, 1.0);
+    }
+
+    proptest! {
+        #[test]
+        fn comparison_outputs_are_finite_and_bounded(
+            frequencies1 in prop::collection::hash_map("[a-z]{1,3}", 0.0f64..1.0, 0..8),
+            frequencies2 in prop::collection::hash_map("[a-z]{1,3}", 0.0f64..1.0, 0..8),
+            length1 in 0.0f64..100.0,
+            length2 in 0.0f64..100.0,
+        ) {
+            let mut features1 = features(length1, 10);
+            let mut features2 = features(length2, 10);
+            features1.word_frequencies = frequencies1;
+            features2.word_frequencies = frequencies2;
+
+            let details = compare_features(&features1, &features2);
+            for detail in &details {
+                if let Some(difference) = detail.difference {
+                    prop_assert!((0.0..=1.0).contains(&difference), "{}: {}", detail.aspect, difference);
+                }
+                prop_assert!((0.0..=1.0).contains(&detail.reliability));
+            }
+            let confidence = calculate_confidence(&details);
+            prop_assert!((0.0..=1.0).contains(&confidence));
+        }
+    }
+}
@@ -0,0 +1,1087 @@
+use crate::aspects::{self, Dimension, Transform, ASPECTS};
+use crate::plugins;
+use crate::preprocess::{Pipeline, UnknownStep, STEP_NAMES};
+use crate::record::InputPolicy;
+use crate::scoring::Backend;
+use crate::token_filter::{TokenFilters, UnknownFilter};
+use crate::tokenizer::{Dictionary, TokenizerSpec};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+
+pub const CONFIG_ENV: &str = "AUTHOR_COMPARER_CONFIG";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct Config {
+    pub evidence: EvidenceThresholds,
+    /// Names of the preprocessing steps applied to both texts, in order.
+    pub preprocessing: Vec<String>,
+    /// Names of the filters every word passes through before it is counted, in order.
+    pub token_filters: Vec<String>,
+    pub scoring: ScoringConfig,
+    /// Per-aspect difference transforms, keyed by aspect name; aspects not
+    /// listed keep their default.
+    pub transforms: BTreeMap<String, Transform>,
+    /// Per-aspect weights in the weighted confidence, keyed by aspect name;
+    /// aspects not listed keep their default.
+    pub weights: BTreeMap<String, f64>,
+    pub decision: DecisionProfile,
+    pub word_usage: WordUsageConfig,
+    pub baseline: BaselineConfig,
+    pub token_cache: TokenCacheConfig,
+    /// Named tokenizers a request can pick with `dictionary`, besides the
+    /// built-in `ipadic` and `unidic`.
+    pub dictionaries: BTreeMap<String, TokenizerSpec>,
+    pub profiles: ProfilesConfig,
+    pub embedding: EmbeddingConfig,
+    pub signing: SigningConfig,
+    pub listen: ListenConfig,
+    pub rate_limit: RateLimitConfig,
+    pub telemetry: TelemetryConfig,
+    pub history: HistoryConfig,
+    pub shadow: ShadowConfig,
+    /// Named alternative decision profiles, each applied to a `fraction` of
+    /// `/compare` requests.
+    pub experiments: BTreeMap<String, ExperimentConfig>,
+    /// Aspects skipped for the requests a rule matches, checked in order.
+    pub gating: Vec<GatingRule>,
+    pub short_text: ShortTextConfig,
+    pub topic_overlap: TopicOverlapConfig,
+    pub attribution: AttributionConfig,
+    /// Tenant names keyed to the SHA-256 of their API key, in hex, so no key
+    /// is written into the config. Plugins and hooks are set per tenant.
+    pub tenants: BTreeMap<String, String>,
+    pub plugins: PluginsConfig,
+    pub hooks: HooksConfig,
+    pub records: RecordsConfig,
+    /// Endpoints told about events, each with a signed POST.
+    pub webhooks: Vec<WebhookConfig>,
+    /// Maintenance tasks the server runs on a schedule.
+    pub jobs: Vec<JobConfig>,
+    pub queue: QueueConfig,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct ScoringConfig {
+    pub backend: Backend,
+    /// Directory of `.txt` samples, or of author folders holding them, the
+    /// Mahalanobis backend estimates its covariance from.
+    pub reference_corpus: Option<PathBuf>,
+    /// ONNX verifier model behind the neural backend.
+    pub neural_model: Option<PathBuf>,
+    /// Coefficients behind the logistic backend, as written by `train`.
+    pub logistic_model: Option<PathBuf>,
+    /// Length-bucketed calibration of one backend's scores, as written by `calibrate`.
+    pub calibration: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct WordUsageConfig {
+    /// Compare only this many most frequent words; picked from the length of
+    /// the shorter text when unset.
+    pub mfw: Option<NonZeroUsize>,
+    pub similarity: WordSimilarity,
+}
+
+/// How the word frequencies of two texts are compared.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WordSimilarity {
+    /// Cosine of the frequency vectors.
+    #[default]
+    Cosine,
+    /// Rank correlation over the words both texts use, which ignores the
+    /// frequency scale differences between texts of very different lengths.
+    Spearman,
+    /// Kilgarriff's chi-square by degrees of freedom over word counts, a
+    /// corpus similarity measure with well-studied behavior on Japanese.
+    ChiSquare,
+}
+
+/// Labeled corpus that per-aspect percentiles are measured against.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct BaselineConfig {
+    /// One folder of `.txt` files per author; no percentiles when unset.
+    pub corpus: Option<PathBuf>,
+    /// Number of random different-author pairs to compare.
+    pub pairs: usize,
+    pub seed: u64,
+}
+
+impl Default for BaselineConfig {
+    fn default() -> Self {
+        Self {
+            corpus: None,
+            pairs: 200,
+            seed: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct TokenCacheConfig {
+    /// Tokenized sentences kept in memory; 0 turns the cache off.
+    pub capacity: usize,
+}
+
+impl Default for TokenCacheConfig {
+    fn default() -> Self {
+        Self { capacity: 10_000 }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct ProfilesConfig {
+    /// Where author profiles are stored, one `<author>.json` each.
+    pub directory: PathBuf,
+}
+
+impl Default for ProfilesConfig {
+    fn default() -> Self {
+        Self {
+            directory: PathBuf::from("profiles"),
+        }
+    }
+}
+
+/// Sentence-embedding model behind the optional "Semantic Style" aspect.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct EmbeddingConfig {
+    /// ONNX model file; the aspect is skipped when unset.
+    pub model: Option<PathBuf>,
+    /// `tokenizer.json` for the model; defaults to the one next to the model.
+    pub tokenizer: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct SigningConfig {
+    /// File holding the server's 32-byte Ed25519 secret key in hex; results are unsigned when unset.
+    pub key: Option<PathBuf>,
+}
+
+/// Opt-in aggregates of the comparisons served, for spotting calibration
+/// drift; no text or individual result is kept.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct TelemetryConfig {
+    pub enabled: bool,
+    /// Length of one aggregation window, in seconds.
+    pub window_seconds: u64,
+    /// Number of windows kept; older ones are dropped.
+    pub retention: usize,
+    /// File holding the bearer token `GET /admin/telemetry` requires; the
+    /// endpoint is open when unset.
+    pub token_file: Option<PathBuf>,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            window_seconds: 3600,
+            retention: 24 * 7,
+            token_file: None,
+        }
+    }
+}
+
+/// WASM feature extractors uploaded by tenants, each adding a "Plugin: <name>"
+/// aspect to that tenant's comparisons.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct PluginsConfig {
+    /// Modules are kept in one folder per tenant here; uploads are refused when unset.
+    pub directory: Option<PathBuf>,
+    /// Instructions one extraction may run before it is stopped.
+    pub fuel: u64,
+    pub max_memory_mb: usize,
+    pub max_module_kb: usize,
+}
+
+impl Default for PluginsConfig {
+    fn default() -> Self {
+        Self {
+            directory: None,
+            fuel: 10_000_000,
+            max_memory_mb: 16,
+            max_module_kb: 1024,
+        }
+    }
+}
+
+/// Scripts run on every finished `/compare` result, which can change its
+/// verdict or attach notes.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct HooksConfig {
+    /// Run for requests from no tenant, or a tenant without its own script.
+    pub default: Option<PathBuf>,
+    /// Scripts by tenant name, run instead of the default.
+    pub tenants: BTreeMap<String, PathBuf>,
+    /// Operations one run may take before it is stopped.
+    pub max_operations: u64,
+}
+
+impl Default for HooksConfig {
+    fn default() -> Self {
+        Self {
+            default: None,
+            tenants: BTreeMap::new(),
+            max_operations: 100_000,
+        }
+    }
+}
+
+/// What `POST /compare/record` keeps of the compared texts.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct RecordsConfig {
+    pub inputs: InputPolicy,
+}
+
+/// A candidate model scored next to the active one on a sample of
+/// `/compare` requests; its results are logged, never returned.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct ShadowConfig {
+    /// Config file of the candidate, read like the main one; no shadow
+    /// scoring when unset.
+    pub config: Option<PathBuf>,
+    /// Share of requests scored by both, from 0.0 to 1.0.
+    pub sample_rate: f64,
+    /// JSON lines file the comparisons are appended to; stdout when unset.
+    pub log: Option<PathBuf>,
+}
+
+impl Default for ShadowConfig {
+    fn default() -> Self {
+        Self {
+            config: None,
+            sample_rate: 0.1,
+            log: None,
+        }
+    }
+}
+
+/// Pairs whose shorter text has fewer than `max_chars` characters are
+/// compared by character n-grams, sentence endings and emoji instead, and
+/// calibrated separately.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct ShortTextConfig {
+    /// 80 by default, about the length of an SNS post; 0 turns the mode
+    /// off.
+    pub max_chars: usize,
+    /// Weighted aspect similarity at which the confidence is 0.5.
+    pub midpoint: f64,
+    pub steepness: f64,
+}
+
+impl Default for ShortTextConfig {
+    fn default() -> Self {
+        Self {
+            max_chars: 80,
+            midpoint: 0.55,
+            steepness: 10.0,
+        }
+    }
+}
+
+/// When two texts share much of their vocabulary but not their function
+/// words, the shared words probably come from a shared topic, so part of the
+/// lexical aspects' weight moves to the syntactic ones.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct TopicOverlapConfig {
+    /// Cosine similarity of the content-word frequencies above which the
+    /// overlap counts as high.
+    pub content_overlap_above: f64,
+    /// Cosine similarity of the function-word frequencies below which the
+    /// texts' grammar counts as disagreeing.
+    pub function_agreement_below: f64,
+    /// Share of their weight the lexical aspects keep; 1 turns the
+    /// adjustment off.
+    pub lexical_share: f64,
+}
+
+impl Default for TopicOverlapConfig {
+    fn default() -> Self {
+        Self {
+            content_overlap_above: 0.5,
+            function_agreement_below: 0.85,
+            lexical_share: 0.5,
+        }
+    }
+}
+
+/// How far the top candidate of an attribution has to lead the runner-up,
+/// in confidence, to count as a clear winner; tune to the backend's spread.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct AttributionConfig {
+    /// A lead of at least this much is `clear`.
+    pub clear_margin: f64,
+    /// A lead below this is a `near_tie`; anything between is `probable`.
+    pub near_tie_margin: f64,
+    /// In open-set attribution, no candidate is named when the top one's
+    /// confidence is below this; `decision.same_author_above` when unset.
+    pub reject_below: Option<f64>,
+}
+
+impl Default for AttributionConfig {
+    fn default() -> Self {
+        Self {
+            clear_margin: 0.15,
+            near_tie_margin: 0.05,
+            reject_below: None,
+        }
+    }
+}
+
+/// Skips aspects when a request matches `when`. Skipped aspects are
+/// reported without a difference and left out of the confidence.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct GatingRule {
+    /// Aspect names, or dimension names such as `formatting` for all of
+    /// the dimension's aspects.
+    pub skip: Vec<String>,
+    pub when: GatingCondition,
+}
+
+impl GatingRule {
+    pub fn skips(&self, aspect: &str) -> bool {
+        let dimension = aspects::dimension(aspect).map(|d| d.name());
+        self.skip.iter().any(|name| name == aspect || Some(name.as_str()) == dimension)
+    }
+}
+
+/// Every condition that is set must hold; a rule without any always applies.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct GatingCondition {
+    /// The request declared different genres for the two texts.
+    pub genres_differ: bool,
+    /// This preprocessing step was applied to both texts.
+    pub preprocessed_with: Option<String>,
+}
+
+impl GatingCondition {
+    pub fn matches(&self, genres: Option<[&str; 2]>, preprocessing: &[&str]) -> bool {
+        let genres_differ = genres.is_some_and(|[genre1, genre2]| genre1 != genre2);
+        (!self.genres_differ || genres_differ)
+            && self.preprocessed_with.as_deref().is_none_or(|step| preprocessing.contains(&step))
+    }
+
+    /// Why a matching rule skipped an aspect, for its `missing_reason`.
+    pub fn describe(&self) -> String {
+        let mut reasons = Vec::new();
+        if self.genres_differ {
+            reasons.push("the genres differ".to_string());
+        }
+        if let Some(step) = &self.preprocessed_with {
+            reasons.push(format!("the texts were preprocessed with {}", step));
+        }
+        if reasons.is_empty() {
+            return "skipped by a gating rule".to_string();
+        }
+        format!("skipped because {}", reasons.join(" and "))
+    }
+}
+
+/// How a confidence becomes a verdict.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct DecisionProfile {
+    /// Confidences above this are judged the same author.
+    pub same_author_above: f64,
+}
+
+impl Default for DecisionProfile {
+    fn default() -> Self {
+        Self { same_author_above: 0.6 }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct ExperimentConfig {
+    /// Share of requests, from 0.0 to 1.0, decided by this profile instead
+    /// of the default one.
+    pub fraction: f64,
+    #[serde(flatten)]
+    pub decision: DecisionProfile,
+}
+
+/// Where the server accepts connections. Sockets passed by systemd socket
+/// activation take precedence over both.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct ListenConfig {
+    /// TCP address; defaults to 127.0.0.1:8000 unless only a Unix socket is configured.
+    pub address: Option<String>,
+    /// Unix domain socket path, e.g. for a reverse proxy on the same host.
+    pub unix_socket: Option<PathBuf>,
+}
+
+impl ListenConfig {
+    pub const DEFAULT_ADDRESS: &'static str = "127.0.0.1:8000";
+
+    pub fn tcp_address(&self) -> Option<&str> {
+        match (&self.address, &self.unix_socket) {
+            (Some(address), _) => Some(address),
+            (None, Some(_)) => None,
+            (None, None) => Some(Self::DEFAULT_ADDRESS),
+        }
+    }
+}
+
+/// How many requests each client may make. Clients are told apart by their
+/// `X-Api-Key`, or by their address when they send none.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    /// Steady rate per client; 0 (the default) for no limit.
+    pub requests_per_minute: u32,
+    /// Requests a client may make back to back before the rate applies;
+    /// a minute's worth when 0.
+    pub burst: u32,
+}
+
+impl RateLimitConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.requests_per_minute > 0
+    }
+
+    pub fn capacity(&self) -> u32 {
+        if self.burst == 0 {
+            self.requests_per_minute
+        } else {
+            self.burst
+        }
+    }
+}
+
+/// Where every `/compare` result is stored, for `GET /comparisons`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct HistoryConfig {
+    /// An SQLite file (optionally as `sqlite://path`) or a `postgres://` URL;
+    /// nothing is stored when unset.
+    pub database: Option<String>,
+}
+
+/// Where scheduled runs of shared jobs are queued, so that of several
+/// instances with the same `[[jobs]]` only one performs each run.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct QueueConfig {
+    /// A `postgres://` or `redis://` URL; each instance queues its own runs
+    /// in memory when unset.
+    pub url: Option<String>,
+}
+
+/// Something a webhook can be told about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum WebhookEvent {
+    /// An identification named a registered author with at least the
+    /// webhook's `match_above` confidence.
+    #[serde(rename = "profile.match")]
+    ProfileMatch,
+    /// A scheduled job ran, successfully or not.
+    #[serde(rename = "job.completed")]
+    JobCompleted,
+    /// A job refitted a model, such as the calibration.
+    #[serde(rename = "training.completed")]
+    TrainingCompleted,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct WebhookConfig {
+    /// An `http://` or `https://` URL the events are POSTed to.
+    pub url: String,
+    /// File holding the key payloads are signed with (HMAC-SHA256); sent
+    /// unsigned when unset.
+    pub secret_file: Option<PathBuf>,
+    /// Events sent to this webhook; all of them when empty.
+    pub events: Vec<WebhookEvent>,
+    /// Confidence from which an identification is a `profile.match`.
+    pub match_above: f64,
+    /// Tries per delivery; the wait doubles after each failure, from one second.
+    pub attempts: u32,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            secret_file: None,
+            events: Vec::new(),
+            match_above: 0.9,
+            attempts: 5,
+        }
+    }
+}
+
+impl WebhookConfig {
+    pub fn wants(&self, event: WebhookEvent) -> bool {
+        self.events.is_empty() || self.events.contains(&event)
+    }
+}
+
+/// A task run every `every_minutes`, first that long after startup.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct JobConfig {
+    /// Shown in `GET /jobs`; unique among the jobs.
+    pub name: String,
+    pub every_minutes: u64,
+    #[serde(flatten)]
+    pub task: JobTask,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "task", rename_all = "snake_case")]
+pub enum JobTask {
+    /// Deletes stored comparisons older than `keep_days` from the history.
+    RetentionPurge { keep_days: u32 },
+    /// Rebuilds the baseline percentiles from `baseline.corpus`, picking up
+    /// the texts added to it since.
+    BaselineRefresh,
+    /// Refits `scoring.calibration` for the default backend on a corpus of
+    /// author folders, like `calibrate`, and scores with it from then on.
+    CalibrationRefit {
+        corpus: PathBuf,
+        /// Token counts separating the length buckets; 100, 300 and 1000
+        /// when unset.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        buckets: Option<Vec<usize>>,
+    },
+}
+
+impl JobTask {
+    pub fn name(&self) -> &'static str {
+        match self {
+            JobTask::RetentionPurge { .. } => "retention_purge",
+            JobTask::BaselineRefresh => "baseline_refresh",
+            JobTask::CalibrationRefit { .. } => "calibration_refit",
+        }
+    }
+
+    /// Whether a run's effect is shared by every instance, so one instance
+    /// running it is enough. The others rebuild the running instance's
+    /// in-memory state, so every instance runs them itself.
+    pub fn shared(&self) -> bool {
+        matches!(self, JobTask::RetentionPurge { .. })
+    }
+}
+
+/// Below these amounts a text is too short for any aspect to mean something.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct EvidenceThresholds {
+    pub min_tokens: usize,
+    pub min_sentences: usize,
+}
+
+impl Default for EvidenceThresholds {
+    fn default() -> Self {
+        Self {
+            min_tokens: 5,
+            min_sentences: 1,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    Preprocessing(UnknownStep),
+    Reference(String),
+    Embedding(String),
+    Scoring(String),
+    Signing(String),
+    Transform(String),
+    Weight(String),
+    TokenFilter(UnknownFilter),
+    Experiment(String),
+    Gating(String),
+    Plugins(String),
+    Tenants(String),
+    Hooks(String),
+    History(String),
+    Webhooks(String),
+    Jobs(String),
+    Queue(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "failed to read config: {}", e),
+            ConfigError::Parse(e) => write!(f, "invalid config: {}", e),
+            ConfigError::Preprocessing(e) => write!(f, "invalid config: {}", e),
+            ConfigError::Reference(e) => write!(f, "invalid reference corpus: {}", e),
+            ConfigError::Embedding(e) => write!(f, "invalid embedding model: {}", e),
+            ConfigError::Scoring(e) => write!(f, "invalid scoring backend: {}", e),
+            ConfigError::Signing(e) => write!(f, "invalid signing key: {}", e),
+            ConfigError::Transform(e) => write!(f, "invalid aspect transform: {}", e),
+            ConfigError::Weight(e) => write!(f, "invalid aspect weight: {}", e),
+            ConfigError::TokenFilter(e) => write!(f, "invalid config: {}", e),
+            ConfigError::Experiment(e) => write!(f, "invalid experiment: {}", e),
+            ConfigError::Gating(e) => write!(f, "invalid gating rule: {}", e),
+            ConfigError::Plugins(e) => write!(f, "invalid plugins: {}", e),
+            ConfigError::Tenants(e) => write!(f, "invalid tenants: {}", e),
+            ConfigError::Hooks(e) => write!(f, "invalid hooks: {}", e),
+            ConfigError::History(e) => write!(f, "invalid history database: {}", e),
+            ConfigError::Webhooks(e) => write!(f, "invalid webhook: {}", e),
+            ConfigError::Jobs(e) => write!(f, "invalid job: {}", e),
+            ConfigError::Queue(e) => write!(f, "invalid job queue: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Config, ConfigError> {
+        let contents = std::fs::read_to_string(path).map_err(ConfigError::Io)?;
+        let config: Config = toml::from_str(&contents).map_err(ConfigError::Parse)?;
+        // Fail at startup rather than on the first request
+        config.pipeline().map_err(ConfigError::Preprocessing)?;
+        config.validate_transforms().map_err(ConfigError::Transform)?;
+        validate_weights(&config.weights).map_err(ConfigError::Weight)?;
+        config.token_filters().map_err(ConfigError::TokenFilter)?;
+        config.validate_experiments().map_err(ConfigError::Experiment)?;
+        config.validate_gating().map_err(ConfigError::Gating)?;
+        config.validate_tenants().map_err(ConfigError::Tenants)?;
+        if let Some(tenant) = config.hooks.tenants.keys().find(|t| !config.tenants.contains_key(*t)) {
+            return Err(ConfigError::Hooks(format!("'{}' is not a configured tenant", tenant)));
+        }
+        config.validate_webhooks().map_err(ConfigError::Webhooks)?;
+        config.validate_jobs().map_err(ConfigError::Jobs)?;
+        Ok(config)
+    }
+
+    // Tenant names become folder names for their plugins
+    fn validate_tenants(&self) -> Result<(), String> {
+        for (tenant, hash) in &self.tenants {
+            if !plugins::valid_name(tenant) {
+                return Err(format!("invalid tenant name '{}': use 1 to 64 lowercase letters, digits, '-' or '_'", tenant));
+            }
+            if hash.len() != 64 || crate::signing::from_hex(hash).is_none() {
+                return Err(format!("{}: expected the API key's SHA-256 as 64 hex digits", tenant));
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_gating(&self) -> Result<(), String> {
+        for rule in &self.gating {
+            let known = |name: &String| {
+                ASPECTS.iter().any(|info| info.name == name) || Dimension::ALL.iter().any(|d| d.name() == name)
+            };
+            if let Some(name) = rule.skip.iter().find(|name| !known(name)) {
+                return Err(format!("unknown aspect or dimension '{}'", name));
+            }
+            if let Some(step) = rule.when.preprocessed_with.as_deref().filter(|step| !STEP_NAMES.contains(step)) {
+                return Err(format!("unknown preprocessing step '{}'", step));
+            }
+        }
+        Ok(())
+    }
+
+    // Requests outside every experiment are the control group, so the
+    // fractions can't add up to more than all of them
+    fn validate_experiments(&self) -> Result<(), String> {
+        if self.experiments.contains_key("control") {
+            return Err("'control' names the requests outside every experiment".to_string());
+        }
+        if let Some((name, _)) = self.experiments.iter().find(|(_, e)| !(0.0..=1.0).contains(&e.fraction)) {
+            return Err(format!("{}: fraction must be between 0 and 1", name));
+        }
+        let total: f64 = self.experiments.values().map(|e| e.fraction).sum();
+        if total > 1.0 + 1e-9 {
+            return Err(format!("fractions add up to {}, more than 1", total));
+        }
+        Ok(())
+    }
+
+    fn validate_transforms(&self) -> Result<(), String> {
+        for (aspect, transform) in &self.transforms {
+            if !ASPECTS.iter().any(|info| info.name == aspect) {
+                return Err(format!("unknown aspect '{}'", aspect));
+            }
+            transform.validate().map_err(|e| format!("{}: {}", aspect, e))?;
+        }
+        Ok(())
+    }
+
+    pub fn weight(&self, aspect: &str) -> f64 {
+        self.weights.get(aspect).copied().unwrap_or_else(|| aspects::default_weight(aspect))
+    }
+
+    pub fn transform(&self, aspect: &str) -> Transform {
+        self.transforms
+            .get(aspect)
+            .copied()
+            .unwrap_or_else(|| aspects::default_transform(aspect))
+    }
+
+    /// Resolves a request's `dictionary`, configured names first.
+    pub fn tokenizer_spec(&self, name: &str) -> Option<TokenizerSpec> {
+        if let Some(spec) = self.dictionaries.get(name) {
+            return Some(spec.clone());
+        }
+        Dictionary::ALL
+            .into_iter()
+            .find(|dictionary| dictionary.name() == name)
+            .map(|dictionary| TokenizerSpec {
+                dictionary,
+                user_dictionary: None,
+            })
+    }
+
+    /// Everything a request's `dictionary` may name.
+    pub fn dictionary_names(&self) -> Vec<String> {
+        let builtin = Dictionary::ALL
+            .iter()
+            .map(|d| d.name().to_string())
+            .filter(|name| !self.dictionaries.contains_key(name));
+        builtin.chain(self.dictionaries.keys().cloned()).collect()
+    }
+
+    fn validate_webhooks(&self) -> Result<(), String> {
+        for webhook in &self.webhooks {
+            if !webhook.url.starts_with("http://") && !webhook.url.starts_with("https://") {
+                return Err(format!("'{}': expected an http:// or https:// URL", webhook.url));
+            }
+            if !(0.0..=1.0).contains(&webhook.match_above) {
+                return Err(format!("{}: match_above must be between 0 and 1", webhook.url));
+            }
+            if webhook.attempts == 0 {
+                return Err(format!("{}: attempts must be at least 1", webhook.url));
+            }
+        }
+        Ok(())
+    }
+
+    // Each task needs what it maintains to be configured
+    fn validate_jobs(&self) -> Result<(), String> {
+        let mut names = BTreeSet::new();
+        for job in &self.jobs {
+            if job.name.is_empty() || !names.insert(job.name.as_str()) {
+                return Err(format!("job names must be unique and not empty, found '{}'", job.name));
+            }
+            if job.every_minutes == 0 {
+                return Err(format!("{}: every_minutes must be at least 1", job.name));
+            }
+            let missing = match &job.task {
+                JobTask::RetentionPurge { keep_days: 0 } => Some("keep_days of at least 1"),
+                JobTask::RetentionPurge { .. } => self.history.database.is_none().then_some("a [history] database"),
+                JobTask::BaselineRefresh => self.baseline.corpus.is_none().then_some("baseline.corpus"),
+                JobTask::CalibrationRefit { .. } => self.scoring.calibration.is_none().then_some("scoring.calibration to write to"),
+            };
+            if let Some(missing) = missing {
+                return Err(format!("{}: {} needs {}", job.name, job.task.name(), missing));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn pipeline(&self) -> Result<Pipeline, UnknownStep> {
+        Pipeline::from_names(&self.preprocessing)
+    }
+
+    pub fn token_filters(&self) -> Result<TokenFilters, UnknownFilter> {
+        TokenFilters::from_names(&self.token_filters)
+    }
+
+    /// Settings that differ from `other`, one `key: old -> new` line each,
+    /// with nested keys dotted (`evidence.min_tokens`).
+    pub fn diff(&self, other: &Config) -> Vec<String> {
+        let (old, new) = (flatten(self), flatten(other));
+        let keys: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+        let show = |value: Option<&serde_json::Value>| value.map_or_else(|| "(unset)".to_string(), |v| v.to_string());
+        keys.into_iter()
+            .filter(|key| old.get(*key) != new.get(*key))
+            .map(|key| format!("{}: {} -> {}", key, show(old.get(key)), show(new.get(key))))
+            .collect()
+    }
+
+    /// Content hash of these settings and the model version, so equal
+    /// configurations get the same id across restarts.
+    pub fn snapshot_id(&self) -> String {
+        let canonical = serde_json::to_value(self).unwrap_or_default().to_string();
+        // FNV-1a, whose output doesn't change between Rust releases
+        let hash = format!("{}\n{}", crate::MODEL_VERSION, canonical)
+            .bytes()
+            .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3));
+        format!("{:016x}", hash)
+    }
+
+    /// Loads the file named by `AUTHOR_COMPARER_CONFIG`, or the defaults when unset.
+    pub fn from_env() -> Result<Config, ConfigError> {
+        match std::env::var_os(CONFIG_ENV) {
+            Some(path) => Config::load(Path::new(&path)),
+            None => Ok(Config::default()),
+        }
+    }
+}
+
+/// Checks configured or per-request weights: known aspects, none negative.
+pub fn validate_weights(weights: &BTreeMap<String, f64>) -> Result<(), String> {
+    for (aspect, weight) in weights {
+        if !aspect.starts_with(plugins::ASPECT_PREFIX) && !ASPECTS.iter().any(|info| info.name == aspect) {
+            return Err(format!("unknown aspect '{}'", aspect));
+        }
+        if !weight.is_finite() || *weight < 0.0 {
+            return Err(format!("{}: weight must be a finite number of at least 0", aspect));
+        }
+    }
+    Ok(())
+}
+
+fn flatten(config: &Config) -> BTreeMap<String, serde_json::Value> {
+    fn walk(prefix: String, value: serde_json::Value, out: &mut BTreeMap<String, serde_json::Value>) {
+        match value {
+            serde_json::Value::Object(fields) => {
+                for (key, value) in fields {
+                    let key = if prefix.is_empty() { key } else { format!("{}.{}", prefix, key) };
+                    walk(key, value, out);
+                }
+            }
+            serde_json::Value::Null => {}
+            value => {
+                out.insert(prefix, value);
+            }
+        }
+    }
+    let mut out = BTreeMap::new();
+    walk(String::new(), serde_json::to_value(config).unwrap_or_default(), &mut out);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partial_config_keeps_defaults() {
+        let config: Config = toml::from_str("[evidence]\nmin_tokens = 20\n").unwrap();
+
+        assert_eq!(config.evidence.min_tokens, 20);
+        assert_eq!(config.evidence.min_sentences, EvidenceThresholds::default().min_sentences);
+        assert!(config.preprocessing.is_empty());
+    }
+
+    #[test]
+    fn test_transforms() {
+        let config: Config = toml::from_str(
+            "[transforms.\"Word Usage\"]\nkind = \"logistic\"\nmidpoint = 0.3\nsteepness = 10.0\n\n[transforms.Punctuation]\nkind = \"identity\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(config.transform("Word Usage"), Transform::Logistic { midpoint: 0.3, steepness: 10.0 });
+        assert_eq!(config.transform("Punctuation"), Transform::Identity);
+        assert_eq!(config.transform("Sentence Length"), Transform::Capped { cap: 0.5 });
+        assert!(config.validate_transforms().is_ok());
+
+        let (none, _) = config.transform("Word Usage").apply(0.0);
+        let (midpoint, _) = config.transform("Word Usage").apply(0.3);
+        assert!(none.abs() < 1e-12 && midpoint > 0.4 && midpoint < 0.5, "{} {}", none, midpoint);
+
+        let unknown: Config = toml::from_str("[transforms.Rhyme]\nkind = \"identity\"\n").unwrap();
+        assert!(unknown.validate_transforms().unwrap_err().contains("unknown aspect"));
+        let negative: Config = toml::from_str("[transforms.Punctuation]\nkind = \"capped\"\ncap = -1.0\n").unwrap();
+        assert!(negative.validate_transforms().is_err());
+    }
+
+    #[test]
+    fn test_token_filters() {
+        let config: Config = toml::from_str("token_filters = [\"lowercase\", \"fold_digits\"]\n").unwrap();
+        assert_eq!(config.token_filters().unwrap().names(), vec!["lowercase", "fold_digits"]);
+
+        let config: Config = toml::from_str("token_filters = [\"stem\"]\n").unwrap();
+        assert!(config.token_filters().is_err());
+    }
+
+    #[test]
+    fn test_scoring_backend() {
+        let config: Config = toml::from_str("[scoring]\nbackend = \"mahalanobis\"\nreference_corpus = \"corpus\"\n").unwrap();
+
+        assert_eq!(config.scoring.backend, Backend::Mahalanobis);
+        assert_eq!(config.scoring.reference_corpus, Some(PathBuf::from("corpus")));
+        assert_eq!(Config::default().scoring.backend, Backend::Weighted);
+    }
+
+    #[test]
+    fn test_dictionaries() {
+        let config: Config =
+            toml::from_str("[dictionaries.legal]\ndictionary = \"unidic\"\nuser_dictionary = \"dict/legal.csv\"\n").unwrap();
+
+        let legal = config.tokenizer_spec("legal").unwrap();
+        assert_eq!(legal.dictionary, Dictionary::Unidic);
+        assert_eq!(legal.user_dictionary, Some(PathBuf::from("dict/legal.csv")));
+        assert_eq!(config.tokenizer_spec("ipadic"), Some(TokenizerSpec::default()));
+        assert_eq!(config.tokenizer_spec("jumandic"), None);
+        assert_eq!(config.dictionary_names(), vec!["ipadic", "unidic", "legal"]);
+    }
+
+    #[test]
+    fn test_preprocessing_steps() {
+        let config: Config = toml::from_str("preprocessing = [\"normalize\", \"remove_quotes\"]\n").unwrap();
+        assert_eq!(config.pipeline().unwrap().names(), vec!["normalize", "remove_quotes"]);
+
+        let config: Config = toml::from_str("preprocessing = [\"stem\"]\n").unwrap();
+        assert!(config.pipeline().is_err());
+    }
+
+    #[test]
+    fn test_weights() {
+        let config: Config =
+            toml::from_str("[weights]\n\"Word Usage\" = 2.0\n[decision]\nsame_author_above = 0.7\n").unwrap();
+        assert!(validate_weights(&config.weights).is_ok());
+        assert_eq!(config.weight("Word Usage"), 2.0);
+        assert_eq!(config.weight("Sentence Length"), aspects::default_weight("Sentence Length"));
+        assert_eq!(config.decision.same_author_above, 0.7);
+
+        assert!(validate_weights(&BTreeMap::from([("Handwriting".to_string(), 1.0)])).is_err());
+        assert!(validate_weights(&BTreeMap::from([("Word Usage".to_string(), -1.0)])).is_err());
+    }
+
+    #[test]
+    fn test_experiments() {
+        let config: Config = toml::from_str("[experiments.strict]\nfraction = 0.2\nsame_author_above = 0.75\n").unwrap();
+        let strict = &config.experiments["strict"];
+        assert_eq!((strict.fraction, strict.decision.same_author_above), (0.2, 0.75));
+        assert!(config.validate_experiments().is_ok());
+
+        let control: Config = toml::from_str("[experiments.control]\nfraction = 0.1\n").unwrap();
+        assert!(control.validate_experiments().is_err());
+        let over: Config = toml::from_str("[experiments.a]\nfraction = 0.6\n[experiments.b]\nfraction = 0.6\n").unwrap();
+        assert!(over.validate_experiments().unwrap_err().contains("more than 1"));
+    }
+
+    #[test]
+    fn test_gating_rules() {
+        let config: Config = toml::from_str(
+            "[[gating]]\nskip = [\"Word Usage\"]\nwhen = { genres_differ = true }\n\n[[gating]]\nskip = [\"formatting\"]\nwhen = { preprocessed_with = \"normalize\" }\n",
+        )
+        .unwrap();
+        assert!(config.validate_gating().is_ok());
+        let [genre, whitespace] = [&config.gating[0], &config.gating[1]];
+
+        assert!(genre.skips("Word Usage") && !genre.skips("Punctuation"));
+        assert!(genre.when.matches(Some(["blog", "novel"]), &[]));
+        assert!(!genre.when.matches(Some(["blog", "blog"]), &[]) && !genre.when.matches(None, &[]));
+        assert!(whitespace.skips("Unusual Whitespace"));
+        assert!(whitespace.when.matches(None, &["normalize"]) && !whitespace.when.matches(None, &[]));
+        assert_eq!(whitespace.when.describe(), "skipped because the texts were preprocessed with normalize");
+
+        let unknown: Config = toml::from_str("[[gating]]\nskip = [\"Rhyme\"]\n").unwrap();
+        assert!(unknown.validate_gating().unwrap_err().contains("Rhyme"));
+        let step: Config = toml::from_str("[[gating]]\nskip = [\"lexical\"]\nwhen = { preprocessed_with = \"stem\" }\n").unwrap();
+        assert!(step.validate_gating().is_err());
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = Config::default();
+        let new: Config = toml::from_str("preprocessing = [\"normalize\"]\n[evidence]\nmin_tokens = 20\n[signing]\nkey = \"k\"\n").unwrap();
+
+        assert_eq!(
+            old.diff(&new),
+            vec![
+                "evidence.min_tokens: 5 -> 20",
+                "preprocessing: [] -> [\"normalize\"]",
+                "signing.key: (unset) -> \"k\"",
+            ]
+        );
+        assert!(new.diff(&new).is_empty());
+    }
+
+    #[test]
+    fn test_snapshot_id() {
+        let config = Config::default();
+        assert_eq!(config.snapshot_id(), Config::default().snapshot_id());
+        assert_eq!(config.snapshot_id().len(), 16);
+
+        let changed = Config {
+            preprocessing: vec!["normalize".to_string()],
+            ..Config::default()
+        };
+        assert_ne!(config.snapshot_id(), changed.snapshot_id());
+    }
+
+    #[test]
+    fn test_listen_addresses() {
+        assert_eq!(Config::default().listen.tcp_address(), Some(ListenConfig::DEFAULT_ADDRESS));
+
+        // A Unix socket alone replaces the default TCP address
+        let config: Config = toml::from_str("[listen]\nunix_socket = \"/run/ac.sock\"\n").unwrap();
+        assert_eq!(config.listen.tcp_address(), None);
+
+        let config: Config = toml::from_str("[listen]\nunix_socket = \"/run/ac.sock\"\naddress = \"0.0.0.0:9000\"\n").unwrap();
+        assert_eq!(config.listen.tcp_address(), Some("0.0.0.0:9000"));
+    }
+
+    #[test]
+    fn test_webhooks() {
+        let config: Config = toml::from_str(
+            "[[webhooks]]\nurl = \"https://example.com/hook\"\nevents = [\"job.completed\"]\n\n[[webhooks]]\nurl = \"http://localhost:9000\"\nmatch_above = 0.75\n",
+        )
+        .unwrap();
+        assert!(config.validate_webhooks().is_ok());
+        assert_eq!(config.webhooks[0].events, [WebhookEvent::JobCompleted]);
+        assert!(!config.webhooks[0].wants(WebhookEvent::ProfileMatch));
+        assert_eq!((config.webhooks[0].match_above, config.webhooks[0].attempts), (0.9, 5));
+        assert!(config.webhooks[1].wants(WebhookEvent::ProfileMatch));
+
+        for invalid in ["url = \"ftp://example.com\"", "url = \"https://a\"\nmatch_above = 2.0", "url = \"https://a\"\nattempts = 0"] {
+            let config: Config = toml::from_str(&format!("[[webhooks]]\n{}\n", invalid)).unwrap();
+            assert!(config.validate_webhooks().is_err(), "{}", invalid);
+        }
+        assert!(toml::from_str::<Config>("[[webhooks]]\nurl = \"https://a\"\nevents = [\"compare\"]\n").is_err());
+    }
+
+    #[test]
+    fn test_jobs() {
+        let config: Config = toml::from_str(
+            "[history]\ndatabase = \"h.db\"\n[scoring]\ncalibration = \"c.json\"\n\n[[jobs]]\nname = \"purge\"\nevery_minutes = 1440\ntask = \"retention_purge\"\nkeep_days = 90\n\n[[jobs]]\nname = \"refit\"\nevery_minutes = 10080\ntask = \"calibration_refit\"\ncorpus = \"labeled\"\n",
+        )
+        .unwrap();
+        assert!(config.validate_jobs().is_ok());
+        assert_eq!(config.jobs[0].task, JobTask::RetentionPurge { keep_days: 90 });
+        assert_eq!(
+            config.jobs[1].task,
+            JobTask::CalibrationRefit {
+                corpus: PathBuf::from("labeled"),
+                buckets: None
+            }
+        );
+
+        let unconfigured: Config = toml::from_str("[[jobs]]\nname = \"p\"\nevery_minutes = 60\ntask = \"retention_purge\"\nkeep_days = 7\n").unwrap();
+        assert!(unconfigured.validate_jobs().unwrap_err().contains("[history]"));
+        let unconfigured: Config = toml::from_str("[[jobs]]\nname = \"b\"\nevery_minutes = 60\ntask = \"baseline_refresh\"\n").unwrap();
+        assert!(unconfigured.validate_jobs().unwrap_err().contains("baseline.corpus"));
+        let twice = Config {
+            jobs: vec![config.jobs[0].clone(), config.jobs[0].clone()],
+            ..config.clone()
+        };
+        assert!(twice.validate_jobs().is_err());
+        assert!(toml::from_str::<Config>("[[jobs]]\nname = \"a\"\nevery_minutes = 60\ntask = \"reindex\"\n").is_err());
+    }
+}
@@ -0,0 +1,308 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LabeledText {
+    pub author: String,
+    pub text: String,
+    #[serde(default)]
+    pub genre: Option<String>,
+}
+
+/// A pre-paired verification problem, as distributed by PAN.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerificationPair {
+    pub id: String,
+    pub text1: String,
+    pub text2: String,
+    pub same_author: bool,
+}
+
+/// Benchmarks either label single texts, which are paired by the evaluation,
+/// or ship ready-made pairs.
+#[derive(Debug, Clone)]
+pub enum Dataset {
+    Texts(Vec<LabeledText>),
+    Pairs(Vec<VerificationPair>),
+}
+
+impl Dataset {
+    pub fn len(&self) -> usize {
+        match self {
+            Dataset::Texts(texts) => texts.len(),
+            Dataset::Pairs(pairs) => pairs.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[derive(Debug)]
+pub enum DatasetError {
+    Io(io::Error),
+    /// A malformed record, with its 1-based line number.
+    Parse { line: usize, message: String },
+    MissingColumn(String),
+    MissingTruth(String),
+}
+
+impl fmt::Display for DatasetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DatasetError::Io(e) => write!(f, "failed to read dataset: {}", e),
+            DatasetError::Parse { line, message } => write!(f, "invalid dataset at line {}: {}", line, message),
+            DatasetError::MissingColumn(name) => write!(f, "dataset has no '{}' column", name),
+            DatasetError::MissingTruth(id) => write!(f, "no truth entry for pair '{}'", id),
+        }
+    }
+}
+
+impl std::error::Error for DatasetError {}
+
+impl From<io::Error> for DatasetError {
+    fn from(e: io::Error) -> Self {
+        DatasetError::Io(e)
+    }
+}
+
+/// Reads a corpus laid out as one folder per author, each holding `.txt` files.
+pub fn load_author_dirs(dir: &Path) -> io::Result<Vec<LabeledText>> {
+    let mut authors: Vec<_> = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<io::Result<_>>()?;
+    authors.retain(|path| path.is_dir());
+    authors.sort();
+
+    let mut texts = Vec::new();
+    for author_dir in authors {
+        let author = author_dir
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        for text in load_text_files(&author_dir)? {
+            texts.push(LabeledText {
+                author: author.clone(),
+                text,
+                genre: None,
+            });
+        }
+    }
+    Ok(texts)
+}
+
+/// Paths of the `.txt` files directly inside `dir`, in file name order.
+pub fn text_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files: Vec<_> = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<io::Result<_>>()?;
+    files.retain(|path| path.extension().is_some_and(|ext| ext == "txt"));
+    files.sort();
+    Ok(files)
+}
+
+/// Reads every `.txt` file directly inside `dir`, in file name order.
+pub fn load_text_files(dir: &Path) -> io::Result<Vec<String>> {
+    text_files(dir)?.into_iter().map(std::fs::read_to_string).collect()
+}
+
+// Accepted names for the label column, in order of preference
+const LABEL_COLUMNS: &[&str] = &["author", "label"];
+
+/// Splits RFC 4180 CSV into records, honouring quoted fields with embedded
+/// commas, newlines and doubled quotes. Returns each record with its line number.
+pub(crate) fn parse_csv(input: &str) -> Vec<(usize, Vec<String>)> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut record_line = 1;
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == '\n' {
+            line += 1;
+        }
+        match (in_quotes, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            (true, '"') => in_quotes = false,
+            (true, _) => field.push(c),
+            (false, '"') if field.is_empty() => in_quotes = true,
+            (false, ',') => record.push(std::mem::take(&mut field)),
+            (false, '\r') if chars.peek() == Some(&'\n') => {}
+            (false, '\n') => {
+                record.push(std::mem::take(&mut field));
+                records.push((record_line, std::mem::take(&mut record)));
+                record_line = line;
+            }
+            (false, _) => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push((record_line, record));
+    }
+    // Blank lines carry no record
+    records.retain(|(_, r)| !(r.len() == 1 && r[0].is_empty()));
+    records
+}
+
+/// Reads a CSV file with a header row naming a `text` column and an `author`
+/// (or `label`) column, plus an optional `genre` column.
+pub fn load_csv(path: &Path) -> Result<Vec<LabeledText>, DatasetError> {
+    let contents = std::fs::read_to_string(path)?;
+    let mut records = parse_csv(contents.trim_start_matches('\u{FEFF}')).into_iter();
+    let Some((_, header)) = records.next() else {
+        return Ok(Vec::new());
+    };
+    let column = |name: &str| header.iter().position(|h| h.trim().eq_ignore_ascii_case(name));
+    let text_column = column("text").ok_or_else(|| DatasetError::MissingColumn("text".to_string()))?;
+    let label_column = LABEL_COLUMNS
+        .iter()
+        .find_map(|name| column(name))
+        .ok_or_else(|| DatasetError::MissingColumn(LABEL_COLUMNS.join("' or '")))?;
+    let genre_column = column("genre");
+
+    records
+        .map(|(line, record)| match (record.get(label_column), record.get(text_column)) {
+            (Some(author), Some(text)) => Ok(LabeledText {
+                author: author.clone(),
+                text: text.clone(),
+                genre: genre_column
+                    .and_then(|i| record.get(i))
+                    .filter(|genre| !genre.is_empty())
+                    .cloned(),
+            }),
+            _ => Err(DatasetError::Parse {
+                line,
+                message: format!("expected {} fields, found {}", header.len(), record.len()),
+            }),
+        })
+        .collect()
+}
+
+#[derive(Serialize, Deserialize)]
+struct PanPair {
+    id: String,
+    pair: (String, String),
+}
+
+#[derive(Serialize, Deserialize)]
+struct PanTruth {
+    id: String,
+    same: bool,
+}
+
+fn read_jsonl<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<Vec<T>, DatasetError> {
+    let contents = std::fs::read_to_string(path)?;
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line).map_err(|e| DatasetError::Parse {
+                line: i + 1,
+                message: e.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Reads a PAN authorship verification dataset: `pairs` holds one
+/// `{"id", "pair": [text1, text2]}` object per line and `truth` one
+/// `{"id", "same"}` object per line.
+pub fn load_pan(pairs: &Path, truth: &Path) -> Result<Vec<VerificationPair>, DatasetError> {
+    let truth: HashMap<String, bool> = read_jsonl::<PanTruth>(truth)?
+        .into_iter()
+        .map(|t| (t.id, t.same))
+        .collect();
+
+    read_jsonl::<PanPair>(pairs)?
+        .into_iter()
+        .map(|p| {
+            let same_author = *truth.get(&p.id).ok_or_else(|| DatasetError::MissingTruth(p.id.clone()))?;
+            Ok(VerificationPair {
+                id: p.id,
+                text1: p.pair.0,
+                text2: p.pair.1,
+                same_author,
+            })
+        })
+        .collect()
+}
+
+/// Writes pairs in the layout `load_pan` reads back.
+pub fn write_pan(pairs: &[VerificationPair], pairs_path: &Path, truth_path: &Path) -> io::Result<()> {
+    let mut pairs_out = String::new();
+    let mut truth_out = String::new();
+    for pair in pairs {
+        let record = PanPair {
+            id: pair.id.clone(),
+            pair: (pair.text1.clone(), pair.text2.clone()),
+        };
+        let truth = PanTruth {
+            id: pair.id.clone(),
+            same: pair.same_author,
+        };
+        pairs_out.push_str(&serde_json::to_string(&record).map_err(io::Error::other)?);
+        pairs_out.push('\n');
+        truth_out.push_str(&serde_json::to_string(&truth).map_err(io::Error::other)?);
+        truth_out.push('\n');
+    }
+    std::fs::write(pairs_path, pairs_out)?;
+    std::fs::write(truth_path, truth_out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_csv() {
+        let records = parse_csv("author,text\r\na,\"こんにちは、\"\"世界\"\"\"\nb,\"一行目\n二行目\"\n\nc,plain");
+        let fields: Vec<Vec<&str>> = records.iter().map(|(_, r)| r.iter().map(String::as_str).collect()).collect();
+
+        assert_eq!(
+            fields,
+            vec![
+                vec!["author", "text"],
+                vec!["a", "こんにちは、\"世界\""],
+                vec!["b", "一行目\n二行目"],
+                vec!["c", "plain"],
+            ]
+        );
+        let lines: Vec<usize> = records.iter().map(|(line, _)| *line).collect();
+        assert_eq!(lines, vec![1, 2, 3, 6]);
+    }
+
+    #[test]
+    fn test_load_pan() {
+        let dir = std::env::temp_dir().join(format!("author-comparer-pan-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let pairs = dir.join("pairs.jsonl");
+        let truth = dir.join("truth.jsonl");
+        std::fs::write(
+            &pairs,
+            "{\"id\": \"x1\", \"fandoms\": [\"a\", \"b\"], \"pair\": [\"一つ目\", \"二つ目\"]}\n{\"id\": \"x2\", \"pair\": [\"三\", \"四\"]}\n",
+        )
+        .unwrap();
+        std::fs::write(&truth, "{\"id\": \"x2\", \"same\": false}\n{\"id\": \"x1\", \"same\": true, \"authors\": [\"1\", \"1\"]}\n").unwrap();
+
+        let loaded = load_pan(&pairs, &truth).unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!((loaded[0].id.as_str(), loaded[0].text2.as_str(), loaded[0].same_author), ("x1", "二つ目", true));
+        assert!(!loaded[1].same_author);
+
+        std::fs::write(&truth, "{\"id\": \"x1\", \"same\": true}\n").unwrap();
+        assert!(matches!(load_pan(&pairs, &truth), Err(DatasetError::MissingTruth(id)) if id == "x2"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
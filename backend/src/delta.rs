@@ -0,0 +1,91 @@
+use crate::comparison::DetailedResult;
+use serde::{Deserialize, Serialize};
+
+/// How one aspect's difference moved between two analyses of texts that
+/// changed in between, e.g. successive saves of a draft.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AspectDelta {
+    pub aspect: String,
+    /// None when the aspect couldn't be computed.
+    pub before: Option<f64>,
+    pub after: Option<f64>,
+}
+
+impl AspectDelta {
+    /// Signed change of the difference; None unless both sides were computed.
+    pub fn change(&self) -> Option<f64> {
+        Some(self.after? - self.before?)
+    }
+}
+
+/// Aspects whose difference moved by at least `threshold`, or that became
+/// computable or stopped being so, largest move first.
+pub fn aspect_deltas(before: &[DetailedResult], after: &[DetailedResult], threshold: f64) -> Vec<AspectDelta> {
+    let mut deltas: Vec<AspectDelta> = after
+        .iter()
+        .map(|result| AspectDelta {
+            aspect: result.aspect.clone(),
+            before: before.iter().find(|r| r.aspect == result.aspect).and_then(|r| r.difference),
+            after: result.difference,
+        })
+        .chain(
+            before
+                .iter()
+                .filter(|r| !after.iter().any(|a| a.aspect == r.aspect))
+                .map(|r| AspectDelta {
+                    aspect: r.aspect.clone(),
+                    before: r.difference,
+                    after: None,
+                }),
+        )
+        .filter(|delta| match delta.change() {
+            Some(change) => change.abs() >= threshold,
+            None => delta.before.is_some() != delta.after.is_some(),
+        })
+        .collect();
+    // Appearing or vanishing aspects sort first
+    let magnitude = |delta: &AspectDelta| delta.change().map_or(f64::INFINITY, f64::abs);
+    deltas.sort_by(|a, b| magnitude(b).total_cmp(&magnitude(a)));
+    deltas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(aspect: &str, difference: Option<f64>) -> DetailedResult {
+        DetailedResult {
+            aspect: aspect.to_string(),
+            difference,
+            explanation: String::new(),
+            weight: 1.0,
+            capped: false,
+            reliability: 1.0,
+            missing_reason: None,
+            percentile: None,
+        }
+    }
+
+    #[test]
+    fn test_aspect_deltas() {
+        let before = [
+            result("Sentence Length", Some(0.3)),
+            result("Punctuation", Some(0.1)),
+            result("Verb Usage", Some(0.2)),
+            result("Code Switching", None),
+        ];
+        let after = [
+            result("Sentence Length", Some(0.1)),
+            result("Punctuation", Some(0.105)),
+            result("Verb Usage", Some(0.25)),
+            result("Code Switching", Some(0.4)),
+        ];
+
+        let deltas = aspect_deltas(&before, &after, 0.01);
+        let aspects: Vec<&str> = deltas.iter().map(|d| d.aspect.as_str()).collect();
+        assert_eq!(aspects, ["Code Switching", "Sentence Length", "Verb Usage"]);
+        assert!((deltas[1].change().unwrap() + 0.2).abs() < 1e-9);
+        assert_eq!(deltas[0].change(), None);
+        assert!(aspect_deltas(&after, &after, 0.01).is_empty());
+    }
+}
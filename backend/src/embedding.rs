@@ -0,0 +1,160 @@
+use crate::config::{ConfigError, EmbeddingConfig};
+use crate::language;
+use std::fmt;
+use std::path::Path;
+
+// Only the leading sentences are embedded; the style vector is their mean
+const MAX_SENTENCES: usize = 32;
+// Input limit of the small BERT-style models this is meant for
+#[cfg(feature = "embeddings")]
+const MAX_TOKENS: usize = 128;
+
+/// Sentence-embedding model run through ONNX Runtime.
+#[cfg(feature = "embeddings")]
+pub struct Embedder {
+    session: ort::Session,
+    tokenizer: tokenizers::Tokenizer,
+    // Not every exported model takes segment ids
+    uses_token_types: bool,
+}
+
+/// Uninhabited without the `embeddings` feature, so no model can be configured.
+#[cfg(not(feature = "embeddings"))]
+pub enum Embedder {}
+
+impl fmt::Debug for Embedder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Embedder")
+    }
+}
+
+impl Embedder {
+    /// Loads the configured model, or returns `None` when there is none.
+    pub fn from_config(config: &EmbeddingConfig) -> Result<Option<Embedder>, ConfigError> {
+        let Some(model) = &config.model else {
+            return Ok(None);
+        };
+        let tokenizer = match &config.tokenizer {
+            Some(tokenizer) => tokenizer.clone(),
+            None => model.with_file_name("tokenizer.json"),
+        };
+        Embedder::load(model, &tokenizer).map(Some)
+    }
+
+    #[cfg(feature = "embeddings")]
+    fn load(model: &Path, tokenizer: &Path) -> Result<Embedder, ConfigError> {
+        let error = |e: &dyn fmt::Display| ConfigError::Embedding(format!("{}: {}", model.display(), e));
+        let session = ort::Session::builder()
+            .and_then(|builder| builder.commit_from_file(model))
+            .map_err(|e| error(&e))?;
+        let tokenizer = tokenizers::Tokenizer::from_file(tokenizer).map_err(|e| error(&e))?;
+        let uses_token_types = session.inputs.iter().any(|input| input.name == "token_type_ids");
+        Ok(Embedder {
+            session,
+            tokenizer,
+            uses_token_types,
+        })
+    }
+
+    #[cfg(not(feature = "embeddings"))]
+    fn load(_model: &Path, _tokenizer: &Path) -> Result<Embedder, ConfigError> {
+        Err(ConfigError::Embedding("built without the `embeddings` feature".to_string()))
+    }
+
+    /// Mean-pooled embedding of one sentence, L2-normalized.
+    #[cfg(feature = "embeddings")]
+    fn embed(&self, sentence: &str) -> Result<Vec<f32>, String> {
+        use ndarray::Array2;
+
+        let encoding = self.tokenizer.encode(sentence, true).map_err(|e| e.to_string())?;
+        let len = encoding.get_ids().len().min(MAX_TOKENS);
+        let tensor = |values: &[u32]| {
+            Array2::from_shape_vec((1, len), values[..len].iter().map(|&v| v as i64).collect())
+                .map_err(|e| e.to_string())
+        };
+        let ids = tensor(encoding.get_ids())?;
+        let mask = tensor(encoding.get_attention_mask())?;
+        let types = tensor(encoding.get_type_ids())?;
+
+        let inputs = if self.uses_token_types {
+            ort::inputs!["input_ids" => ids, "attention_mask" => mask, "token_type_ids" => types]
+        } else {
+            ort::inputs!["input_ids" => ids, "attention_mask" => mask]
+        }
+        .map_err(|e| e.to_string())?;
+        let outputs = self.session.run(inputs).map_err(|e| e.to_string())?;
+        // Token embeddings, shaped [1, tokens, dimensions]
+        let hidden = outputs[0].try_extract_tensor::<f32>().map_err(|e| e.to_string())?;
+        let dimensions = *hidden.shape().last().ok_or("model returned a scalar")?;
+        let values: Vec<f32> = hidden.iter().copied().collect();
+
+        let mut pooled = vec![0.0; dimensions];
+        let mut counted: f32 = 0.0;
+        for (token, &attended) in encoding.get_attention_mask()[..len].iter().enumerate() {
+            if attended == 0 {
+                continue;
+            }
+            let row = &values[token * dimensions..(token + 1) * dimensions];
+            pooled.iter_mut().zip(row).for_each(|(p, v)| *p += v);
+            counted += 1.0;
+        }
+        pooled.iter_mut().for_each(|p| *p /= counted.max(1.0));
+        Ok(normalized(pooled))
+    }
+
+    #[cfg(not(feature = "embeddings"))]
+    fn embed(&self, _sentence: &str) -> Result<Vec<f32>, String> {
+        match *self {}
+    }
+
+    /// Averages the embeddings of the text's leading sentences into one style vector.
+    pub fn style_vector(&self, text: &str) -> Result<Vec<f32>, String> {
+        let mut sum: Vec<f32> = Vec::new();
+        for segment in language::segment(text).iter().take(MAX_SENTENCES) {
+            let sentence = segment.text.trim();
+            if sentence.is_empty() {
+                continue;
+            }
+            let embedding = self.embed(sentence)?;
+            if sum.is_empty() {
+                sum = embedding;
+            } else {
+                sum.iter_mut().zip(&embedding).for_each(|(s, e)| *s += e);
+            }
+        }
+        if sum.is_empty() {
+            return Err("no sentences to embed".to_string());
+        }
+        Ok(normalized(sum))
+    }
+}
+
+fn normalized(mut vector: Vec<f32>) -> Vec<f32> {
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|v| *v /= norm);
+    }
+    vector
+}
+
+/// Style difference on the usual 0–1 scale: 0 for identical directions.
+pub fn embedding_difference(vector1: &[f32], vector2: &[f32]) -> f64 {
+    let cosine: f32 = vector1.iter().zip(vector2).map(|(a, b)| a * b).sum();
+    (1.0 - cosine as f64).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedding_difference() {
+        let a = normalized(vec![3.0, 4.0]);
+        assert!((a[0] - 0.6).abs() < 1e-6);
+        assert_eq!(embedding_difference(&a, &a), 0.0);
+        assert!((embedding_difference(&[1.0, 0.0], &[0.0, 1.0]) - 1.0).abs() < 1e-9);
+        // Opposite directions are no more different than unrelated ones
+        assert_eq!(embedding_difference(&[1.0, 0.0], &[-1.0, 0.0]), 1.0);
+        assert!(Embedder::from_config(&EmbeddingConfig::default()).unwrap().is_none());
+    }
+}
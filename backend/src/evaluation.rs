@@ -0,0 +1,209 @@
+use crate::comparison::{compare_extracted, Verdict};
+use crate::config::Config;
+use crate::datasets::{Dataset, LabeledText};
+use crate::features::{extract_features, TextFeatures};
+use crate::numerics::safe_div;
+use crate::scoring::Scorer;
+use crate::tokenizer::{LinderaResult, Tokenizer};
+use serde::{Deserialize, Serialize};
+
+/// A pair of corpus indices and whether both texts share an author.
+pub type LabeledPair = (usize, usize, bool);
+
+/// Deals each author's texts round-robin into `folds` folds and pairs every two
+/// texts within a fold, so no text is scored in more than one fold.
+pub fn fold_pairs(texts: &[LabeledText], folds: usize) -> Vec<Vec<LabeledPair>> {
+    let folds = folds.max(1);
+    let mut members: Vec<Vec<usize>> = vec![Vec::new(); folds];
+    let mut seen: Vec<(&str, usize)> = Vec::new();
+    for (i, text) in texts.iter().enumerate() {
+        let position = match seen.iter_mut().find(|(author, _)| *author == text.author) {
+            Some((_, count)) => {
+                *count += 1;
+                *count
+            }
+            None => {
+                seen.push((&text.author, 0));
+                0
+            }
+        };
+        members[position % folds].push(i);
+    }
+
+    members
+        .iter()
+        .map(|fold| {
+            let mut pairs = Vec::new();
+            for (k, &i) in fold.iter().enumerate() {
+                for &j in &fold[k + 1..] {
+                    pairs.push((i, j, texts[i].author == texts[j].author));
+                }
+            }
+            pairs
+        })
+        .collect()
+}
+
+/// Confusion counts with "same author" as the positive class. Pairs the
+/// comparison declined to judge are counted separately.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Confusion {
+    pub true_positives: usize,
+    pub false_positives: usize,
+    pub true_negatives: usize,
+    pub false_negatives: usize,
+    pub insufficient: usize,
+}
+
+impl Confusion {
+    pub fn record(&mut self, same_author: bool, verdict: Verdict) {
+        match (same_author, verdict) {
+            // An identical pair says nothing about how well style is told apart
+            (_, Verdict::InsufficientData | Verdict::IdenticalText | Verdict::Inconclusive) => self.insufficient += 1,
+            (true, Verdict::SameAuthor) => self.true_positives += 1,
+            (false, Verdict::SameAuthor) => self.false_positives += 1,
+            (false, Verdict::DifferentAuthor) => self.true_negatives += 1,
+            (true, Verdict::DifferentAuthor) => self.false_negatives += 1,
+        }
+    }
+
+    pub fn merge(&mut self, other: &Confusion) {
+        self.true_positives += other.true_positives;
+        self.false_positives += other.false_positives;
+        self.true_negatives += other.true_negatives;
+        self.false_negatives += other.false_negatives;
+        self.insufficient += other.insufficient;
+    }
+
+    pub fn judged(&self) -> usize {
+        self.true_positives + self.false_positives + self.true_negatives + self.false_negatives
+    }
+
+    pub fn accuracy(&self) -> f64 {
+        safe_div((self.true_positives + self.true_negatives) as f64, self.judged() as f64, 0.0)
+    }
+
+    pub fn precision(&self) -> f64 {
+        safe_div(self.true_positives as f64, (self.true_positives + self.false_positives) as f64, 0.0)
+    }
+
+    pub fn recall(&self) -> f64 {
+        safe_div(self.true_positives as f64, (self.true_positives + self.false_negatives) as f64, 0.0)
+    }
+
+    pub fn f1(&self) -> f64 {
+        let (precision, recall) = (self.precision(), self.recall());
+        safe_div(2.0 * precision * recall, precision + recall, 0.0)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FoldResult {
+    pub fold: usize,
+    pub pairs: usize,
+    pub confusion: Confusion,
+}
+
+fn score_fold(fold: usize, scored: impl Iterator<Item = (bool, Verdict)>) -> FoldResult {
+    let mut confusion = Confusion::default();
+    let mut pairs = 0;
+    for (same_author, verdict) in scored {
+        confusion.record(same_author, verdict);
+        pairs += 1;
+    }
+    FoldResult { fold, pairs, confusion }
+}
+
+/// Runs the comparison over every fold's pairs. Labeled texts are paired
+/// within their fold and tokenized once; ready-made pairs are dealt into folds
+/// in order.
+pub fn cross_validate(
+    dataset: &Dataset,
+    folds: usize,
+    tokenizer: &Tokenizer,
+    config: &Config,
+    scorer: &dyn Scorer,
+) -> LinderaResult<Vec<FoldResult>> {
+    let compare = |text1: &TextFeatures, text2: &TextFeatures| compare_extracted(text1, text2, config, scorer).verdict;
+
+    match dataset {
+        Dataset::Texts(texts) => {
+            let features: Vec<TextFeatures> = texts
+                .iter()
+                .map(|t| extract_features(&t.text, tokenizer))
+                .collect::<LinderaResult<_>>()?;
+            Ok(fold_pairs(texts, folds)
+                .into_iter()
+                .enumerate()
+                .map(|(fold, pairs)| {
+                    score_fold(
+                        fold,
+                        pairs
+                            .into_iter()
+                            .map(|(i, j, same_author)| (same_author, compare(&features[i], &features[j]))),
+                    )
+                })
+                .collect())
+        }
+        Dataset::Pairs(pairs) => {
+            let folds = folds.max(1);
+            (0..folds)
+                .map(|fold| {
+                    let scored = pairs
+                        .iter()
+                        .skip(fold)
+                        .step_by(folds)
+                        .map(|pair| {
+                            let features1 = extract_features(&pair.text1, tokenizer)?;
+                            let features2 = extract_features(&pair.text2, tokenizer)?;
+                            Ok((pair.same_author, compare(&features1, &features2)))
+                        })
+                        .collect::<LinderaResult<Vec<_>>>()?;
+                    Ok(score_fold(fold, scored.into_iter()))
+                })
+                .collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(author: &str) -> LabeledText {
+        LabeledText {
+            author: author.to_string(),
+            text: String::new(),
+            genre: None,
+        }
+    }
+
+    #[test]
+    fn test_fold_pairs() {
+        let texts = vec![text("a"), text("a"), text("b"), text("b"), text("a"), text("b")];
+        let folds = fold_pairs(&texts, 2);
+
+        // Fold 0 gets the first and third text of each author, fold 1 the second
+        assert_eq!(folds[0], vec![(0, 2, false), (0, 4, true), (0, 5, false), (2, 4, false), (2, 5, true), (4, 5, false)]);
+        assert_eq!(folds[1], vec![(1, 3, false)]);
+    }
+
+    #[test]
+    fn test_confusion_metrics() {
+        let mut confusion = Confusion::default();
+        confusion.record(true, Verdict::SameAuthor);
+        confusion.record(true, Verdict::DifferentAuthor);
+        confusion.record(false, Verdict::DifferentAuthor);
+        confusion.record(false, Verdict::SameAuthor);
+        confusion.record(false, Verdict::DifferentAuthor);
+        confusion.record(true, Verdict::InsufficientData);
+
+        assert_eq!(confusion.judged(), 5);
+        assert_eq!(confusion.insufficient, 1);
+        assert!((confusion.accuracy() - 0.6).abs() < 1e-9);
+        assert!((confusion.precision() - 0.5).abs() < 1e-9);
+        assert!((confusion.recall() - 0.5).abs() < 1e-9);
+        assert!((confusion.f1() - 0.5).abs() < 1e-9);
+        assert_eq!(Confusion::default().f1(), 0.0);
+    }
+}
@@ -0,0 +1,145 @@
+use crate::comparison::{calculate_confidence, compare_features_with_config, confidence_scale, normalized_difference, DetailedResult};
+use crate::config::Config;
+use crate::features::TextFeatures;
+use crate::numerics::{clamp, safe_div};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// How one aspect feeds into the weighted confidence.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct AspectExplanation {
+    pub aspect: String,
+    /// The feature both texts are compared on, for aspects built on a single number.
+    pub values: Option<[f64; 2]>,
+    pub difference: Option<f64>,
+    /// Difference as a share of the largest one the aspect's transform allows.
+    pub normalized_difference: Option<f64>,
+    pub weight: f64,
+    /// This aspect's share of the confidence, before it is clamped to 0–1.
+    pub contribution: f64,
+    /// Confidence if the texts did not differ in this aspect at all.
+    pub confidence_if_matched: Option<f64>,
+    pub counterfactual: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Explanation {
+    /// Confidence of the weighted backend, which the decomposition follows.
+    pub confidence: f64,
+    pub aspects: Vec<AspectExplanation>,
+}
+
+fn values(aspect: &str, features1: &TextFeatures, features2: &TextFeatures) -> Option<[f64; 2]> {
+    let value: fn(&TextFeatures) -> f64 = match aspect {
+        "Sentence Length" => |f| f.avg_sentence_length,
+        "Particle Usage" => |f| f.particle_ratio,
+        "Verb Usage" => |f| f.verb_ratio,
+        "Adjective Usage" => |f| f.adjective_ratio,
+        "Punctuation" => |f| f.punctuation_ratio,
+        "Vocabulary Richness" => |f| f.unique_words_ratio,
+        "Code Switching" => |f| f.english_segment_ratio,
+        "Unusual Whitespace" => |f| f.unusual_whitespace_ratio,
+        _ => return None,
+    };
+    Some([value(features1), value(features2)])
+}
+
+/// Breaks the weighted confidence down by aspect, with the confidence each
+/// aspect would leave if it matched exactly.
+pub fn explain(features1: &TextFeatures, features2: &TextFeatures, config: &Config) -> Explanation {
+    let details = compare_features_with_config(features1, features2, config);
+    decompose(&details, config, |aspect| values(aspect, features1, features2))
+}
+
+/// The same breakdown for aspects already compared, such as a stored
+/// result's, whose features are no longer at hand to report values from.
+pub fn explain_details(details: &[DetailedResult], config: &Config) -> Explanation {
+    decompose(details, config, |_| None)
+}
+
+fn decompose(details: &[DetailedResult], config: &Config, values: impl Fn(&str) -> Option<[f64; 2]>) -> Explanation {
+    let confidence = calculate_confidence(details);
+    // Mirrors calculate_confidence: each computed aspect adds (1 - difference) * weight / scale
+    let scale = confidence_scale(details);
+
+    let aspects = details
+        .iter()
+        .enumerate()
+        .map(|(i, detail)| {
+            let contribution = detail
+                .difference
+                .map_or(0.0, |d| safe_div((1.0 - clamp(d, 0.0, 1.0)) * detail.weight, scale, 0.0));
+            let confidence_if_matched = detail.difference.map(|_| {
+                let mut matched = details.to_vec();
+                matched[i].difference = Some(0.0);
+                calculate_confidence(&matched)
+            });
+            AspectExplanation {
+                aspect: detail.aspect.clone(),
+                values: values(&detail.aspect),
+                difference: detail.difference,
+                normalized_difference: normalized_difference(detail, config),
+                weight: detail.weight,
+                contribution,
+                confidence_if_matched,
+                counterfactual: confidence_if_matched
+                    .map(|c| format!("if {} matched, confidence would be {:.2}", detail.aspect.to_lowercase(), c)),
+            }
+        })
+        .collect();
+
+    Explanation { confidence, aspects }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::features::PosCounts;
+    use std::collections::HashMap;
+
+    fn features(avg_sentence_length: f64) -> TextFeatures {
+        TextFeatures {
+            word_frequencies: HashMap::from([("公園".to_string(), 1.0)]),
+            particle_ratio: 0.3,
+            verb_ratio: 0.2,
+            adjective_ratio: 0.1,
+            unique_words_ratio: 0.8,
+            avg_sentence_length,
+            punctuation_ratio: 0.1,
+            english_segment_ratio: 0.0,
+            symbol_ratio: 0.0,
+            unusual_whitespace_ratio: 0.0,
+            token_count: 20,
+            sentence_count: 2,
+            pos_counts: PosCounts {
+                tagged: 20,
+                particles: 3,
+                verbs: 2,
+                adjectives: 1,
+            },
+            pos_distribution: Default::default(),
+            char_ngrams: Default::default(),
+            pos_ngrams: Default::default(),
+            particle_distribution: Default::default(),
+            register: None,
+            script_ratios: None,
+        }
+    }
+
+    #[test]
+    fn test_explain() {
+        let explanation = explain(&features(10.0), &features(15.0), &Config::default());
+        let total: f64 = explanation.aspects.iter().map(|a| a.contribution).sum();
+        assert!((total - explanation.confidence).abs() < 1e-9);
+
+        let sentence_length = explanation.aspects.iter().find(|a| a.aspect == "Sentence Length").unwrap();
+        assert_eq!(sentence_length.values, Some([10.0, 15.0]));
+        assert!(sentence_length.confidence_if_matched.unwrap() > explanation.confidence);
+        assert!(sentence_length.counterfactual.as_ref().unwrap().starts_with("if sentence length matched"));
+
+        // Aspects that already match change nothing
+        let word_usage = &explanation.aspects[0];
+        assert_eq!(word_usage.values, None);
+        assert_eq!(word_usage.confidence_if_matched, Some(explanation.confidence));
+    }
+}
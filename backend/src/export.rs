@@ -0,0 +1,261 @@
+use crate::datasets::LabeledText;
+use crate::features::{extract_features, TextFeatures, FEATURE_SCHEMA_VERSION};
+use crate::tokenizer::{LinderaResult, Tokenizer};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// Scalar features in export column order.
+pub const SCALAR_FEATURES: &[&str] = &[
+    "particle_ratio",
+    "verb_ratio",
+    "adjective_ratio",
+    "unique_words_ratio",
+    "avg_sentence_length",
+    "punctuation_ratio",
+    "english_segment_ratio",
+    "symbol_ratio",
+    "token_count",
+    "sentence_count",
+];
+
+// Word frequency columns are named with this prefix
+const WORD_PREFIX: &str = "word:";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Arff,
+    Libsvm,
+    Parquet,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Arff | ExportFormat::Libsvm => "text/plain; charset=utf-8",
+            ExportFormat::Parquet => "application/vnd.apache.parquet",
+        }
+    }
+}
+
+/// One row per text: the scalar features followed by the relative frequency
+/// of each vocabulary word.
+#[derive(Debug, Clone)]
+pub struct FeatureTable {
+    pub columns: Vec<String>,
+    pub labels: Vec<String>,
+    pub rows: Vec<Vec<f64>>,
+}
+
+fn scalar_values(features: &TextFeatures) -> [f64; SCALAR_FEATURES.len()] {
+    [
+        features.particle_ratio,
+        features.verb_ratio,
+        features.adjective_ratio,
+        features.unique_words_ratio,
+        features.avg_sentence_length,
+        features.punctuation_ratio,
+        features.english_segment_ratio,
+        features.symbol_ratio,
+        features.token_count as f64,
+        features.sentence_count as f64,
+    ]
+}
+
+/// Extracts features for every text. The vocabulary is the `vocabulary` words
+/// used by the most texts, so columns stay comparable across rows.
+pub fn feature_table(texts: &[LabeledText], vocabulary: usize, tokenizer: &Tokenizer) -> LinderaResult<FeatureTable> {
+    let features = texts.iter().map(|t| extract_features(&t.text, tokenizer)).collect::<LinderaResult<Vec<TextFeatures>>>()?;
+
+    let mut document_frequency: HashMap<&str, usize> = HashMap::new();
+    for f in &features {
+        for word in f.word_frequencies.keys() {
+            *document_frequency.entry(word).or_default() += 1;
+        }
+    }
+    let mut words: Vec<(&str, usize)> = document_frequency.into_iter().collect();
+    words.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    words.truncate(vocabulary);
+
+    let columns = SCALAR_FEATURES
+        .iter()
+        .map(|name| name.to_string())
+        .chain(words.iter().map(|(word, _)| format!("{}{}", WORD_PREFIX, word)))
+        .collect();
+    let rows = features
+        .iter()
+        .map(|f| {
+            let mut row = scalar_values(f).to_vec();
+            row.extend(words.iter().map(|(word, _)| *f.word_frequencies.get(*word).unwrap_or(&0.0)));
+            row
+        })
+        .collect();
+
+    Ok(FeatureTable {
+        columns,
+        labels: texts.iter().map(|t| t.author.clone()).collect(),
+        rows,
+    })
+}
+
+impl FeatureTable {
+    /// Distinct labels in order of first appearance; LibSVM class `i + 1` is `classes()[i]`.
+    pub fn classes(&self) -> Vec<&str> {
+        let mut classes: Vec<&str> = Vec::new();
+        for label in &self.labels {
+            if !classes.contains(&label.as_str()) {
+                classes.push(label);
+            }
+        }
+        classes
+    }
+
+    pub fn write(&self, format: ExportFormat, out: &mut (impl Write + Send)) -> io::Result<()> {
+        match format {
+            ExportFormat::Arff => self.write_arff(out),
+            ExportFormat::Libsvm => self.write_libsvm(out),
+            ExportFormat::Parquet => self.write_parquet(out),
+        }
+    }
+
+    pub fn write_arff(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "% feature_schema_version: {}", FEATURE_SCHEMA_VERSION)?;
+        writeln!(out, "@RELATION author_features")?;
+        writeln!(out)?;
+        for column in &self.columns {
+            writeln!(out, "@ATTRIBUTE {} NUMERIC", arff_quote(column))?;
+        }
+        let classes: Vec<String> = self.classes().into_iter().map(arff_quote).collect();
+        writeln!(out, "@ATTRIBUTE author {{{}}}", classes.join(","))?;
+        writeln!(out)?;
+        writeln!(out, "@DATA")?;
+        for (label, row) in self.labels.iter().zip(&self.rows) {
+            let values: Vec<String> = row.iter().map(|v| v.to_string()).collect();
+            writeln!(out, "{},{}", values.join(","), arff_quote(label))?;
+        }
+        Ok(())
+    }
+
+    /// Sparse `class index:value` lines with 1-based feature indices and zeros omitted.
+    pub fn write_libsvm(&self, out: &mut impl Write) -> io::Result<()> {
+        let classes = self.classes();
+        for (label, row) in self.labels.iter().zip(&self.rows) {
+            let class = classes.iter().position(|c| c == label).unwrap_or(0) + 1;
+            write!(out, "{}", class)?;
+            for (i, value) in row.iter().enumerate().filter(|(_, v)| **v != 0.0) {
+                write!(out, " {}:{}", i + 1, value)?;
+            }
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "parquet")]
+    pub fn write_parquet(&self, out: &mut (impl Write + Send)) -> io::Result<()> {
+        use parquet::basic::{LogicalType, Repetition, Type as PhysicalType};
+        use parquet::data_type::{ByteArray, ByteArrayType, DoubleType};
+        use parquet::file::properties::WriterProperties;
+        use parquet::format::KeyValue;
+        use parquet::file::writer::SerializedFileWriter;
+        use parquet::schema::types::Type;
+        use std::sync::Arc;
+
+        let other = |e: parquet::errors::ParquetError| io::Error::other(e.to_string());
+        let mut fields = vec![Arc::new(
+            Type::primitive_type_builder("author", PhysicalType::BYTE_ARRAY)
+                .with_repetition(Repetition::REQUIRED)
+                .with_logical_type(Some(LogicalType::String))
+                .build()
+                .map_err(other)?,
+        )];
+        for column in &self.columns {
+            fields.push(Arc::new(
+                Type::primitive_type_builder(column, PhysicalType::DOUBLE)
+                    .with_repetition(Repetition::REQUIRED)
+                    .build()
+                    .map_err(other)?,
+            ));
+        }
+        let schema = Type::group_type_builder("author_features")
+            .with_fields(fields)
+            .build()
+            .map_err(other)?;
+
+        let properties = WriterProperties::builder()
+            .set_key_value_metadata(Some(vec![KeyValue::new(
+                "feature_schema_version".to_string(),
+                FEATURE_SCHEMA_VERSION.to_string(),
+            )]))
+            .build();
+        let properties = Arc::new(properties);
+        let mut writer = SerializedFileWriter::new(out, Arc::new(schema), properties).map_err(other)?;
+        let mut row_group = writer.next_row_group().map_err(other)?;
+        // The author column comes first, then one column per feature
+        let mut index = 0;
+        while let Some(mut column) = row_group.next_column().map_err(other)? {
+            if index == 0 {
+                let labels: Vec<ByteArray> = self.labels.iter().map(|l| ByteArray::from(l.as_str())).collect();
+                column.typed::<ByteArrayType>().write_batch(&labels, None, None).map_err(other)?;
+            } else {
+                let values: Vec<f64> = self.rows.iter().map(|row| row[index - 1]).collect();
+                column.typed::<DoubleType>().write_batch(&values, None, None).map_err(other)?;
+            }
+            column.close().map_err(other)?;
+            index += 1;
+        }
+        row_group.close().map_err(other)?;
+        writer.close().map_err(other)?;
+        Ok(())
+    }
+
+    #[cfg(not(feature = "parquet"))]
+    pub fn write_parquet(&self, _out: &mut (impl Write + Send)) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "built without Parquet support; enable the `parquet` feature",
+        ))
+    }
+}
+
+/// Quotes an ARFF name or nominal value when it holds anything but plain ASCII
+/// word characters.
+fn arff_quote(value: &str) -> String {
+    if !value.is_empty() && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> FeatureTable {
+        FeatureTable {
+            columns: vec!["particle_ratio".to_string(), "word:です".to_string()],
+            labels: vec!["b".to_string(), "o'brien".to_string(), "b".to_string()],
+            rows: vec![vec![0.25, 0.0], vec![0.5, 0.125], vec![0.0, 1.0]],
+        }
+    }
+
+    #[test]
+    fn test_write_libsvm() {
+        let mut out = Vec::new();
+        table().write_libsvm(&mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "1 1:0.25\n2 1:0.5 2:0.125\n1 2:1\n");
+    }
+
+    #[test]
+    fn test_write_arff() {
+        let mut out = Vec::new();
+        table().write_arff(&mut out).unwrap();
+        let arff = String::from_utf8(out).unwrap();
+
+        assert!(arff.starts_with(&format!("% feature_schema_version: {}\n", FEATURE_SCHEMA_VERSION)));
+        assert!(arff.contains("@ATTRIBUTE particle_ratio NUMERIC\n@ATTRIBUTE 'word:です' NUMERIC\n"));
+        assert!(arff.contains("@ATTRIBUTE author {b,'o\\'brien'}\n"));
+        assert!(arff.ends_with("@DATA\n0.25,0,b\n0.5,0.125,'o\\'brien'\n0,1,b\n"));
+    }
+}
@@ -0,0 +1,498 @@
+use crate::language::{self, Language};
+use crate::pos::Pos;
+use crate::punctuation;
+use crate::register::{self, RegisterProfile};
+use crate::script::{script_ratios, ScriptRatios};
+use crate::sentences;
+use crate::symbols;
+use crate::token_cache;
+use crate::token_filter::TokenFilters;
+use crate::tokenizer::{LinderaResult, TokenInfo, Tokenizer};
+use crate::whitespace;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextFeatures {
+    pub word_frequencies: HashMap<String, f64>,
+    pub particle_ratio: f64,
+    pub verb_ratio: f64,
+    pub adjective_ratio: f64,
+    pub unique_words_ratio: f64,
+    pub avg_sentence_length: f64,
+    pub punctuation_ratio: f64,
+    pub english_segment_ratio: f64,
+    pub symbol_ratio: f64,
+    /// Share of characters that are full-width, typographic or zero-width spaces.
+    pub unusual_whitespace_ratio: f64,
+    pub token_count: usize,
+    pub sentence_count: usize,
+    #[serde(default)]
+    pub pos_counts: PosCounts,
+    /// Share of each part of speech among the Japanese tokens; empty for
+    /// features migrated from before it was recorded.
+    pub pos_distribution: BTreeMap<Pos, f64>,
+    /// Relative frequencies of character bigrams and trigrams; empty for
+    /// features migrated from before they were recorded.
+    pub char_ngrams: HashMap<String, f64>,
+    /// Relative frequencies of part-of-speech bigrams and trigrams within
+    /// sentences, keyed like `noun particle verb`; empty for features
+    /// migrated from before they were recorded.
+    pub pos_ngrams: HashMap<String, f64>,
+    /// Share of each particle (は, が, を, ...) among all particles; empty for
+    /// features migrated from before it was recorded.
+    pub particle_distribution: HashMap<String, f64>,
+    /// Share of Japanese sentences ending in each register, and of those
+    /// using keigo; `None` for a text without Japanese sentences and for
+    /// features migrated from before it was recorded.
+    pub register: Option<RegisterProfile>,
+    /// Share of kanji, hiragana, katakana, Latin letters and digits among
+    /// the text's letters and digits; `None` for a text without any and for
+    /// features migrated from before it was recorded.
+    pub script_ratios: Option<ScriptRatios>,
+}
+
+/// Raw counts behind the POS ratios, so callers can tell "none observed"
+/// apart from a small ratio.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct PosCounts {
+    // Japanese content tokens lindera tagged; the denominator of the POS ratios
+    pub tagged: usize,
+    pub particles: usize,
+    pub verbs: usize,
+    pub adjectives: usize,
+}
+
+/// Beta prior for a POS ratio: `mean` is the typical share among tagged tokens,
+/// `strength` how many tokens of evidence the prior is worth.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct PosPrior {
+    pub mean: f64,
+    pub strength: f64,
+}
+
+impl PosPrior {
+    /// Posterior mean: short texts stay near the prior, long texts follow their own counts.
+    pub fn shrink(&self, count: usize, tagged: usize) -> f64 {
+        (count as f64 + self.mean * self.strength) / (tagged as f64 + self.strength)
+    }
+}
+
+/// Layout version of `TextFeatures`. Feature vectors computed elsewhere must
+/// declare this version to be accepted for comparison.
+pub const FEATURE_SCHEMA_VERSION: u32 = 8;
+
+// Typical shares in IPADIC-tagged contemporary written Japanese
+pub const PARTICLE_PRIOR: PosPrior = PosPrior { mean: 0.28, strength: 20.0 };
+pub const VERB_PRIOR: PosPrior = PosPrior { mean: 0.15, strength: 20.0 };
+pub const ADJECTIVE_PRIOR: PosPrior = PosPrior { mean: 0.03, strength: 20.0 };
+
+/// Feature buckets a token was counted in, for the debug view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Bucket {
+    Word,
+    Punctuation,
+    Particle,
+    Verb,
+    Adjective,
+    // Excluded from every statistic
+    Symbol,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TokenTrace {
+    #[serde(flatten)]
+    pub token: TokenInfo,
+    pub buckets: Vec<Bucket>,
+}
+
+/// Relative frequencies of the character bigrams and trigrams within runs of
+/// non-whitespace characters.
+pub fn char_ngrams(text: &str) -> HashMap<String, f64> {
+    let mut counts: HashMap<String, f64> = HashMap::new();
+    for run in text.split_whitespace() {
+        let chars: Vec<char> = run.chars().collect();
+        for n in 2..=3 {
+            for gram in chars.windows(n) {
+                *counts.entry(gram.iter().collect()).or_default() += 1.0;
+            }
+        }
+    }
+    let total: f64 = counts.values().sum();
+    counts.values_mut().for_each(|count| *count /= total);
+    counts
+}
+
+// Relative frequencies of the POS bigrams and trigrams of each sentence,
+// which never span a sentence boundary
+fn pos_ngrams(sentences: &[Vec<Pos>]) -> HashMap<String, f64> {
+    let mut counts: HashMap<String, f64> = HashMap::new();
+    for tags in sentences {
+        for n in 2..=3 {
+            for gram in tags.windows(n) {
+                let key = gram.iter().map(|pos| pos.name()).collect::<Vec<_>>().join(" ");
+                *counts.entry(key).or_default() += 1.0;
+            }
+        }
+    }
+    let total: f64 = counts.values().sum();
+    counts.values_mut().for_each(|count| *count /= total);
+    counts
+}
+
+// Symbol runs say nothing about style, and spaces, including those sanitized
+// from unusual ones, only separate words, so neither counts as a token
+fn is_counted(text: &str) -> bool {
+    !symbols::is_symbol_run(text) && !text.trim().is_empty()
+}
+
+pub fn extract_features(text: &str, tokenizer: &Tokenizer) -> LinderaResult<TextFeatures> {
+    extract(text, tokenizer, &TokenFilters::default(), None)
+}
+
+/// Like `extract_features`, but passes every word through `filters` before
+/// counting it.
+pub fn extract_features_filtered(text: &str, tokenizer: &Tokenizer, filters: &TokenFilters) -> LinderaResult<TextFeatures> {
+    extract(text, tokenizer, filters, None)
+}
+
+/// Like `extract_features_filtered`, but also reports every token and the buckets it fed.
+pub fn extract_features_traced(
+    text: &str,
+    tokenizer: &Tokenizer,
+    filters: &TokenFilters,
+) -> LinderaResult<(TextFeatures, Vec<TokenTrace>)> {
+    let mut trace = Vec::new();
+    let features = extract(text, tokenizer, filters, Some(&mut trace))?;
+    Ok((features, trace))
+}
+
+fn extract(
+    text: &str,
+    tokenizer: &Tokenizer,
+    filters: &TokenFilters,
+    mut trace: Option<&mut Vec<TokenTrace>>,
+) -> LinderaResult<TextFeatures> {
+    // Drawings and emoji runs say nothing about writing style, so keep them out of every ratio
+    let symbol_ratio = symbols::symbol_ratio(text);
+    // Measured, then replaced so full-width spaces and zero-width characters never end up in a word
+    let unusual_whitespace_ratio = whitespace::unusual_whitespace_ratio(text);
+    let sanitized = whitespace::sanitize(text);
+    let text = &*symbols::strip_ascii_art(&sanitized);
+
+    // English segments get their own whitespace pipeline so they don't dilute the POS ratios
+    let segments = language::segment(text);
+    let english_segment_ratio = language::english_segment_ratio(&segments);
+    // Tokenized a sentence at a time so repeated sentences hit the token cache
+    let sentences = segments
+        .iter()
+        .filter(|s| s.language != Language::English)
+        .map(|s| token_cache::tokenize_sentence(s.text, tokenizer))
+        .collect::<LinderaResult<Vec<_>>>()?;
+    let tokens = sentences.iter().flat_map(|tokens| tokens.iter());
+    let sentence_tags: Vec<Vec<Pos>> = sentences
+        .iter()
+        .map(|tokens| tokens.iter().filter(|token| is_counted(&token.text)).map(|token| token.pos).collect())
+        .collect();
+    let japanese_tokens = tokens.clone().filter(|token| is_counted(&token.text)).count() as f64;
+    let mut word_frequencies: HashMap<String, f64> = HashMap::new();
+    let mut particle_counts: HashMap<String, f64> = HashMap::new();
+    let (mut particles, mut verbs, mut adjectives) = (0, 0, 0);
+    let mut pos_totals: BTreeMap<Pos, usize> = BTreeMap::new();
+    let mut punctuation_count = 0.0;
+    let mut japanese_punctuation_count = 0.0;
+    let mut english_tokens = 0.0;
+
+    // Copies the token only when tracing
+    let mut record = |text: &str, language: Language, pos: &[String], buckets: Vec<Bucket>| {
+        if let Some(trace) = trace.as_deref_mut() {
+            trace.push(TokenTrace {
+                token: TokenInfo { text: text.to_string(), language, pos: pos.to_vec() },
+                buckets,
+            });
+        }
+    };
+
+    for segment in segments.iter().filter(|s| s.language == Language::English) {
+        for raw in segment.text.split_whitespace() {
+            if symbols::is_symbol_run(raw) {
+                record(raw, Language::English, &[], vec![Bucket::Symbol]);
+                continue;
+            }
+            let mut buckets = Vec::new();
+            let word = punctuation::trim_punctuation(raw);
+            let punctuation = raw.chars().count() - word.chars().count();
+            punctuation_count += punctuation as f64;
+            english_tokens += punctuation as f64;
+            if !word.is_empty() {
+                *word_frequencies.entry(filters.apply(&word.to_lowercase())).or_insert(0.0) += 1.0;
+                english_tokens += 1.0;
+                buckets.push(Bucket::Word);
+            }
+            if punctuation > 0 {
+                buckets.push(Bucket::Punctuation);
+            }
+            record(raw, Language::English, &[], buckets);
+        }
+    }
+
+    let total_tokens = japanese_tokens + english_tokens;
+
+    let sentence_count = sentences::count(text) as f64;
+
+    for token in tokens {
+        let word = &token.text;
+        if symbols::is_symbol_run(word) {
+            record(word, Language::Japanese, &token.details, vec![Bucket::Symbol]);
+            continue;
+        }
+        if !is_counted(word) {
+            record(word, Language::Japanese, &token.details, Vec::new());
+            continue;
+        }
+        *pos_totals.entry(token.pos).or_default() += 1;
+
+        let mut buckets = Vec::new();
+        if !punctuation::is_punctuation_token(word) {
+            *word_frequencies.entry(filters.apply(word)).or_insert(0.0) += 1.0;
+            buckets.push(Bucket::Word);
+        } else {
+            punctuation_count += 1.0;
+            japanese_punctuation_count += 1.0;
+            buckets.push(Bucket::Punctuation);
+        }
+
+        let pos_bucket = match token.pos {
+            Pos::Particle => {
+                particles += 1;
+                *particle_counts.entry(word.clone()).or_default() += 1.0;
+                Some(Bucket::Particle)
+            }
+            Pos::Verb => {
+                verbs += 1;
+                Some(Bucket::Verb)
+            }
+            Pos::Adjective => {
+                adjectives += 1;
+                Some(Bucket::Adjective)
+            }
+            Pos::Noun
+            | Pos::Pronoun
+            | Pos::AdjectivalNoun
+            | Pos::Adverb
+            | Pos::Prenominal
+            | Pos::Conjunction
+            | Pos::Interjection
+            | Pos::AuxiliaryVerb
+            | Pos::Prefix
+            | Pos::Suffix
+            | Pos::Symbol
+            | Pos::Filler
+            | Pos::Whitespace
+            | Pos::Other
+            | Pos::Unknown => None,
+        };
+        buckets.extend(pos_bucket);
+        record(word, Language::Japanese, &token.details, buckets);
+    }
+
+    let pos_counts = PosCounts {
+        tagged: (japanese_tokens - japanese_punctuation_count) as usize,
+        particles,
+        verbs,
+        adjectives,
+    };
+    let pos_distribution: BTreeMap<Pos, f64> = pos_totals
+        .into_iter()
+        .map(|(pos, count)| (pos, count as f64 / japanese_tokens))
+        .collect();
+    let particle_distribution: HashMap<String, f64> = particle_counts
+        .into_iter()
+        .map(|(particle, count)| (particle, count / particles as f64))
+        .collect();
+
+    // Punctuation isn't a word, so neither branch counts it towards the evidence thresholds
+    let content_tokens = total_tokens - punctuation_count;
+
+    // Handle empty or very short text
+    if total_tokens < 2.0 {
+        return Ok(TextFeatures {
+            word_frequencies: HashMap::new(),
+            particle_ratio: PARTICLE_PRIOR.shrink(pos_counts.particles, pos_counts.tagged),
+            verb_ratio: VERB_PRIOR.shrink(pos_counts.verbs, pos_counts.tagged),
+            adjective_ratio: ADJECTIVE_PRIOR.shrink(pos_counts.adjectives, pos_counts.tagged),
+            unique_words_ratio: 0.0,
+            avg_sentence_length: total_tokens,
+            punctuation_ratio: 0.0,
+            english_segment_ratio,
+            symbol_ratio,
+            unusual_whitespace_ratio,
+            token_count: content_tokens as usize,
+            sentence_count: sentence_count as usize,
+            pos_counts,
+            pos_distribution,
+            char_ngrams: char_ngrams(text),
+            pos_ngrams: pos_ngrams(&sentence_tags),
+            particle_distribution,
+            register: register::profile(text),
+            script_ratios: script_ratios(text),
+        });
+    }
+
+    Ok(TextFeatures {
+        word_frequencies: word_frequencies
+            .iter()
+            .map(|(k, v)| (k.clone(), v / content_tokens))
+            .collect(),
+        // POS ratios only make sense over the tokens lindera actually tagged
+        particle_ratio: PARTICLE_PRIOR.shrink(pos_counts.particles, pos_counts.tagged),
+        verb_ratio: VERB_PRIOR.shrink(pos_counts.verbs, pos_counts.tagged),
+        adjective_ratio: ADJECTIVE_PRIOR.shrink(pos_counts.adjectives, pos_counts.tagged),
+        unique_words_ratio: if content_tokens > 0.0 { word_frequencies.len() as f64 / content_tokens } else { 0.0 },
+        avg_sentence_length: if sentence_count > 0.0 { content_tokens / sentence_count } else { content_tokens },
+        punctuation_ratio: if total_tokens > 0.0 { punctuation_count / total_tokens } else { 0.0 },
+        english_segment_ratio,
+        symbol_ratio,
+        unusual_whitespace_ratio,
+        token_count: content_tokens as usize,
+        sentence_count: sentence_count as usize,
+        pos_counts,
+        pos_distribution,
+        char_ngrams: char_ngrams(text),
+        pos_ngrams: pos_ngrams(&sentence_tags),
+        particle_distribution,
+        register: register::profile(text),
+        script_ratios: script_ratios(text),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::new_tokenizer;
+
+    #[test]
+    fn test_text_features() {
+        let tokenizer = new_tokenizer().unwrap();
+        
+        let text = "私は今日公園に行きました。";
+        let features = extract_features(text, &tokenizer).unwrap();
+
+        // Test basic feature existence and bounds
+        assert!(features.particle_ratio >= 0.0 && features.particle_ratio <= 1.0);
+        assert!(features.verb_ratio >= 0.0 && features.verb_ratio <= 1.0);
+        assert!(features.avg_sentence_length > 0.0);
+        assert!(features.unique_words_ratio >= 0.0 && features.unique_words_ratio <= 1.0);
+        assert!(features.punctuation_ratio >= 0.0 && features.punctuation_ratio <= 1.0);
+        assert!((features.char_ngrams.values().sum::<f64>() - 1.0).abs() < 1e-9);
+        assert!(features.char_ngrams.contains_key("公園"));
+        assert!((features.pos_ngrams.values().sum::<f64>() - 1.0).abs() < 1e-9);
+        assert!(features.pos_ngrams.contains_key("noun particle"));
+        // は and に once each
+        assert_eq!(features.particle_distribution.get("は"), Some(&0.5));
+        assert_eq!(features.particle_distribution.get("に"), Some(&0.5));
+    }
+
+    #[test]
+    fn test_japanese_sentences() {
+        let tokenizer = new_tokenizer().unwrap();
+        let features = extract_features("雨が降った。「傘は？」と聞いた。本当に！？\n晴れた", &tokenizer).unwrap();
+
+        assert_eq!(features.sentence_count, 4);
+        assert!(features.avg_sentence_length < features.token_count as f64);
+    }
+
+    #[test]
+    fn test_cjk_punctuation() {
+        let tokenizer = new_tokenizer().unwrap();
+        let features = extract_features("「公園」に行った、と言いました。", &tokenizer).unwrap();
+
+        for mark in ["「", "」", "、", "。"] {
+            assert!(!features.word_frequencies.contains_key(mark), "{}", mark);
+        }
+        assert!(features.punctuation_ratio > 0.0);
+        // Nor does it count as a token, however short the text
+        assert_eq!(extract_features("。", &tokenizer).unwrap().token_count, 0);
+    }
+
+    #[test]
+    fn test_unusual_whitespace() {
+        let tokenizer = new_tokenizer().unwrap();
+        let plain = extract_features("公園に行きました。", &tokenizer).unwrap();
+        let spaced = extract_features("\u{FEFF}公園\u{200B}に\u{3000}行きました。", &tokenizer).unwrap();
+
+        assert_eq!(plain.unusual_whitespace_ratio, 0.0);
+        assert!(spaced.unusual_whitespace_ratio > 0.0);
+        assert_eq!(plain.word_frequencies.len(), spaced.word_frequencies.len());
+        assert!(spaced.word_frequencies.keys().all(|w| !w.chars().any(whitespace::is_unusual_whitespace)));
+        // The spaces separate words without counting as tokens themselves
+        assert_eq!(plain.token_count, spaced.token_count);
+        assert_eq!(plain.pos_distribution, spaced.pos_distribution);
+        assert_eq!(plain.particle_ratio, spaced.particle_ratio);
+    }
+
+    #[test]
+    fn test_code_switching_features() {
+        let tokenizer = new_tokenizer().unwrap();
+
+        let japanese = extract_features("売上実績について報告いたします。", &tokenizer).unwrap();
+        let mixed = extract_features(
+            "売上実績について報告いたします。Please see the attached file for details.",
+            &tokenizer,
+        )
+        .unwrap();
+
+        assert_eq!(japanese.english_segment_ratio, 0.0);
+        assert!(mixed.english_segment_ratio > 0.0);
+        // The English sentence must not dilute the Japanese POS ratios
+        assert!((japanese.particle_ratio - mixed.particle_ratio).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_symbol_runs_are_excluded() {
+        let tokenizer = new_tokenizer().unwrap();
+
+        let plain = extract_features("私は今日公園に行きました。\n", &tokenizer).unwrap();
+        let decorated = extract_features("私は今日公園に行きました。😀🎉\n＼(^o^)／＼(^o^)／\n", &tokenizer).unwrap();
+
+        assert_eq!(plain.symbol_ratio, 0.0);
+        assert!(decorated.symbol_ratio > 0.0);
+        assert_eq!(plain.token_count, decorated.token_count);
+        assert!((plain.punctuation_ratio - decorated.punctuation_ratio).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_traced_buckets() {
+        let tokenizer = new_tokenizer().unwrap();
+        let text = "私は公園に行きました。😀";
+
+        let (features, trace) = extract_features_traced(text, &tokenizer, &TokenFilters::default()).unwrap();
+        assert_eq!(features.particle_ratio, extract_features(text, &tokenizer).unwrap().particle_ratio);
+
+        let buckets = |word: &str| trace.iter().find(|t| t.token.text == word).unwrap().buckets.clone();
+        assert_eq!(buckets("は"), vec![Bucket::Word, Bucket::Particle]);
+        assert_eq!(buckets("😀"), vec![Bucket::Symbol]);
+    }
+
+    #[test]
+    fn test_pos_shrinkage() {
+        // No evidence means the prior itself
+        assert_eq!(ADJECTIVE_PRIOR.shrink(0, 0), ADJECTIVE_PRIOR.mean);
+
+        // A short text without adjectives is pulled less far from the prior than a long one
+        let short = ADJECTIVE_PRIOR.shrink(0, 5);
+        let long = ADJECTIVE_PRIOR.shrink(0, 500);
+        assert!(long < short && short < ADJECTIVE_PRIOR.mean);
+        assert!(long > 0.0);
+
+        // With plenty of evidence the observed ratio dominates
+        assert!((PARTICLE_PRIOR.shrink(500, 1000) - 0.5).abs() < 0.01);
+
+        // A single word is too little for the other ratios, but still gets the priors
+        let features = extract_features("猫", &new_tokenizer().unwrap()).unwrap();
+        assert_eq!(features.verb_ratio, VERB_PRIOR.shrink(0, 1));
+        assert_eq!(features.particle_ratio, PARTICLE_PRIOR.shrink(0, 1));
+    }
+}
@@ -0,0 +1,64 @@
+use crate::tokenizer::shared_tokenizer;
+use std::ffi::{c_char, CString};
+use std::panic::{self, AssertUnwindSafe};
+
+unsafe fn text_from_raw<'a>(ptr: *const u8, len: usize) -> Result<&'a str, String> {
+    if ptr.is_null() {
+        return if len == 0 {
+            Ok("")
+        } else {
+            Err("null text pointer".to_string())
+        };
+    }
+    let bytes = std::slice::from_raw_parts(ptr, len);
+    std::str::from_utf8(bytes).map_err(|e| format!("text is not valid UTF-8: {}", e))
+}
+
+fn compare_json(text1: &str, text2: &str) -> Result<String, String> {
+    let tokenizer = shared_tokenizer().map_err(|e| e.to_string())?;
+    let analysis = crate::compare(text1, text2, tokenizer).map_err(|e| e.to_string())?;
+    serde_json::to_string(&analysis).map_err(|e| e.to_string())
+}
+
+fn error_json(message: &str) -> String {
+    serde_json::json!({ "error": message }).to_string()
+}
+
+/// Compares two UTF-8 buffers and returns the analysis as a NUL-terminated JSON
+/// string. Failures are reported as `{"error": "..."}`. The returned string must
+/// be released with `author_comparer_free_string`.
+///
+/// # Safety
+///
+/// `text1`/`text2` must point to `text1_len`/`text2_len` readable bytes, or be
+/// null with a length of zero.
+#[no_mangle]
+pub unsafe extern "C" fn author_comparer_compare(
+    text1: *const u8,
+    text1_len: usize,
+    text2: *const u8,
+    text2_len: usize,
+) -> *mut c_char {
+    let result = text_from_raw(text1, text1_len).and_then(|text1| {
+        let text2 = text_from_raw(text2, text2_len)?;
+        // Never let a panic unwind into the host application
+        panic::catch_unwind(AssertUnwindSafe(|| compare_json(text1, text2)))
+            .unwrap_or_else(|_| Err("analysis panicked".to_string()))
+    });
+
+    let json = result.unwrap_or_else(|message| error_json(&message));
+    // serde_json escapes control characters, so the output never contains NUL
+    CString::new(json).map_or(std::ptr::null_mut(), CString::into_raw)
+}
+
+/// Releases a string returned by `author_comparer_compare`. Passing null is a no-op.
+///
+/// # Safety
+///
+/// `s` must have been returned by this library and not freed before.
+#[no_mangle]
+pub unsafe extern "C" fn author_comparer_free_string(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
@@ -0,0 +1,141 @@
+use crate::config::Config;
+use crate::features::{extract_features, TextFeatures};
+use crate::profile::sample_fit;
+use crate::scoring::Scorer;
+use crate::tokenizer::{LinderaResult, Tokenizer};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+// Separators git can't produce inside a field: NUL between fields, RS between commits
+const FIELD: char = '\u{0}';
+const RECORD: char = '\u{1e}';
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommitMessage {
+    pub hash: String,
+    /// The author field as `Name <email>`.
+    pub author: String,
+    pub message: String,
+}
+
+/// A commit whose message reads unlike its claimed author's other ones.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Suspicious {
+    pub hash: String,
+    pub author: String,
+    /// First line of the message.
+    pub summary: String,
+    /// Same-author confidence against the author's other messages.
+    pub confidence: f64,
+}
+
+/// Reads the commit messages of a repository with `git log`, newest first.
+pub fn read_commits(repo: &Path, limit: Option<usize>) -> io::Result<Vec<CommitMessage>> {
+    let mut command = Command::new("git");
+    command
+        .arg("-C")
+        .arg(repo)
+        .arg("log")
+        .arg("--no-merges")
+        .arg("--format=%H%x00%an <%ae>%x00%B%x1e");
+    if let Some(limit) = limit {
+        command.arg(format!("--max-count={}", limit));
+    }
+    let output = command.output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+    }
+    Ok(parse_log(&String::from_utf8_lossy(&output.stdout)))
+}
+
+pub fn parse_log(log: &str) -> Vec<CommitMessage> {
+    log.split(RECORD)
+        .filter_map(|record| {
+            let mut fields = record.trim_start_matches('\n').splitn(3, FIELD);
+            let hash = fields.next()?.trim();
+            let author = fields.next()?;
+            let message = fields.next()?.trim();
+            (!hash.is_empty()).then(|| CommitMessage {
+                hash: hash.to_string(),
+                author: author.to_string(),
+                message: message.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Profiles every author from their commit messages and returns the commits
+/// scoring below `threshold` against the rest of their author's messages,
+/// least fitting first. Authors with fewer than `min_commits` are skipped.
+pub fn audit(
+    commits: &[CommitMessage],
+    tokenizer: &Tokenizer,
+    config: &Config,
+    scorer: &dyn Scorer,
+    min_commits: usize,
+    threshold: f64,
+) -> LinderaResult<Vec<Suspicious>> {
+    let mut by_author: BTreeMap<&str, Vec<&CommitMessage>> = BTreeMap::new();
+    for commit in commits {
+        by_author.entry(commit.author.as_str()).or_default().push(commit);
+    }
+
+    let mut suspicious = Vec::new();
+    for commits in by_author.values().filter(|c| c.len() >= min_commits.max(2)) {
+        let features = commits.iter().map(|c| extract_features(&c.message, tokenizer)).collect::<LinderaResult<Vec<TextFeatures>>>()?;
+        for (commit, confidence) in commits.iter().zip(sample_fit(&features, config, scorer)) {
+            if confidence < threshold {
+                suspicious.push(Suspicious {
+                    hash: commit.hash.clone(),
+                    author: commit.author.clone(),
+                    summary: commit.message.lines().next().unwrap_or_default().to_string(),
+                    confidence,
+                });
+            }
+        }
+    }
+    suspicious.sort_by(|a, b| a.confidence.total_cmp(&b.confidence));
+    Ok(suspicious)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scoring::WeightedScorer;
+
+    #[test]
+    fn test_parse_log() {
+        let log = "abc\u{0}Natsume <n@example.com>\u{0}公園の修正\n\n詳細\n\u{1e}\ndef\u{0}Mori <m@example.com>\u{0}会議\n\u{1e}\n";
+        let commits = parse_log(log);
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[0].hash, "abc");
+        assert_eq!(commits[0].author, "Natsume <n@example.com>");
+        assert_eq!(commits[0].message, "公園の修正\n\n詳細");
+        assert_eq!(commits[1].hash, "def");
+    }
+
+    #[test]
+    fn test_audit() {
+        let commit = |hash: &str, author: &str, message: &str| CommitMessage {
+            hash: hash.to_string(),
+            author: author.to_string(),
+            message: message.to_string(),
+        };
+        let commits = [
+            commit("1", "natsume", "ログイン画面の表示を修正しました。入力欄の幅がずれていました。"),
+            commit("2", "natsume", "検索画面の表示を修正しました。ボタンの位置がずれていました。"),
+            commit("3", "natsume", "設定画面の表示を修正しました。文字の色が薄すぎました。"),
+            commit("4", "natsume", "やばいバグ直した！！マジで焦ったｗ とりあえずpush！！"),
+            commit("5", "mori", "初回のコミットです。"),
+        ];
+        let tokenizer = crate::tokenizer::new_tokenizer().unwrap();
+
+        let suspicious = audit(&commits, &tokenizer, &Config::default(), &WeightedScorer, 3, 1.0).unwrap();
+        assert_eq!(suspicious.len(), 4);
+        assert_eq!(suspicious[0].hash, "4");
+        assert!(suspicious.iter().all(|s| s.author == "natsume"));
+    }
+}
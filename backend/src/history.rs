@@ -0,0 +1,496 @@
+use crate::comparison::{Analysis, Verdict};
+use crate::config::{ConfigError, HistoryConfig};
+use crate::scoring::Backend;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[cfg(feature = "history")]
+const SQLITE_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS comparisons (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    created_at INTEGER NOT NULL,
+    input1_sha256 TEXT NOT NULL,
+    input2_sha256 TEXT NOT NULL,
+    verdict TEXT NOT NULL,
+    confidence REAL NOT NULL,
+    scoring TEXT NOT NULL,
+    analysis TEXT NOT NULL,
+    case_id TEXT
+)";
+
+#[cfg(feature = "history")]
+const POSTGRES_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS comparisons (
+    id BIGSERIAL PRIMARY KEY,
+    created_at BIGINT NOT NULL,
+    input1_sha256 TEXT NOT NULL,
+    input2_sha256 TEXT NOT NULL,
+    verdict TEXT NOT NULL,
+    confidence DOUBLE PRECISION NOT NULL,
+    scoring TEXT NOT NULL,
+    analysis TEXT NOT NULL,
+    case_id TEXT
+)";
+
+// Databases created before cases existed get the column added on open
+#[cfg(feature = "history")]
+const SQLITE_CASE_COLUMN: &str = "ALTER TABLE comparisons ADD COLUMN case_id TEXT";
+
+#[cfg(feature = "history")]
+const POSTGRES_CASE_COLUMN: &str = "ALTER TABLE comparisons ADD COLUMN IF NOT EXISTS case_id TEXT";
+
+#[cfg(feature = "history")]
+const SUMMARY_COLUMNS: &str = "id, created_at, input1_sha256, input2_sha256, verdict, confidence, scoring, case_id";
+
+/// One stored `/compare` result, without its aspects.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ComparisonSummary {
+    pub id: i64,
+    /// Unix time the result was served at, in seconds.
+    pub created_at: i64,
+    /// SHA-256 of both inputs exactly as submitted, in hex.
+    pub input_sha256: [String; 2],
+    pub verdict: Verdict,
+    pub confidence: f64,
+    pub scoring: Backend,
+    /// The case the comparison was filed under, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub case: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct StoredComparison {
+    pub id: i64,
+    pub created_at: i64,
+    pub input_sha256: [String; 2],
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub case: Option<String>,
+    /// The response as it was returned.
+    pub analysis: Analysis,
+}
+
+/// A result ready to be stored, built before handing it to a blocking
+/// thread.
+#[cfg(feature = "history")]
+#[derive(Debug, Clone)]
+pub struct HistoryEntry {
+    created_at: i64,
+    input_sha256: [String; 2],
+    verdict: String,
+    confidence: f64,
+    scoring: String,
+    analysis: String,
+    case: Option<String>,
+}
+
+/// Empty without the `history` feature, where no `History` exists to fill one.
+#[cfg(not(feature = "history"))]
+#[derive(Debug, Clone)]
+pub struct HistoryEntry;
+
+#[derive(Debug)]
+pub struct HistoryError(String);
+
+impl fmt::Display for HistoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "comparison history: {}", self.0)
+    }
+}
+
+impl std::error::Error for HistoryError {}
+
+// Verdicts and backends are stored under their serialized names
+#[cfg(feature = "history")]
+fn name_of(value: impl Serialize) -> String {
+    serde_json::to_value(value).ok().and_then(|v| v.as_str().map(str::to_string)).unwrap_or_default()
+}
+
+#[cfg(feature = "history")]
+fn parse_name<T: serde::de::DeserializeOwned>(name: &str) -> Result<T, HistoryError> {
+    serde_json::from_value(serde_json::Value::String(name.to_string())).map_err(|e| HistoryError(format!("'{}': {}", name, e)))
+}
+
+/// Columns of one row, as read back from either database.
+#[cfg(feature = "history")]
+struct Row {
+    id: i64,
+    created_at: i64,
+    input_sha256: [String; 2],
+    verdict: String,
+    confidence: f64,
+    scoring: String,
+    case: Option<String>,
+    analysis: Option<String>,
+}
+
+/// A query parameter, bound the way each database wants it.
+#[cfg(feature = "history")]
+enum Param<'a> {
+    Int(i64),
+    Text(&'a str),
+}
+
+#[cfg(feature = "history")]
+impl Row {
+    fn summary(&self) -> Result<ComparisonSummary, HistoryError> {
+        Ok(ComparisonSummary {
+            id: self.id,
+            created_at: self.created_at,
+            input_sha256: self.input_sha256.clone(),
+            verdict: parse_name(&self.verdict)?,
+            confidence: self.confidence,
+            scoring: parse_name(&self.scoring)?,
+            case: self.case.clone(),
+        })
+    }
+
+    fn stored(self) -> Result<StoredComparison, HistoryError> {
+        let analysis = serde_json::from_str(self.analysis.as_deref().unwrap_or_default())
+            .map_err(|e| HistoryError(format!("comparison {}: {}", self.id, e)))?;
+        Ok(StoredComparison {
+            id: self.id,
+            created_at: self.created_at,
+            input_sha256: self.input_sha256,
+            case: self.case,
+            analysis,
+        })
+    }
+}
+
+#[cfg(feature = "history")]
+enum Database {
+    Sqlite(rusqlite::Connection),
+    Postgres(crate::sync_postgres::Client),
+}
+
+/// Every served `/compare` result, in SQLite or Postgres. The calls block,
+/// so handlers run them off the async workers.
+#[cfg(feature = "history")]
+pub struct History {
+    database: std::sync::Mutex<Database>,
+}
+
+/// Uninhabited without the `history` feature, so no database can be configured.
+#[cfg(not(feature = "history"))]
+pub enum History {}
+
+impl fmt::Debug for History {
+    // Connection strings may hold a password
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("History")
+    }
+}
+
+impl History {
+    /// Opens the configured database, or returns `None` when history is off.
+    pub fn from_config(config: &HistoryConfig) -> Result<Option<History>, ConfigError> {
+        match &config.database {
+            Some(url) => History::open(url).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// `postgres://` and `postgresql://` URLs connect to Postgres; anything
+    /// else, with or without `sqlite://`, is an SQLite file, created if
+    /// missing. Safe to call from async code: Postgres connects on a thread
+    /// of its own.
+    #[cfg(feature = "history")]
+    pub fn open(url: &str) -> Result<History, ConfigError> {
+        let database = if url.starts_with("postgres://") || url.starts_with("postgresql://") {
+            let mut client = crate::sync_postgres::Client::connect(url).map_err(|e| ConfigError::History(e.to_string()))?;
+            client.batch_execute(POSTGRES_SCHEMA).map_err(|e| ConfigError::History(e.to_string()))?;
+            client.batch_execute(POSTGRES_CASE_COLUMN).map_err(|e| ConfigError::History(e.to_string()))?;
+            Database::Postgres(client)
+        } else {
+            let path = url.strip_prefix("sqlite://").unwrap_or(url);
+            let connection = rusqlite::Connection::open(path).map_err(|e| ConfigError::History(format!("{}: {}", path, e)))?;
+            connection.execute_batch(SQLITE_SCHEMA).map_err(|e| ConfigError::History(format!("{}: {}", path, e)))?;
+            // SQLite has no ADD COLUMN IF NOT EXISTS
+            let has_case: bool = connection
+                .query_row("SELECT COUNT(*) > 0 FROM pragma_table_info('comparisons') WHERE name = 'case_id'", [], |row| row.get(0))
+                .map_err(|e| ConfigError::History(format!("{}: {}", path, e)))?;
+            if !has_case {
+                connection.execute_batch(SQLITE_CASE_COLUMN).map_err(|e| ConfigError::History(format!("{}: {}", path, e)))?;
+            }
+            Database::Sqlite(connection)
+        };
+        Ok(History {
+            database: std::sync::Mutex::new(database),
+        })
+    }
+
+    #[cfg(not(feature = "history"))]
+    pub fn open(_url: &str) -> Result<History, ConfigError> {
+        Err(ConfigError::History("built without the `history` feature".to_string()))
+    }
+
+    /// Hashes the inputs and serializes the analysis as returned, filed
+    /// under `case` when given.
+    #[cfg(feature = "history")]
+    pub fn entry(&self, analysis: &Analysis, inputs: [&[u8]; 2], case: Option<&str>) -> HistoryEntry {
+        use sha2::{Digest, Sha256};
+        use std::time::{SystemTime, UNIX_EPOCH};
+
+        HistoryEntry {
+            created_at: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64),
+            input_sha256: inputs.map(|input| crate::signing::to_hex(&Sha256::digest(input))),
+            verdict: name_of(analysis.verdict),
+            confidence: analysis.confidence,
+            scoring: name_of(analysis.scoring),
+            // Analysis serializes without fallible parts
+            analysis: serde_json::to_string(analysis).unwrap_or_default(),
+            case: case.map(str::to_string),
+        }
+    }
+
+    #[cfg(not(feature = "history"))]
+    pub fn entry(&self, _analysis: &Analysis, _inputs: [&[u8]; 2], _case: Option<&str>) -> HistoryEntry {
+        match *self {}
+    }
+
+    /// Stores a result and returns its id.
+    #[cfg(feature = "history")]
+    pub fn insert(&self, entry: &HistoryEntry) -> Result<i64, HistoryError> {
+        let mut database = self.database.lock().unwrap_or_else(|e| e.into_inner());
+        let [input1, input2] = &entry.input_sha256;
+        match &mut *database {
+            Database::Sqlite(connection) => {
+                connection
+                    .execute(
+                        "INSERT INTO comparisons (created_at, input1_sha256, input2_sha256, verdict, confidence, scoring, analysis, case_id)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                        rusqlite::params![
+                            entry.created_at,
+                            input1,
+                            input2,
+                            entry.verdict,
+                            entry.confidence,
+                            entry.scoring,
+                            entry.analysis,
+                            entry.case
+                        ],
+                    )
+                    .map_err(|e| HistoryError(e.to_string()))?;
+                Ok(connection.last_insert_rowid())
+            }
+            Database::Postgres(client) => client
+                .query_one(
+                    "INSERT INTO comparisons (created_at, input1_sha256, input2_sha256, verdict, confidence, scoring, analysis, case_id)
+                     VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING id",
+                    &[
+                        &entry.created_at,
+                        input1,
+                        input2,
+                        &entry.verdict,
+                        &entry.confidence,
+                        &entry.scoring,
+                        &entry.analysis,
+                        &entry.case,
+                    ],
+                )
+                .and_then(|row| row.try_get(0))
+                .map_err(|e| HistoryError(e.to_string())),
+        }
+    }
+
+    #[cfg(not(feature = "history"))]
+    pub fn insert(&self, _entry: &HistoryEntry) -> Result<i64, HistoryError> {
+        match *self {}
+    }
+
+    /// Up to `limit` results, newest first, only those older than `before`
+    /// (an id) when given.
+    #[cfg(feature = "history")]
+    pub fn list(&self, limit: usize, before: Option<i64>) -> Result<Vec<ComparisonSummary>, HistoryError> {
+        let before = before.unwrap_or(i64::MAX);
+        let limit = i64::try_from(limit).unwrap_or(i64::MAX);
+        let rows = self.select(
+            &format!("SELECT {} FROM comparisons WHERE id < ?1 ORDER BY id DESC LIMIT ?2", SUMMARY_COLUMNS),
+            &format!("SELECT {} FROM comparisons WHERE id < $1 ORDER BY id DESC LIMIT $2", SUMMARY_COLUMNS),
+            &[Param::Int(before), Param::Int(limit)],
+            false,
+        )?;
+        rows.iter().map(Row::summary).collect()
+    }
+
+    #[cfg(not(feature = "history"))]
+    pub fn list(&self, _limit: usize, _before: Option<i64>) -> Result<Vec<ComparisonSummary>, HistoryError> {
+        match *self {}
+    }
+
+    #[cfg(feature = "history")]
+    pub fn get(&self, id: i64) -> Result<Option<StoredComparison>, HistoryError> {
+        let rows = self.select(
+            &format!("SELECT {}, analysis FROM comparisons WHERE id = ?1", SUMMARY_COLUMNS),
+            &format!("SELECT {}, analysis FROM comparisons WHERE id = $1", SUMMARY_COLUMNS),
+            &[Param::Int(id)],
+            true,
+        )?;
+        rows.into_iter().next().map(Row::stored).transpose()
+    }
+
+    #[cfg(not(feature = "history"))]
+    pub fn get(&self, _id: i64) -> Result<Option<StoredComparison>, HistoryError> {
+        match *self {}
+    }
+
+    /// Every result filed under `case`, oldest first.
+    #[cfg(feature = "history")]
+    pub fn case(&self, case: &str) -> Result<Vec<StoredComparison>, HistoryError> {
+        let rows = self.select(
+            &format!("SELECT {}, analysis FROM comparisons WHERE case_id = ?1 ORDER BY id", SUMMARY_COLUMNS),
+            &format!("SELECT {}, analysis FROM comparisons WHERE case_id = $1 ORDER BY id", SUMMARY_COLUMNS),
+            &[Param::Text(case)],
+            true,
+        )?;
+        rows.into_iter().map(Row::stored).collect()
+    }
+
+    #[cfg(not(feature = "history"))]
+    pub fn case(&self, _case: &str) -> Result<Vec<StoredComparison>, HistoryError> {
+        match *self {}
+    }
+
+    /// Deletes the results served before `before` (Unix time, in seconds)
+    /// and returns how many there were.
+    #[cfg(feature = "history")]
+    pub fn purge(&self, before: i64) -> Result<usize, HistoryError> {
+        let mut database = self.database.lock().unwrap_or_else(|e| e.into_inner());
+        match &mut *database {
+            Database::Sqlite(connection) => connection
+                .execute("DELETE FROM comparisons WHERE created_at < ?1", [before])
+                .map_err(|e| HistoryError(e.to_string())),
+            Database::Postgres(client) => client
+                .execute("DELETE FROM comparisons WHERE created_at < $1", &[&before])
+                .map(|deleted| deleted as usize)
+                .map_err(|e| HistoryError(e.to_string())),
+        }
+    }
+
+    #[cfg(not(feature = "history"))]
+    pub fn purge(&self, _before: i64) -> Result<usize, HistoryError> {
+        match *self {}
+    }
+
+    // The two dialects only differ in their placeholders; `with_analysis`
+    // reads the analysis selected after the summary columns
+    #[cfg(feature = "history")]
+    fn select(&self, sqlite: &str, postgres: &str, params: &[Param], with_analysis: bool) -> Result<Vec<Row>, HistoryError> {
+        let mut database = self.database.lock().unwrap_or_else(|e| e.into_inner());
+        match &mut *database {
+            Database::Sqlite(connection) => {
+                let mut statement = connection.prepare(sqlite).map_err(|e| HistoryError(e.to_string()))?;
+                let params = params.iter().map(|param| match param {
+                    Param::Int(value) => value as &dyn rusqlite::ToSql,
+                    Param::Text(value) => value as &dyn rusqlite::ToSql,
+                });
+                let rows = statement
+                    .query_map(rusqlite::params_from_iter(params), |row| {
+                        Ok(Row {
+                            id: row.get(0)?,
+                            created_at: row.get(1)?,
+                            input_sha256: [row.get(2)?, row.get(3)?],
+                            verdict: row.get(4)?,
+                            confidence: row.get(5)?,
+                            scoring: row.get(6)?,
+                            case: row.get(7)?,
+                            analysis: if with_analysis { Some(row.get(8)?) } else { None },
+                        })
+                    })
+                    .map_err(|e| HistoryError(e.to_string()))?;
+                rows.collect::<Result<Vec<_>, _>>().map_err(|e| HistoryError(e.to_string()))
+            }
+            Database::Postgres(client) => {
+                let params: Vec<&(dyn postgres::types::ToSql + Sync)> = params
+                    .iter()
+                    .map(|param| match param {
+                        Param::Int(value) => value as &(dyn postgres::types::ToSql + Sync),
+                        Param::Text(value) => value as &(dyn postgres::types::ToSql + Sync),
+                    })
+                    .collect();
+                let rows = client.query(postgres, &params).map_err(|e| HistoryError(e.to_string()))?;
+                rows.iter()
+                    .map(|row| {
+                        Ok(Row {
+                            id: row.try_get(0)?,
+                            created_at: row.try_get(1)?,
+                            input_sha256: [row.try_get(2)?, row.try_get(3)?],
+                            verdict: row.try_get(4)?,
+                            confidence: row.try_get(5)?,
+                            scoring: row.try_get(6)?,
+                            case: row.try_get(7)?,
+                            analysis: if with_analysis { Some(row.try_get(8)?) } else { None },
+                        })
+                    })
+                    .collect::<Result<Vec<_>, postgres::Error>>()
+                    .map_err(|e| HistoryError(e.to_string()))
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "history"))]
+mod tests {
+    use super::*;
+    use crate::comparison::unscored;
+
+    #[test]
+    fn test_history() {
+        let history = History::open(":memory:").unwrap();
+        let mut analysis = unscored(Backend::Weighted);
+        analysis.verdict = Verdict::SameAuthor;
+        analysis.confidence = 0.8;
+        let first = history.insert(&history.entry(&analysis, [b"a", b"b"], Some("case-1"))).unwrap();
+        analysis.verdict = Verdict::DifferentAuthor;
+        let second = history.insert(&history.entry(&analysis, [b"a", b"c"], None)).unwrap();
+        assert!(second > first);
+
+        let listed = history.list(10, None).unwrap();
+        assert_eq!(listed.iter().map(|c| c.id).collect::<Vec<_>>(), [second, first]);
+        assert_eq!(listed[1].verdict, Verdict::SameAuthor);
+        assert_eq!(listed[0].input_sha256[0], listed[1].input_sha256[0]);
+        assert_ne!(listed[0].input_sha256[1], listed[1].input_sha256[1]);
+        assert_eq!(history.list(10, Some(second)).unwrap().len(), 1);
+        assert_eq!(history.list(1, None).unwrap().len(), 1);
+
+        let stored = history.get(first).unwrap().unwrap();
+        assert_eq!(stored.analysis.verdict, Verdict::SameAuthor);
+        assert!((stored.analysis.confidence - 0.8).abs() < 1e-12);
+        assert!(history.get(second + 1).unwrap().is_none());
+
+        assert_eq!(listed[1].case.as_deref(), Some("case-1"));
+        let case = history.case("case-1").unwrap();
+        assert_eq!(case.iter().map(|c| c.id).collect::<Vec<_>>(), [first]);
+        assert!(history.case("case-2").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_purge() {
+        let history = History::open(":memory:").unwrap();
+        let mut old = history.entry(&unscored(Backend::Weighted), [b"a", b"b"], None);
+        old.created_at = 1_000;
+        let old = history.insert(&old).unwrap();
+        let recent = history.insert(&history.entry(&unscored(Backend::Weighted), [b"a", b"c"], None)).unwrap();
+
+        assert_eq!(history.purge(2_000).unwrap(), 1);
+        assert!(history.get(old).unwrap().is_none());
+        assert!(history.get(recent).unwrap().is_some());
+        assert_eq!(history.purge(2_000).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_case_column_added() {
+        let path = std::env::temp_dir().join(format!("author-comparer-history-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let connection = rusqlite::Connection::open(&path).unwrap();
+        connection.execute_batch(&SQLITE_SCHEMA.replace(",\n    case_id TEXT", "")).unwrap();
+        drop(connection);
+
+        let history = History::open(path.to_str().unwrap()).unwrap();
+        let id = history.insert(&history.entry(&unscored(Backend::Weighted), [b"a", b"b"], Some("case-1"))).unwrap();
+        assert_eq!(history.get(id).unwrap().unwrap().case.as_deref(), Some("case-1"));
+        // Opening again leaves the column alone
+        drop(history);
+        History::open(path.to_str().unwrap()).unwrap();
+        let _ = std::fs::remove_file(&path);
+    }
+}
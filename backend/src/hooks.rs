@@ -0,0 +1,201 @@
+use crate::comparison::Analysis;
+use crate::config::{ConfigError, HooksConfig};
+#[cfg(feature = "scripting")]
+use crate::comparison::Verdict;
+#[cfg(feature = "scripting")]
+use crate::warnings::{Warning, WarningCode};
+#[cfg(feature = "scripting")]
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// What a hook knows about the request besides its result.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HookContext<'a> {
+    pub tenant: Option<&'a str>,
+    /// Characters in each text; unknown for requests sending feature vectors.
+    pub lengths: Option<[usize; 2]>,
+    pub genres: Option<[&'a str; 2]>,
+}
+
+// As serialized, e.g. `same_author` or `SHORT_TEXT`
+#[cfg(feature = "scripting")]
+fn serialized_name<T: serde::Serialize>(value: &T) -> String {
+    serde_json::to_value(value).ok().and_then(|v| v.as_str().map(str::to_string)).unwrap_or_default()
+}
+
+/// Rhai scripts run on finished results, the tenant's own or the default.
+/// A script sees `verdict`, `confidence`, `scoring`, `warnings` (codes),
+/// `aspects` (differences by name), `tenant`, `lengths` and `genres`, unset
+/// ones as `()`, and may assign `verdict` or push strings onto `notes`.
+#[cfg(feature = "scripting")]
+pub struct Hooks {
+    engine: rhai::Engine,
+    default: Option<rhai::AST>,
+    tenants: BTreeMap<String, rhai::AST>,
+}
+
+/// Uninhabited without the `scripting` feature, so no hook ever runs.
+#[cfg(not(feature = "scripting"))]
+pub enum Hooks {}
+
+impl fmt::Debug for Hooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Hooks")
+    }
+}
+
+impl Hooks {
+    /// Compiles every configured script, or returns `None` when there is none.
+    #[cfg(feature = "scripting")]
+    pub fn from_config(config: &HooksConfig) -> Result<Option<Hooks>, ConfigError> {
+        if config.default.is_none() && config.tenants.is_empty() {
+            return Ok(None);
+        }
+        let mut engine = rhai::Engine::new();
+        // No files, no output: a script only sees what it is handed
+        engine.set_module_resolver(rhai::module_resolvers::DummyModuleResolver::new());
+        engine.on_print(|_| {});
+        engine.on_debug(|_, _, _| {});
+        engine.set_max_operations(config.max_operations);
+        engine.set_max_call_levels(16);
+        engine.set_max_string_size(64 * 1024);
+        engine.set_max_array_size(1024);
+        engine.set_max_map_size(1024);
+
+        let compile = |path: &std::path::Path| -> Result<rhai::AST, ConfigError> {
+            let script = std::fs::read_to_string(path).map_err(ConfigError::Io)?;
+            engine.compile(&script).map_err(|e| ConfigError::Hooks(format!("{}: {}", path.display(), e)))
+        };
+        let default = config.default.as_deref().map(compile).transpose()?;
+        let tenants = config
+            .tenants
+            .iter()
+            .map(|(tenant, path)| Ok((tenant.clone(), compile(path)?)))
+            .collect::<Result<_, ConfigError>>()?;
+        Ok(Some(Hooks { engine, default, tenants }))
+    }
+
+    #[cfg(not(feature = "scripting"))]
+    pub fn from_config(config: &HooksConfig) -> Result<Option<Hooks>, ConfigError> {
+        if config.default.is_none() && config.tenants.is_empty() {
+            return Ok(None);
+        }
+        Err(ConfigError::Hooks("built without the `scripting` feature".to_string()))
+    }
+
+    /// Runs the script for `context.tenant` on `analysis`. A script that
+    /// fails, runs too long or sets an unknown verdict changes nothing but
+    /// adds a `HOOK_FAILED` warning.
+    #[cfg(feature = "scripting")]
+    pub fn apply(&self, analysis: &mut Analysis, context: &HookContext) {
+        let script = context.tenant.and_then(|tenant| self.tenants.get(tenant)).or(self.default.as_ref());
+        if let Some(script) = script {
+            if let Err(e) = self.run(script, analysis, context) {
+                analysis.warnings.push(Warning::new(WarningCode::HookFailed, format!("verdict hook failed: {}", e)));
+            }
+        }
+    }
+
+    #[cfg(not(feature = "scripting"))]
+    pub fn apply(&self, _analysis: &mut Analysis, _context: &HookContext) {
+        match *self {}
+    }
+
+    #[cfg(feature = "scripting")]
+    fn run(&self, script: &rhai::AST, analysis: &mut Analysis, context: &HookContext) -> Result<(), String> {
+        use rhai::{Array, Dynamic, Map, Scope};
+
+        let optional = |value: Option<Dynamic>| value.unwrap_or(Dynamic::UNIT);
+        let mut scope = Scope::new();
+        scope.push("verdict", serialized_name(&analysis.verdict));
+        scope.push("confidence", analysis.confidence);
+        scope.push("scoring", analysis.scoring.name().to_string());
+        let warnings: Array = analysis.warnings.iter().map(|w| Dynamic::from(serialized_name(&w.code))).collect();
+        scope.push("warnings", warnings);
+        let aspects: Map = analysis
+            .detailed_analysis
+            .iter()
+            .map(|d| (d.aspect.as_str().into(), optional(d.difference.map(Dynamic::from_float))))
+            .collect();
+        scope.push("aspects", aspects);
+        scope.push_dynamic("tenant", optional(context.tenant.map(|t| Dynamic::from(t.to_string()))));
+        let lengths = context.lengths.map(|lengths| Dynamic::from_array(lengths.map(|n| Dynamic::from_int(n as i64)).to_vec()));
+        scope.push_dynamic("lengths", optional(lengths));
+        let genres = context.genres.map(|genres| Dynamic::from_array(genres.map(|g| Dynamic::from(g.to_string())).to_vec()));
+        scope.push_dynamic("genres", optional(genres));
+        scope.push("notes", Array::new());
+
+        self.engine.run_ast_with_scope(&mut scope, script).map_err(|e| e.to_string())?;
+
+        let verdict = scope.get_value::<rhai::ImmutableString>("verdict").ok_or("`verdict` is no longer a string")?;
+        let verdict: Verdict = serde_json::from_value(serde_json::Value::String(verdict.to_string()))
+            .map_err(|_| format!("unknown verdict '{}'", verdict))?;
+        let notes = scope.get_value::<Array>("notes").ok_or("`notes` is no longer an array")?;
+        let notes = notes
+            .into_iter()
+            .map(|note| note.into_string().map_err(|_| "every note must be a string".to_string()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if verdict != analysis.verdict {
+            analysis.verdict = verdict;
+            analysis.same_author = verdict == Verdict::SameAuthor;
+        }
+        analysis.notes.extend(notes);
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "scripting"))]
+mod tests {
+    use super::*;
+    use crate::comparison::compare;
+    use crate::tokenizer::new_tokenizer;
+
+    #[test]
+    fn test_verdict_hooks() {
+        let dir = std::env::temp_dir().join(format!("author-comparer-hooks-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let short = dir.join("short.rhai");
+        std::fs::write(
+            &short,
+            r#"if lengths != () && lengths[0] < 200 && lengths[1] < 200 && genres != () && genres[0] != genres[1] {
+                verdict = "inconclusive";
+                notes.push("both texts are short and their genres differ");
+            }"#,
+        )
+        .unwrap();
+        let spin = dir.join("spin.rhai");
+        std::fs::write(&spin, "loop { verdict = \"same_author\"; }").unwrap();
+        let config = HooksConfig {
+            default: Some(short),
+            tenants: BTreeMap::from([("acme".to_string(), spin)]),
+            ..HooksConfig::default()
+        };
+        let hooks = Hooks::from_config(&config).unwrap().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let tokenizer = new_tokenizer().unwrap();
+        let scored = || compare("私は今日公園に行きました。", "私は昨日公園で遊びました。", &tokenizer).unwrap();
+        let context = HookContext {
+            lengths: Some([13, 13]),
+            genres: Some(["blog", "novel"]),
+            ..HookContext::default()
+        };
+        let mut analysis = scored();
+        hooks.apply(&mut analysis, &context);
+        assert_eq!((analysis.verdict, analysis.same_author), (Verdict::Inconclusive, false));
+        assert_eq!(analysis.notes, ["both texts are short and their genres differ"]);
+        assert_eq!(analysis.confidence, scored().confidence);
+
+        let mut analysis = scored();
+        hooks.apply(&mut analysis, &HookContext { genres: None, ..context });
+        assert_eq!(analysis.verdict, scored().verdict);
+
+        // Stopped by the operation limit, leaving the result as scored
+        let mut analysis = scored();
+        hooks.apply(&mut analysis, &HookContext { tenant: Some("acme"), ..context });
+        assert_eq!(analysis.verdict, scored().verdict);
+        assert_eq!(analysis.warnings.last().map(|w| w.code), Some(WarningCode::HookFailed));
+        assert!(Hooks::from_config(&HooksConfig::default()).unwrap().is_none());
+    }
+}
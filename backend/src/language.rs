@@ -0,0 +1,98 @@
+use crate::sentences;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    Japanese,
+    English,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Segment<'a> {
+    pub text: &'a str,
+    pub language: Language,
+}
+
+fn is_japanese_char(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{309F}' // Hiragana
+        | '\u{30A0}'..='\u{30FF}' // Katakana
+        | '\u{31F0}'..='\u{31FF}' // Katakana phonetic extensions
+        | '\u{4E00}'..='\u{9FFF}' // CJK unified ideographs
+        | '\u{3400}'..='\u{4DBF}' // CJK extension A
+        | '\u{FF66}'..='\u{FF9F}' // Half-width katakana
+    )
+}
+
+pub fn detect_language(sentence: &str) -> Language {
+    let japanese = sentence.chars().filter(|c| is_japanese_char(*c)).count();
+    let latin = sentence.chars().filter(|c| c.is_ascii_alphabetic()).count();
+
+    if japanese == 0 && latin == 0 {
+        return Language::Unknown;
+    }
+
+    // A single Japanese character carries roughly as much as a short English
+    // word, so embedded product names like "Rust" don't flip a sentence.
+    if japanese * 3 >= latin {
+        Language::Japanese
+    } else {
+        Language::English
+    }
+}
+
+/// Splits text into sentences (see `sentences::split`) and tags each with its language.
+/// Segments keep their terminators, so concatenating them yields the input.
+pub fn segment(text: &str) -> Vec<Segment<'_>> {
+    sentences::split(text)
+        .into_iter()
+        .map(|text| Segment {
+            text,
+            language: detect_language(text),
+        })
+        .collect()
+}
+
+/// Ratio of English segments among segments with a detectable language.
+pub fn english_segment_ratio(segments: &[Segment]) -> f64 {
+    let detected = segments
+        .iter()
+        .filter(|s| s.language != Language::Unknown)
+        .count();
+    if detected == 0 {
+        return 0.0;
+    }
+    let english = segments
+        .iter()
+        .filter(|s| s.language == Language::English)
+        .count();
+    english as f64 / detected as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_language() {
+        assert_eq!(detect_language("私は今日公園に行きました。"), Language::Japanese);
+        assert_eq!(detect_language("システムの実装にはRustを使用しました。"), Language::Japanese);
+        assert_eq!(detect_language("Please find the attached report."), Language::English);
+        assert_eq!(detect_language("110%"), Language::Unknown);
+    }
+
+    #[test]
+    fn test_segment_round_trip() {
+        let text = "本日の会議は以上です。Thanks for joining. 次回もよろしく！";
+        let segments = segment(text);
+        let rebuilt: String = segments.iter().map(|s| s.text).collect();
+
+        assert_eq!(rebuilt, text);
+        assert_eq!(segments.len(), 3);
+        assert_eq!(segments[1].language, Language::English);
+        assert!((english_segment_ratio(&segments) - 1.0 / 3.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,73 @@
+pub mod alignment;
+pub mod anomaly;
+pub mod anonymize;
+pub mod aozora;
+pub mod aspects;
+pub mod attribution;
+pub mod baseline;
+pub mod calibration;
+pub mod chat;
+pub mod clustering;
+pub mod comparison;
+pub mod config;
+pub mod datasets;
+pub mod delta;
+pub mod embedding;
+pub mod evaluation;
+pub mod explain;
+pub mod export;
+pub mod features;
+pub mod git_audit;
+pub mod history;
+pub mod hooks;
+pub mod language;
+pub mod logistic;
+pub mod neural;
+pub mod numerics;
+pub mod overlap;
+pub mod plugins;
+pub mod pos;
+pub mod punctuation;
+pub mod preprocess;
+pub mod profile;
+pub mod profile_store;
+pub mod projection;
+pub mod record;
+pub mod register;
+pub mod robustness;
+pub mod schema;
+pub mod script;
+pub mod scoring;
+pub mod sentences;
+pub mod short_text;
+pub mod signing;
+pub mod symbols;
+#[cfg(any(feature = "history", feature = "queue"))]
+pub mod sync_postgres;
+pub mod synthetic;
+pub mod timeline;
+pub mod token_cache;
+pub mod token_filter;
+pub mod tokenizer;
+pub mod vocabulary;
+pub mod warnings;
+pub mod web_import;
+pub mod whitespace;
+
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "node")]
+mod node;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "wasm")]
+mod wasm;
+
+pub const MODEL_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+pub use comparison::{compare, compare_extracted, compare_with_config, compare_with_options, Analysis, CompareOptions, DetailedResult, Verdict};
+pub use config::Config;
+pub use features::{extract_features, TextFeatures};
+pub use profile::{build_profile, AuthorProfile};
@@ -0,0 +1,372 @@
+use crate::comparison::particle_divergence;
+use crate::config::ConfigError;
+use crate::datasets::Dataset;
+use crate::evaluation::fold_pairs;
+use crate::features::{extract_features, TextFeatures};
+use crate::numerics::{clamp, safe_div};
+use crate::register::register_difference;
+use crate::script::script_difference;
+use crate::scoring::{Backend, Scorer, ScoringInput};
+use crate::short_text::ngram_distance;
+use crate::tokenizer::{LinderaResult, Tokenizer};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+/// Absolute difference of every scalar feature and every part of speech's
+/// share, the cosine distances of the word frequencies, character n-grams
+/// and part-of-speech n-grams, and the divergence of the particle
+/// distributions, keyed by name.
+pub fn difference_vector(features1: &TextFeatures, features2: &TextFeatures) -> BTreeMap<String, f64> {
+    let scalars = |f: &TextFeatures| {
+        [
+            ("particle_ratio", f.particle_ratio),
+            ("verb_ratio", f.verb_ratio),
+            ("adjective_ratio", f.adjective_ratio),
+            ("unique_words_ratio", f.unique_words_ratio),
+            ("log_avg_sentence_length", f.avg_sentence_length.max(0.0).ln_1p()),
+            ("punctuation_ratio", f.punctuation_ratio),
+            ("english_segment_ratio", f.english_segment_ratio),
+            ("symbol_ratio", f.symbol_ratio),
+            ("unusual_whitespace_ratio", f.unusual_whitespace_ratio),
+        ]
+    };
+    let mut vector: BTreeMap<String, f64> = scalars(features1)
+        .iter()
+        .zip(scalars(features2))
+        .map(|((name, value1), (_, value2))| (name.to_string(), (value1 - value2).abs()))
+        .collect();
+
+    let tags: BTreeSet<_> = features1.pos_distribution.keys().chain(features2.pos_distribution.keys()).collect();
+    for tag in tags {
+        let share = |f: &TextFeatures| f.pos_distribution.get(tag).copied().unwrap_or(0.0);
+        // Serialized the way the features are, e.g. pos_auxiliary_verb
+        let name = serde_json::to_value(tag).ok().and_then(|v| v.as_str().map(str::to_string)).unwrap_or_default();
+        vector.insert(format!("pos_{}", name), (share(features1) - share(features2)).abs());
+    }
+
+    let dot: f64 = features1
+        .word_frequencies
+        .iter()
+        .filter_map(|(word, f1)| features2.word_frequencies.get(word).map(|f2| f1 * f2))
+        .sum();
+    let norm = |f: &TextFeatures| f.word_frequencies.values().map(|v| v * v).sum::<f64>().sqrt();
+    let similarity = clamp(safe_div(dot, norm(features1) * norm(features2), 0.0), 0.0, 1.0);
+    vector.insert("word_frequencies".to_string(), 1.0 - similarity);
+    if let Ok(distance) = ngram_distance(&features1.char_ngrams, &features2.char_ngrams) {
+        vector.insert("char_ngrams".to_string(), distance);
+    }
+    if let Ok(distance) = ngram_distance(&features1.pos_ngrams, &features2.pos_ngrams) {
+        vector.insert("pos_ngrams".to_string(), distance);
+    }
+    if !features1.particle_distribution.is_empty() && !features2.particle_distribution.is_empty() {
+        vector.insert("particle_distribution".to_string(), particle_divergence(features1, features2));
+    }
+    if let (Some(register1), Some(register2)) = (&features1.register, &features2.register) {
+        vector.insert("register".to_string(), register_difference(register1, register2));
+    }
+    if let (Some(ratios1), Some(ratios2)) = (&features1.script_ratios, &features2.script_ratios) {
+        vector.insert("script_ratios".to_string(), script_difference(ratios1, ratios2));
+    }
+    vector
+}
+
+/// Logistic model over `difference_vector`, as written by `train`. Features
+/// without a coefficient are ignored, and missing ones count as 0.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogisticModel {
+    pub intercept: f64,
+    pub coefficients: BTreeMap<String, f64>,
+    /// Pairs the model was fitted on.
+    #[serde(default)]
+    pub trained_on: usize,
+    /// How far each feature shifts between same-author pairs within and
+    /// across genres, as penalized in training (see `genre_sensitivity`).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub genre_sensitivity: BTreeMap<String, f64>,
+}
+
+impl LogisticModel {
+    pub fn load(path: &Path) -> Result<LogisticModel, ConfigError> {
+        let json = fs::read_to_string(path).map_err(|e| ConfigError::Scoring(format!("{}: {}", path.display(), e)))?;
+        serde_json::from_str(&json).map_err(|e| ConfigError::Scoring(format!("{}: {}", path.display(), e)))
+    }
+
+    pub fn probability(&self, differences: &BTreeMap<String, f64>) -> f64 {
+        let logit = self.coefficients.iter().fold(self.intercept, |logit, (name, coefficient)| {
+            logit + coefficient * differences.get(name).copied().unwrap_or(0.0)
+        });
+        1.0 / (1.0 + (-logit).exp())
+    }
+}
+
+impl Scorer for LogisticModel {
+    fn backend(&self) -> Backend {
+        Backend::Logistic
+    }
+
+    fn score(&self, input: &ScoringInput) -> Result<f64, String> {
+        Ok(self.probability(&difference_vector(input.features1, input.features2)))
+    }
+}
+
+/// A pair's difference vector and label, tagged with the genres of its two texts.
+#[derive(Debug, Clone)]
+pub struct TrainingPair {
+    pub differences: BTreeMap<String, f64>,
+    pub same_author: bool,
+    /// Sorted; None when either text's genre is unknown.
+    pub genres: Option<[String; 2]>,
+}
+
+impl TrainingPair {
+    pub fn untagged(differences: BTreeMap<String, f64>, same_author: bool) -> TrainingPair {
+        TrainingPair {
+            differences,
+            same_author,
+            genres: None,
+        }
+    }
+
+    /// The genre combination, e.g. `blog` or `blog+novel`.
+    pub fn combination(&self) -> Option<String> {
+        self.genres.as_ref().map(|[genre1, genre2]| {
+            if genre1 == genre2 { genre1.clone() } else { format!("{}+{}", genre1, genre2) }
+        })
+    }
+
+    pub fn cross_genre(&self) -> Option<bool> {
+        self.genres.as_ref().map(|[genre1, genre2]| genre1 != genre2)
+    }
+}
+
+/// Difference vectors of every pair in a dataset: every two labeled texts,
+/// each tokenized once and tagged with their genres, or the ready-made pairs.
+pub fn training_pairs(dataset: &Dataset, tokenizer: &Tokenizer) -> LinderaResult<Vec<TrainingPair>> {
+    match dataset {
+        Dataset::Texts(texts) => {
+            let features =
+                texts.iter().map(|t| extract_features(&t.text, tokenizer)).collect::<LinderaResult<Vec<TextFeatures>>>()?;
+            Ok(fold_pairs(texts, 1)
+                .into_iter()
+                .flatten()
+                .map(|(i, j, same_author)| {
+                    let genres = texts[i].genre.clone().zip(texts[j].genre.clone()).map(|(genre1, genre2)| {
+                        let mut genres = [genre1, genre2];
+                        genres.sort();
+                        genres
+                    });
+                    TrainingPair {
+                        differences: difference_vector(&features[i], &features[j]),
+                        same_author,
+                        genres,
+                    }
+                })
+                .collect())
+        }
+        Dataset::Pairs(pairs) => pairs
+            .iter()
+            .map(|pair| {
+                let (features1, features2) = (extract_features(&pair.text1, tokenizer)?, extract_features(&pair.text2, tokenizer)?);
+                Ok(TrainingPair::untagged(difference_vector(&features1, &features2), pair.same_author))
+            })
+            .collect(),
+    }
+}
+
+/// How much each feature's difference grows when one author writes in two
+/// genres instead of one: the mean over cross-genre same-author pairs minus
+/// the mean over same-genre ones, in standard deviations of all pairs, mapped
+/// onto [0, 1). Features that flip with the genre (a formal and a casual text
+/// by one author looking like two authors) score high; empty without both
+/// kinds of same-author pairs.
+pub fn genre_sensitivity(pairs: &[TrainingPair]) -> BTreeMap<String, f64> {
+    let same_author = |cross: bool| -> Vec<&TrainingPair> {
+        pairs.iter().filter(|p| p.same_author && p.cross_genre() == Some(cross)).collect()
+    };
+    let (within, across) = (same_author(false), same_author(true));
+    if within.is_empty() || across.is_empty() {
+        return BTreeMap::new();
+    }
+    let names: BTreeSet<&String> = pairs.iter().flat_map(|p| p.differences.keys()).collect();
+    let value = |pair: &TrainingPair, name: &String| pair.differences.get(name).copied().unwrap_or(0.0);
+    let mean = |pairs: &[&TrainingPair], name: &String| {
+        safe_div(pairs.iter().map(|p| value(p, name)).sum(), pairs.len() as f64, 0.0)
+    };
+    let all: Vec<&TrainingPair> = pairs.iter().collect();
+    names
+        .into_iter()
+        .map(|name| {
+            let overall = mean(&all, name);
+            let variance = safe_div(all.iter().map(|p| (value(p, name) - overall).powi(2)).sum(), all.len() as f64, 0.0);
+            let shift = safe_div(mean(&across, name) - mean(&within, name), variance.sqrt(), 0.0).max(0.0);
+            (name.clone(), shift / (1.0 + shift))
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TrainOptions {
+    pub epochs: usize,
+    pub learning_rate: f64,
+    /// L2 penalty on the standardized coefficients.
+    pub l2: f64,
+    /// Extra L2 penalty on each coefficient, scaled by the feature's genre
+    /// sensitivity, so genre-stable features carry the model.
+    pub genre_penalty: f64,
+}
+
+impl Default for TrainOptions {
+    fn default() -> Self {
+        TrainOptions {
+            epochs: 500,
+            learning_rate: 0.5,
+            l2: 0.01,
+            genre_penalty: 1.0,
+        }
+    }
+}
+
+/// Fits the model by batch gradient descent on standardized differences,
+/// then folds the standardization back into the coefficients.
+pub fn train(pairs: &[TrainingPair], options: &TrainOptions) -> LogisticModel {
+    let names: Vec<String> = pairs.iter().flat_map(|p| p.differences.keys().cloned()).collect::<BTreeSet<_>>().into_iter().collect();
+    let rows: Vec<Vec<f64>> = pairs
+        .iter()
+        .map(|p| names.iter().map(|name| p.differences.get(name).copied().unwrap_or(0.0)).collect())
+        .collect();
+    let n = rows.len() as f64;
+    let means: Vec<f64> = (0..names.len()).map(|j| safe_div(rows.iter().map(|r| r[j]).sum(), n, 0.0)).collect();
+    let std_devs: Vec<f64> = (0..names.len())
+        .map(|j| {
+            let variance = safe_div(rows.iter().map(|r| (r[j] - means[j]).powi(2)).sum(), n, 0.0);
+            // Constant features get a unit scale and keep a zero coefficient
+            if variance > 0.0 { variance.sqrt() } else { 1.0 }
+        })
+        .collect();
+    let standardized: Vec<Vec<f64>> = rows
+        .iter()
+        .map(|row| row.iter().enumerate().map(|(j, value)| (value - means[j]) / std_devs[j]).collect())
+        .collect();
+    let sensitivity = genre_sensitivity(pairs);
+    let penalties: Vec<f64> = names
+        .iter()
+        .map(|name| options.l2 + options.genre_penalty * sensitivity.get(name).copied().unwrap_or(0.0))
+        .collect();
+
+    let mut weights = vec![0.0; names.len()];
+    let mut bias = 0.0;
+    for _ in 0..options.epochs {
+        let mut gradient = vec![0.0; names.len()];
+        let mut bias_gradient = 0.0;
+        for (row, pair) in standardized.iter().zip(pairs) {
+            let logit = bias + row.iter().zip(&weights).map(|(x, w)| x * w).sum::<f64>();
+            let error = 1.0 / (1.0 + (-logit).exp()) - f64::from(u8::from(pair.same_author));
+            gradient.iter_mut().zip(row).for_each(|(g, x)| *g += error * x);
+            bias_gradient += error;
+        }
+        for ((w, g), penalty) in weights.iter_mut().zip(&gradient).zip(&penalties) {
+            *w -= options.learning_rate * (safe_div(*g, n, 0.0) + penalty * *w);
+        }
+        bias -= options.learning_rate * safe_div(bias_gradient, n, 0.0);
+    }
+
+    let coefficients: BTreeMap<String, f64> =
+        names.into_iter().zip(weights.iter().zip(&std_devs).map(|(w, s)| w / s)).collect();
+    let shift: f64 = weights.iter().zip(means.iter().zip(&std_devs)).map(|(w, (m, s))| w * m / s).sum();
+    LogisticModel {
+        intercept: bias - shift,
+        coefficients,
+        trained_on: pairs.len(),
+        genre_sensitivity: sensitivity.into_iter().filter(|(_, s)| *s > 0.0).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::features::PosCounts;
+    use crate::pos::Pos;
+    use std::collections::HashMap;
+
+    fn features(particle_ratio: f64, avg_sentence_length: f64) -> TextFeatures {
+        TextFeatures {
+            word_frequencies: HashMap::from([("雨".to_string(), 0.5), ("傘".to_string(), 0.5)]),
+            particle_ratio,
+            verb_ratio: 0.15,
+            adjective_ratio: 0.1,
+            unique_words_ratio: 0.8,
+            avg_sentence_length,
+            punctuation_ratio: 0.1,
+            english_segment_ratio: 0.0,
+            symbol_ratio: 0.0,
+            unusual_whitespace_ratio: 0.0,
+            token_count: 50,
+            sentence_count: 5,
+            pos_counts: PosCounts::default(),
+            pos_distribution: BTreeMap::from([(Pos::AuxiliaryVerb, 0.1)]),
+            char_ngrams: Default::default(),
+            pos_ngrams: Default::default(),
+            particle_distribution: Default::default(),
+            register: None,
+            script_ratios: None,
+        }
+    }
+
+    #[test]
+    fn test_logistic_model() {
+        let vector = difference_vector(&features(0.2, 15.0), &features(0.3, 15.0));
+        assert!((vector["particle_ratio"] - 0.1).abs() < 1e-9);
+        assert_eq!(vector["pos_auxiliary_verb"], 0.0);
+        assert!(vector["word_frequencies"].abs() < 1e-9);
+
+        // Same-author pairs differ little in particle use, different-author ones a lot
+        let pairs: Vec<TrainingPair> = (0..40)
+            .map(|i| {
+                let same = i % 2 == 0;
+                let gap = if same { 0.01 } else { 0.15 } + (i % 5) as f64 * 0.005;
+                TrainingPair::untagged(difference_vector(&features(0.2, 15.0), &features(0.2 + gap, 15.0 + (i % 3) as f64)), same)
+            })
+            .collect();
+        let model = train(&pairs, &TrainOptions::default());
+        assert_eq!(model.trained_on, 40);
+        assert!(model.coefficients["particle_ratio"] < 0.0);
+
+        let close = difference_vector(&features(0.2, 15.0), &features(0.21, 16.0));
+        let far = difference_vector(&features(0.2, 15.0), &features(0.36, 16.0));
+        assert!(model.probability(&close) > 0.5);
+        assert!(model.probability(&far) < 0.5);
+    }
+
+    #[test]
+    fn test_genre_robust_training() {
+        // One author's blog posts and novels differ in sentence length, as do
+        // different authors; particle use only tells the authors apart
+        let pair = |particle_gap: f64, length_gap: f64, same_author: bool, genres: [&str; 2]| TrainingPair {
+            differences: difference_vector(&features(0.2, 15.0), &features(0.2 + particle_gap, 15.0 + length_gap)),
+            same_author,
+            genres: Some(genres.map(str::to_string)),
+        };
+        let pairs: Vec<TrainingPair> = (0..30)
+            .map(|i| match i % 3 {
+                0 => pair(0.01, 0.0, true, ["blog", "blog"]),
+                1 => pair(0.01, 10.0, true, ["blog", "novel"]),
+                _ => pair(0.15, 10.0, false, ["blog", "blog"]),
+            })
+            .collect();
+        assert_eq!(pairs[1].combination().as_deref(), Some("blog+novel"));
+
+        let sensitivity = genre_sensitivity(&pairs);
+        assert!(sensitivity["log_avg_sentence_length"] > 0.5);
+        assert!(sensitivity.get("particle_ratio").copied().unwrap_or(0.0) < 0.01);
+
+        let plain = train(&pairs, &TrainOptions { genre_penalty: 0.0, ..TrainOptions::default() });
+        let robust = train(&pairs, &TrainOptions::default());
+        let length = |model: &LogisticModel| model.coefficients["log_avg_sentence_length"].abs();
+        assert!(length(&robust) < length(&plain));
+        assert!(robust.genre_sensitivity.contains_key("log_avg_sentence_length"));
+        // A formal and a casual text by one author still read as the same author
+        assert!(robust.probability(&pairs[1].differences) > 0.5);
+    }
+}
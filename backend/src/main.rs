@@ -1,448 +1,789 @@
-use actix_cors::Cors;
-use actix_web::{post, web, App, HttpServer, Result};
-use lindera_core::mode::Mode;
-use lindera_dictionary::{DictionaryConfig, DictionaryKind};
-use lindera_tokenizer::tokenizer::{Tokenizer, TokenizerConfig};
-use nalgebra::DVector;
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+mod cli;
+
+use author_comparer::attribution::{attribute, write_report, AttributionMode};
+use author_comparer::calibration::{scored_pairs, Calibration};
+use author_comparer::chat::{compare_speakers, load_chat, speaker_profiles, ChatFormat};
+use author_comparer::config::Config;
+use author_comparer::datasets::Dataset;
+use author_comparer::evaluation::cross_validate;
+use author_comparer::datasets::write_pan;
+use author_comparer::export::feature_table;
+use author_comparer::comparison::compare_extracted;
+use author_comparer::comparison::Analysis;
+use author_comparer::delta::{aspect_deltas, AspectDelta};
+use author_comparer::embedding::Embedder;
+use author_comparer::git_audit::{audit, read_commits};
+use author_comparer::logistic::{train, training_pairs, TrainOptions, TrainingPair};
+use author_comparer::features::{extract_features, extract_features_filtered};
+use author_comparer::profile::{build_profile, mean_features, merge_profiles, migrate_profile, sample_fit, ProfileMigration};
+use author_comparer::profile_store::ProfileStore;
+use author_comparer::record::{drift, ExperimentRecord};
+use author_comparer::scoring::Scorers;
+use author_comparer::synthetic::{generate, SyntheticOptions};
+use author_comparer::tokenizer::{new_tokenizer, TokenizerPool, TokenizerSpec};
+use clap::Parser;
+use cli::{Cli, Command, ProfileCommand};
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+// How often the watched draft's modification time is checked
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
 
-#[derive(Debug, Deserialize, Serialize)]
-struct ComparisonQuery {
-    text1: String,
-    text2: String,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct Analysis {
-    same_author: bool,
-    confidence: f64,
-    detailed_analysis: Vec<DetailedResult>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct DetailedResult {
-    aspect: String,
-    difference: f64,
-    explanation: String,
-}
-
-struct TextFeatures {
-    word_frequencies: HashMap<String, f64>,
-    particle_ratio: f64,
-    verb_ratio: f64,
-    adjective_ratio: f64,
-    unique_words_ratio: f64,
-    avg_sentence_length: f64,
-    punctuation_ratio: f64,
-}
-
-fn extract_features(text: &str, tokenizer: &Tokenizer) -> TextFeatures {
-    let tokens = tokenizer.tokenize(text).unwrap();
-    let total_tokens = tokens.len() as f64;
-    let mut word_frequencies: HashMap<String, f64> = HashMap::new();
-    let mut pos_frequencies: HashMap<String, f64> = HashMap::new();
-    let mut punctuation_count = 0.0;
-
-    // Count sentences by looking for sentence endings
-    let sentence_count = text
-        .split(['.', '!', '?'])
-        .filter(|s| !s.trim().is_empty())
-        .count() as f64;
-
-    // Handle empty or very short text
-    if total_tokens < 2.0 {
-        return TextFeatures {
-            word_frequencies: HashMap::new(),
-            particle_ratio: 0.0,
-            verb_ratio: 0.0,
-            adjective_ratio: 0.0,
-            unique_words_ratio: 0.0,
-            avg_sentence_length: total_tokens,
-            punctuation_ratio: 0.0,
-        };
-    }
-
-    for mut token in tokens {
-        let word = token.text.to_string();
-        if !word.chars().all(|c| c.is_ascii_punctuation()) {
-            *word_frequencies.entry(word.clone()).or_insert(0.0) += 1.0;
-        } else {
-            punctuation_count += 1.0;
+#[actix_web::main]
+async fn main() -> io::Result<()> {
+    match Cli::parse().command {
+        None | Some(Command::Serve) => author_comparer::server::run().await,
+        Some(Command::Evaluate {
+            corpus,
+            format,
+            truth,
+            folds,
+        }) => {
+            let config = Config::from_env().map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            let tokenizer = new_tokenizer().map_err(|e| io::Error::other(e.to_string()))?;
+            let scorers =
+                Scorers::from_config(&config, &tokenizer).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            let dataset = cli::load_dataset(&corpus, format, truth.as_deref())
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            let unit = if matches!(dataset, Dataset::Pairs(_)) { "pairs" } else { "texts" };
+            println!("{} {} from {}", dataset.len(), unit, corpus.display());
+            let results = cross_validate(&dataset, folds, &tokenizer, &config, scorers.default_scorer())
+                .map_err(|e| io::Error::other(e.to_string()))?;
+            cli::print_report(&results);
+            Ok(())
         }
-
-        // Get part of speech from token
-        let pos = if let Some(details) = token.get_details() {
-            if let Some(pos) = details.get(0) {
-                pos
-            } else {
-                ""
+        Some(Command::Generate {
+            corpus,
+            format,
+            output,
+            pairs,
+            min_length,
+            max_length,
+            same_author_share,
+            mix_genres,
+            seed,
+        }) => {
+            let dataset =
+                cli::load_dataset(&corpus, format, None).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            let Dataset::Texts(texts) = dataset else {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "generate needs single labeled texts, not pairs"));
+            };
+            let options = SyntheticOptions {
+                pairs,
+                min_length,
+                max_length,
+                same_author_share,
+                mix_genres,
+                seed,
+            };
+            let generated = generate(&texts, &options);
+            std::fs::create_dir_all(&output)?;
+            write_pan(&generated, &output.join("pairs.jsonl"), &output.join("truth.jsonl"))?;
+            let same = generated.iter().filter(|p| p.same_author).count();
+            println!(
+                "{} pairs ({} same author, {} different) written to {}",
+                generated.len(),
+                same,
+                generated.len() - same,
+                output.display()
+            );
+            Ok(())
+        }
+        Some(Command::Export {
+            corpus,
+            format,
+            output,
+            to,
+            vocabulary,
+        }) => {
+            let dataset =
+                cli::load_dataset(&corpus, format, None).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            let Dataset::Texts(texts) = dataset else {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "export needs single labeled texts, not pairs"));
+            };
+            let tokenizer = new_tokenizer().map_err(|e| io::Error::other(e.to_string()))?;
+            let table = feature_table(&texts, vocabulary, &tokenizer).map_err(|e| io::Error::other(e.to_string()))?;
+            let mut file = io::BufWriter::new(std::fs::File::create(&output)?);
+            table.write(to.into(), &mut file)?;
+            io::Write::flush(&mut file)?;
+            println!("{} rows of {} features written to {}", table.rows.len(), table.columns.len(), output.display());
+            Ok(())
+        }
+        Some(Command::Train {
+            corpus,
+            format,
+            truth,
+            output,
+            epochs,
+            l2,
+            genre_penalty,
+        }) => {
+            let dataset = cli::load_dataset(&corpus, format, truth.as_deref())
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            let tokenizer = new_tokenizer().map_err(|e| io::Error::other(e.to_string()))?;
+            let pairs = training_pairs(&dataset, &tokenizer).map_err(|e| io::Error::other(e.to_string()))?;
+            if !pairs.iter().any(|p| p.same_author) || pairs.iter().all(|p| p.same_author) {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "training needs both same- and different-author pairs"));
             }
-        } else {
-            ""
-        };
-
-        match pos {
-            "助詞" | "動詞" | "形容詞" => {
-                *pos_frequencies.entry(pos.to_string()).or_insert(0.0) += 1.0;
+            let options = TrainOptions {
+                epochs,
+                l2,
+                genre_penalty,
+                ..TrainOptions::default()
+            };
+            let model = train(&pairs, &options);
+            let correct = |pairs: &[&TrainingPair]| {
+                pairs.iter().filter(|p| (model.probability(&p.differences) > 0.5) == p.same_author).count()
+            };
+            std::fs::write(&output, serde_json::to_string_pretty(&model).map_err(io::Error::other)?)?;
+            println!(
+                "{} coefficients fitted on {} pairs ({:.3} training accuracy), written to {}",
+                model.coefficients.len(),
+                pairs.len(),
+                correct(&pairs.iter().collect::<Vec<_>>()) as f64 / pairs.len() as f64,
+                output.display()
+            );
+            // Cross-genre pairs are where a model leaning on genre-bound features fails
+            let mut combinations: BTreeMap<String, Vec<&TrainingPair>> = BTreeMap::new();
+            for pair in &pairs {
+                if let Some(combination) = pair.combination() {
+                    combinations.entry(combination).or_default().push(pair);
+                }
+            }
+            for (combination, pairs) in &combinations {
+                println!("  {}: {} pairs, {:.3} accuracy", combination, pairs.len(), correct(pairs) as f64 / pairs.len() as f64);
+            }
+            let mut sensitive: Vec<_> = model.genre_sensitivity.iter().collect();
+            sensitive.sort_by(|a, b| b.1.total_cmp(a.1));
+            for (feature, sensitivity) in sensitive.iter().take(5) {
+                println!("  genre-sensitive: {} ({:.2})", feature, sensitivity);
+            }
+            Ok(())
+        }
+        Some(Command::Calibrate {
+            corpus,
+            format,
+            truth,
+            output,
+            buckets,
+        }) => {
+            let mut config = Config::from_env().map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            // Fitted on the raw scores, not on those of an earlier calibration
+            config.scoring.calibration = None;
+            let tokenizer = new_tokenizer().map_err(|e| io::Error::other(e.to_string()))?;
+            let scorers =
+                Scorers::from_config(&config, &tokenizer).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            let dataset = cli::load_dataset(&corpus, format, truth.as_deref())
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            let scorer = scorers.default_scorer();
+            let scored = scored_pairs(&dataset, &tokenizer, &config, scorer).map_err(|e| io::Error::other(e.to_string()))?;
+            let calibration = Calibration::fit(scorer.backend(), &scored, &buckets);
+            std::fs::write(&output, serde_json::to_string_pretty(&calibration).map_err(io::Error::other)?)?;
+            for bucket in &calibration.buckets {
+                let range = match bucket.max_tokens {
+                    Some(max) => format!("{}-{}", bucket.min_tokens, max),
+                    None => format!("{}+", bucket.min_tokens),
+                };
+                println!("{} tokens: {} pairs, 0.7 -> {:.3}", range, bucket.pairs, calibration.apply(0.7, bucket.min_tokens));
             }
-            _ => {}
+            println!(
+                "{} of {} length buckets fitted on {} pairs for the {} backend, written to {}",
+                calibration.buckets.len(),
+                buckets.len() + 1,
+                scored.len(),
+                scorer.backend().name(),
+                output.display()
+            );
+            Ok(())
         }
+        Some(Command::Attribute {
+            known,
+            format,
+            disputed,
+            output,
+            to,
+            closed_set,
+            reject_below,
+        }) => {
+            let dataset =
+                cli::load_dataset(&known, format, None).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            let Dataset::Texts(known) = dataset else {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "attribute needs single labeled texts, not pairs"));
+            };
+            let documents = author_comparer::datasets::text_files(&disputed)?
+                .into_iter()
+                .map(|file| {
+                    let name = file.file_name().unwrap_or_default().to_string_lossy().into_owned();
+                    Ok((name, std::fs::read_to_string(&file)?))
+                })
+                .collect::<io::Result<Vec<_>>>()?;
+            if documents.is_empty() {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("no .txt files in {}", disputed.display())));
+            }
+            let mut config = Config::from_env().map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            if reject_below.is_some() {
+                config.attribution.reject_below = reject_below;
+            }
+            let mode = if closed_set { AttributionMode::Closed } else { AttributionMode::Open };
+            let tokenizer = new_tokenizer().map_err(|e| io::Error::other(e.to_string()))?;
+            let scorers =
+                Scorers::from_config(&config, &tokenizer).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            let results = attribute(&known, &documents, &tokenizer, &config, scorers.default_scorer(), mode)
+                .map_err(|e| io::Error::other(e.to_string()))?;
+            let mut file = io::BufWriter::new(std::fs::File::create(&output)?);
+            write_report(&results, to.into(), &mut file)?;
+            io::Write::flush(&mut file)?;
+            for result in &results {
+                if let Some(top) = result.candidates.first() {
+                    let named = result.best_match.as_deref().unwrap_or("(none of the candidates)");
+                    println!("{:<32} {:<24} {:>6.3}", result.document, named, top.confidence);
+                }
+            }
+            println!("{} documents attributed, report written to {}", results.len(), output.display());
+            Ok(())
+        }
+        Some(Command::MigrateProfiles { input, output }) => {
+            let output = output.unwrap_or_else(|| input.clone());
+            std::fs::create_dir_all(&output)?;
+            let tokenizer = new_tokenizer().map_err(|e| io::Error::other(e.to_string()))?;
+
+            let mut files: Vec<_> = std::fs::read_dir(&input)?
+                .map(|entry| entry.map(|e| e.path()))
+                .collect::<io::Result<_>>()?;
+            files.retain(|path| path.extension().is_some_and(|ext| ext == "json"));
+            files.sort();
+
+            let mut failed = 0;
+            for path in files {
+                let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+                let stored: serde_json::Value = match serde_json::from_str(&std::fs::read_to_string(&path)?) {
+                    Ok(stored) => stored,
+                    Err(e) => {
+                        println!("{}: failed: {}", name, e);
+                        failed += 1;
+                        continue;
+                    }
+                };
+                match migrate_profile(stored, &tokenizer) {
+                    Ok((profile, migration)) => {
+                        let json = serde_json::to_string_pretty(&profile).map_err(io::Error::other)?;
+                        std::fs::write(output.join(&name), json)?;
+                        let status = match migration {
+                            ProfileMigration::UpToDate => "up to date".to_string(),
+                            ProfileMigration::Mapped { from } => format!("mapped from version {}", from),
+                            ProfileMigration::Reextracted { from } => format!("re-extracted from version {}", from),
+                        };
+                        println!("{}: {}", name, status);
+                    }
+                    Err(e) => {
+                        println!("{}: failed: {}", name, e);
+                        failed += 1;
+                    }
+                }
+            }
+            if failed > 0 {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} profiles could not be migrated", failed)));
+            }
+            Ok(())
+        }
+        Some(Command::CompareDirs { dir1, dir2, outliers }) => compare_dirs(&dir1, &dir2, outliers),
+        #[cfg(feature = "aozora")]
+        Some(Command::ImportAozora { sources, output }) => import_aozora(&sources, &output),
+        #[cfg(feature = "web-import")]
+        Some(Command::ImportWeb {
+            urls,
+            warc,
+            jsonl,
+            output,
+            min_chars,
+            any_language,
+        }) => import_web(urls.as_deref(), &warc, &jsonl, &output, min_chars, any_language),
+        Some(Command::CompareChat {
+            file,
+            format,
+            min_messages,
+        }) => compare_chat(&file, format.map(Into::into), min_messages),
+        Some(Command::AuditCommits {
+            repo,
+            limit,
+            min_commits,
+            threshold,
+        }) => {
+            let config = Config::from_env().map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            let tokenizer = new_tokenizer().map_err(|e| io::Error::other(e.to_string()))?;
+            let scorers =
+                Scorers::from_config(&config, &tokenizer).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            let commits = read_commits(&repo, limit)?;
+            let suspicious = audit(&commits, &tokenizer, &config, scorers.default_scorer(), min_commits, threshold)
+                .map_err(|e| io::Error::other(e.to_string()))?;
+            println!("{} commits read, {} flagged", commits.len(), suspicious.len());
+            for commit in suspicious {
+                let hash = &commit.hash[..commit.hash.len().min(10)];
+                println!("{} {:>6.3} {}: {}", hash, commit.confidence, commit.author, commit.summary);
+            }
+            Ok(())
+        }
+        Some(Command::Watch {
+            reference,
+            draft,
+            threshold,
+        }) => watch(&reference, &draft, threshold),
+        Some(Command::Replay {
+            record,
+            current_config,
+            threshold,
+        }) => replay(&record, current_config, threshold),
+        Some(Command::Regress {
+            records,
+            current_config,
+            tolerance,
+        }) => regress(&records, current_config, tolerance),
+        Some(Command::Profile { command }) => profile(command),
     }
+}
 
-    let content_tokens = total_tokens - punctuation_count;
-    let min_ratio = 0.1; // Minimum ratio to ensure non-zero confidence
-    
-    TextFeatures {
-        word_frequencies: word_frequencies
+fn compare_dirs(dir1: &Path, dir2: &Path, outliers: usize) -> io::Result<()> {
+    let config = Config::from_env().map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let tokenizer = new_tokenizer().map_err(|e| io::Error::other(e.to_string()))?;
+    let scorers =
+        Scorers::from_config(&config, &tokenizer).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let scorer = scorers.default_scorer();
+
+    let mut bodies = Vec::new();
+    for dir in [dir1, dir2] {
+        let files = author_comparer::datasets::text_files(dir)?;
+        if files.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("no .txt files in {}", dir.display())));
+        }
+        let features = files
             .iter()
-            .map(|(k, v)| (k.clone(), v / content_tokens))
-            .collect(),
-        particle_ratio: (pos_frequencies.get("助詞").unwrap_or(&0.0) / content_tokens).max(min_ratio),
-        verb_ratio: (pos_frequencies.get("動詞").unwrap_or(&0.0) / content_tokens).max(min_ratio),
-        adjective_ratio: (pos_frequencies.get("形容詞").unwrap_or(&0.0) / content_tokens).max(min_ratio),
-        unique_words_ratio: if content_tokens > 0.0 { word_frequencies.len() as f64 / content_tokens } else { 0.0 },
-        avg_sentence_length: if sentence_count > 0.0 { content_tokens / sentence_count } else { content_tokens },
-        punctuation_ratio: if total_tokens > 0.0 { punctuation_count / total_tokens } else { 0.0 },
+            .map(|file| {
+                let text = std::fs::read_to_string(file)?;
+                extract_features(&text, &tokenizer).map_err(|e| io::Error::other(e.to_string()))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        bodies.push((dir, files, features));
     }
-}
-
-fn calculate_frequency_similarity(
-    freq1: &HashMap<String, f64>,
-    freq2: &HashMap<String, f64>,
-) -> f64 {
-    let mut all_words: Vec<String> = freq1.keys().cloned().collect();
-    all_words.extend(freq2.keys().cloned());
-    all_words.sort_unstable();
-    all_words.dedup();
-
-    let vec1: Vec<f64> = all_words
-        .iter()
-        .map(|word| *freq1.get(word).unwrap_or(&0.0))
-        .collect();
-    let vec2: Vec<f64> = all_words
-        .iter()
-        .map(|word| *freq2.get(word).unwrap_or(&0.0))
-        .collect();
 
-    let v1 = DVector::from_vec(vec1);
-    let v2 = DVector::from_vec(vec2);
-
-    println!("v1: {:#?}, v2: {:#?}", v1.norm(), v2.norm());
+    // Each folder is averaged sample by sample, as a profile would be
+    let analysis = compare_extracted(&mean_features(&bodies[0].2), &mean_features(&bodies[1].2), &config, scorer);
+    println!(
+        "{} ({} files) vs {} ({} files): {:?}, confidence {:.3}",
+        dir1.display(),
+        bodies[0].1.len(),
+        dir2.display(),
+        bodies[1].1.len(),
+        analysis.verdict,
+        analysis.confidence
+    );
+    for result in &analysis.detailed_analysis {
+        match result.difference {
+            Some(difference) => println!("  {:<24} {:>8.3}", result.aspect, difference),
+            None => println!("  {:<24} {:>8}", result.aspect, "-"),
+        }
+    }
 
-    let cosine_similarity = (v1.dot(&v2)) / (v1.norm() * v2.norm());
-    cosine_similarity
+    for (dir, files, features) in &bodies {
+        let fit = sample_fit(features, &config, scorer);
+        if fit.is_empty() {
+            continue;
+        }
+        let mut ranked: Vec<usize> = (0..fit.len()).collect();
+        ranked.sort_by(|&a, &b| fit[a].total_cmp(&fit[b]));
+        println!();
+        println!("least like the rest of {} (confidence against the other files):", dir.display());
+        for &i in ranked.iter().take(outliers) {
+            let name = files[i].file_name().unwrap_or_default().to_string_lossy();
+            println!("  {:<32} {:>6.3}", name, fit[i]);
+        }
+    }
+    Ok(())
 }
 
-fn compare_features(features1: &TextFeatures, features2: &TextFeatures) -> Vec<DetailedResult> {
-    let mut results = Vec::new();
-    
-    // Compare word frequency distributions
-    let freq_similarity = calculate_frequency_similarity(&features1.word_frequencies, &features2.word_frequencies);
-    results.push(DetailedResult {
-        aspect: "Word Usage".to_string(),
-        difference: 1.0 - freq_similarity,
-        explanation: "Difference in word choice and frequency".to_string(),
-    });
-
-    // Compare basic text statistics with tolerance for different text lengths
-    let length_ratio = if features1.avg_sentence_length > features2.avg_sentence_length {
-        features2.avg_sentence_length / features1.avg_sentence_length
-    } else {
-        features1.avg_sentence_length / features2.avg_sentence_length
-    };
-
-    results.push(DetailedResult {
-        aspect: "Sentence Length".to_string(),
-        difference: (1.0 - length_ratio).min(0.5), // Cap the difference at 0.5 to avoid over-penalizing
-        explanation: "Difference in average sentence length".to_string(),
-    });
-
-    // Compare writing style markers
-    let style_markers = vec![
-        ("Particle Usage", features1.particle_ratio, features2.particle_ratio),
-        ("Verb Usage", features1.verb_ratio, features2.verb_ratio),
-        ("Adjective Usage", features1.adjective_ratio, features2.adjective_ratio),
-        ("Punctuation", features1.punctuation_ratio, features2.punctuation_ratio),
-    ];
+#[cfg(feature = "aozora")]
+fn import_aozora(sources: &[String], output: &Path) -> io::Result<()> {
+    use author_comparer::aozora::{read_source, source_files, write_corpus};
 
-    println!("Style markers: {:#?}", style_markers);
+    let mut expanded = Vec::new();
+    for source in sources {
+        if Path::new(source).is_dir() {
+            expanded.extend(source_files(Path::new(source))?.into_iter().map(|p| p.to_string_lossy().into_owned()));
+        } else {
+            expanded.push(source.clone());
+        }
+    }
+    // One unreadable work shouldn't stop a large import
+    let mut works = Vec::new();
+    for source in &expanded {
+        match read_source(source) {
+            Ok(read) => works.extend(read),
+            Err(e) => eprintln!("skipped {}: {}", source, e),
+        }
+    }
+    let written = write_corpus(&works, output)?;
+    let authors: std::collections::BTreeSet<&str> = works.iter().map(|w| w.author.as_str()).collect();
+    println!("Wrote {} works by {} authors to {}", written.len(), authors.len(), output.display());
+    Ok(())
+}
 
-    for (name, ratio1, ratio2) in style_markers {
-        let ratio_diff = (ratio1 - ratio2).abs();
-        results.push(DetailedResult {
-            aspect: name.to_string(),
-            difference: ratio_diff.min(0.5), // Cap the difference at 0.5
-            explanation: format!("Difference in {}", name.to_lowercase()),
-        });
+#[cfg(feature = "web-import")]
+fn import_web(
+    urls: Option<&Path>,
+    warc: &[std::path::PathBuf],
+    jsonl: &[std::path::PathBuf],
+    output: &Path,
+    min_chars: usize,
+    any_language: bool,
+) -> io::Result<()> {
+    use author_comparer::language::Language;
+    use author_comparer::web_import::{fetch, parse_jsonl, read_warc, write_documents};
+
+    let mut documents = Vec::new();
+    if let Some(urls) = urls {
+        let list = std::fs::read_to_string(urls)?;
+        for url in list.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            match fetch(url) {
+                Ok(document) => documents.push(document),
+                Err(e) => eprintln!("skipped {}: {}", url, e),
+            }
+        }
+    }
+    for path in warc {
+        documents.extend(read_warc(path)?);
+    }
+    for path in jsonl {
+        let dump = std::fs::read_to_string(path)?;
+        documents.extend(parse_jsonl(&dump).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?);
     }
 
-    // Compare vocabulary richness
-    let vocab_diff = (features1.unique_words_ratio - features2.unique_words_ratio).abs();
-    results.push(DetailedResult {
-        aspect: "Vocabulary Richness".to_string(),
-        difference: vocab_diff.min(0.5), // Cap the difference at 0.5
-        explanation: "Difference in vocabulary diversity".to_string(),
+    let read = documents.len();
+    documents.retain(|document| {
+        document.text.chars().count() >= min_chars
+            && (document.language == Language::Japanese || any_language && document.language == Language::English)
     });
-
-    results
+    let written = write_documents(&documents, output)?;
+    println!(
+        "{} documents read, {} kept, {} new in {}",
+        read,
+        documents.len(),
+        written,
+        output.display()
+    );
+    Ok(())
 }
 
-fn calculate_confidence(details: &[DetailedResult]) -> f64 {
-    let total_weight = details.len() as f64;
-    let weighted_sum: f64 = details
-        .iter()
-        .map(|detail| {
-            let weight = match detail.aspect.as_str() {
-                "Word Usage" => 3.0, // Increase weight of word usage
-                "Sentence Length" => 1.5,
-                "Particle Usage" => 1.5,
-                "Verb Usage" => 1.2,
-                "Adjective Usage" => 1.2,
-                "Vocabulary Richness" => 1.5,
-                _ => 1.0,
-            };
-            (1.0 - detail.difference) * weight
-        })
-        .sum();
-
-    let confidence = weighted_sum / (total_weight * 2.0); // Adjust normalization for new max weight
-    clamp(confidence, 0.0, 1.0)
-}
+fn compare_chat(file: &Path, format: Option<ChatFormat>, min_messages: usize) -> io::Result<()> {
+    let format = format.or_else(|| ChatFormat::from_path(file)).ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, format!("can't tell the chat format of {}; pass --format", file.display()))
+    })?;
+    let config = Config::from_env().map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let tokenizer = new_tokenizer().map_err(|e| io::Error::other(e.to_string()))?;
+    let scorers =
+        Scorers::from_config(&config, &tokenizer).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+    let messages = load_chat(file, format).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    let profiles = speaker_profiles(&messages, &tokenizer, min_messages).map_err(|e| io::Error::other(e.to_string()))?;
+    println!("{} messages, {} speakers with at least {} messages:", messages.len(), profiles.len(), min_messages);
+    for (speaker, profile) in &profiles {
+        println!("  {:<24} {:>6}", speaker, profile.sample_count);
+    }
+    if profiles.len() < 2 {
+        return Ok(());
+    }
 
-fn clamp(value: f64, min: f64, max: f64) -> f64 {
-    value.min(max).max(min)
+    println!();
+    for pair in compare_speakers(&profiles, &config, scorers.default_scorer()) {
+        println!("{:<24} {:<24} {:?}, confidence {:.3}", pair.speaker1, pair.speaker2, pair.verdict, pair.confidence);
+    }
+    Ok(())
 }
 
-#[post("/compare")]
-async fn compare_texts(body: web::Json<ComparisonQuery>) -> Result<web::Json<Analysis>> {
-    let config = TokenizerConfig {
-        dictionary: DictionaryConfig {
-            kind: Some(DictionaryKind::IPADIC),
-            path: None,
-        },
-        user_dictionary: None,
-        mode: Mode::Normal,
+fn watch(reference: &Path, draft: &Path, threshold: f64) -> io::Result<()> {
+    let config = Config::from_env().map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let pipeline = config.pipeline().map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let filters = config.token_filters().map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let tokenizer = new_tokenizer().map_err(|e| io::Error::other(e.to_string()))?;
+    let scorers =
+        Scorers::from_config(&config, &tokenizer).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let extract = |path: &Path| -> io::Result<_> {
+        let text = pipeline.apply(&std::fs::read_to_string(path)?);
+        extract_features_filtered(&text, &tokenizer, &filters).map_err(|e| io::Error::other(e.to_string()))
     };
-
-    let tokenizer = Tokenizer::from_config(config).unwrap();
-    let features1 = extract_features(&body.text1, &tokenizer);
-    let features2 = extract_features(&body.text2, &tokenizer);
-
-    // Calculate overall similarity score
-    let detailed_analysis = compare_features(&features1, &features2);
-
-    // Calculate overall difference and determine if same author
-    let confidence = calculate_confidence(&detailed_analysis);
-    let same_author = confidence > 0.6; // Increase threshold to be more strict
-
-    Ok(web::Json(Analysis {
-        same_author,
-        confidence,
-        detailed_analysis,
-    }))
+    // The reference is extracted once; only the draft changes between runs
+    let reference_features = extract(reference)?;
+    println!("Watching {} against {}; press Ctrl-C to stop", draft.display(), reference.display());
+
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    let mut seen: Option<SystemTime> = None;
+    let mut previous: Option<Analysis> = None;
+    loop {
+        let current = modified(draft);
+        if current.is_none() || current == seen {
+            std::thread::sleep(WATCH_INTERVAL);
+            continue;
+        }
+        seen = current;
+
+        // A half-written save is read again on the next change
+        let features = match extract(draft) {
+            Ok(features) => features,
+            Err(e) => {
+                eprintln!("Failed to read {}: {}", draft.display(), e);
+                continue;
+            }
+        };
+        let analysis = compare_extracted(&reference_features, &features, &config, scorers.default_scorer());
+        match &previous {
+            None => {
+                println!("{:?}, confidence {:.3}", analysis.verdict, analysis.confidence);
+                for result in &analysis.detailed_analysis {
+                    if let Some(difference) = result.difference {
+                        println!("  {:<24} {:>7.3}", result.aspect, difference);
+                    }
+                }
+            }
+            Some(previous) => {
+                let shift = analysis.confidence - previous.confidence;
+                let verdict = if analysis.verdict == previous.verdict {
+                    format!("{:?}", analysis.verdict)
+                } else {
+                    format!("{:?} -> {:?}", previous.verdict, analysis.verdict)
+                };
+                println!("{}, confidence {:.3} ({:+.3})", verdict, analysis.confidence, shift);
+                print_deltas(&aspect_deltas(&previous.detailed_analysis, &analysis.detailed_analysis, threshold));
+            }
+        }
+        previous = Some(analysis);
+    }
 }
 
-#[actix_web::main]
-async fn main() -> std::io::Result<()> {
-    println!("Listening on http://localhost:8000");
-    HttpServer::new(|| {
-        let cors = Cors::permissive(); // For development only
-
-        App::new().wrap(cors).service(compare_texts)
-    })
-    .bind("127.0.0.1:8000")?
-    .run()
-    .await
+fn print_deltas(deltas: &[AspectDelta]) {
+    for delta in deltas {
+        let value = |v: Option<f64>| v.map_or("-".to_string(), |v| format!("{:.3}", v));
+        let change = delta.change().map_or(String::new(), |c| format!(" ({:+.3})", c));
+        println!("  {:<24} {:>7} -> {:>7}{}", delta.aspect, value(delta.before), value(delta.after), change);
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use actix_web::{test, web, App};
+// Scores experiment records with this build. Scorers and embedders are built
+// once per config and dictionary, not once per record
+struct Replayer {
+    current: Option<Config>,
+    tokenizers: TokenizerPool,
+    models: HashMap<String, (Scorers, Option<Embedder>)>,
+}
 
-    async fn test_compare_handler(payload: web::Json<ComparisonQuery>) -> Result<web::Json<Analysis>> {
-        let dictionary = DictionaryConfig {
-            kind: Some(DictionaryKind::IPADIC),
-            path: None,
+impl Replayer {
+    /// Under the config in `AUTHOR_COMPARER_CONFIG` when `current_config`,
+    /// otherwise under each record's own.
+    fn new(current_config: bool) -> io::Result<Replayer> {
+        let current = if current_config {
+            Some(Config::from_env().map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?)
+        } else {
+            None
         };
+        Ok(Replayer {
+            current,
+            tokenizers: TokenizerPool::default(),
+            models: HashMap::new(),
+        })
+    }
 
-        let config = TokenizerConfig {
-            dictionary,
-            user_dictionary: None,
-            mode: Mode::Normal,
+    fn replay(&mut self, record: &ExperimentRecord) -> io::Result<Analysis> {
+        let config = self.current.as_ref().unwrap_or(&record.config);
+        let spec = match &record.dictionary {
+            Some(name) => config
+                .tokenizer_spec(name)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("unknown dictionary '{}'", name)))?,
+            None => TokenizerSpec::default(),
         };
-
-        let tokenizer = Tokenizer::from_config(config).unwrap();
-        
-        let text1_features = extract_features(&payload.text1, &tokenizer);
-        let text2_features = extract_features(&payload.text2, &tokenizer);
-        
-        let detailed_results = compare_features(&text1_features, &text2_features);
-        let confidence = calculate_confidence(&detailed_results);
-        let same_author = confidence > 0.6; // Increase threshold to be more strict
-
-        Ok(web::Json(Analysis {
-            same_author,
-            confidence,
-            detailed_analysis: detailed_results,
-        }))
+        let tokenizer = self.tokenizers.get(&spec).map_err(|e| io::Error::other(e.to_string()))?;
+        let key = format!("{}/{}", config.snapshot_id(), record.dictionary.as_deref().unwrap_or_default());
+        let (scorers, embedder) = match self.models.entry(key) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let scorers = Scorers::from_config(config, tokenizer)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+                let embedder = Embedder::from_config(&config.embedding)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+                entry.insert((scorers, embedder))
+            }
+        };
+        record
+            .replay(tokenizer, config, scorers.default_scorer(), embedder.as_ref())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
     }
+}
 
-    #[actix_rt::test]
-    async fn test_compare_texts() {
-        // Initialize the app
-        let app = test::init_service(
-            App::new()
-                .service(web::resource("/compare").route(web::post().to(test_compare_handler)))
-        ).await;
-
-        // Test cases with expected results
-        let test_cases = vec![
-            // Test case 1: Similar casual writing style
-            (
-                "私は今日公園に行きました。とても楽しかったです。",
-                "私は昨日公園で遊びました。本当に楽しかったです。",
-                true,  // same author
-                0.5    // minimum confidence
-            ),
-            
-            // Test case 2: Different formality levels
-            (
-                "本日の会議にて、以下の事項が決定致しました。ご確認ください。",
-                "やっほー！今日めっちゃ楽しかった！またあそぼーね！",
-                false,
-                0.2
-            ),
-
-            // Test case 3: Similar formal business style
-            (
-                "第三四半期の売上実績について報告いたします。前年比110%となっております。",
-                "本年度の業績見通しについてご報告申し上げます。予想を上回る結果となっております。",
-                true,
-                0.5
-            ),
-
-            // Test case 4: Similar academic style
-            (
-                "本研究では、言語処理における形態素解析の重要性について考察する。",
-                "自然言語処理において、形態素解析は基礎的かつ重要な要素である。",
-                true,
-                0.5
-            ),
-
-            // Test case 5: Different context but similar casual style
-            (
-                "昨日の映画はとても面白かった！また見に行きたいな。",
-                "今日のライブ最高だった！また行きたいな！",
-                true,
-                0.5
-            ),
-
-            // Test case 6: Mixed styles
-            (
-                "明日の天気予報によると、関東地方は晴れるでしょう。",
-                "あしたは晴れるみたい！外で遊べるね！",
-                false,
-                0.2
-            ),
-
-            // Test case 7: Short vs Long text
-            (
-                "はい、そうですね。そのとおりです。",
-                "申し訳ございませんが、その件については改めて詳しくご説明させていただく必要があるかと存じます。",
-                false,
-                0.1
-            ),
-
-            // Test case 8: Similar technical style
-            (
-                "システムの実装にはRustを使用し、非同期処理を実現しました。",
-                "バックエンドの開発ではRustを採用し、並行処理を実装しています。",
-                true,
-                0.5
-            ),
-
-            // Test case 9: Different emotional expression
-            (
-                "今日は最悪な一日だった...もう嫌になっちゃう...",
-                "今日は最高の一日！とっても楽しかった！",
-                false,
-                0.3
-            ),
-
-            // Test case 10: Similar poetic style
-            (
-                "桜舞い散る春の日に、心が躍る。",
-                "紅葉舞う秋の夕べ、心が癒される。",
-                true,
-                0.5
-            ),
-        ];
-
-        // Run all test cases
-        for (i, (text1, text2, expected_same_author, min_confidence)) in test_cases.iter().enumerate() {
-            let payload = ComparisonQuery {
-                text1: text1.to_string(),
-                text2: text2.to_string(),
-            };
+fn read_record(path: &Path) -> io::Result<ExperimentRecord> {
+    ExperimentRecord::from_json(&std::fs::read_to_string(path)?).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
 
-            let req = test::TestRequest::post()
-                .uri("/compare")
-                .set_json(&payload)
-                .to_request();
-            
-            let resp: Analysis = test::call_and_read_body_json(&app, req).await;
-            
-            assert_eq!(
-                resp.same_author, 
-                *expected_same_author,
-                "Test case {} failed: expected same_author={}, got={}", 
-                i + 1, 
-                expected_same_author, 
-                resp.same_author
-            );
+fn replay(path: &Path, current_config: bool, threshold: f64) -> io::Result<()> {
+    let record = read_record(path)?;
+    let replayed = Replayer::new(current_config)?.replay(&record)?;
+
+    let recorded = &record.analysis;
+    println!(
+        "recorded by {} (feature schema {}), replayed with {} from {}",
+        record.model_version,
+        record.feature_schema_version,
+        author_comparer::MODEL_VERSION,
+        if record.has_texts() { "the texts" } else { "the recorded features" }
+    );
+    let verdict = if replayed.verdict == recorded.verdict {
+        format!("{:?}", replayed.verdict)
+    } else {
+        format!("{:?} -> {:?}", recorded.verdict, replayed.verdict)
+    };
+    println!(
+        "{}, confidence {:.3} -> {:.3} ({:+.3})",
+        verdict,
+        recorded.confidence,
+        replayed.confidence,
+        replayed.confidence - recorded.confidence
+    );
+    print_deltas(&aspect_deltas(&recorded.detailed_analysis, &replayed.detailed_analysis, threshold));
+    Ok(())
+}
 
-            assert!(
-                resp.confidence > *min_confidence,
-                "Test case {} failed: confidence {} is not > {}", 
-                i + 1, 
-                resp.confidence, 
-                min_confidence
-            );
+fn regress(paths: &[PathBuf], current_config: bool, tolerance: f64) -> io::Result<()> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let mut records: Vec<_> = std::fs::read_dir(path)?
+                .map(|entry| entry.map(|e| e.path()))
+                .collect::<io::Result<_>>()?;
+            records.retain(|path| path.extension().is_some_and(|ext| ext == "json"));
+            records.sort();
+            files.extend(records);
+        } else {
+            files.push(path.clone());
         }
     }
+    if files.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "no experiment records to replay"));
+    }
 
-    #[actix_rt::test]
-    async fn test_text_features() {
-        let dictionary = DictionaryConfig {
-            kind: Some(DictionaryKind::IPADIC),
-            path: None,
+    let mut replayer = Replayer::new(current_config)?;
+    let (mut changed, mut flipped, mut failed) = (0, 0, 0);
+    for file in &files {
+        // One unreadable or unscorable record shouldn't hide the others' drift
+        let result = read_record(file).and_then(|record| Ok((replayer.replay(&record)?, record)));
+        let (replayed, record) = match result {
+            Ok(result) => result,
+            Err(e) => {
+                println!("{}: failed: {}", file.display(), e);
+                failed += 1;
+                continue;
+            }
         };
-
-        let config = TokenizerConfig {
-            dictionary,
-            user_dictionary: None,
-            mode: Mode::Normal,
+        let Some(drift) = drift(&record.analysis, &replayed, tolerance) else {
+            continue;
         };
-
-        let tokenizer = Tokenizer::from_config(config).unwrap();
-        
-        let text = "私は今日公園に行きました。";
-        let features = extract_features(text, &tokenizer);
-
-        // Test basic feature existence and bounds
-        assert!(features.particle_ratio >= 0.0 && features.particle_ratio <= 1.0);
-        assert!(features.verb_ratio >= 0.0 && features.verb_ratio <= 1.0);
-        assert!(features.avg_sentence_length > 0.0);
-        assert!(features.unique_words_ratio >= 0.0 && features.unique_words_ratio <= 1.0);
-        assert!(features.punctuation_ratio >= 0.0 && features.punctuation_ratio <= 1.0);
+        changed += 1;
+        let verdict = if drift.verdict_changed() {
+            flipped += 1;
+            format!("{:?} -> {:?}", drift.recorded, drift.replayed)
+        } else {
+            format!("{:?}", drift.replayed)
+        };
+        println!(
+            "{} (recorded by {}): {}, confidence {:.3} -> {:.3} ({:+.3})",
+            file.display(),
+            record.model_version,
+            verdict,
+            record.analysis.confidence,
+            replayed.confidence,
+            drift.confidence_change
+        );
+        print_deltas(&drift.aspects);
+    }
+    println!(
+        "{} records replayed with {}: {} changed beyond {} ({} verdicts), {} failed",
+        files.len(),
+        author_comparer::MODEL_VERSION,
+        changed,
+        tolerance,
+        flipped,
+        failed
+    );
+    if changed > 0 || failed > 0 {
+        return Err(io::Error::other(format!("{} of {} records did not replay as recorded", changed + failed, files.len())));
     }
+    Ok(())
+}
 
-    #[actix_rt::test]
-    async fn test_clamp() {
-        assert_eq!(clamp(1.5, 0.0, 1.0), 1.0);
-        assert_eq!(clamp(-0.5, 0.0, 1.0), 0.0);
-        assert_eq!(clamp(0.5, 0.0, 1.0), 0.5);
+fn profile(command: ProfileCommand) -> io::Result<()> {
+    let config = Config::from_env().map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let store = ProfileStore::new(&config.profiles.directory);
+    let invalid = |e: author_comparer::profile_store::ProfileStoreError| io::Error::new(io::ErrorKind::InvalidData, e);
+
+    match command {
+        ProfileCommand::Build {
+            author,
+            directory,
+            keep_samples,
+        } => {
+            let texts = author_comparer::datasets::load_text_files(&directory)?;
+            if texts.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("no .txt files in {}", directory.display()),
+                ));
+            }
+            let tokenizer = new_tokenizer().map_err(|e| io::Error::other(e.to_string()))?;
+            let mut profile = build_profile(&texts, &tokenizer).map_err(|e| io::Error::other(e.to_string()))?;
+            if keep_samples {
+                profile = profile.with_samples(&texts);
+            }
+            store.save(&author, &profile).map_err(invalid)?;
+            println!("{}: built from {} samples", author, texts.len());
+        }
+        ProfileCommand::List => {
+            println!("{:<24} {:>8} {:>8}", "author", "samples", "tokens");
+            for author in store.authors().map_err(invalid)? {
+                match store.load(&author) {
+                    Ok(profile) => println!(
+                        "{:<24} {:>8} {:>8}",
+                        author, profile.sample_count, profile.features.token_count
+                    ),
+                    Err(e) => println!("{:<24} {}", author, e),
+                }
+            }
+        }
+        ProfileCommand::Verify { author, file } => {
+            let profile = store.load(&author).map_err(invalid)?;
+            let text = std::fs::read_to_string(&file)?;
+            let tokenizer = new_tokenizer().map_err(|e| io::Error::other(e.to_string()))?;
+            let scorers =
+                Scorers::from_config(&config, &tokenizer).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            let features = extract_features(&text, &tokenizer).map_err(|e| io::Error::other(e.to_string()))?;
+            let analysis = compare_extracted(&features, &profile.features, &config, scorers.default_scorer());
+            println!("{} against {}: {:?}, confidence {:.3}", file.display(), author, analysis.verdict, analysis.confidence);
+            for result in &analysis.detailed_analysis {
+                match result.difference {
+                    Some(difference) => println!("  {:<24} {:>8.3}", result.aspect, difference),
+                    None => println!("  {:<24} {:>8}", result.aspect, "-"),
+                }
+            }
+            for warning in &analysis.warnings {
+                println!("  warning: {}", warning);
+            }
+        }
+        ProfileCommand::Merge { into, authors } => {
+            let profiles = authors
+                .iter()
+                .map(|author| store.load(author))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(invalid)?;
+            let merged = merge_profiles(&profiles);
+            store.save(&into, &merged).map_err(invalid)?;
+            println!("{}: merged {} profiles, {} samples", into, profiles.len(), merged.sample_count);
+        }
     }
+    Ok(())
 }
@@ -0,0 +1,89 @@
+use crate::config::ConfigError;
+use crate::numerics::clamp;
+use crate::scoring::{Backend, Scorer, ScoringInput};
+use std::fmt;
+use std::path::Path;
+
+/// Characters of each text the verifier reads; longer texts are cut, shorter padded with 0.
+pub const MAX_CHARS: usize = 1024;
+
+/// Learned same-author verifier run through ONNX Runtime. The model takes two
+/// int64 inputs, `text1` and `text2`, shaped [1, MAX_CHARS] and holding Unicode
+/// code points, and returns the same-author probability as its first output.
+#[cfg(feature = "neural")]
+pub struct NeuralVerifier {
+    session: ort::Session,
+}
+
+/// Uninhabited without the `neural` feature, so no model can be configured.
+#[cfg(not(feature = "neural"))]
+pub enum NeuralVerifier {}
+
+impl fmt::Debug for NeuralVerifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("NeuralVerifier")
+    }
+}
+
+/// Code points of the leading characters, zero-padded to `MAX_CHARS`.
+pub fn encode(text: &str) -> Vec<i64> {
+    let mut codes: Vec<i64> = text.chars().take(MAX_CHARS).map(|c| c as i64).collect();
+    codes.resize(MAX_CHARS, 0);
+    codes
+}
+
+impl NeuralVerifier {
+    #[cfg(feature = "neural")]
+    pub fn load(model: &Path) -> Result<NeuralVerifier, ConfigError> {
+        let session = ort::Session::builder()
+            .and_then(|builder| builder.commit_from_file(model))
+            .map_err(|e| ConfigError::Scoring(format!("{}: {}", model.display(), e)))?;
+        Ok(NeuralVerifier { session })
+    }
+
+    #[cfg(not(feature = "neural"))]
+    pub fn load(_model: &Path) -> Result<NeuralVerifier, ConfigError> {
+        Err(ConfigError::Scoring("built without the `neural` feature".to_string()))
+    }
+
+    #[cfg(feature = "neural")]
+    fn probability(&self, text1: &str, text2: &str) -> Result<f64, String> {
+        use ndarray::Array2;
+
+        let tensor = |text: &str| Array2::from_shape_vec((1, MAX_CHARS), encode(text)).map_err(|e| e.to_string());
+        let inputs = ort::inputs!["text1" => tensor(text1)?, "text2" => tensor(text2)?].map_err(|e| e.to_string())?;
+        let outputs = self.session.run(inputs).map_err(|e| e.to_string())?;
+        let output = outputs[0].try_extract_tensor::<f32>().map_err(|e| e.to_string())?;
+        let probability = output.iter().next().copied().ok_or("the verifier returned no output")?;
+        Ok(probability as f64)
+    }
+
+    #[cfg(not(feature = "neural"))]
+    fn probability(&self, _text1: &str, _text2: &str) -> Result<f64, String> {
+        match *self {}
+    }
+}
+
+impl Scorer for NeuralVerifier {
+    fn backend(&self) -> Backend {
+        Backend::Neural
+    }
+
+    fn score(&self, input: &ScoringInput) -> Result<f64, String> {
+        let (text1, text2) = input.texts.ok_or("the neural verifier needs the texts, not feature vectors")?;
+        Ok(clamp(self.probability(text1, text2)?, 0.0, 1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        let codes = encode("あa");
+        assert_eq!(codes.len(), MAX_CHARS);
+        assert_eq!(&codes[..3], &[0x3042, 0x61, 0]);
+        assert_eq!(encode(&"字".repeat(MAX_CHARS + 5)).len(), MAX_CHARS);
+    }
+}
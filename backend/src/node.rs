@@ -0,0 +1,52 @@
+use crate::comparison::Analysis;
+use crate::tokenizer::{shared_tokenizer, Tokenizer};
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use serde::Serialize;
+
+fn tokenizer() -> Result<&'static Tokenizer> {
+    shared_tokenizer().map_err(|e| Error::from_reason(e.to_string()))
+}
+
+fn to_js<T: Serialize>(value: &T) -> Result<serde_json::Value> {
+    serde_json::to_value(value).map_err(|e| Error::from_reason(e.to_string()))
+}
+
+#[napi]
+pub fn extract_features(text: String) -> Result<serde_json::Value> {
+    to_js(&crate::extract_features(&text, tokenizer()?).map_err(|e| Error::from_reason(e.to_string()))?)
+}
+
+#[napi]
+pub fn compare(text1: String, text2: String) -> Result<serde_json::Value> {
+    to_js(&crate::compare(&text1, &text2, tokenizer()?).map_err(|e| Error::from_reason(e.to_string()))?)
+}
+
+#[napi]
+pub fn build_profile(texts: Vec<String>) -> Result<serde_json::Value> {
+    to_js(&crate::build_profile(&texts, tokenizer()?).map_err(|e| Error::from_reason(e.to_string()))?)
+}
+
+pub struct CompareTask {
+    text1: String,
+    text2: String,
+}
+
+impl Task for CompareTask {
+    type Output = Analysis;
+    type JsValue = serde_json::Value;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        crate::compare(&self.text1, &self.text2, tokenizer()?).map_err(|e| Error::from_reason(e.to_string()))
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        to_js(&output)
+    }
+}
+
+/// Runs the comparison on the libuv thread pool instead of the JS thread.
+#[napi]
+pub fn compare_async(text1: String, text2: String) -> AsyncTask<CompareTask> {
+    AsyncTask::new(CompareTask { text1, text2 })
+}
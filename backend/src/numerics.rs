@@ -0,0 +1,172 @@
+use nalgebra::DVector;
+
+/// Clamps `value` into `[min, max]`. NaN maps to `min`, so a poisoned
+/// computation can never read as a confident result.
+pub fn clamp(value: f64, min: f64, max: f64) -> f64 {
+    if value.is_nan() {
+        return min;
+    }
+    value.min(max).max(min)
+}
+
+/// Cosine similarity of two non-negative vectors, in `[0, 1]`.
+///
+/// Empty and all-zero vectors have no direction, so they are treated as
+/// sharing nothing with the other vector rather than producing `0 / 0`.
+pub fn cosine_similarity(v1: &DVector<f64>, v2: &DVector<f64>) -> f64 {
+    let norms = v1.norm() * v2.norm();
+    if v1.is_empty() || v2.is_empty() || norms == 0.0 || !norms.is_finite() {
+        return 0.0;
+    }
+    clamp(v1.dot(v2) / norms, 0.0, 1.0)
+}
+
+/// Ratio of the smaller to the larger of two non-negative magnitudes, in `[0, 1]`.
+/// Two zero magnitudes are identical, so they give 1.
+pub fn magnitude_ratio(a: f64, b: f64) -> f64 {
+    let (smaller, larger) = if a > b { (b, a) } else { (a, b) };
+    if larger <= 0.0 || !larger.is_finite() {
+        return if smaller == larger { 1.0 } else { 0.0 };
+    }
+    clamp(smaller / larger, 0.0, 1.0)
+}
+
+/// 1-based ranks of `values`, with ties sharing their average rank.
+fn ranks(values: &[f64]) -> Vec<f64> {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|&a, &b| values[a].total_cmp(&values[b]));
+    let mut ranks = vec![0.0; values.len()];
+    let mut start = 0;
+    while start < order.len() {
+        let mut end = start + 1;
+        while end < order.len() && values[order[end]] == values[order[start]] {
+            end += 1;
+        }
+        let rank = (start + end + 1) as f64 / 2.0;
+        for &i in &order[start..end] {
+            ranks[i] = rank;
+        }
+        start = end;
+    }
+    ranks
+}
+
+/// Spearman rank correlation of two equally long series, in `[-1, 1]`.
+/// A series without any spread has no order to correlate, which gives 0.
+pub fn spearman_correlation(x: &[f64], y: &[f64]) -> f64 {
+    let (rx, ry) = (ranks(x), ranks(y));
+    let n = rx.len().min(ry.len()) as f64;
+    let mean = (n + 1.0) / 2.0;
+    let (mut covariance, mut variance_x, mut variance_y) = (0.0, 0.0, 0.0);
+    for (a, b) in rx.iter().zip(&ry) {
+        covariance += (a - mean) * (b - mean);
+        variance_x += (a - mean) * (a - mean);
+        variance_y += (b - mean) * (b - mean);
+    }
+    clamp(safe_div(covariance, (variance_x * variance_y).sqrt(), 0.0), -1.0, 1.0)
+}
+
+/// Jensen-Shannon divergence of two distributions over the same outcomes, in
+/// base 2 so it lies in `[0, 1]`. Both are normalized first; an all-zero one
+/// shares nothing with the other.
+pub fn js_divergence(p: &[f64], q: &[f64]) -> f64 {
+    let (sum_p, sum_q) = (p.iter().sum::<f64>(), q.iter().sum::<f64>());
+    if sum_p <= 0.0 || sum_q <= 0.0 {
+        return if sum_p == sum_q { 0.0 } else { 1.0 };
+    }
+    let divergence: f64 = p
+        .iter()
+        .zip(q)
+        .map(|(a, b)| {
+            let (a, b) = (a / sum_p, b / sum_q);
+            let m = (a + b) / 2.0;
+            let term = |x: f64| if x > 0.0 { x * (x / m).log2() } else { 0.0 };
+            (term(a) + term(b)) / 2.0
+        })
+        .sum();
+    clamp(divergence, 0.0, 1.0)
+}
+
+/// Divides, falling back to `fallback` when the denominator is zero or the
+/// result is not finite.
+pub fn safe_div(numerator: f64, denominator: f64, fallback: f64) -> f64 {
+    let value = numerator / denominator;
+    if denominator == 0.0 || !value.is_finite() {
+        fallback
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_clamp() {
+        assert_eq!(clamp(1.5, 0.0, 1.0), 1.0);
+        assert_eq!(clamp(-0.5, 0.0, 1.0), 0.0);
+        assert_eq!(clamp(0.5, 0.0, 1.0), 0.5);
+        assert_eq!(clamp(f64::NAN, 0.0, 1.0), 0.0);
+    }
+
+    #[test]
+    fn test_degenerate_vectors() {
+        let empty = DVector::from_vec(vec![]);
+        let zero = DVector::from_vec(vec![0.0, 0.0]);
+        let some = DVector::from_vec(vec![0.5, 0.5]);
+
+        assert_eq!(cosine_similarity(&empty, &empty), 0.0);
+        assert_eq!(cosine_similarity(&zero, &some), 0.0);
+        assert!((cosine_similarity(&some, &some) - 1.0).abs() < 1e-9);
+        assert_eq!(magnitude_ratio(0.0, 0.0), 1.0);
+        assert_eq!(magnitude_ratio(0.0, 4.0), 0.0);
+        assert_eq!(safe_div(1.0, 0.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn test_spearman_correlation() {
+        // Only the order matters, not the scale
+        assert!((spearman_correlation(&[0.1, 0.2, 0.4], &[10.0, 20.0, 90.0]) - 1.0).abs() < 1e-9);
+        assert!((spearman_correlation(&[0.1, 0.2, 0.4], &[3.0, 2.0, 1.0]) + 1.0).abs() < 1e-9);
+        assert!((spearman_correlation(&[1.0, 2.0, 2.0, 3.0], &[1.0, 2.0, 3.0, 4.0]) - 0.9486832980505138).abs() < 1e-9);
+        assert_eq!(spearman_correlation(&[0.5, 0.5], &[0.1, 0.2]), 0.0);
+        assert_eq!(spearman_correlation(&[], &[]), 0.0);
+    }
+
+    #[test]
+    fn test_js_divergence() {
+        assert_eq!(js_divergence(&[0.2, 0.8], &[2.0, 8.0]), 0.0);
+        // Disjoint supports are as far apart as distributions get
+        assert!((js_divergence(&[1.0, 0.0], &[0.0, 1.0]) - 1.0).abs() < 1e-9);
+        let half = js_divergence(&[0.5, 0.5], &[1.0, 0.0]);
+        assert!(half > 0.0 && half < 1.0);
+        assert_eq!(js_divergence(&[0.0, 0.0], &[0.0, 0.0]), 0.0);
+    }
+
+    proptest! {
+        #[test]
+        fn cosine_is_finite_and_bounded(
+            pairs in prop::collection::vec((0.0f64..1e6, 0.0f64..1e6), 0..64),
+        ) {
+            let (a, b): (Vec<f64>, Vec<f64>) = pairs.into_iter().unzip();
+            let similarity = cosine_similarity(&DVector::from_vec(a), &DVector::from_vec(b));
+            prop_assert!(similarity.is_finite());
+            prop_assert!((0.0..=1.0).contains(&similarity));
+        }
+
+        #[test]
+        fn magnitude_ratio_is_finite_and_bounded(a in 0.0f64..1e9, b in 0.0f64..1e9) {
+            let ratio = magnitude_ratio(a, b);
+            prop_assert!(ratio.is_finite());
+            prop_assert!((0.0..=1.0).contains(&ratio));
+        }
+
+        #[test]
+        fn clamp_is_always_in_range(value in any::<f64>()) {
+            let clamped = clamp(value, 0.0, 1.0);
+            prop_assert!((0.0..=1.0).contains(&clamped));
+        }
+    }
+}
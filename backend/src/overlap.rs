@@ -0,0 +1,146 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Shortest shared run, in normalized characters, reported as an overlap.
+pub const DEFAULT_MIN_LENGTH: usize = 10;
+
+/// Half-open range of character (Unicode scalar value) offsets into a text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct CharRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct OverlapSpan {
+    pub text1: CharRange,
+    pub text2: CharRange,
+    // Matched length after normalization
+    pub length: usize,
+    pub excerpt: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Overlap {
+    pub spans: Vec<OverlapSpan>,
+    // Share of each text's normalized characters inside a shared span
+    pub coverage1: f64,
+    pub coverage2: f64,
+}
+
+/// Keeps only letters, digits and ideographs (lowercased, full-width folded), so
+/// copies with changed punctuation, spacing or width still line up. Returns the
+/// normalized characters and the original char offset of each.
+fn normalize(text: &str) -> (Vec<char>, Vec<usize>) {
+    let mut chars = Vec::new();
+    let mut offsets = Vec::new();
+    for (offset, c) in text.chars().enumerate() {
+        let c = match c {
+            '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+            _ => c,
+        };
+        if c.is_alphanumeric() {
+            for lower in c.to_lowercase() {
+                chars.push(lower);
+                offsets.push(offset);
+            }
+        }
+    }
+    (chars, offsets)
+}
+
+/// Whether the texts are the same once normalized, however short.
+pub fn same_normalized(text1: &str, text2: &str) -> bool {
+    let (a, _) = normalize(text1);
+    !a.is_empty() && a == normalize(text2).0
+}
+
+/// Finds maximal shared runs of at least `min_length` normalized characters,
+/// greedily taking the longest match at each position of `text2`.
+pub fn find_overlaps(text1: &str, text2: &str, min_length: usize) -> Overlap {
+    let min_length = min_length.max(1);
+    let (a, a_offsets) = normalize(text1);
+    let (b, b_offsets) = normalize(text2);
+
+    let mut index: HashMap<&[char], Vec<usize>> = HashMap::new();
+    for i in 0..a.len().saturating_sub(min_length - 1) {
+        index.entry(&a[i..i + min_length]).or_default().push(i);
+    }
+
+    let mut spans = Vec::new();
+    let mut covered1 = vec![false; a.len()];
+    let mut covered2 = 0;
+    let mut j = 0;
+    while j + min_length <= b.len() {
+        let best = index.get(&b[j..j + min_length]).and_then(|starts| {
+            starts
+                .iter()
+                .map(|&i| {
+                    let extra = a[i + min_length..]
+                        .iter()
+                        .zip(&b[j + min_length..])
+                        .take_while(|(x, y)| x == y)
+                        .count();
+                    (i, min_length + extra)
+                })
+                .max_by_key(|&(_, length)| length)
+        });
+
+        let Some((i, length)) = best else {
+            j += 1;
+            continue;
+        };
+
+        let start1 = a_offsets[i];
+        let end1 = a_offsets[i + length - 1] + 1;
+        spans.push(OverlapSpan {
+            text1: CharRange { start: start1, end: end1 },
+            text2: CharRange {
+                start: b_offsets[j],
+                end: b_offsets[j + length - 1] + 1,
+            },
+            length,
+            excerpt: text1.chars().skip(start1).take(end1 - start1).collect(),
+        });
+        covered1[i..i + length].iter_mut().for_each(|c| *c = true);
+        covered2 += length;
+        j += length;
+    }
+
+    let share = |covered: usize, total: usize| if total == 0 { 0.0 } else { covered as f64 / total as f64 };
+    Overlap {
+        coverage1: share(covered1.iter().filter(|c| **c).count(), a.len()),
+        coverage2: share(covered2, b.len()),
+        spans,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_overlaps() {
+        let original = "第三四半期の売上実績について報告いたします。前年比110%となっております。";
+        let copied = "概要：第三四半期の売上実績について、報告いたします。以上。";
+
+        let overlap = find_overlaps(original, copied, DEFAULT_MIN_LENGTH);
+        assert_eq!(overlap.spans.len(), 1);
+
+        let span = &overlap.spans[0];
+        assert_eq!(span.excerpt, "第三四半期の売上実績について報告いたします");
+        let copied_chars: Vec<char> = copied.chars().collect();
+        let in_copy: String = copied_chars[span.text2.start..span.text2.end].iter().collect();
+        // The inserted comma is skipped by normalization but kept in the range
+        assert_eq!(in_copy, "第三四半期の売上実績について、報告いたします");
+        assert!(overlap.coverage2 > overlap.coverage1);
+
+        assert!(find_overlaps(original, "今日は晴れです。", DEFAULT_MIN_LENGTH).spans.is_empty());
+        assert!(find_overlaps("", "", DEFAULT_MIN_LENGTH).spans.is_empty());
+
+        assert!(same_normalized("雨じゃん！", "雨じゃん"));
+        assert!(!same_normalized("雨じゃん", "雨だね"));
+        assert!(!same_normalized("。", "！"));
+    }
+}
@@ -0,0 +1,388 @@
+use crate::config::{ConfigError, PluginsConfig};
+use crate::tokenizer::Tokenizer;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+#[cfg(feature = "plugins")]
+use std::path::Path;
+#[cfg(feature = "plugins")]
+use std::sync::{Arc, RwLock};
+
+/// Aspects a plugin adds are named after it with this prefix.
+pub const ASPECT_PREFIX: &str = "Plugin: ";
+
+/// One module a tenant has uploaded.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct PluginInfo {
+    pub name: String,
+    pub size_bytes: usize,
+}
+
+#[derive(Debug)]
+pub enum PluginError {
+    InvalidName(String),
+    TooLarge { size: usize, limit: usize },
+    /// Not a module implementing the extractor interface.
+    Invalid(String),
+    Io(std::io::Error),
+}
+
+impl fmt::Display for PluginError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PluginError::InvalidName(name) => {
+                write!(f, "invalid plugin name '{}': use 1 to 64 lowercase letters, digits, '-' or '_'", name)
+            }
+            PluginError::TooLarge { size, limit } => write!(f, "module is {} bytes, over the {} byte limit", size, limit),
+            PluginError::Invalid(e) => write!(f, "invalid plugin module: {}", e),
+            PluginError::Io(e) => write!(f, "failed to store the plugin: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for PluginError {}
+
+/// Names safe as file names: lowercase letters, digits, `-` and `_`.
+pub(crate) fn valid_name(name: &str) -> bool {
+    (1..=64).contains(&name.len())
+        && name.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-' || b == b'_')
+}
+
+/// Mean relative difference over every feature either text got; a feature
+/// one text lacks counts as 0 there.
+#[cfg(any(feature = "plugins", test))]
+fn feature_difference(features1: &BTreeMap<String, f64>, features2: &BTreeMap<String, f64>) -> Result<f64, String> {
+    let names: Vec<&String> = features1.keys().chain(features2.keys().filter(|k| !features1.contains_key(*k))).collect();
+    if names.is_empty() {
+        return Err("the plugin returned no features".to_string());
+    }
+    let mut total = 0.0;
+    for name in &names {
+        let a = features1.get(*name).copied().unwrap_or(0.0);
+        let b = features2.get(*name).copied().unwrap_or(0.0);
+        if !a.is_finite() || !b.is_finite() {
+            return Err(format!("feature '{}' is not a finite number", name));
+        }
+        let scale = a.abs().max(b.abs());
+        if scale > 0.0 {
+            total += (a - b).abs() / scale;
+        }
+    }
+    Ok(total / names.len() as f64)
+}
+
+/// Runs modules with the configured fuel and memory limits. Every call gets
+/// a fresh instance, so nothing leaks between texts or tenants.
+#[cfg(feature = "plugins")]
+#[derive(Clone)]
+struct Sandbox {
+    engine: wasmtime::Engine,
+    fuel: u64,
+    max_memory_bytes: usize,
+    max_module_bytes: usize,
+}
+
+#[cfg(feature = "plugins")]
+impl Sandbox {
+    fn new(config: &PluginsConfig) -> Result<Sandbox, ConfigError> {
+        let mut engine_config = wasmtime::Config::new();
+        engine_config.consume_fuel(true);
+        let engine = wasmtime::Engine::new(&engine_config).map_err(|e| ConfigError::Plugins(e.to_string()))?;
+        Ok(Sandbox {
+            engine,
+            fuel: config.fuel,
+            max_memory_bytes: config.max_memory_mb * 1024 * 1024,
+            max_module_bytes: config.max_module_kb * 1024,
+        })
+    }
+
+    /// Compiles a module and checks it exports `memory`, `alloc` and `extract`.
+    fn compile(&self, bytes: &[u8]) -> Result<wasmtime::Module, PluginError> {
+        use wasmtime::ExternType;
+
+        if bytes.len() > self.max_module_bytes {
+            return Err(PluginError::TooLarge {
+                size: bytes.len(),
+                limit: self.max_module_bytes,
+            });
+        }
+        let module = wasmtime::Module::new(&self.engine, bytes).map_err(|e| PluginError::Invalid(e.to_string()))?;
+        if !matches!(module.get_export("memory"), Some(ExternType::Memory(_))) {
+            return Err(PluginError::Invalid("no exported memory".to_string()));
+        }
+        for name in ["alloc", "extract"] {
+            if !matches!(module.get_export(name), Some(ExternType::Func(_))) {
+                return Err(PluginError::Invalid(format!("no exported function '{}'", name)));
+            }
+        }
+        Ok(module)
+    }
+
+    /// Hands the tokens, as JSON, to `extract(ptr, len)`, which returns
+    /// `ptr << 32 | len` of a JSON object of named numbers.
+    fn extract(&self, module: &wasmtime::Module, tokens: &[u8]) -> Result<BTreeMap<String, f64>, String> {
+        let limits = wasmtime::StoreLimitsBuilder::new().memory_size(self.max_memory_bytes).instances(1).build();
+        let mut store = wasmtime::Store::new(&self.engine, limits);
+        store.limiter(|limits| limits);
+        store.set_fuel(self.fuel).map_err(|e| e.to_string())?;
+
+        let instance = wasmtime::Instance::new(&mut store, module, &[]).map_err(|e| e.to_string())?;
+        let memory = instance.get_memory(&mut store, "memory").ok_or("no exported memory")?;
+        let alloc = instance.get_typed_func::<i32, i32>(&mut store, "alloc").map_err(|e| e.to_string())?;
+        let extract = instance.get_typed_func::<(i32, i32), i64>(&mut store, "extract").map_err(|e| e.to_string())?;
+
+        let len = i32::try_from(tokens.len()).map_err(|_| "text too long for the plugin".to_string())?;
+        let ptr = alloc.call(&mut store, len).map_err(|e| e.to_string())?;
+        memory.write(&mut store, ptr as u32 as usize, tokens).map_err(|e| e.to_string())?;
+        let packed = extract.call(&mut store, (ptr, len)).map_err(|e| e.to_string())? as u64;
+
+        let (out_ptr, out_len) = ((packed >> 32) as usize, (packed & 0xffff_ffff) as usize);
+        let mut output = vec![0; out_len];
+        memory.read(&store, out_ptr, &mut output).map_err(|e| e.to_string())?;
+        serde_json::from_slice(&output).map_err(|e| format!("malformed output: {}", e))
+    }
+}
+
+/// Every tenant's uploaded modules, kept under `<directory>/<tenant>/<name>.wasm`.
+#[cfg(feature = "plugins")]
+pub struct Plugins {
+    sandbox: Sandbox,
+    directory: std::path::PathBuf,
+    modules: RwLock<BTreeMap<String, Arc<TenantPlugins>>>,
+}
+
+/// Uninhabited without the `plugins` feature, so no module can be uploaded.
+#[cfg(not(feature = "plugins"))]
+pub enum Plugins {}
+
+/// The modules one tenant's comparisons run.
+#[cfg(feature = "plugins")]
+#[derive(Clone)]
+pub struct TenantPlugins {
+    sandbox: Sandbox,
+    modules: BTreeMap<String, (wasmtime::Module, usize)>,
+}
+
+#[cfg(not(feature = "plugins"))]
+pub enum TenantPlugins {}
+
+impl fmt::Debug for Plugins {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Plugins")
+    }
+}
+
+impl fmt::Debug for TenantPlugins {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("TenantPlugins")
+    }
+}
+
+impl Plugins {
+    /// Loads every stored module of the configured `tenants`, or returns
+    /// `None` when no directory is configured.
+    #[cfg(feature = "plugins")]
+    pub fn from_config(config: &PluginsConfig, tenants: &BTreeMap<String, String>) -> Result<Option<Plugins>, ConfigError> {
+        let Some(directory) = &config.directory else {
+            return Ok(None);
+        };
+        let sandbox = Sandbox::new(config)?;
+        let mut modules = BTreeMap::new();
+        for tenant in tenants.keys() {
+            let plugins = TenantPlugins::load(&sandbox, &directory.join(tenant))?;
+            modules.insert(tenant.clone(), Arc::new(plugins));
+        }
+        Ok(Some(Plugins {
+            sandbox,
+            directory: directory.clone(),
+            modules: RwLock::new(modules),
+        }))
+    }
+
+    #[cfg(not(feature = "plugins"))]
+    pub fn from_config(config: &PluginsConfig, _tenants: &BTreeMap<String, String>) -> Result<Option<Plugins>, ConfigError> {
+        match config.directory {
+            Some(_) => Err(ConfigError::Plugins("built without the `plugins` feature".to_string())),
+            None => Ok(None),
+        }
+    }
+
+    /// Checks and stores a module, replacing the tenant's module of the same name.
+    #[cfg(feature = "plugins")]
+    pub fn upload(&self, tenant: &str, name: &str, bytes: &[u8]) -> Result<PluginInfo, PluginError> {
+        if !valid_name(name) {
+            return Err(PluginError::InvalidName(name.to_string()));
+        }
+        let module = self.sandbox.compile(bytes)?;
+        let folder = self.directory.join(tenant);
+        std::fs::create_dir_all(&folder).map_err(PluginError::Io)?;
+        std::fs::write(folder.join(format!("{}.wasm", name)), bytes).map_err(PluginError::Io)?;
+
+        let mut modules = self.modules.write().unwrap_or_else(|e| e.into_inner());
+        let mut plugins = modules.get(tenant).map_or_else(
+            || TenantPlugins {
+                sandbox: self.sandbox.clone(),
+                modules: BTreeMap::new(),
+            },
+            |plugins| (**plugins).clone(),
+        );
+        plugins.modules.insert(name.to_string(), (module, bytes.len()));
+        // Comparisons already running keep the set they started with
+        modules.insert(tenant.to_string(), Arc::new(plugins));
+        Ok(PluginInfo {
+            name: name.to_string(),
+            size_bytes: bytes.len(),
+        })
+    }
+
+    #[cfg(not(feature = "plugins"))]
+    pub fn upload(&self, _tenant: &str, _name: &str, _bytes: &[u8]) -> Result<PluginInfo, PluginError> {
+        match *self {}
+    }
+
+    #[cfg(feature = "plugins")]
+    pub fn list(&self, tenant: &str) -> Vec<PluginInfo> {
+        self.for_tenant(tenant).map_or_else(Vec::new, |plugins| plugins.list())
+    }
+
+    #[cfg(not(feature = "plugins"))]
+    pub fn list(&self, _tenant: &str) -> Vec<PluginInfo> {
+        match *self {}
+    }
+
+    #[cfg(feature = "plugins")]
+    pub fn for_tenant(&self, tenant: &str) -> Option<Arc<TenantPlugins>> {
+        let modules = self.modules.read().unwrap_or_else(|e| e.into_inner());
+        modules.get(tenant).filter(|plugins| !plugins.modules.is_empty()).cloned()
+    }
+
+    #[cfg(not(feature = "plugins"))]
+    pub fn for_tenant(&self, _tenant: &str) -> Option<std::sync::Arc<TenantPlugins>> {
+        match *self {}
+    }
+}
+
+impl TenantPlugins {
+    #[cfg(feature = "plugins")]
+    fn load(sandbox: &Sandbox, folder: &Path) -> Result<TenantPlugins, ConfigError> {
+        let mut modules = BTreeMap::new();
+        // No folder until the tenant's first upload
+        let entries: Vec<_> = match std::fs::read_dir(folder) {
+            Ok(entries) => entries.collect(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(ConfigError::Io(e)),
+        };
+        for entry in entries {
+            let path = entry.map_err(ConfigError::Io)?.path();
+            let Some(name) = path.extension().filter(|ext| *ext == "wasm").and(path.file_stem()).and_then(|s| s.to_str()) else {
+                continue;
+            };
+            let bytes = std::fs::read(&path).map_err(ConfigError::Io)?;
+            let module = sandbox
+                .compile(&bytes)
+                .map_err(|e| ConfigError::Plugins(format!("{}: {}", path.display(), e)))?;
+            modules.insert(name.to_string(), (module, bytes.len()));
+        }
+        Ok(TenantPlugins {
+            sandbox: sandbox.clone(),
+            modules,
+        })
+    }
+
+    #[cfg(feature = "plugins")]
+    pub fn list(&self) -> Vec<PluginInfo> {
+        self.modules
+            .iter()
+            .map(|(name, (_, size_bytes))| PluginInfo {
+                name: name.clone(),
+                size_bytes: *size_bytes,
+            })
+            .collect()
+    }
+
+    #[cfg(not(feature = "plugins"))]
+    pub fn list(&self) -> Vec<PluginInfo> {
+        match *self {}
+    }
+
+    /// Each plugin's difference between the two texts, by plugin name. A
+    /// plugin that traps, runs out of fuel or returns malformed output gets
+    /// the reason instead.
+    #[cfg(feature = "plugins")]
+    pub fn differences(&self, text1: &str, text2: &str, tokenizer: &Tokenizer) -> Vec<(String, Result<f64, String>)> {
+        use crate::tokenizer::tokenize_with_pos;
+
+        let tokens = |text: &str| {
+            let tokens = tokenize_with_pos(text, tokenizer).map_err(|e| e.to_string())?;
+            serde_json::to_vec(&tokens).map_err(|e| e.to_string())
+        };
+        let inputs = tokens(text1).and_then(|input1| tokens(text2).map(|input2| (input1, input2)));
+        self.modules
+            .iter()
+            .map(|(name, (module, _))| {
+                let difference = inputs.clone().and_then(|(input1, input2)| {
+                    let features1 = self.sandbox.extract(module, &input1)?;
+                    let features2 = self.sandbox.extract(module, &input2)?;
+                    feature_difference(&features1, &features2)
+                });
+                (name.clone(), difference)
+            })
+            .collect()
+    }
+
+    #[cfg(not(feature = "plugins"))]
+    pub fn differences(&self, _text1: &str, _text2: &str, _tokenizer: &Tokenizer) -> Vec<(String, Result<f64, String>)> {
+        match *self {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_feature_difference() {
+        let features = |pairs: &[(&str, f64)]| pairs.iter().map(|(k, v)| (k.to_string(), *v)).collect();
+        let a = features(&[("keigo", 2.0), ("dialect", 0.0)]);
+        let b = features(&[("keigo", 1.0), ("slang", 3.0)]);
+
+        assert_eq!(feature_difference(&a, &a), Ok(0.0));
+        // keigo 0.5, dialect 0, slang 1
+        assert!((feature_difference(&a, &b).unwrap() - 0.5).abs() < 1e-9);
+        assert!(feature_difference(&BTreeMap::new(), &BTreeMap::new()).is_err());
+        assert!(valid_name("keigo-v2") && !valid_name("../x") && !valid_name(""));
+    }
+
+    #[cfg(feature = "plugins")]
+    #[test]
+    fn test_sandboxed_extraction() {
+        // Returns {"keigo":2} whatever the tokens
+        const CONSTANT: &str = r#"(module
+            (memory (export "memory") 1)
+            (data (i32.const 16) "{\"keigo\":2}")
+            (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+            (func (export "extract") (param i32 i32) (result i64) (i64.const 68719476747)))"#;
+        const LOOP: &str = r#"(module
+            (memory (export "memory") 1)
+            (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+            (func (export "extract") (param i32 i32) (result i64) (loop $spin (br $spin)) (i64.const 0)))"#;
+
+        let sandbox = Sandbox::new(&PluginsConfig::default()).unwrap();
+        assert!(matches!(sandbox.compile(b"(module)"), Err(PluginError::Invalid(_))));
+        let plugins = TenantPlugins {
+            modules: BTreeMap::from([
+                ("constant".to_string(), (sandbox.compile(CONSTANT.as_bytes()).unwrap(), CONSTANT.len())),
+                ("loop".to_string(), (sandbox.compile(LOOP.as_bytes()).unwrap(), LOOP.len())),
+            ]),
+            sandbox,
+        };
+
+        let tokenizer = crate::tokenizer::new_tokenizer().unwrap();
+        let differences = plugins.differences("今日は晴れです。", "明日は雨でしょう。", &tokenizer);
+        assert_eq!(differences[0], ("constant".to_string(), Ok(0.0)));
+        // Stopped by the fuel limit rather than hanging
+        assert_eq!(differences[1].0, "loop");
+        assert!(differences[1].1.is_err());
+    }
+}
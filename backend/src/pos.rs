@@ -0,0 +1,118 @@
+use serde::{Deserialize, Serialize};
+
+/// Top-level part of speech of a token, covering the IPADIC and UniDic tag sets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Pos {
+    Noun,
+    Pronoun,
+    /// UniDic's 形状詞 (na-adjective stems); IPADIC tags these as nouns.
+    AdjectivalNoun,
+    Verb,
+    Adjective,
+    Adverb,
+    Prenominal,
+    Conjunction,
+    Interjection,
+    Particle,
+    AuxiliaryVerb,
+    Prefix,
+    Suffix,
+    Symbol,
+    Filler,
+    Whitespace,
+    Other,
+    /// No details, or a tag neither dictionary uses.
+    Unknown,
+}
+
+impl Pos {
+    pub fn from_tag(tag: &str) -> Pos {
+        match tag {
+            "名詞" => Pos::Noun,
+            "代名詞" => Pos::Pronoun,
+            "形状詞" => Pos::AdjectivalNoun,
+            "動詞" => Pos::Verb,
+            "形容詞" => Pos::Adjective,
+            "副詞" => Pos::Adverb,
+            "連体詞" => Pos::Prenominal,
+            "接続詞" => Pos::Conjunction,
+            "感動詞" => Pos::Interjection,
+            "助詞" => Pos::Particle,
+            "助動詞" => Pos::AuxiliaryVerb,
+            "接頭詞" | "接頭辞" => Pos::Prefix,
+            "接尾辞" => Pos::Suffix,
+            "記号" | "補助記号" => Pos::Symbol,
+            "フィラー" => Pos::Filler,
+            "空白" => Pos::Whitespace,
+            "その他" => Pos::Other,
+            _ => Pos::Unknown,
+        }
+    }
+
+    /// As serialized, e.g. `auxiliary_verb`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Pos::Noun => "noun",
+            Pos::Pronoun => "pronoun",
+            Pos::AdjectivalNoun => "adjectival_noun",
+            Pos::Verb => "verb",
+            Pos::Adjective => "adjective",
+            Pos::Adverb => "adverb",
+            Pos::Prenominal => "prenominal",
+            Pos::Conjunction => "conjunction",
+            Pos::Interjection => "interjection",
+            Pos::Particle => "particle",
+            Pos::AuxiliaryVerb => "auxiliary_verb",
+            Pos::Prefix => "prefix",
+            Pos::Suffix => "suffix",
+            Pos::Symbol => "symbol",
+            Pos::Filler => "filler",
+            Pos::Whitespace => "whitespace",
+            Pos::Other => "other",
+            Pos::Unknown => "unknown",
+        }
+    }
+
+    /// Reads the part of speech from lindera's token details, which lead with it.
+    pub fn of(details: &[String]) -> Pos {
+        details.first().map_or(Pos::Unknown, |tag| Pos::from_tag(tag))
+    }
+
+    /// Parts of speech that carry grammar rather than vocabulary.
+    pub fn is_function_word(self) -> bool {
+        match self {
+            Pos::Particle | Pos::AuxiliaryVerb | Pos::Symbol | Pos::Conjunction | Pos::Filler | Pos::Whitespace => true,
+            Pos::Noun
+            | Pos::Pronoun
+            | Pos::AdjectivalNoun
+            | Pos::Verb
+            | Pos::Adjective
+            | Pos::Adverb
+            | Pos::Prenominal
+            | Pos::Interjection
+            | Pos::Prefix
+            | Pos::Suffix
+            | Pos::Other
+            | Pos::Unknown => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pos_from_details() {
+        let details = |tags: &[&str]| tags.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(Pos::of(&details(&["助詞", "格助詞", "一般"])), Pos::Particle);
+        assert_eq!(Pos::of(&details(&["補助記号", "句点"])), Pos::Symbol);
+        assert_eq!(Pos::of(&details(&["UNK"])), Pos::Unknown);
+        assert_eq!(Pos::of(&[]), Pos::Unknown);
+        assert!(Pos::AuxiliaryVerb.is_function_word());
+        assert!(!Pos::Noun.is_function_word());
+        assert_eq!(serde_json::to_value(Pos::AuxiliaryVerb).unwrap(), Pos::AuxiliaryVerb.name());
+    }
+}
@@ -0,0 +1,287 @@
+use std::fmt;
+
+/// A single cleaning step. Steps run in the order they are listed in the
+/// config or request, each receiving the previous step's output.
+pub trait Preprocessor: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn apply(&self, text: &str) -> String;
+}
+
+pub const STEP_NAMES: &[&str] = &["normalize", "strip_markup", "remove_quotes", "mask_entities"];
+
+pub fn step(name: &str) -> Option<Box<dyn Preprocessor>> {
+    match name {
+        "normalize" => Some(Box::new(Normalize)),
+        "strip_markup" => Some(Box::new(StripMarkup)),
+        "remove_quotes" => Some(Box::new(RemoveQuotes)),
+        "mask_entities" => Some(Box::new(MaskEntities)),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownStep(pub String);
+
+impl fmt::Display for UnknownStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown preprocessing step \"{}\" (expected one of: {})",
+            self.0,
+            STEP_NAMES.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for UnknownStep {}
+
+#[derive(Default)]
+pub struct Pipeline {
+    steps: Vec<Box<dyn Preprocessor>>,
+}
+
+impl Pipeline {
+    pub fn from_names<S: AsRef<str>>(names: &[S]) -> Result<Pipeline, UnknownStep> {
+        let steps = names
+            .iter()
+            .map(|name| step(name.as_ref()).ok_or_else(|| UnknownStep(name.as_ref().to_string())))
+            .collect::<Result<_, _>>()?;
+        Ok(Pipeline { steps })
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.steps.iter().map(|s| s.name()).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    pub fn apply(&self, text: &str) -> String {
+        self.steps
+            .iter()
+            .fold(text.to_string(), |text, step| step.apply(&text))
+    }
+}
+
+/// Folds full-width ASCII and ideographic spaces to their half-width forms and
+/// unifies line endings.
+pub struct Normalize;
+
+impl Preprocessor for Normalize {
+    fn name(&self) -> &'static str {
+        "normalize"
+    }
+
+    fn apply(&self, text: &str) -> String {
+        text.replace("\r\n", "\n")
+            .chars()
+            .map(|c| match c {
+                '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+                '\u{3000}' => ' ',
+                _ => c,
+            })
+            .collect()
+    }
+}
+
+/// Removes HTML tags and the common Markdown decorations, keeping link text.
+pub struct StripMarkup;
+
+impl Preprocessor for StripMarkup {
+    fn name(&self) -> &'static str {
+        "strip_markup"
+    }
+
+    fn apply(&self, text: &str) -> String {
+        let chars: Vec<char> = text.chars().collect();
+        let mut out = String::with_capacity(text.len());
+        let mut i = 0;
+        let mut line_start = true;
+
+        while i < chars.len() {
+            let c = chars[i];
+
+            if line_start {
+                // Headings, bullets and indentation only mean something at the start of a line
+                let mut j = i;
+                while j < chars.len() && matches!(chars[j], '#' | ' ' | '\t') {
+                    j += 1;
+                }
+                if j + 1 < chars.len() && matches!(chars[j], '-' | '*' | '+') && chars[j + 1] == ' ' {
+                    j += 2;
+                }
+                i = j;
+                line_start = false;
+                continue;
+            }
+
+            match c {
+                '<' if chars
+                    .get(i + 1)
+                    .is_some_and(|n| n.is_ascii_alphabetic() || matches!(n, '/' | '!')) =>
+                {
+                    match chars[i..].iter().position(|&c| c == '>') {
+                        Some(end) => i += end + 1,
+                        None => {
+                            out.push(c);
+                            i += 1;
+                        }
+                    }
+                }
+                // [text](url) keeps only the text
+                ']' if chars.get(i + 1) == Some(&'(') => {
+                    match chars[i..].iter().position(|&c| c == ')') {
+                        Some(end) => i += end + 1,
+                        None => i += 1,
+                    }
+                }
+                '[' | ']' | '*' | '`' => i += 1,
+                '\n' => {
+                    out.push(c);
+                    line_start = true;
+                    i += 1;
+                }
+                _ => {
+                    out.push(c);
+                    i += 1;
+                }
+            }
+        }
+
+        out
+    }
+}
+
+/// Drops quoted lines (`>` or `＞` prefixes) from replies, so only the
+/// author's own words are compared.
+pub struct RemoveQuotes;
+
+impl Preprocessor for RemoveQuotes {
+    fn name(&self) -> &'static str {
+        "remove_quotes"
+    }
+
+    fn apply(&self, text: &str) -> String {
+        text.split_inclusive('\n')
+            .filter(|line| !line.trim_start().starts_with(['>', '＞']))
+            .collect()
+    }
+}
+
+/// Replaces URLs, email addresses and numbers with fixed placeholders so they
+/// don't register as distinct vocabulary.
+pub struct MaskEntities;
+
+fn is_email_local(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '%' | '+' | '-')
+}
+
+fn is_email_domain(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '.' | '-')
+}
+
+impl Preprocessor for MaskEntities {
+    fn name(&self) -> &'static str {
+        "mask_entities"
+    }
+
+    fn apply(&self, text: &str) -> String {
+        let chars: Vec<char> = text.chars().collect();
+        let mut out = String::with_capacity(text.len());
+        let mut i = 0;
+
+        while i < chars.len() {
+            let rest: String = chars[i..chars.len().min(i + 8)].iter().collect();
+            if rest.starts_with("http://") || rest.starts_with("https://") {
+                while i < chars.len() && chars[i].is_ascii_graphic() {
+                    i += 1;
+                }
+                out.push_str("URL");
+                continue;
+            }
+
+            if is_email_local(chars[i]) {
+                let mut end = i;
+                while end < chars.len() && is_email_local(chars[end]) {
+                    end += 1;
+                }
+                if chars.get(end) == Some(&'@') {
+                    let mut domain_end = end + 1;
+                    while domain_end < chars.len() && is_email_domain(chars[domain_end]) {
+                        domain_end += 1;
+                    }
+                    if chars[end + 1..domain_end].contains(&'.') {
+                        out.push_str("EMAIL");
+                        i = domain_end;
+                        continue;
+                    }
+                }
+            }
+
+            if chars[i].is_ascii_digit() {
+                while i < chars.len()
+                    && (chars[i].is_ascii_digit()
+                        || (matches!(chars[i], ',' | '.')
+                            && chars.get(i + 1).is_some_and(|c| c.is_ascii_digit())))
+                {
+                    i += 1;
+                }
+                out.push('0');
+                continue;
+            }
+
+            // Consume whole ASCII words so an email's local part is never split mid-way
+            if chars[i].is_ascii_alphabetic() {
+                while i < chars.len() && chars[i].is_ascii_alphabetic() {
+                    out.push(chars[i]);
+                    i += 1;
+                }
+                continue;
+            }
+
+            out.push(chars[i]);
+            i += 1;
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pipeline_order() {
+        let pipeline = Pipeline::from_names(&["normalize", "mask_entities"]).unwrap();
+        assert_eq!(pipeline.names(), vec!["normalize", "mask_entities"]);
+        // Full-width digits only become maskable after normalization
+        assert_eq!(pipeline.apply("前年比１１０％"), "前年比0%");
+
+        let reversed = Pipeline::from_names(&["mask_entities", "normalize"]).unwrap();
+        assert_eq!(reversed.apply("前年比１１０％"), "前年比110%");
+
+        assert_eq!(
+            Pipeline::from_names(&["normalize", "lowercase"]).err(),
+            Some(UnknownStep("lowercase".to_string()))
+        );
+        assert!(Pipeline::default().is_empty());
+    }
+
+    #[test]
+    fn test_steps() {
+        assert_eq!(
+            StripMarkup.apply("# 見出し\n- **重要**な<b>お知らせ</b>は[こちら](https://example.com)"),
+            "見出し\n重要なお知らせはこちら"
+        );
+        assert_eq!(
+            RemoveQuotes.apply("> 了解しました。\n＞ 前の話\nありがとうございます。"),
+            "ありがとうございます。"
+        );
+        assert_eq!(
+            MaskEntities.apply("詳細はhttps://example.com/a?b=1を、連絡はtaro.y@example.co.jpまで。3,000円"),
+            "詳細はURLを、連絡はEMAILまで。0円"
+        );
+    }
+}
@@ -0,0 +1,366 @@
+use crate::comparison::compare_extracted;
+use crate::config::Config;
+use crate::features::{extract_features, PosCounts, TextFeatures, FEATURE_SCHEMA_VERSION};
+use crate::register::RegisterProfile;
+use crate::script::ScriptRatios;
+use crate::schema::{upgrade_features, SchemaError, UNVERSIONED};
+use crate::scoring::Scorer;
+use serde::{Deserialize, Serialize};
+use crate::pos::Pos;
+use crate::tokenizer::{LinderaResult, Tokenizer};
+use std::collections::{BTreeMap, HashMap};
+
+/// Stored profiles from older schema versions are migrated while deserializing;
+/// newer ones are refused.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "StoredProfile")]
+pub struct AuthorProfile {
+    pub feature_schema_version: u32,
+    pub sample_count: usize,
+    pub features: TextFeatures,
+    /// Source texts, kept only when asked for so later schema versions can
+    /// re-extract features instead of mapping old ones.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub samples: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct StoredProfile {
+    #[serde(default)]
+    feature_schema_version: Option<u32>,
+    sample_count: usize,
+    features: serde_json::Value,
+    #[serde(default)]
+    samples: Vec<String>,
+}
+
+impl TryFrom<StoredProfile> for AuthorProfile {
+    type Error = SchemaError;
+
+    fn try_from(stored: StoredProfile) -> Result<Self, SchemaError> {
+        let version = stored.feature_schema_version.unwrap_or(UNVERSIONED);
+        Ok(AuthorProfile {
+            feature_schema_version: FEATURE_SCHEMA_VERSION,
+            sample_count: stored.sample_count,
+            features: upgrade_features(stored.features, version)?,
+            samples: stored.samples,
+        })
+    }
+}
+
+impl AuthorProfile {
+    /// Keeps the source texts in the profile, making it re-extractable.
+    pub fn with_samples<S: AsRef<str>>(mut self, texts: &[S]) -> Self {
+        self.samples = texts.iter().map(|t| t.as_ref().to_string()).collect();
+        self
+    }
+}
+
+/// How a stored profile was brought up to the current schema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProfileMigration {
+    UpToDate,
+    /// Old features were mapped field by field.
+    Mapped { from: u32 },
+    /// Features were extracted again from the retained samples.
+    Reextracted { from: u32 },
+}
+
+/// Upgrades a stored profile, preferring re-extraction from its samples over
+/// mapping, since mapped fields can only be filled with defaults.
+pub fn migrate_profile(
+    stored: serde_json::Value,
+    tokenizer: &Tokenizer,
+) -> Result<(AuthorProfile, ProfileMigration), SchemaError> {
+    let version = match stored.get("feature_schema_version") {
+        None | Some(serde_json::Value::Null) => UNVERSIONED,
+        Some(v) => v
+            .as_u64()
+            .map(|v| v as u32)
+            .ok_or_else(|| SchemaError::Invalid("feature_schema_version is not a number".to_string()))?,
+    };
+    if version > FEATURE_SCHEMA_VERSION {
+        return Err(SchemaError::Newer(version));
+    }
+
+    let samples: Vec<String> = stored
+        .get("samples")
+        .and_then(|s| serde_json::from_value(s.clone()).ok())
+        .unwrap_or_default();
+    if version < FEATURE_SCHEMA_VERSION && !samples.is_empty() {
+        let profile = build_profile(&samples, tokenizer)
+            .map_err(|e| SchemaError::Reextraction(e.to_string()))?
+            .with_samples(&samples);
+        return Ok((profile, ProfileMigration::Reextracted { from: version }));
+    }
+
+    let profile: AuthorProfile = serde_json::from_value(stored).map_err(|e| SchemaError::Invalid(e.to_string()))?;
+    let migration = if version == FEATURE_SCHEMA_VERSION {
+        ProfileMigration::UpToDate
+    } else {
+        ProfileMigration::Mapped { from: version }
+    };
+    Ok((profile, migration))
+}
+
+pub fn build_profile<S: AsRef<str>>(texts: &[S], tokenizer: &Tokenizer) -> LinderaResult<AuthorProfile> {
+    let samples = texts
+        .iter()
+        .map(|text| extract_features(text.as_ref(), tokenizer))
+        .collect::<LinderaResult<Vec<TextFeatures>>>()?;
+
+    Ok(AuthorProfile {
+        feature_schema_version: FEATURE_SCHEMA_VERSION,
+        sample_count: samples.len(),
+        features: mean_features(&samples),
+        samples: Vec::new(),
+    })
+}
+
+/// Averages per-sample features so that every sample counts equally,
+/// regardless of its length.
+pub fn mean_features(samples: &[TextFeatures]) -> TextFeatures {
+    let samples: Vec<(&TextFeatures, f64)> = samples.iter().map(|f| (f, 1.0)).collect();
+    weighted_mean_features(&samples)
+}
+
+// Counts are summed whatever the weight, since a profile's already are
+fn weighted_mean_features(samples: &[(&TextFeatures, f64)]) -> TextFeatures {
+    let total = samples.iter().map(|(_, w)| w).sum::<f64>();
+    let total = if total > 0.0 { total } else { 1.0 };
+    let mean = |f: fn(&TextFeatures) -> f64| samples.iter().map(|(s, w)| f(s) * w).sum::<f64>() / total;
+
+    let mut word_frequencies: HashMap<String, f64> = HashMap::new();
+    for (sample, weight) in samples {
+        for (word, freq) in &sample.word_frequencies {
+            *word_frequencies.entry(word.clone()).or_insert(0.0) += freq * weight / total;
+        }
+    }
+    let mut pos_distribution: BTreeMap<Pos, f64> = BTreeMap::new();
+    for (sample, weight) in samples {
+        for (pos, share) in &sample.pos_distribution {
+            *pos_distribution.entry(*pos).or_insert(0.0) += share * weight / total;
+        }
+    }
+
+    let mut char_ngrams: HashMap<String, f64> = HashMap::new();
+    let mut pos_ngrams: HashMap<String, f64> = HashMap::new();
+    let mut particle_distribution: HashMap<String, f64> = HashMap::new();
+    for (sample, weight) in samples {
+        for (gram, freq) in &sample.char_ngrams {
+            *char_ngrams.entry(gram.clone()).or_insert(0.0) += freq * weight / total;
+        }
+        for (gram, freq) in &sample.pos_ngrams {
+            *pos_ngrams.entry(gram.clone()).or_insert(0.0) += freq * weight / total;
+        }
+        for (particle, share) in &sample.particle_distribution {
+            *particle_distribution.entry(particle.clone()).or_insert(0.0) += share * weight / total;
+        }
+    }
+
+    // Over the samples that have Japanese sentences at all
+    let registers: Vec<_> = samples.iter().filter_map(|(f, weight)| f.register.map(|r| (r, *weight))).collect();
+    let register_total: f64 = registers.iter().map(|(_, weight)| weight).sum();
+    let register = (register_total > 0.0).then(|| {
+        let mean = |share: fn(&RegisterProfile) -> f64| registers.iter().map(|(r, w)| share(r) * w).sum::<f64>() / register_total;
+        RegisterProfile {
+            polite: mean(|r| r.polite),
+            plain: mean(|r| r.plain),
+            casual: mean(|r| r.casual),
+            keigo: mean(|r| r.keigo),
+        }
+    });
+    // Likewise over the samples with letters or digits
+    let scripts: Vec<_> = samples.iter().filter_map(|(f, weight)| f.script_ratios.map(|s| (s, *weight))).collect();
+    let script_total: f64 = scripts.iter().map(|(_, weight)| weight).sum();
+    let script_ratios = (script_total > 0.0).then(|| {
+        let mean = |share: fn(&ScriptRatios) -> f64| scripts.iter().map(|(s, w)| share(s) * w).sum::<f64>() / script_total;
+        ScriptRatios {
+            kanji: mean(|s| s.kanji),
+            hiragana: mean(|s| s.hiragana),
+            katakana: mean(|s| s.katakana),
+            latin: mean(|s| s.latin),
+            digit: mean(|s| s.digit),
+        }
+    });
+
+    TextFeatures {
+        word_frequencies,
+        particle_ratio: mean(|f| f.particle_ratio),
+        verb_ratio: mean(|f| f.verb_ratio),
+        adjective_ratio: mean(|f| f.adjective_ratio),
+        unique_words_ratio: mean(|f| f.unique_words_ratio),
+        avg_sentence_length: mean(|f| f.avg_sentence_length),
+        punctuation_ratio: mean(|f| f.punctuation_ratio),
+        english_segment_ratio: mean(|f| f.english_segment_ratio),
+        symbol_ratio: mean(|f| f.symbol_ratio),
+        unusual_whitespace_ratio: mean(|f| f.unusual_whitespace_ratio),
+        // Evidence accumulates across samples rather than averaging out
+        token_count: samples.iter().map(|(f, _)| f.token_count).sum(),
+        sentence_count: samples.iter().map(|(f, _)| f.sentence_count).sum(),
+        pos_counts: PosCounts {
+            tagged: samples.iter().map(|(f, _)| f.pos_counts.tagged).sum(),
+            particles: samples.iter().map(|(f, _)| f.pos_counts.particles).sum(),
+            verbs: samples.iter().map(|(f, _)| f.pos_counts.verbs).sum(),
+            adjectives: samples.iter().map(|(f, _)| f.pos_counts.adjectives).sum(),
+        },
+        pos_distribution,
+        char_ngrams,
+        pos_ngrams,
+        particle_distribution,
+        register,
+        script_ratios,
+    }
+}
+
+/// Combines profiles of one author as if they were built from all their
+/// samples at once: each profile weighs in by its sample count.
+pub fn merge_profiles(profiles: &[AuthorProfile]) -> AuthorProfile {
+    let features: Vec<(&TextFeatures, f64)> = profiles.iter().map(|p| (&p.features, p.sample_count as f64)).collect();
+    AuthorProfile {
+        feature_schema_version: FEATURE_SCHEMA_VERSION,
+        sample_count: profiles.iter().map(|p| p.sample_count).sum(),
+        features: weighted_mean_features(&features),
+        samples: profiles.iter().flat_map(|p| p.samples.iter().cloned()).collect(),
+    }
+}
+
+/// Same-author confidence of each sample against the mean of all the others,
+/// so the samples least like the rest of their set stand out. Empty for
+/// fewer than two samples.
+pub fn sample_fit(samples: &[TextFeatures], config: &Config, scorer: &dyn Scorer) -> Vec<f64> {
+    if samples.len() < 2 {
+        return Vec::new();
+    }
+    (0..samples.len())
+        .map(|i| {
+            let rest: Vec<TextFeatures> = samples
+                .iter()
+                .enumerate()
+                .filter(|&(j, _)| j != i)
+                .map(|(_, f)| f.clone())
+                .collect();
+            compare_extracted(&samples[i], &mean_features(&rest), config, scorer).confidence
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mean_features() {
+        let sample = |word: &str, particle_ratio: f64| TextFeatures {
+            word_frequencies: HashMap::from([(word.to_string(), 1.0)]),
+            particle_ratio,
+            verb_ratio: 0.2,
+            adjective_ratio: 0.1,
+            unique_words_ratio: 1.0,
+            avg_sentence_length: 10.0,
+            punctuation_ratio: 0.1,
+            english_segment_ratio: 0.0,
+            symbol_ratio: 0.0,
+            unusual_whitespace_ratio: 0.0,
+            token_count: 10,
+            sentence_count: 1,
+            pos_counts: PosCounts::default(),
+            pos_distribution: Default::default(),
+            char_ngrams: Default::default(),
+            pos_ngrams: Default::default(),
+            particle_distribution: Default::default(),
+            register: None,
+            script_ratios: None,
+        };
+
+        let mean = mean_features(&[sample("公園", 0.2), sample("会議", 0.4)]);
+
+        assert!((mean.particle_ratio - 0.3).abs() < 1e-9);
+        assert_eq!(mean.word_frequencies.get("公園"), Some(&0.5));
+        assert_eq!(mean.word_frequencies.get("会議"), Some(&0.5));
+        assert_eq!(mean.token_count, 20);
+
+        let profile = |sample_count, features| AuthorProfile {
+            feature_schema_version: FEATURE_SCHEMA_VERSION,
+            sample_count,
+            features,
+            samples: Vec::new(),
+        };
+        let merged = merge_profiles(&[profile(3, sample("公園", 0.2)), profile(1, sample("会議", 0.6))]);
+        assert_eq!(merged.sample_count, 4);
+        assert!((merged.features.particle_ratio - 0.3).abs() < 1e-9);
+        assert_eq!(merged.features.word_frequencies.get("公園"), Some(&0.75));
+        assert_eq!(merged.features.token_count, 20);
+    }
+
+    #[test]
+    fn test_sample_fit() {
+        let tokenizer = crate::tokenizer::new_tokenizer().unwrap();
+        let samples: Vec<TextFeatures> = [
+            "私は今日公園に行きました。とても楽しかったです。",
+            "私は昨日公園で遊びました。本当に楽しかったです。",
+            "私は先週公園を歩きました。とても気持ちよかったです。",
+            "第三四半期の売上実績について報告いたします。前年比110%となっております。",
+        ]
+        .iter()
+        .map(|text| extract_features(text, &tokenizer).unwrap())
+        .collect();
+
+        let fit = sample_fit(&samples, &Config::default(), &crate::scoring::WeightedScorer);
+        assert_eq!(fit.len(), 4);
+        let worst = (0..fit.len()).min_by(|&a, &b| fit[a].total_cmp(&fit[b])).unwrap();
+        assert_eq!(worst, 3);
+        assert!(sample_fit(&samples[..1], &Config::default(), &crate::scoring::WeightedScorer).is_empty());
+    }
+
+    #[test]
+    fn test_stored_profile_versions() {
+        let legacy = serde_json::json!({
+            "sample_count": 2,
+            "features": {
+                "word_frequencies": {},
+                "particle_ratio": 0.3,
+                "verb_ratio": 0.2,
+                "adjective_ratio": 0.05,
+                "unique_words_ratio": 0.8,
+                "avg_sentence_length": 12.0,
+                "punctuation_ratio": 0.1,
+                "english_segment_ratio": 0.0,
+                "token_count": 20,
+                "sentence_count": 2,
+            }
+        });
+
+        let profile: AuthorProfile = serde_json::from_value(legacy.clone()).unwrap();
+        assert_eq!(profile.feature_schema_version, FEATURE_SCHEMA_VERSION);
+        let saved = serde_json::to_value(&profile).unwrap();
+        assert_eq!(saved["feature_schema_version"], serde_json::json!(FEATURE_SCHEMA_VERSION));
+
+        let mut newer = legacy;
+        newer["feature_schema_version"] = serde_json::json!(FEATURE_SCHEMA_VERSION + 1);
+        assert!(serde_json::from_value::<AuthorProfile>(newer).is_err());
+    }
+
+    #[test]
+    fn test_migrate_profile() {
+        let tokenizer = crate::tokenizer::new_tokenizer().unwrap();
+        let texts = ["今日は公園に行きました。", "明日は図書館に行きます。"];
+        let current = serde_json::to_value(build_profile(&texts, &tokenizer).unwrap().with_samples(&texts)).unwrap();
+
+        let (_, migration) = migrate_profile(current.clone(), &tokenizer).unwrap();
+        assert_eq!(migration, ProfileMigration::UpToDate);
+
+        let mut legacy = current;
+        legacy.as_object_mut().unwrap().remove("feature_schema_version");
+        legacy["features"].as_object_mut().unwrap().remove("symbol_ratio");
+        let (profile, migration) = migrate_profile(legacy.clone(), &tokenizer).unwrap();
+        assert_eq!(migration, ProfileMigration::Reextracted { from: UNVERSIONED });
+        assert_eq!(profile.samples.len(), 2);
+
+        legacy.as_object_mut().unwrap().remove("samples");
+        let (profile, migration) = migrate_profile(legacy, &tokenizer).unwrap();
+        assert_eq!(migration, ProfileMigration::Mapped { from: UNVERSIONED });
+        assert_eq!(profile.feature_schema_version, FEATURE_SCHEMA_VERSION);
+    }
+}
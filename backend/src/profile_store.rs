@@ -0,0 +1,126 @@
+use crate::profile::AuthorProfile;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Author profiles kept as one `<author>.json` file each in a directory,
+/// shared by the CLI's `profile` commands and the server.
+#[derive(Debug, Clone)]
+pub struct ProfileStore {
+    directory: PathBuf,
+}
+
+#[derive(Debug)]
+pub enum ProfileStoreError {
+    Io(io::Error),
+    /// Empty, or would escape the store's directory.
+    InvalidAuthor(String),
+    NotFound(String),
+    /// The stored file isn't a profile, or is from a newer schema.
+    Invalid { author: String, message: String },
+}
+
+impl fmt::Display for ProfileStoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProfileStoreError::Io(e) => write!(f, "failed to access the profile store: {}", e),
+            ProfileStoreError::InvalidAuthor(author) => write!(f, "invalid author name \"{}\"", author),
+            ProfileStoreError::NotFound(author) => write!(f, "no stored profile for \"{}\"", author),
+            ProfileStoreError::Invalid { author, message } => write!(f, "stored profile of \"{}\" is invalid: {}", author, message),
+        }
+    }
+}
+
+impl std::error::Error for ProfileStoreError {}
+
+impl From<io::Error> for ProfileStoreError {
+    fn from(e: io::Error) -> Self {
+        ProfileStoreError::Io(e)
+    }
+}
+
+impl ProfileStore {
+    pub fn new(directory: impl Into<PathBuf>) -> ProfileStore {
+        ProfileStore {
+            directory: directory.into(),
+        }
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    fn path(&self, author: &str) -> Result<PathBuf, ProfileStoreError> {
+        let invalid = author.is_empty() || author.starts_with('.') || author.contains(['/', '\\']);
+        if invalid {
+            return Err(ProfileStoreError::InvalidAuthor(author.to_string()));
+        }
+        Ok(self.directory.join(format!("{}.json", author)))
+    }
+
+    /// Authors with a stored profile, sorted; empty when the directory doesn't exist yet.
+    pub fn authors(&self) -> Result<Vec<String>, ProfileStoreError> {
+        let entries = match std::fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut authors = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                if let Some(stem) = path.file_stem() {
+                    authors.push(stem.to_string_lossy().into_owned());
+                }
+            }
+        }
+        authors.sort();
+        Ok(authors)
+    }
+
+    /// Loads a profile, migrating it from an older schema version if needed.
+    pub fn load(&self, author: &str) -> Result<AuthorProfile, ProfileStoreError> {
+        let json = match std::fs::read_to_string(self.path(author)?) {
+            Ok(json) => json,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Err(ProfileStoreError::NotFound(author.to_string())),
+            Err(e) => return Err(e.into()),
+        };
+        serde_json::from_str(&json).map_err(|e| ProfileStoreError::Invalid {
+            author: author.to_string(),
+            message: e.to_string(),
+        })
+    }
+
+    /// Stores a profile, replacing any earlier one of the same author.
+    pub fn save(&self, author: &str, profile: &AuthorProfile) -> Result<(), ProfileStoreError> {
+        let path = self.path(author)?;
+        std::fs::create_dir_all(&self.directory)?;
+        let json = serde_json::to_string_pretty(profile).map_err(io::Error::other)?;
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::build_profile;
+
+    #[test]
+    fn test_profile_store() {
+        let dir = std::env::temp_dir().join(format!("author-comparer-profiles-{}", std::process::id()));
+        let store = ProfileStore::new(&dir);
+        assert!(store.authors().unwrap().is_empty());
+
+        let tokenizer = crate::tokenizer::new_tokenizer().unwrap();
+        let profile = build_profile(&["今日は公園に行きました。"], &tokenizer).unwrap();
+        store.save("natsume", &profile).unwrap();
+        store.save("mori", &profile).unwrap();
+        assert_eq!(store.authors().unwrap(), ["mori", "natsume"]);
+        assert_eq!(store.load("natsume").unwrap().sample_count, 1);
+
+        assert!(matches!(store.load("akutagawa"), Err(ProfileStoreError::NotFound(_))));
+        assert!(matches!(store.save("../escape", &profile), Err(ProfileStoreError::InvalidAuthor(_))));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,102 @@
+use crate::export::FeatureTable;
+use crate::numerics::safe_div;
+use nalgebra::DMatrix;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ProjectedPoint {
+    pub label: String,
+    pub x: f64,
+    pub y: f64,
+}
+
+/// The texts placed on the first two principal components of their features.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Projection {
+    pub points: Vec<ProjectedPoint>,
+    /// Share of the total variance along each axis.
+    pub explained_variance: [f64; 2],
+}
+
+/// Projects the table's rows to 2D with PCA. Columns are standardized first so
+/// word frequencies and sentence lengths weigh the same; columns that never
+/// vary are left out.
+pub fn project(table: &FeatureTable) -> Projection {
+    let n = table.rows.len();
+    let columns: Vec<(usize, f64, f64)> = (0..table.columns.len())
+        .filter_map(|j| {
+            let mean = table.rows.iter().map(|row| row[j]).sum::<f64>() / n as f64;
+            let variance = safe_div(table.rows.iter().map(|row| (row[j] - mean).powi(2)).sum(), n as f64 - 1.0, 0.0);
+            (variance > 0.0).then(|| (j, mean, variance.sqrt()))
+        })
+        .collect();
+
+    let mut coordinates = vec![[0.0; 2]; n];
+    let mut explained_variance = [0.0; 2];
+    if n >= 2 && !columns.is_empty() {
+        let standardized = DMatrix::from_fn(n, columns.len(), |i, k| {
+            let (j, mean, deviation) = columns[k];
+            (table.rows[i][j] - mean) / deviation
+        });
+        let covariance = standardized.transpose() * &standardized / (n as f64 - 1.0);
+        let eigen = covariance.symmetric_eigen();
+
+        let mut order: Vec<usize> = (0..eigen.eigenvalues.len()).collect();
+        order.sort_by(|&a, &b| eigen.eigenvalues[b].total_cmp(&eigen.eigenvalues[a]));
+        let total: f64 = eigen.eigenvalues.iter().map(|v| v.max(0.0)).sum();
+
+        for (axis, &component) in order.iter().take(2).enumerate() {
+            let mut direction = eigen.eigenvectors.column(component).clone_owned();
+            // Eigenvectors have no inherent sign; fix one so repeated calls agree
+            if direction.iter().sum::<f64>() < 0.0 {
+                direction = -direction;
+            }
+            let scores = &standardized * &direction;
+            for (i, coordinate) in coordinates.iter_mut().enumerate() {
+                coordinate[axis] = scores[i];
+            }
+            explained_variance[axis] = safe_div(eigen.eigenvalues[component].max(0.0), total, 0.0);
+        }
+    }
+
+    Projection {
+        points: table
+            .labels
+            .iter()
+            .zip(coordinates)
+            .map(|(label, [x, y])| ProjectedPoint { label: label.clone(), x, y })
+            .collect(),
+        explained_variance,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_project() {
+        // The first two columns move together, the third is constant
+        let table = FeatureTable {
+            columns: vec!["a".to_string(), "b".to_string(), "c".to_string()],
+            labels: vec!["x".to_string(), "x".to_string(), "y".to_string(), "y".to_string()],
+            rows: vec![
+                vec![0.0, 0.1, 1.0],
+                vec![1.0, 1.0, 1.0],
+                vec![2.0, 2.1, 1.0],
+                vec![3.0, 2.9, 1.0],
+            ],
+        };
+        let projection = project(&table);
+
+        assert_eq!(projection.points.len(), 4);
+        assert!(projection.explained_variance[0] > 0.99);
+        assert!((projection.explained_variance[0] + projection.explained_variance[1] - 1.0).abs() < 1e-9);
+        let xs: Vec<f64> = projection.points.iter().map(|p| p.x).collect();
+        assert!(xs.windows(2).all(|w| w[0] < w[1]));
+
+        let single = FeatureTable { rows: table.rows[..1].to_vec(), labels: table.labels[..1].to_vec(), ..table };
+        assert_eq!(project(&single).explained_variance, [0.0, 0.0]);
+    }
+}
@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+
+/// What a punctuation mark does, across ASCII, full-width and CJK forms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PunctuationClass {
+    /// Ends a sentence: 。．！？… and their ASCII forms.
+    Terminal,
+    /// Pauses within a sentence: 、，：；
+    Pause,
+    /// Opening and closing brackets and quotation marks.
+    Bracket,
+    /// Separates items in a list or the parts of a foreign name: ・
+    MiddleDot,
+    /// Any other punctuation or typographic symbol, such as ※ or 〃.
+    Symbol,
+}
+
+pub fn classify(c: char) -> Option<PunctuationClass> {
+    use PunctuationClass::*;
+    let class = match c {
+        '。' | '｡' | '．' | '.' | '！' | '!' | '？' | '?' | '…' | '‥' | '‼' | '⁇' | '⁈' | '⁉' => Terminal,
+        '、' | '､' | '，' | ',' | '：' | ':' | '；' | ';' => Pause,
+        '「' | '」' | '『' | '』' | '（' | '）' | '(' | ')' | '［' | ']' | '[' | '］' | '【' | '】' | '〔' | '〕'
+        | '〈' | '〉' | '《' | '》' | '｛' | '｝' | '{' | '}' | '〖' | '〗' | '〘' | '〙' | '〚' | '〛' | '｢' | '｣'
+        | '"' | '\'' | '“' | '”' | '‘' | '’' | '＂' | '＇' | '«' | '»' | '〝' | '〞' | '〟' => Bracket,
+        '・' | '･' | '·' | '•' | '‧' => MiddleDot,
+        _ if c.is_ascii_punctuation() => Symbol,
+        // CJK symbols and punctuation, general punctuation, and the
+        // full-width forms of ASCII punctuation
+        '\u{3001}'..='\u{3004}' | '\u{3012}'..='\u{3020}' | '\u{3030}' | '\u{303D}' => Symbol,
+        '\u{2010}'..='\u{2027}' | '\u{2030}'..='\u{205E}' => Symbol,
+        '\u{FF01}'..='\u{FF0F}' | '\u{FF1A}'..='\u{FF20}' | '\u{FF3B}'..='\u{FF40}' | '\u{FF5B}'..='\u{FF65}' => Symbol,
+        '¡' | '§' | '¶' | '¿' => Symbol,
+        _ => return None,
+    };
+    Some(class)
+}
+
+pub fn is_punctuation(c: char) -> bool {
+    classify(c).is_some()
+}
+
+/// A non-empty token made only of punctuation.
+pub fn is_punctuation_token(token: &str) -> bool {
+    !token.is_empty() && token.chars().all(is_punctuation)
+}
+
+/// Strips leading and trailing punctuation of any class.
+pub fn trim_punctuation(word: &str) -> &str {
+    word.trim_matches(is_punctuation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(classify('。'), Some(PunctuationClass::Terminal));
+        assert_eq!(classify('？'), Some(PunctuationClass::Terminal));
+        assert_eq!(classify('、'), Some(PunctuationClass::Pause));
+        assert_eq!(classify('「'), Some(PunctuationClass::Bracket));
+        assert_eq!(classify('・'), Some(PunctuationClass::MiddleDot));
+        assert_eq!(classify('※'), Some(PunctuationClass::Symbol));
+        assert_eq!(classify('#'), Some(PunctuationClass::Symbol));
+        assert_eq!(classify('ー'), None);
+        assert_eq!(classify('あ'), None);
+
+        assert!(is_punctuation_token("」。"));
+        assert!(!is_punctuation_token("公園"));
+        assert!(!is_punctuation_token(""));
+        assert_eq!(trim_punctuation("「公園」。"), "公園");
+    }
+}
@@ -0,0 +1,54 @@
+use crate::tokenizer::{shared_tokenizer, Tokenizer};
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use serde::Serialize;
+
+fn tokenizer() -> PyResult<&'static Tokenizer> {
+    shared_tokenizer().map_err(|e| PyRuntimeError::new_err(e.to_string()))
+}
+
+// Round-trip through JSON so Python callers get plain dicts with the exact
+// field names the HTTP API uses.
+fn to_python<T: Serialize>(py: Python<'_>, value: &T) -> PyResult<PyObject> {
+    let json = serde_json::to_string(value).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    let object = py.import("json")?.call_method1("loads", (json,))?;
+    Ok(object.into_py(py))
+}
+
+#[pyfunction]
+#[pyo3(name = "extract_features")]
+fn py_extract_features(py: Python<'_>, text: &str) -> PyResult<PyObject> {
+    let tokenizer = tokenizer()?;
+    let features = py
+        .allow_threads(|| crate::extract_features(text, tokenizer))
+        .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+    to_python(py, &features)
+}
+
+#[pyfunction]
+#[pyo3(name = "compare")]
+fn py_compare(py: Python<'_>, text1: &str, text2: &str) -> PyResult<PyObject> {
+    let tokenizer = tokenizer()?;
+    let analysis = py
+        .allow_threads(|| crate::compare(text1, text2, tokenizer))
+        .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+    to_python(py, &analysis)
+}
+
+#[pyfunction]
+#[pyo3(name = "build_profile")]
+fn py_build_profile(py: Python<'_>, texts: Vec<String>) -> PyResult<PyObject> {
+    let tokenizer = tokenizer()?;
+    let profile = py
+        .allow_threads(|| crate::profile::build_profile(&texts, tokenizer))
+        .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+    to_python(py, &profile)
+}
+
+#[pymodule]
+fn author_comparer(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(py_extract_features, m)?)?;
+    m.add_function(wrap_pyfunction!(py_compare, m)?)?;
+    m.add_function(wrap_pyfunction!(py_build_profile, m)?)?;
+    Ok(())
+}
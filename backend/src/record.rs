@@ -0,0 +1,247 @@
+use crate::comparison::{compare_extracted, compare_with_options, Analysis, CompareOptions, Verdict};
+use crate::config::Config;
+use crate::delta::{aspect_deltas, AspectDelta};
+use crate::embedding::Embedder;
+use crate::features::TextFeatures;
+use crate::preprocess::Pipeline;
+use crate::schema::{upgrade_features, SchemaError, UNVERSIONED};
+use crate::scoring::Scorer;
+use crate::tokenizer::Tokenizer;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+/// Layout version of `ExperimentRecord`, bumped when older builds could no
+/// longer read it.
+pub const RECORD_FORMAT_VERSION: u32 = 1;
+
+/// Whether experiment records carry the compared texts or only their hashes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum InputPolicy {
+    /// Only the SHA-256 of each input; such records replay from their features.
+    #[default]
+    Hashed,
+    /// The texts as sent, so a replay extracts their features again.
+    Raw,
+}
+
+/// One side of a recorded comparison.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct RecordedInput {
+    /// Of the text as sent, or of the feature vector's JSON for requests
+    /// sending features.
+    pub sha256: String,
+    /// Only kept under the `raw` input policy.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+/// Everything needed to score a comparison again: its inputs, the features
+/// extracted from them, the effective configuration and the result as it
+/// was returned.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ExperimentRecord {
+    pub format_version: u32,
+    /// Seconds since the Unix epoch.
+    pub created_at: i64,
+    /// Version of the build that produced the record.
+    pub model_version: String,
+    pub feature_schema_version: u32,
+    pub inputs: [RecordedInput; 2],
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dictionary: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub genres: Option<[String; 2]>,
+    /// As extracted by `extract_features`.
+    #[schemars(with = "[Value; 2]")]
+    pub features: [TextFeatures; 2],
+    pub config: Config,
+    pub analysis: Analysis,
+}
+
+#[derive(Debug)]
+pub enum RecordError {
+    /// Written by a newer build than this one.
+    Newer(u32),
+    Schema(SchemaError),
+    Invalid(String),
+    /// The recorded texts couldn't be tokenized again.
+    Tokenizer(String),
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordError::Newer(version) => write!(
+                f,
+                "record format version {} is newer than this build supports ({})",
+                version, RECORD_FORMAT_VERSION
+            ),
+            RecordError::Schema(e) => write!(f, "{}", e),
+            RecordError::Invalid(e) => write!(f, "invalid experiment record: {}", e),
+            RecordError::Tokenizer(e) => write!(f, "tokenizing the recorded texts: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for RecordError {}
+
+impl From<SchemaError> for RecordError {
+    fn from(e: SchemaError) -> Self {
+        RecordError::Schema(e)
+    }
+}
+
+impl From<serde_json::Error> for RecordError {
+    fn from(e: serde_json::Error) -> Self {
+        RecordError::Invalid(e.to_string())
+    }
+}
+
+impl ExperimentRecord {
+    /// Reads a record, migrating its features from older schema versions
+    /// like imported feature vectors. `feature_schema_version` stays the
+    /// recorded one.
+    pub fn from_json(json: &str) -> Result<ExperimentRecord, RecordError> {
+        let mut value: Value = serde_json::from_str(json)?;
+        let format = value.get("format_version").and_then(Value::as_u64).unwrap_or(0) as u32;
+        if format > RECORD_FORMAT_VERSION {
+            return Err(RecordError::Newer(format));
+        }
+        let version = value.get("feature_schema_version").and_then(Value::as_u64).map_or(UNVERSIONED, |v| v as u32);
+        if let Some(Value::Array(features)) = value.get_mut("features") {
+            for features in features.iter_mut() {
+                *features = serde_json::to_value(upgrade_features(features.take(), version)?)?;
+            }
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Whether the record carries both texts, rather than only their hashes.
+    pub fn has_texts(&self) -> bool {
+        self.inputs.iter().all(|input| input.text.is_some())
+    }
+
+    /// Scores the comparison again with this build, under `config`: from
+    /// the texts, extracted anew, when the record has them, otherwise from
+    /// the recorded features.
+    pub fn replay(
+        &self,
+        tokenizer: &Tokenizer,
+        config: &Config,
+        scorer: &dyn Scorer,
+        embedder: Option<&Embedder>,
+    ) -> Result<Analysis, RecordError> {
+        let [Some(text1), Some(text2)] = [&self.inputs[0].text, &self.inputs[1].text] else {
+            let [features1, features2] = &self.features;
+            return Ok(compare_extracted(features1, features2, config, scorer));
+        };
+        let pipeline = Pipeline::from_names(&config.preprocessing).map_err(|e| RecordError::Invalid(e.to_string()))?;
+        let steps = pipeline.names();
+        let options = CompareOptions {
+            scorer,
+            embedder,
+            genres: self.genres.as_ref().map(|[genre1, genre2]| [genre1.as_str(), genre2.as_str()]),
+            preprocessing: &steps,
+            ..CompareOptions::default()
+        };
+        compare_with_options(&pipeline.apply(text1), &pipeline.apply(text2), tokenizer, config, options)
+            .map_err(|e| RecordError::Tokenizer(e.to_string()))
+    }
+}
+
+/// How a replayed result differs from the recorded one.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Drift {
+    pub recorded: Verdict,
+    pub replayed: Verdict,
+    pub confidence_change: f64,
+    /// Aspects whose difference moved by at least the tolerance, largest move first.
+    pub aspects: Vec<AspectDelta>,
+}
+
+impl Drift {
+    pub fn verdict_changed(&self) -> bool {
+        self.recorded != self.replayed
+    }
+}
+
+/// The drift of `replayed` from `recorded`, or `None` when the verdict is
+/// the same and the confidence moved by no more than `tolerance`.
+pub fn drift(recorded: &Analysis, replayed: &Analysis, tolerance: f64) -> Option<Drift> {
+    let confidence_change = replayed.confidence - recorded.confidence;
+    if recorded.verdict == replayed.verdict && confidence_change.abs() <= tolerance {
+        return None;
+    }
+    Some(Drift {
+        recorded: recorded.verdict,
+        replayed: replayed.verdict,
+        confidence_change,
+        aspects: aspect_deltas(&recorded.detailed_analysis, &replayed.detailed_analysis, tolerance),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comparison::compare_with_config;
+    use crate::features::{extract_features, FEATURE_SCHEMA_VERSION};
+    use crate::scoring::WeightedScorer;
+    use crate::tokenizer::new_tokenizer;
+
+    #[test]
+    fn test_replay_record() {
+        let tokenizer = new_tokenizer().unwrap();
+        let texts = ["私は今日公園に行きました。とても楽しかったです。", "猫がとても好きだ！本当に可愛いから。"];
+        // Recorded features are scored on the usual aspects, so the texts are too
+        let mut config = Config::default();
+        config.short_text.max_chars = 0;
+        let record = |text: Option<&str>| RecordedInput {
+            sha256: "0".repeat(64),
+            text: text.map(str::to_string),
+        };
+        let raw = ExperimentRecord {
+            format_version: RECORD_FORMAT_VERSION,
+            created_at: 0,
+            model_version: crate::MODEL_VERSION.to_string(),
+            feature_schema_version: FEATURE_SCHEMA_VERSION,
+            inputs: texts.map(|text| record(Some(text))),
+            dictionary: None,
+            genres: None,
+            features: texts.map(|text| extract_features(text, &tokenizer).unwrap()),
+            config: config.clone(),
+            analysis: compare_with_config(texts[0], texts[1], &tokenizer, &config).unwrap(),
+        };
+        let read = ExperimentRecord::from_json(&serde_json::to_string(&raw).unwrap()).unwrap();
+        assert!(read.has_texts());
+        let replayed = read.replay(&tokenizer, &read.config, &WeightedScorer, None).unwrap();
+        assert_eq!((replayed.verdict, replayed.confidence), (raw.analysis.verdict, raw.analysis.confidence));
+
+        // Without the texts, the recorded features are scored
+        let hashed = ExperimentRecord {
+            inputs: [record(None), record(None)],
+            ..read
+        };
+        assert!(!hashed.has_texts());
+        let replayed = hashed.replay(&tokenizer, &config, &WeightedScorer, None).unwrap();
+        assert_eq!(replayed.confidence, raw.analysis.confidence);
+
+        assert!(drift(&raw.analysis, &replayed, 0.0).is_none());
+
+        // Scored with other weights, the same features drift
+        let mut reweighted = config.clone();
+        reweighted.weights.insert("Word Usage".to_string(), 0.1);
+        let replayed = hashed.replay(&tokenizer, &reweighted, &WeightedScorer, None).unwrap();
+        let moved = drift(&raw.analysis, &replayed, 0.001).unwrap();
+        assert_eq!(moved.confidence_change, replayed.confidence - raw.analysis.confidence);
+        assert!(moved.confidence_change.abs() > 0.001);
+        // Weights move the confidence, not the aspects' differences
+        assert!(moved.aspects.is_empty());
+
+        let mut newer = serde_json::to_value(&raw).unwrap();
+        newer["format_version"] = Value::from(RECORD_FORMAT_VERSION + 1);
+        assert!(matches!(ExperimentRecord::from_json(&newer.to_string()), Err(RecordError::Newer(_))));
+    }
+}
@@ -0,0 +1,121 @@
+use crate::numerics::js_divergence;
+use crate::punctuation::is_punctuation;
+use crate::sentences;
+use crate::symbols::{is_emoji, is_symbol};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+// です・ます predicates, longest first
+const POLITE: &[&str] = &["ございます", "ましょう", "でしょう", "ました", "ません", "でした", "ください", "下さい", "ます", "です"];
+// Particles that can follow a polite predicate without making it casual (ですよね, ますか)
+const POLITE_TAILS: &[&str] = &["よね", "かね", "よ", "ね", "か", "わ"];
+// Sentence-final particles and forms of speech rather than prose; anything
+// else that is neither these nor polite reads as だ・である style
+const CASUAL: &[&str] = &["じゃん", "じゃない", "っす", "だよ", "だね", "よね", "かな", "けど", "のよ", "わよ", "よ", "ね", "ぞ", "ぜ", "わ", "な"];
+// Honorific and humble verbs and set phrases
+const KEIGO: &[&str] = &[
+    "いらっしゃ", "おっしゃ", "召し上が", "ご覧", "くださ", "下さ", "申し上げ", "申し", "存じ", "伺", "参り", "拝見", "いたし", "致し",
+    "差し上げ", "ており", "ござい", "恐れ入",
+];
+// Keigo makes up this share of the difference, the sentence endings the rest
+const KEIGO_SHARE: f64 = 0.25;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Register {
+    Polite,
+    Plain,
+    Casual,
+}
+
+/// How formally a text is written: the share of its sentences ending in
+/// each register, and of those using keigo.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct RegisterProfile {
+    /// です・ます endings.
+    pub polite: f64,
+    /// だ・である and other plain endings.
+    pub plain: f64,
+    /// Sentence-final particles and colloquial forms such as じゃん or っす.
+    pub casual: f64,
+    /// Sentences with honorific or humble forms such as いらっしゃる or 申し上げる.
+    pub keigo: f64,
+}
+
+fn classify(sentence: &str) -> Option<Register> {
+    let core = sentence.trim_end_matches(|c: char| {
+        c.is_whitespace() || is_punctuation(c) || is_symbol(c) || is_emoji(c) || matches!(c, 'ー' | '〜' | '～' | 'w' | 'ｗ' | '笑')
+    });
+    // English sentences have no Japanese register to speak of
+    if !core.chars().any(|c| matches!(c, '\u{3041}'..='\u{30FF}')) {
+        return None;
+    }
+    let predicate = POLITE_TAILS.iter().find_map(|tail| core.strip_suffix(tail)).unwrap_or(core);
+    if POLITE.iter().any(|ending| core.ends_with(ending) || predicate.ends_with(ending)) {
+        Some(Register::Polite)
+    } else if CASUAL.iter().any(|ending| core.ends_with(ending)) {
+        Some(Register::Casual)
+    } else {
+        Some(Register::Plain)
+    }
+}
+
+/// The register of every Japanese sentence, or `None` for a text without any.
+pub fn profile(text: &str) -> Option<RegisterProfile> {
+    let mut profile = RegisterProfile::default();
+    let mut total = 0.0;
+    for sentence in sentences::split(text) {
+        let Some(register) = classify(sentence) else {
+            continue;
+        };
+        match register {
+            Register::Polite => profile.polite += 1.0,
+            Register::Plain => profile.plain += 1.0,
+            Register::Casual => profile.casual += 1.0,
+        }
+        if KEIGO.iter().any(|marker| sentence.contains(marker)) {
+            profile.keigo += 1.0;
+        }
+        total += 1.0;
+    }
+    if total == 0.0 {
+        return None;
+    }
+    for share in [&mut profile.polite, &mut profile.plain, &mut profile.casual, &mut profile.keigo] {
+        *share /= total;
+    }
+    Some(profile)
+}
+
+/// Jensen-Shannon divergence of the sentence endings, with the difference
+/// in keigo use mixed in.
+pub fn register_difference(profile1: &RegisterProfile, profile2: &RegisterProfile) -> f64 {
+    let endings = |p: &RegisterProfile| [p.polite, p.plain, p.casual];
+    let endings = js_divergence(&endings(profile1), &endings(profile2));
+    (1.0 - KEIGO_SHARE) * endings + KEIGO_SHARE * (profile1.keigo - profile2.keigo).abs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_register_profile() {
+        assert_eq!(classify("明日は晴れるでしょう。"), Some(Register::Polite));
+        assert_eq!(classify("そうですよね！"), Some(Register::Polite));
+        assert_eq!(classify("これは問題である。"), Some(Register::Plain));
+        assert_eq!(classify("雨が降った。"), Some(Register::Plain));
+        assert_eq!(classify("まじ最高じゃん😊"), Some(Register::Casual));
+        assert_eq!(classify("行きたいなー"), Some(Register::Casual));
+        assert_eq!(classify("See you."), None);
+
+        let business = profile("ご報告申し上げます。資料をご覧ください。売上は増加した。").unwrap();
+        assert!((business.polite - 2.0 / 3.0).abs() < 1e-9);
+        assert!((business.plain - 1.0 / 3.0).abs() < 1e-9);
+        assert!((business.keigo - 2.0 / 3.0).abs() < 1e-9);
+        assert!(profile("Hello there.").is_none());
+
+        let casual = profile("今日のライブ最高だった！また行きたいな！").unwrap();
+        assert_eq!(register_difference(&business, &business), 0.0);
+        assert!(register_difference(&business, &casual) > 0.5);
+    }
+}
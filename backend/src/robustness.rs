@@ -0,0 +1,196 @@
+use crate::comparison::{Analysis, Verdict};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+// Common words with a near-synonym that changes the surface but not the meaning
+const SYNONYMS: &[(&str, &str)] = &[
+    ("とても", "すごく"),
+    ("すごく", "とても"),
+    ("本当に", "実に"),
+    ("思います", "考えます"),
+    ("思う", "考える"),
+    ("今日", "本日"),
+    ("少し", "ちょっと"),
+    ("ちょっと", "少し"),
+    ("やはり", "やっぱり"),
+    ("やっぱり", "やはり"),
+    ("たくさん", "多く"),
+    ("楽しかった", "面白かった"),
+    ("しかし", "でも"),
+    ("でも", "しかし"),
+];
+
+// Polite to plain endings, longest first so "ました" isn't caught by "ます"
+const PLAIN_FORMS: &[(&str, &str)] = &[
+    ("でした", "だった"),
+    ("ました", "た"),
+    ("ません", "ない"),
+    ("です", "だ"),
+    ("ます", "る"),
+];
+
+// How many of the most frequent swappable words to replace
+const MAX_SWAPS: usize = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PerturbationResult {
+    pub name: String,
+    pub description: String,
+    // Number of replacements made in the perturbed text
+    pub edits: usize,
+    pub confidence: f64,
+    pub confidence_shift: f64,
+    pub verdict: Verdict,
+    pub verdict_changed: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RobustnessReport {
+    pub perturbations: Vec<PerturbationResult>,
+    pub max_confidence_shift: f64,
+    pub verdict_flips: usize,
+}
+
+fn replace_counting(text: &str, replacements: &[(&str, &str)]) -> (String, usize) {
+    let mut out = String::with_capacity(text.len());
+    let mut edits = 0;
+    let mut rest = text;
+    'scan: while let Some(c) = rest.chars().next() {
+        for (from, to) in replacements {
+            if let Some(after) = rest.strip_prefix(from) {
+                out.push_str(to);
+                rest = after;
+                edits += 1;
+                continue 'scan;
+            }
+        }
+        out.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    (out, edits)
+}
+
+/// Swaps the most frequent words that have a synonym.
+fn swap_frequent_words(text: &str) -> (String, usize) {
+    let mut swappable: Vec<(&str, &str, usize)> = SYNONYMS
+        .iter()
+        .map(|(from, to)| (*from, *to, text.matches(from).count()))
+        .filter(|(_, _, count)| *count > 0)
+        .collect();
+    swappable.sort_by_key(|(_, _, count)| std::cmp::Reverse(*count));
+    let swaps: Vec<(&str, &str)> = swappable.iter().take(MAX_SWAPS).map(|(from, to, _)| (*from, *to)).collect();
+    replace_counting(text, &swaps)
+}
+
+type Perturb = fn(&str) -> (String, usize);
+
+const PERTURBATIONS: &[(&str, &str, Perturb)] = &[
+    ("synonym_swap", "Most frequent words replaced by near-synonyms", swap_frequent_words),
+    ("drop_commas", "All commas removed", |text| replace_counting(text, &[("、", ""), (",", "")])),
+    ("exclamations", "Full stops turned into exclamation marks", |text| {
+        replace_counting(text, &[("。", "！")])
+    }),
+    ("plain_form", "Polite endings shifted to the plain form", |text| {
+        replace_counting(text, PLAIN_FORMS)
+    }),
+];
+
+/// Perturbs `text2` in ways that keep its meaning and re-runs `compare` on each
+/// variant, showing how far cheap edits can move the verdict. Stops at the
+/// first variant `compare` fails on.
+pub fn robustness_report<E>(
+    text1: &str,
+    text2: &str,
+    baseline: &Analysis,
+    compare: impl Fn(&str, &str) -> Result<Analysis, E>,
+) -> Result<RobustnessReport, E> {
+    let perturbations = PERTURBATIONS
+        .iter()
+        .map(|(name, description, perturb)| {
+            let (perturbed, edits) = perturb(text2);
+            let analysis = if edits == 0 {
+                None
+            } else {
+                Some(compare(text1, &perturbed)?)
+            };
+            let (confidence, verdict) = analysis
+                .map(|a| (a.confidence, a.verdict))
+                .unwrap_or((baseline.confidence, baseline.verdict));
+            Ok(PerturbationResult {
+                name: name.to_string(),
+                description: description.to_string(),
+                edits,
+                confidence,
+                confidence_shift: confidence - baseline.confidence,
+                verdict,
+                verdict_changed: verdict != baseline.verdict,
+            })
+        })
+        .collect::<Result<Vec<_>, E>>()?;
+
+    Ok(RobustnessReport {
+        max_confidence_shift: perturbations
+            .iter()
+            .map(|p| p.confidence_shift.abs())
+            .fold(0.0, f64::max),
+        verdict_flips: perturbations.iter().filter(|p| p.verdict_changed).count(),
+        perturbations,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_perturbations() {
+        assert_eq!(
+            replace_counting("楽しかったです。行きました。", PLAIN_FORMS),
+            ("楽しかっただ。行きた。".to_string(), 2)
+        );
+        assert_eq!(
+            swap_frequent_words("とても良い。とても楽しい。"),
+            ("すごく良い。すごく楽しい。".to_string(), 2)
+        );
+        // A swap must not be undone by its reverse entry
+        assert_eq!(swap_frequent_words("すごくとても").0, "とてもすごく");
+    }
+
+    #[test]
+    fn test_robustness_report() {
+        let analysis = |confidence: f64| Analysis {
+            verdict: if confidence > 0.6 { Verdict::SameAuthor } else { Verdict::DifferentAuthor },
+            same_author: confidence > 0.6,
+            confidence,
+            scoring: Default::default(),
+            detailed_analysis: Vec::new(),
+            dimensions: Vec::new(),
+            stability: None,
+            warnings: Vec::new(),
+            debug: None,
+            sentence_pairs: None,
+            timeline: None,
+            vocabulary: None,
+            robustness: None,
+            config_snapshot: None,
+            signature: None,
+            experiment: None,
+            overlap: None,
+            notes: Vec::new(),
+        };
+        let baseline = analysis(0.65);
+
+        // Pretend only comma removal matters to the comparison
+        let report = robustness_report("a", "今日、とても良い。", &baseline, |_, perturbed| {
+            Ok::<_, ()>(analysis(if perturbed.contains('、') { 0.65 } else { 0.5 }))
+        })
+        .unwrap();
+
+        let commas = report.perturbations.iter().find(|p| p.name == "drop_commas").unwrap();
+        assert!(commas.verdict_changed);
+        assert_eq!(report.verdict_flips, 1);
+        assert!((report.max_confidence_shift - 0.15).abs() < 1e-9);
+        let plain = report.perturbations.iter().find(|p| p.name == "plain_form").unwrap();
+        assert_eq!((plain.edits, plain.confidence_shift), (0, 0.0));
+    }
+}
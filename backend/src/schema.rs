@@ -0,0 +1,149 @@
+use crate::features::{TextFeatures, FEATURE_SCHEMA_VERSION};
+use serde_json::{Map, Value};
+use std::fmt;
+
+/// Data without a `feature_schema_version` predates versioning and is read as
+/// this version.
+pub const UNVERSIONED: u32 = 0;
+
+type Migration = fn(&mut Map<String, Value>);
+
+// MIGRATIONS[i] upgrades a features object from version i to i + 1
+const MIGRATIONS: &[Migration] = &[
+    add_symbol_ratio,
+    add_pos_distribution,
+    add_unusual_whitespace_ratio,
+    add_char_ngrams,
+    add_pos_ngrams,
+    add_particle_distribution,
+    add_register,
+    add_script_ratios,
+];
+
+/// Oldest version that can still be migrated to the current one.
+pub const OLDEST_FEATURE_SCHEMA_VERSION: u32 = FEATURE_SCHEMA_VERSION - MIGRATIONS.len() as u32;
+
+fn add_symbol_ratio(features: &mut Map<String, Value>) {
+    // Unversioned features were extracted before symbols were counted
+    features.entry("symbol_ratio".to_string()).or_insert(Value::from(0.0));
+}
+
+fn add_pos_distribution(features: &mut Map<String, Value>) {
+    // Without the tokens it can't be recovered; empty leaves the aspect missing
+    features.entry("pos_distribution".to_string()).or_insert(Value::Object(Map::new()));
+}
+
+fn add_unusual_whitespace_ratio(features: &mut Map<String, Value>) {
+    // Like symbols, read as none, which leaves the aspect out unless the other text has any
+    features.entry("unusual_whitespace_ratio".to_string()).or_insert(Value::from(0.0));
+}
+
+fn add_char_ngrams(features: &mut Map<String, Value>) {
+    features.entry("char_ngrams".to_string()).or_insert(Value::Object(Map::new()));
+}
+
+fn add_pos_ngrams(features: &mut Map<String, Value>) {
+    features.entry("pos_ngrams".to_string()).or_insert(Value::Object(Map::new()));
+}
+
+fn add_particle_distribution(features: &mut Map<String, Value>) {
+    features.entry("particle_distribution".to_string()).or_insert(Value::Object(Map::new()));
+}
+
+fn add_register(features: &mut Map<String, Value>) {
+    features.entry("register".to_string()).or_insert(Value::Null);
+}
+
+fn add_script_ratios(features: &mut Map<String, Value>) {
+    features.entry("script_ratios".to_string()).or_insert(Value::Null);
+}
+
+#[derive(Debug)]
+pub enum SchemaError {
+    /// Written by a newer build than this one.
+    Newer(u32),
+    /// Too old to be migrated.
+    Unsupported(u32),
+    Invalid(String),
+    /// The samples kept for re-extraction couldn't be tokenized.
+    Reextraction(String),
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaError::Newer(version) => write!(
+                f,
+                "feature schema version {} is newer than this build supports ({})",
+                version, FEATURE_SCHEMA_VERSION
+            ),
+            SchemaError::Unsupported(version) => write!(
+                f,
+                "feature schema version {} can no longer be migrated (oldest supported is {})",
+                version, OLDEST_FEATURE_SCHEMA_VERSION
+            ),
+            SchemaError::Invalid(e) => write!(f, "invalid features: {}", e),
+            SchemaError::Reextraction(e) => write!(f, "re-extracting features from the samples: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+/// Reads features stored under `version`, migrating them to the current schema.
+pub fn upgrade_features(value: Value, version: u32) -> Result<TextFeatures, SchemaError> {
+    if version > FEATURE_SCHEMA_VERSION {
+        return Err(SchemaError::Newer(version));
+    }
+    let pending = (FEATURE_SCHEMA_VERSION - version) as usize;
+    if pending > MIGRATIONS.len() {
+        return Err(SchemaError::Unsupported(version));
+    }
+
+    let Value::Object(mut features) = value else {
+        return Err(SchemaError::Invalid("expected an object".to_string()));
+    };
+    for migrate in &MIGRATIONS[MIGRATIONS.len() - pending..] {
+        migrate(&mut features);
+    }
+    serde_json::from_value(Value::Object(features)).map_err(|e| SchemaError::Invalid(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upgrade_features() {
+        let legacy = serde_json::json!({
+            "word_frequencies": { "公園": 0.5 },
+            "particle_ratio": 0.3,
+            "verb_ratio": 0.2,
+            "adjective_ratio": 0.05,
+            "unique_words_ratio": 0.8,
+            "avg_sentence_length": 12.0,
+            "punctuation_ratio": 0.1,
+            "english_segment_ratio": 0.0,
+            "token_count": 20,
+            "sentence_count": 2,
+        });
+
+        let features = upgrade_features(legacy.clone(), UNVERSIONED).unwrap();
+        assert_eq!(features.symbol_ratio, 0.0);
+        assert!(features.pos_distribution.is_empty());
+        assert_eq!(features.unusual_whitespace_ratio, 0.0);
+        assert!(features.char_ngrams.is_empty());
+        assert!(features.pos_ngrams.is_empty());
+        assert!(features.particle_distribution.is_empty());
+        assert!(features.register.is_none());
+        assert!(features.script_ratios.is_none());
+        assert_eq!(features.token_count, 20);
+
+        // The current version gets no migrations, so the missing field is an error
+        assert!(matches!(upgrade_features(legacy.clone(), FEATURE_SCHEMA_VERSION), Err(SchemaError::Invalid(_))));
+        assert!(matches!(
+            upgrade_features(legacy, FEATURE_SCHEMA_VERSION + 1),
+            Err(SchemaError::Newer(_))
+        ));
+    }
+}
@@ -0,0 +1,379 @@
+use crate::calibration::{Calibrated, Calibration};
+use crate::comparison::{calculate_confidence, DetailedResult};
+use crate::config::{Config, ConfigError};
+use crate::datasets::text_files;
+use crate::features::{extract_features, TextFeatures};
+use crate::neural::NeuralVerifier;
+use crate::logistic::LogisticModel;
+use crate::numerics::{clamp, safe_div};
+use crate::tokenizer::Tokenizer;
+use nalgebra::{DMatrix, DVector};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Backend {
+    /// Weighted sum of independent per-aspect differences.
+    #[default]
+    Weighted,
+    /// Combined distance that accounts for correlated style features.
+    Mahalanobis,
+    /// Learned verifier reading both texts directly.
+    Neural,
+    /// Trained logistic model over every feature's difference.
+    Logistic,
+    /// Character n-grams, sentence endings and emoji; picked by text length
+    /// rather than configured or requested.
+    ShortText,
+}
+
+impl Backend {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Backend::Weighted => "weighted",
+            Backend::Mahalanobis => "mahalanobis",
+            Backend::Neural => "neural",
+            Backend::Logistic => "logistic",
+            Backend::ShortText => "short_text",
+        }
+    }
+}
+
+/// Style features entering the combined distance, in vector order.
+pub const MAHALANOBIS_FEATURES: &[&str] = &[
+    "particle_ratio",
+    "verb_ratio",
+    "adjective_ratio",
+    "punctuation_ratio",
+    "unique_words_ratio",
+    "log_avg_sentence_length",
+    "english_segment_ratio",
+];
+
+// Keeps features that never vary in the reference corpus from dominating the distance
+const VARIANCE_FLOOR: f64 = 1e-4;
+
+/// The features of `MAHALANOBIS_FEATURES`, in that order.
+pub fn feature_vector(features: &TextFeatures) -> DVector<f64> {
+    DVector::from_vec(vec![
+        features.particle_ratio,
+        features.verb_ratio,
+        features.adjective_ratio,
+        features.punctuation_ratio,
+        features.unique_words_ratio,
+        features.avg_sentence_length.max(0.0).ln_1p(),
+        features.english_segment_ratio,
+    ])
+}
+
+/// Inverse covariance of the style features, estimated from a reference corpus.
+#[derive(Debug, Clone)]
+pub struct ReferenceModel {
+    mean: DVector<f64>,
+    std_devs: DVector<f64>,
+    inverse_covariance: DMatrix<f64>,
+    sample_count: usize,
+}
+
+impl ReferenceModel {
+    /// Needs at least two samples to estimate any spread.
+    pub fn fit(samples: &[TextFeatures]) -> Option<ReferenceModel> {
+        if samples.len() < 2 {
+            return None;
+        }
+        let dims = MAHALANOBIS_FEATURES.len();
+        let vectors: Vec<DVector<f64>> = samples.iter().map(feature_vector).collect();
+        let n = vectors.len() as f64;
+
+        let mut mean = vec![0.0; dims];
+        for v in &vectors {
+            for (i, m) in mean.iter_mut().enumerate() {
+                *m += v[i] / n;
+            }
+        }
+
+        let mut covariance = DMatrix::<f64>::zeros(dims, dims);
+        for v in &vectors {
+            for i in 0..dims {
+                for j in 0..dims {
+                    covariance[(i, j)] += (v[i] - mean[i]) * (v[j] - mean[j]) / (n - 1.0);
+                }
+            }
+        }
+        for i in 0..dims {
+            covariance[(i, i)] += VARIANCE_FLOOR;
+        }
+
+        let std_devs = DVector::from_iterator(dims, (0..dims).map(|i| covariance[(i, i)].sqrt()));
+        Some(ReferenceModel {
+            mean: DVector::from_vec(mean),
+            std_devs,
+            inverse_covariance: covariance.try_inverse()?,
+            sample_count: samples.len(),
+        })
+    }
+
+    /// Fits a model on every `.txt` file in `dir`, or in its subfolders for a
+    /// corpus laid out by author, one sample per file.
+    pub fn load(dir: &Path, tokenizer: &Tokenizer) -> Result<ReferenceModel, ConfigError> {
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(dir).map_err(ConfigError::Io)? {
+            let path = entry.map_err(ConfigError::Io)?.path();
+            if path.is_dir() {
+                files.extend(text_files(&path).map_err(ConfigError::Io)?);
+            } else if path.extension().is_some_and(|ext| ext == "txt") {
+                files.push(path);
+            }
+        }
+        let mut samples = Vec::new();
+        for path in files {
+            let text = std::fs::read_to_string(&path).map_err(ConfigError::Io)?;
+            let features = extract_features(&text, tokenizer)
+                .map_err(|e| ConfigError::Reference(format!("{}: {}", path.display(), e)))?;
+            samples.push(features);
+        }
+
+        ReferenceModel::fit(&samples).ok_or_else(|| {
+            ConfigError::Reference(format!(
+                "could not estimate a covariance from {} samples in {}",
+                samples.len(),
+                dir.display()
+            ))
+        })
+    }
+
+    pub fn sample_count(&self) -> usize {
+        self.sample_count
+    }
+
+    /// Mean feature vector of the samples.
+    pub fn mean(&self) -> &DVector<f64> {
+        &self.mean
+    }
+
+    /// Standard deviation of each feature across the samples.
+    pub fn std_devs(&self) -> &DVector<f64> {
+        &self.std_devs
+    }
+
+    pub fn squared_distance(&self, features1: &TextFeatures, features2: &TextFeatures) -> f64 {
+        self.squared_distance_between(&feature_vector(features1), &feature_vector(features2))
+    }
+
+    pub fn squared_distance_between(&self, vector1: &DVector<f64>, vector2: &DVector<f64>) -> f64 {
+        let diff = vector1 - vector2;
+        let dims = diff.len();
+        let mut sum = 0.0;
+        for i in 0..dims {
+            for j in 0..dims {
+                sum += diff[i] * self.inverse_covariance[(i, j)] * diff[j];
+            }
+        }
+        sum.max(0.0)
+    }
+
+    /// Maps the distance onto [0, 1]. Two independent corpus texts are expected
+    /// to be 2k apart in squared distance (k features), which lands on 0.5.
+    pub fn confidence(&self, features1: &TextFeatures, features2: &TextFeatures) -> f64 {
+        let expected = 2.0 * MAHALANOBIS_FEATURES.len() as f64;
+        let ratio = safe_div(self.squared_distance(features1, features2), expected, f64::INFINITY);
+        clamp(1.0 / (1.0 + ratio), 0.0, 1.0)
+    }
+}
+
+/// What a scoring backend sees of a comparison. The texts are missing when
+/// only feature vectors were supplied.
+pub struct ScoringInput<'a> {
+    pub features1: &'a TextFeatures,
+    pub features2: &'a TextFeatures,
+    pub details: &'a [DetailedResult],
+    pub texts: Option<(&'a str, &'a str)>,
+}
+
+/// Turns a comparison into the overall same-author confidence.
+pub trait Scorer: fmt::Debug + Send + Sync {
+    fn backend(&self) -> Backend;
+
+    /// Confidence in [0, 1], or why this backend can't score the input.
+    fn score(&self, input: &ScoringInput) -> Result<f64, String>;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WeightedScorer;
+
+impl Scorer for WeightedScorer {
+    fn backend(&self) -> Backend {
+        Backend::Weighted
+    }
+
+    fn score(&self, input: &ScoringInput) -> Result<f64, String> {
+        Ok(calculate_confidence(input.details))
+    }
+}
+
+impl Scorer for ReferenceModel {
+    fn backend(&self) -> Backend {
+        Backend::Mahalanobis
+    }
+
+    fn score(&self, input: &ScoringInput) -> Result<f64, String> {
+        Ok(self.confidence(input.features1, input.features2))
+    }
+}
+
+/// Every backend the configuration makes available, one of them the default.
+pub struct Scorers {
+    default: Backend,
+    scorers: Vec<Box<dyn Scorer>>,
+    reference: Option<ReferenceModel>,
+}
+
+impl Default for Scorers {
+    fn default() -> Self {
+        Scorers {
+            default: Backend::Weighted,
+            scorers: vec![Box::new(WeightedScorer)],
+            reference: None,
+        }
+    }
+}
+
+impl Scorers {
+    /// Loads the reference model, neural verifier and logistic model when they are configured,
+    /// wraps the calibrated backend, and fails if the default backend isn't among them.
+    pub fn from_config(config: &Config, tokenizer: &Tokenizer) -> Result<Scorers, ConfigError> {
+        let mut scorers = Scorers {
+            default: config.scoring.backend,
+            ..Scorers::default()
+        };
+        if let Some(dir) = &config.scoring.reference_corpus {
+            let reference = ReferenceModel::load(dir, tokenizer)?;
+            scorers.scorers.push(Box::new(reference.clone()));
+            scorers.reference = Some(reference);
+        }
+        if let Some(model) = &config.scoring.neural_model {
+            scorers.scorers.push(Box::new(NeuralVerifier::load(model)?));
+        }
+        if let Some(model) = &config.scoring.logistic_model {
+            scorers.scorers.push(Box::new(LogisticModel::load(model)?));
+        }
+        if let Some(path) = &config.scoring.calibration {
+            let calibration = Calibration::load(path)?;
+            let Some(index) = scorers.scorers.iter().position(|s| s.backend() == calibration.backend) else {
+                return Err(ConfigError::Scoring(format!(
+                    "{} calibrates the {} backend, which isn't configured",
+                    path.display(),
+                    calibration.backend.name()
+                )));
+            };
+            let scorer = scorers.scorers.remove(index);
+            scorers.scorers.insert(index, Box::new(Calibrated { scorer, calibration }));
+        }
+
+        if scorers.get(Some(scorers.default)).is_none() {
+            let needs = match scorers.default {
+                Backend::Mahalanobis => "scoring.reference_corpus",
+                Backend::Neural => "scoring.neural_model",
+                Backend::Logistic => "scoring.logistic_model",
+                Backend::ShortText => "texts shorter than short_text.max_chars",
+                Backend::Weighted => unreachable!("the weighted backend is always available"),
+            };
+            return Err(ConfigError::Scoring(format!(
+                "the {} backend needs {}",
+                scorers.default.name(),
+                needs
+            )));
+        }
+        Ok(scorers)
+    }
+
+    /// The requested backend, or the default one when none is requested.
+    pub fn get(&self, backend: Option<Backend>) -> Option<&dyn Scorer> {
+        let backend = backend.unwrap_or(self.default);
+        self.scorers.iter().find(|s| s.backend() == backend).map(|s| s.as_ref())
+    }
+
+    pub fn default_scorer(&self) -> &dyn Scorer {
+        self.get(None).unwrap_or(&WeightedScorer)
+    }
+
+    /// The model fitted on `scoring.reference_corpus`, when one is configured.
+    pub fn reference_model(&self) -> Option<&ReferenceModel> {
+        self.reference.as_ref()
+    }
+
+    pub fn available(&self) -> Vec<Backend> {
+        self.scorers.iter().map(|s| s.backend()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::features::PosCounts;
+    use std::collections::HashMap;
+
+    fn features(particle_ratio: f64, verb_ratio: f64) -> TextFeatures {
+        TextFeatures {
+            word_frequencies: HashMap::new(),
+            particle_ratio,
+            verb_ratio,
+            adjective_ratio: 0.1,
+            unique_words_ratio: 0.8,
+            avg_sentence_length: 10.0,
+            punctuation_ratio: 0.1,
+            english_segment_ratio: 0.0,
+            symbol_ratio: 0.0,
+            unusual_whitespace_ratio: 0.0,
+            token_count: 20,
+            sentence_count: 2,
+            pos_counts: PosCounts::default(),
+            pos_distribution: Default::default(),
+            char_ngrams: Default::default(),
+            pos_ngrams: Default::default(),
+            particle_distribution: Default::default(),
+            register: None,
+            script_ratios: None,
+        }
+    }
+
+    #[test]
+    fn test_correlated_features() {
+        // Particle and verb ratios move together in this corpus
+        let corpus: Vec<TextFeatures> = (0..20)
+            .map(|i| {
+                let x = 0.2 + i as f64 * 0.01;
+                features(x, x - 0.05)
+            })
+            .collect();
+        let model = ReferenceModel::fit(&corpus).unwrap();
+
+        let base = features(0.25, 0.20);
+        let along = features(0.30, 0.25);
+        let against = features(0.30, 0.15);
+
+        assert_eq!(model.squared_distance(&base, &base), 0.0);
+        assert_eq!(model.confidence(&base, &base), 1.0);
+        // A shift that breaks the correlation is more unusual than one following it
+        assert!(model.squared_distance(&base, &against) > model.squared_distance(&base, &along));
+        assert!(model.confidence(&base, &against) < model.confidence(&base, &along));
+
+        assert!(ReferenceModel::fit(&corpus[..1]).is_none());
+    }
+
+    #[test]
+    fn test_scorers() {
+        let scorers = Scorers::default();
+        assert_eq!(scorers.available(), vec![Backend::Weighted]);
+        assert_eq!(scorers.default_scorer().backend(), Backend::Weighted);
+        assert!(scorers.get(Some(Backend::Mahalanobis)).is_none());
+
+        let mut config = Config::default();
+        config.scoring.backend = Backend::Neural;
+        let tokenizer = crate::tokenizer::new_tokenizer().unwrap();
+        assert!(matches!(Scorers::from_config(&config, &tokenizer), Err(ConfigError::Scoring(_))));
+    }
+}
@@ -0,0 +1,115 @@
+use crate::numerics::magnitude_ratio;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+pub fn is_kanji(c: char) -> bool {
+    matches!(c, '\u{4E00}'..='\u{9FFF}' | '\u{3400}'..='\u{4DBF}' | '々')
+}
+
+fn is_hiragana(c: char) -> bool {
+    matches!(c, '\u{3041}'..='\u{309F}')
+}
+
+// The prolonged sound mark ー counts as katakana, where it is mostly written
+fn is_katakana(c: char) -> bool {
+    matches!(c, '\u{30A0}'..='\u{30FF}' | '\u{31F0}'..='\u{31FF}' | '\u{FF66}'..='\u{FF9F}')
+}
+
+// Full-width letters and digits count like ASCII ones; the width is the
+// Punctuation and n-gram aspects' business
+fn is_latin(c: char) -> bool {
+    c.is_ascii_alphabetic() || matches!(c, 'Ａ'..='Ｚ' | 'ａ'..='ｚ' | '\u{00C0}'..='\u{024F}')
+}
+
+fn is_digit(c: char) -> bool {
+    c.is_ascii_digit() || matches!(c, '０'..='９')
+}
+
+/// Share of each script among a text's letters and digits. Authors differ in
+/// how much they write in kanji rather than kana (分かる or わかる, 下さい or
+/// ください), and a few sentences are enough to tell.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ScriptRatios {
+    pub kanji: f64,
+    pub hiragana: f64,
+    pub katakana: f64,
+    pub latin: f64,
+    pub digit: f64,
+}
+
+impl ScriptRatios {
+    fn shares(&self) -> [f64; 5] {
+        [self.kanji, self.hiragana, self.katakana, self.latin, self.digit]
+    }
+}
+
+/// The script composition of `text`, or `None` when it has no letters or
+/// digits in any of the scripts.
+pub fn script_ratios(text: &str) -> Option<ScriptRatios> {
+    let mut counts = [0usize; 5];
+    for c in text.chars() {
+        let script = if is_kanji(c) {
+            0
+        } else if is_hiragana(c) {
+            1
+        } else if is_katakana(c) {
+            2
+        } else if is_latin(c) {
+            3
+        } else if is_digit(c) {
+            4
+        } else {
+            continue;
+        };
+        counts[script] += 1;
+    }
+    let total = counts.iter().sum::<usize>() as f64;
+    if total == 0.0 {
+        return None;
+    }
+    let [kanji, hiragana, katakana, latin, digit] = counts.map(|count| count as f64 / total);
+    Some(ScriptRatios {
+        kanji,
+        hiragana,
+        katakana,
+        latin,
+        digit,
+    })
+}
+
+/// How far apart each script's share is relative to the larger one,
+/// averaged by how much of both texts the script makes up: 0 for the same
+/// composition, 1 for texts sharing no script.
+pub fn script_difference(ratios1: &ScriptRatios, ratios2: &ScriptRatios) -> f64 {
+    ratios1
+        .shares()
+        .iter()
+        .zip(ratios2.shares())
+        .map(|(a, b)| (a + b) / 2.0 * (1.0 - magnitude_ratio(*a, b)))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script_ratios() {
+        let ratios = script_ratios("漢字とカ、ABC123").unwrap();
+        assert_eq!(ratios.kanji, 0.2);
+        assert_eq!(ratios.hiragana, 0.1);
+        assert_eq!(ratios.katakana, 0.1);
+        assert_eq!(ratios.latin, 0.3);
+        assert_eq!(ratios.digit, 0.3);
+        assert!(script_ratios("。、！？ 😊").is_none());
+        // Full-width letters are Latin all the same
+        assert_eq!(script_ratios("ＡＢＣ").unwrap().latin, 1.0);
+
+        let kanji = script_ratios("私は分かりました。下さい。").unwrap();
+        let kana = script_ratios("わたしはわかりました。ください。").unwrap();
+        assert_eq!(script_difference(&kanji, &kanji), 0.0);
+        let difference = script_difference(&kanji, &kana);
+        assert!(difference > 0.3 && difference < 1.0, "{}", difference);
+        assert_eq!(script_difference(&script_ratios("abc").unwrap(), &kana), 1.0);
+    }
+}
@@ -0,0 +1,79 @@
+const TERMINATORS: [char; 7] = ['。', '｡', '．', '！', '？', '!', '?'];
+const OPENING_BRACKETS: [char; 7] = ['「', '『', '（', '(', '【', '〔', '“'];
+const CLOSING_BRACKETS: [char; 8] = ['」', '』', '）', ')', '】', '〕', '”', '’'];
+
+/// Splits text into sentences the way Japanese is written: after 。！？ (and
+/// their ASCII forms), taking along any repeated marks and closing brackets
+/// (`…よ！？」`), never inside 「」 or （） quotes, and always at a line break.
+/// Sentences keep their terminators, so concatenating them yields the input.
+pub fn split(text: &str) -> Vec<&str> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut depth = 0usize;
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        let boundary = match c {
+            // An unclosed quote shouldn't swallow the rest of the text
+            '\n' => {
+                depth = 0;
+                true
+            }
+            c if OPENING_BRACKETS.contains(&c) => {
+                depth += 1;
+                false
+            }
+            c if CLOSING_BRACKETS.contains(&c) => {
+                depth = depth.saturating_sub(1);
+                false
+            }
+            _ if depth > 0 => false,
+            c if TERMINATORS.contains(&c) => true,
+            // Only treat '.' as a terminator before whitespace, so "3.14" and "e.g" stay intact
+            '.' => chars.peek().is_none_or(|(_, next)| next.is_whitespace()),
+            _ => false,
+        };
+        if !boundary {
+            continue;
+        }
+
+        let mut end = i + c.len_utf8();
+        if c != '\n' {
+            while let Some(&(j, next)) = chars.peek() {
+                if !TERMINATORS.contains(&next) && !CLOSING_BRACKETS.contains(&next) && next != '.' {
+                    break;
+                }
+                end = j + next.len_utf8();
+                chars.next();
+            }
+        }
+        sentences.push(&text[start..end]);
+        start = end;
+    }
+    if start < text.len() {
+        sentences.push(&text[start..]);
+    }
+    sentences
+}
+
+/// Number of sentences with any words in them, so blank lines and stray marks don't count.
+pub fn count(text: &str) -> usize {
+    split(text).into_iter().filter(|s| s.chars().any(char::is_alphanumeric)).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split() {
+        let text = "雨が降った。「傘は？どこ？」と聞いた。本当に！？\nはい";
+        let sentences = split(text);
+
+        assert_eq!(sentences, vec!["雨が降った。", "「傘は？どこ？」と聞いた。", "本当に！？", "\n", "はい"]);
+        assert_eq!(sentences.concat(), text);
+        assert_eq!(count(text), 4);
+        assert_eq!(split("行くよ。」次へ"), vec!["行くよ。」", "次へ"]);
+        assert_eq!(split("It is 3.14 wide. Yes"), vec!["It is 3.14 wide.", " Yes"]);
+    }
+}
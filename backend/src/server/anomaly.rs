@@ -0,0 +1,80 @@
+use super::reload::Reloadable;
+use super::{default_tokenizer, request_pipeline};
+use crate::anomaly::{anomaly, AnomalyReport};
+use crate::comparison::has_enough_evidence;
+use crate::config::Config;
+use crate::features::{extract_features_filtered, TextFeatures};
+use crate::profile::AuthorProfile;
+use crate::profile_store::{ProfileStore, ProfileStoreError};
+use crate::scoring::{ReferenceModel, Scorers};
+use crate::tokenizer::TokenizerPool;
+use actix_web::{error, post, web, Result};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AnomalyQuery {
+    pub text: String,
+    /// Scores against this author instead of the reference corpus.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<AuthorProfile>,
+    /// Uses this author's profile from the profile store instead of `profile`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preprocessing: Option<Vec<String>>,
+}
+
+/// How unusual a single text is, for flagging submissions no suspect is
+/// known for.
+#[post("/anomaly")]
+pub async fn anomaly_score(
+    body: web::Json<AnomalyQuery>,
+    config: web::Data<Reloadable<Config>>,
+    scorers: web::Data<Reloadable<Scorers>>,
+    tokenizers: web::Data<TokenizerPool>,
+) -> Result<web::Json<AnomalyReport>> {
+    let (config, scorers) = (config.current(), scorers.current());
+    let pipeline = request_pipeline(body.preprocessing.as_deref(), &config)?;
+    let tokenizer = default_tokenizer(&tokenizers)?;
+    let filters = config.token_filters().unwrap_or_default();
+    let extract = |text: &str| {
+        extract_features_filtered(&pipeline.apply(text), tokenizer, &filters).map_err(error::ErrorInternalServerError)
+    };
+
+    let features = extract(&body.text)?;
+    if !has_enough_evidence(&features, &config.evidence) {
+        return Err(error::ErrorBadRequest("the text is too short to score"));
+    }
+
+    let stored;
+    let profile = match (&body.profile, &body.author) {
+        (None, None) => None,
+        (Some(profile), None) => Some(profile),
+        (None, Some(author)) => {
+            stored = ProfileStore::new(&config.profiles.directory).load(author).map_err(|e| match e {
+                ProfileStoreError::NotFound(_) | ProfileStoreError::InvalidAuthor(_) => error::ErrorNotFound(e),
+                _ => error::ErrorInternalServerError(e),
+            })?;
+            Some(&stored)
+        }
+        _ => return Err(error::ErrorBadRequest("send either profile or author")),
+    };
+
+    let report = match profile {
+        // A profile that kept its samples has a spread of its own
+        Some(profile) if profile.samples.len() >= 2 => {
+            let samples = profile.samples.iter().map(|text| extract(text)).collect::<Result<Vec<TextFeatures>>>()?;
+            let model = ReferenceModel::fit(&samples)
+                .ok_or_else(|| error::ErrorBadRequest("could not estimate a spread from the profile's samples"))?;
+            anomaly(&features, &model, None)
+        }
+        // Otherwise the corpus says how far texts usually stray, around the profile
+        profile => {
+            let model = scorers.reference_model().ok_or_else(|| {
+                error::ErrorBadRequest("needs scoring.reference_corpus, or a profile that kept at least two samples")
+            })?;
+            anomaly(&features, model, profile.map(|p| &p.features))
+        }
+    };
+    Ok(web::Json(report))
+}
@@ -0,0 +1,47 @@
+use super::default_tokenizer;
+use super::reload::Reloadable;
+use crate::anonymize::{suggest, AnonymizationReport};
+use crate::config::{Config, ProfilesConfig};
+use crate::profile::AuthorProfile;
+use crate::profile_store::{ProfileStore, ProfileStoreError};
+use crate::tokenizer::TokenizerPool;
+use actix_web::{error, post, web, Result};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AnonymizeQuery {
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<AuthorProfile>,
+    /// Uses this author's profile from the profile store instead of `profile`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+}
+
+#[post("/anonymize")]
+pub async fn anonymize(
+    body: web::Json<AnonymizeQuery>,
+    config: Option<web::Data<Reloadable<Config>>>,
+    tokenizers: web::Data<TokenizerPool>,
+) -> Result<web::Json<AnonymizationReport>> {
+    let tokenizer = default_tokenizer(&tokenizers)?;
+
+    let stored;
+    let profile = match (&body.profile, &body.author) {
+        (Some(profile), None) => profile,
+        (None, Some(author)) => {
+            let directory = config.map_or_else(
+                || ProfilesConfig::default().directory,
+                |c| c.current().profiles.directory.clone(),
+            );
+            stored = ProfileStore::new(directory).load(author).map_err(|e| match e {
+                ProfileStoreError::NotFound(_) | ProfileStoreError::InvalidAuthor(_) => error::ErrorNotFound(e),
+                _ => error::ErrorInternalServerError(e),
+            })?;
+            &stored
+        }
+        _ => return Err(error::ErrorBadRequest("send either profile or author")),
+    };
+    let report = suggest(&body.text, profile, tokenizer).map_err(error::ErrorInternalServerError)?;
+    Ok(web::Json(report))
+}
@@ -0,0 +1,31 @@
+use actix_web::{web, HttpResponse};
+use rust_embed::RustEmbed;
+
+#[derive(RustEmbed)]
+#[folder = "../frontend/dist/"]
+struct Assets;
+
+async fn serve_asset(path: web::Path<String>) -> HttpResponse {
+    let path = match path.as_str() {
+        "" => "index.html",
+        path => path,
+    };
+
+    // Unknown paths fall back to the app shell so client-side routes still load
+    let (path, file) = match Assets::get(path) {
+        Some(file) => (path, file),
+        None => match Assets::get("index.html") {
+            Some(file) => ("index.html", file),
+            None => return HttpResponse::NotFound().finish(),
+        },
+    };
+
+    HttpResponse::Ok()
+        .content_type(mime_guess::from_path(path).first_or_octet_stream().as_ref())
+        .body(file.data.into_owned())
+}
+
+// Must be registered after the API services, since it matches every GET path
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.route("/{path:.*}", web::get().to(serve_asset));
+}
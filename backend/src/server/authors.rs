@@ -0,0 +1,193 @@
+use super::reload::Reloadable;
+use super::webhooks::Webhooks;
+use super::{default_tokenizer, request_pipeline};
+use crate::attribution::{rejection_threshold, AttributionMode, CandidateScore, RankMargin};
+use crate::comparison::{compare_extracted, has_enough_evidence};
+use crate::config::Config;
+use crate::features::extract_features;
+use crate::profile::{build_profile, AuthorProfile};
+use crate::profile_store::{ProfileStore, ProfileStoreError};
+use crate::scoring::{Backend, Scorers};
+use crate::tokenizer::TokenizerPool;
+use actix_web::{error, get, post, web, Error, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RegisterQuery {
+    pub author: String,
+    /// Known writings of the author, one sample each.
+    pub texts: Vec<String>,
+    /// Keeps the texts in the profile, so it can be re-extracted later.
+    #[serde(default)]
+    pub keep_samples: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preprocessing: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct RegisteredAuthor {
+    pub author: String,
+    pub sample_count: usize,
+    /// Average tokens per sample.
+    pub token_count: usize,
+}
+
+impl RegisteredAuthor {
+    pub(super) fn new(author: &str, profile: &AuthorProfile) -> RegisteredAuthor {
+        RegisteredAuthor {
+            author: author.to_string(),
+            sample_count: profile.sample_count,
+            token_count: profile.features.token_count,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct RegisteredAuthors {
+    pub authors: Vec<RegisteredAuthor>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct IdentifyQuery {
+    pub text: String,
+    /// Only score against these registered authors; all of them when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub authors: Option<Vec<String>>,
+    /// `closed` always names the top candidate; `open`, the default, only
+    /// when it reaches the rejection threshold.
+    #[serde(default)]
+    pub mode: AttributionMode,
+    /// Overrides `attribution.reject_below` for this request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reject_below: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preprocessing: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct Identification {
+    pub scoring: Backend,
+    /// Most likely author first.
+    pub candidates: Vec<CandidateScore>,
+    pub mode: AttributionMode,
+    /// Confidence the top candidate needed, in an open set.
+    pub reject_below: Option<f64>,
+    /// The top candidate, unless none of the candidates reaches
+    /// `reject_below` in an open set.
+    pub best_match: Option<String>,
+    /// How far the top candidate leads the runner-up; unset with a single
+    /// candidate.
+    pub rank_margin: Option<RankMargin>,
+}
+
+pub(super) fn store_error(e: ProfileStoreError) -> Error {
+    match e {
+        ProfileStoreError::NotFound(_) => error::ErrorNotFound(e),
+        ProfileStoreError::InvalidAuthor(_) => error::ErrorBadRequest(e),
+        _ => error::ErrorInternalServerError(e),
+    }
+}
+
+/// Builds a profile from an author's known writings and stores it,
+/// replacing any earlier one under the same name.
+#[post("/authors")]
+pub async fn register_author(
+    body: web::Json<RegisterQuery>,
+    config: web::Data<Reloadable<Config>>,
+    tokenizers: web::Data<TokenizerPool>,
+) -> Result<web::Json<RegisteredAuthor>> {
+    let config = config.current();
+    if body.texts.is_empty() {
+        return Err(error::ErrorBadRequest("send at least one text"));
+    }
+    let pipeline = request_pipeline(body.preprocessing.as_deref(), &config)?;
+    let tokenizer = default_tokenizer(&tokenizers)?;
+    let texts: Vec<String> = body.texts.iter().map(|text| pipeline.apply(text)).collect();
+    // Tokenizing every sample and writing the profile both block
+    let keep_samples = body.keep_samples;
+    let profile = web::block(move || {
+        build_profile(&texts, tokenizer).map(|profile| if keep_samples { profile.with_samples(&texts) } else { profile })
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+    let (store, author) = (ProfileStore::new(&config.profiles.directory), body.author.clone());
+    let profile = web::block(move || store.save(&author, &profile).map(|_| profile)).await?.map_err(store_error)?;
+    Ok(web::Json(RegisteredAuthor::new(&body.author, &profile)))
+}
+
+#[get("/authors")]
+pub async fn list_authors(config: web::Data<Reloadable<Config>>) -> Result<web::Json<RegisteredAuthors>> {
+    let store = ProfileStore::new(&config.current().profiles.directory);
+    // Every profile is read from disk
+    let authors = web::block(move || {
+        store
+            .authors()?
+            .iter()
+            .map(|author| store.load(author).map(|profile| RegisteredAuthor::new(author, &profile)))
+            .collect::<Result<Vec<_>, _>>()
+    })
+    .await?
+    .map_err(store_error)?;
+    Ok(web::Json(RegisteredAuthors { authors }))
+}
+
+/// Scores an unknown text against every registered author's profile. A
+/// best match confident enough is sent to the webhooks as a `profile.match`.
+#[post("/identify")]
+pub async fn identify(
+    body: web::Json<IdentifyQuery>,
+    config: web::Data<Reloadable<Config>>,
+    scorers: web::Data<Reloadable<Scorers>>,
+    tokenizers: web::Data<TokenizerPool>,
+    webhooks: Option<web::Data<Webhooks>>,
+) -> Result<web::Json<Identification>> {
+    let (config, scorers) = (config.current(), scorers.current());
+    let pipeline = request_pipeline(body.preprocessing.as_deref(), &config)?;
+    let tokenizer = default_tokenizer(&tokenizers)?;
+    let text = pipeline.apply(&body.text);
+    let features =
+        web::block(move || extract_features(&text, tokenizer)).await?.map_err(error::ErrorInternalServerError)?;
+    if !has_enough_evidence(&features, &config.evidence) {
+        return Err(error::ErrorBadRequest("the text is too short to identify"));
+    }
+
+    let (store, requested) = (ProfileStore::new(&config.profiles.directory), body.authors.clone());
+    let profiles = web::block(move || {
+        let authors = match requested {
+            Some(authors) => authors,
+            None => store.authors()?,
+        };
+        authors.into_iter().map(|author| store.load(&author).map(|profile| (author, profile))).collect::<Result<Vec<_>, _>>()
+    })
+    .await?
+    .map_err(store_error)?;
+    if profiles.is_empty() {
+        return Err(error::ErrorBadRequest("no authors are registered"));
+    }
+    let scorer = scorers.default_scorer();
+    let mut candidates = Vec::new();
+    for (author, profile) in profiles {
+        let analysis = compare_extracted(&features, &profile.features, &config, scorer);
+        candidates.push(CandidateScore {
+            author,
+            confidence: analysis.confidence,
+            verdict: analysis.verdict,
+        });
+    }
+    candidates.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    let reject_below = body.reject_below.unwrap_or_else(|| rejection_threshold(&config));
+    let best_match = body.mode.best_match(&candidates, reject_below).map(|c| c.author.clone());
+    let confidences: Vec<f64> = candidates.iter().map(|c| c.confidence).collect();
+    if let (Some(webhooks), Some(author)) = (&webhooks, &best_match) {
+        webhooks.profile_match(author, confidences[0], scorer.backend());
+    }
+    Ok(web::Json(Identification {
+        scoring: scorer.backend(),
+        rank_margin: RankMargin::of(&confidences, &config.attribution),
+        candidates,
+        mode: body.mode,
+        reject_below: (body.mode == AttributionMode::Open).then_some(reject_below),
+        best_match,
+    }))
+}
@@ -0,0 +1,209 @@
+use super::reload::Reloadable;
+use super::{request_pipeline, request_tokenizer};
+use super::snapshots::Snapshots;
+use super::telemetry::Telemetry;
+use crate::baseline::Baseline;
+use crate::comparison::{compare_with_options, Analysis, CompareOptions};
+use crate::config::Config;
+use crate::embedding::Embedder;
+use crate::scoring::{Backend, Scorers};
+use crate::tokenizer::{LinderaResult, TokenizerPool};
+use actix_web::{error, post, web, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Hash, Deserialize, Serialize)]
+pub struct BatchPair {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub text1: String,
+    pub text2: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BatchText {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub text: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BatchQuery {
+    #[serde(default)]
+    pub pairs: Vec<BatchPair>,
+    /// Compares every two of these instead of `pairs`, and adds the
+    /// confidence matrix to the response.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub texts: Vec<BatchText>,
+    /// Stop starting new comparisons once this much time has passed; the rest
+    /// are left for a follow-up request carrying `continuation`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_duration_ms: Option<u64>,
+    /// Token from a previous partial response for the same pairs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub continuation: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preprocessing: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dictionary: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scoring: Option<Backend>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct BatchResult {
+    /// Position of the pair in the submitted batch.
+    pub index: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// Positions of the two compared texts, for requests sending `texts`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub texts: Option<[usize; 2]>,
+    pub analysis: Analysis,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct BatchResponse {
+    pub results: Vec<BatchResult>,
+    /// Present when pairs remain; resend the same batch with it to resume.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub continuation: Option<String>,
+    pub remaining: usize,
+    /// Same-author confidence of every two `texts`, 1 on the diagonal; pairs
+    /// not compared in this response are null.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matrix: Option<Vec<Vec<Option<f64>>>>,
+}
+
+// Every two texts, in row order, with the positions they came from
+fn all_pairs(texts: &[BatchText]) -> (Vec<BatchPair>, Vec<[usize; 2]>) {
+    let mut pairs = Vec::new();
+    let mut positions = Vec::new();
+    for (i, first) in texts.iter().enumerate() {
+        for (j, second) in texts.iter().enumerate().skip(i + 1) {
+            let id = first.id.as_ref().zip(second.id.as_ref()).map(|(a, b)| format!("{}|{}", a, b));
+            pairs.push(BatchPair {
+                id,
+                text1: first.text.clone(),
+                text2: second.text.clone(),
+            });
+            positions.push([i, j]);
+        }
+    }
+    (pairs, positions)
+}
+
+// The server keeps no state, so the token names the resume point and the
+// batch it belongs to
+fn batch_hash(pairs: &[BatchPair]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    pairs.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn continuation_token(next: usize, pairs: &[BatchPair]) -> String {
+    format!("{}.{:016x}", next, batch_hash(pairs))
+}
+
+fn resume_index(token: &str, pairs: &[BatchPair]) -> Option<usize> {
+    let (next, hash) = token.split_once('.')?;
+    let next: usize = next.parse().ok()?;
+    (u64::from_str_radix(hash, 16).ok()? == batch_hash(pairs) && next <= pairs.len()).then_some(next)
+}
+
+#[post("/compare/batch")]
+// One extractor per optional piece of shared state
+#[allow(clippy::too_many_arguments)]
+pub async fn compare_batch(
+    body: web::Json<BatchQuery>,
+    config: web::Data<Reloadable<Config>>,
+    scorers: web::Data<Reloadable<Scorers>>,
+    tokenizers: web::Data<TokenizerPool>,
+    embedder: Option<web::Data<Embedder>>,
+    baseline: Option<web::Data<Reloadable<Baseline>>>,
+    snapshots: Option<web::Data<Snapshots>>,
+    telemetry: Option<web::Data<Telemetry>>,
+) -> Result<web::Json<BatchResponse>> {
+    let started = Instant::now();
+    let body = body.into_inner();
+    let (config, scorers) = (config.current(), scorers.current());
+    let baseline = baseline.map(|baseline| baseline.current());
+    let backend = scorers.get(body.scoring).map(|scorer| scorer.backend()).ok_or_else(|| {
+        let backend = body.scoring.unwrap_or_default();
+        error::ErrorBadRequest(format!("scoring backend '{}' is not configured", backend.name()))
+    })?;
+    let (pairs, positions) = match (body.pairs.is_empty(), body.texts.is_empty()) {
+        (_, true) => (body.pairs, Vec::new()),
+        (true, false) => all_pairs(&body.texts),
+        (false, false) => return Err(error::ErrorBadRequest("send either pairs or texts")),
+    };
+    let start = match &body.continuation {
+        Some(token) => resume_index(token, &pairs)
+            .ok_or_else(|| error::ErrorBadRequest("continuation token does not belong to this batch"))?,
+        None => 0,
+    };
+    let pipeline = request_pipeline(body.preprocessing.as_deref(), &config)?;
+    let tokenizer = request_tokenizer(body.dictionary.as_deref(), &config, &tokenizers)?;
+    let budget = body.max_duration_ms.map(Duration::from_millis);
+
+    let snapshot = snapshots.map(|s| s.record(&config, body.preprocessing.as_deref(), backend));
+
+    // The comparisons run off the async workers, and the time budget is
+    // checked between them there
+    let (results, pairs) = web::block(move || -> LinderaResult<_> {
+        let steps = pipeline.names();
+        let options = CompareOptions {
+            // Found above, in the same scorers
+            scorer: scorers.get(Some(backend)).unwrap_or(scorers.default_scorer()),
+            embedder: embedder.as_ref().map(|e| e.get_ref()),
+            baseline: baseline.as_deref(),
+            preprocessing: &steps,
+            ..CompareOptions::default()
+        };
+        let mut results = Vec::new();
+        for (index, pair) in pairs.iter().enumerate().skip(start) {
+            // At least one pair per request, so every continuation makes progress
+            if !results.is_empty() && budget.is_some_and(|budget| started.elapsed() >= budget) {
+                break;
+            }
+            let text1 = pipeline.apply(&pair.text1);
+            let text2 = pipeline.apply(&pair.text2);
+            let mut analysis = compare_with_options(&text1, &text2, tokenizer, &config, options)?;
+            analysis.config_snapshot = snapshot.clone();
+            if let Some(telemetry) = &telemetry {
+                telemetry.record(&analysis, Some([pair.text1.chars().count(), pair.text2.chars().count()]));
+            }
+            results.push(BatchResult {
+                index,
+                id: pair.id.clone(),
+                texts: positions.get(index).copied(),
+                analysis,
+            });
+        }
+        Ok((results, pairs))
+    })
+    .await?
+    .map_err(error::ErrorInternalServerError)?;
+
+    let matrix = (!body.texts.is_empty()).then(|| {
+        let n = body.texts.len();
+        let mut matrix: Vec<Vec<Option<f64>>> = (0..n).map(|i| (0..n).map(|j| (i == j).then_some(1.0)).collect()).collect();
+        for result in &results {
+            if let Some([i, j]) = result.texts {
+                matrix[i][j] = Some(result.analysis.confidence);
+                matrix[j][i] = Some(result.analysis.confidence);
+            }
+        }
+        matrix
+    });
+    let next = start + results.len();
+    Ok(web::Json(BatchResponse {
+        results,
+        continuation: (next < pairs.len()).then(|| continuation_token(next, &pairs)),
+        remaining: pairs.len() - next,
+        matrix,
+    }))
+}
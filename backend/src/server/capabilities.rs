@@ -0,0 +1,75 @@
+use super::reload::Reloadable;
+use crate::aspects::{AspectInfo, ASPECTS};
+use crate::config::Config;
+use crate::features::FEATURE_SCHEMA_VERSION;
+use crate::schema::OLDEST_FEATURE_SCHEMA_VERSION;
+use crate::token_cache::{self, CacheStats};
+use crate::scoring::{Backend, Scorers};
+use crate::MODEL_VERSION;
+use actix_web::{get, web};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct LanguageSupport {
+    pub code: &'static str,
+    pub name: &'static str,
+    pub dictionary: Option<&'static str>,
+    pub pos_tagging: bool,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct Limits {
+    pub max_payload_bytes: usize,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct Capabilities {
+    pub aspects: &'static [AspectInfo],
+    pub languages: Vec<LanguageSupport>,
+    pub model_version: &'static str,
+    pub feature_schema_version: u32,
+    /// Profiles and feature vectors from this version on are migrated rather than refused.
+    pub oldest_feature_schema_version: u32,
+    /// Backends a request may pick with `scoring`.
+    pub scoring_backends: Vec<Backend>,
+    pub limits: Limits,
+    /// Names a request may pick with `dictionary`.
+    pub dictionaries: Vec<String>,
+    /// Hit and miss counts of the sentence tokenization cache, when enabled.
+    pub token_cache: Option<CacheStats>,
+}
+
+#[get("/capabilities")]
+pub async fn capabilities(
+    scorers: Option<web::Data<Reloadable<Scorers>>>,
+    config: Option<web::Data<Reloadable<Config>>>,
+) -> web::Json<Capabilities> {
+    web::Json(Capabilities {
+        aspects: ASPECTS,
+        languages: vec![
+            LanguageSupport {
+                code: "ja",
+                name: "Japanese",
+                dictionary: Some("ipadic"),
+                pos_tagging: true,
+            },
+            // English segments are only counted for vocabulary and code-switching
+            LanguageSupport {
+                code: "en",
+                name: "English",
+                dictionary: None,
+                pos_tagging: false,
+            },
+        ],
+        model_version: MODEL_VERSION,
+        feature_schema_version: FEATURE_SCHEMA_VERSION,
+        oldest_feature_schema_version: OLDEST_FEATURE_SCHEMA_VERSION,
+        scoring_backends: scorers.map_or_else(|| vec![Backend::Weighted], |s| s.current().available()),
+        limits: Limits {
+            max_payload_bytes: super::MAX_PAYLOAD_BYTES,
+        },
+        dictionaries: config.map_or_else(|| Config::default().dictionary_names(), |c| c.current().dictionary_names()),
+        token_cache: token_cache::global().map(|cache| cache.stats()),
+    })
+}
@@ -0,0 +1,310 @@
+use super::authors::{store_error, RegisteredAuthor};
+use super::history::history_of;
+use super::reload::Reloadable;
+use super::snapshots::{ConfigSnapshot, Snapshots};
+use crate::attribution::escape_html;
+use crate::baseline::Baseline;
+use crate::config::Config;
+use crate::features::FEATURE_SCHEMA_VERSION;
+use crate::history::{History, StoredComparison};
+use crate::profile_store::ProfileStore;
+use crate::scoring::{Backend, Scorers};
+use crate::signing::{to_hex, ResultSigner, ALGORITHM};
+use crate::MODEL_VERSION;
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::{error, get, web, HttpResponse, Result};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MAX_CASE_LEN: usize = 128;
+
+/// Case ids name the report's file, so they are kept to letters, digits,
+/// `.`, `_` and `-`.
+pub fn validate_case(case: &str) -> Result<(), String> {
+    let valid_char = |c: char| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-');
+    if case.is_empty() || case.len() > MAX_CASE_LEN || case.starts_with('.') || !case.chars().all(valid_char) {
+        return Err(format!(
+            "case ids are 1 to {} letters, digits, '.', '_' or '-', not starting with '.'",
+            MAX_CASE_LEN
+        ));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+pub struct ModelVersions {
+    pub model_version: &'static str,
+    pub feature_schema_version: u32,
+    /// Backends the case's results were scored with.
+    pub scoring_backends: Vec<Backend>,
+    /// The configurations the results were produced under, as far as this
+    /// server still knows them; ids recorded before a restart under other
+    /// settings are left out.
+    pub config_snapshots: Vec<ConfigSnapshot>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CorpusStats {
+    /// Texts the Mahalanobis reference model was fitted on.
+    pub reference_samples: Option<usize>,
+    /// Different-author pairs behind the percentiles.
+    pub baseline_pairs: Option<usize>,
+}
+
+/// Everything a reviewer needs to follow a case: its results as returned,
+/// and what they were scored against.
+#[derive(Debug, Serialize)]
+pub struct CaseReport {
+    pub case: String,
+    /// Unix time the report was built at, in seconds.
+    pub generated_at: i64,
+    pub versions: ModelVersions,
+    pub corpus: CorpusStats,
+    /// The registered author profiles when the report was built.
+    pub profiles: Vec<RegisteredAuthor>,
+    /// Oldest first.
+    pub comparisons: Vec<StoredComparison>,
+}
+
+#[derive(Debug, Serialize)]
+struct ManifestSignature {
+    algorithm: &'static str,
+    public_key: String,
+}
+
+/// SHA-256 of each file in the bundle. `manifest.sig` signs this file's
+/// bytes, so checking it and the hashes verifies the whole bundle.
+#[derive(Debug, Serialize)]
+struct Manifest<'a> {
+    case: &'a str,
+    generated_at: i64,
+    files: BTreeMap<&'static str, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signature: Option<ManifestSignature>,
+}
+
+fn format_time(seconds: i64) -> String {
+    // Civil date from days since the epoch (Howard Hinnant's algorithm)
+    let (days, secs) = (seconds.div_euclid(86_400), seconds.rem_euclid(86_400));
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC", year, month, day, secs / 3_600, secs % 3_600 / 60, secs % 60)
+}
+
+fn name_of(value: impl Serialize) -> String {
+    serde_json::to_value(value).ok().and_then(|v| v.as_str().map(str::to_string)).unwrap_or_default()
+}
+
+/// A self-contained page: versions and corpora, the registered profiles, a
+/// summary row per comparison, then each comparison's aspects.
+pub fn write_html(report: &CaseReport, out: &mut impl Write) -> io::Result<()> {
+    let case = escape_html(&report.case);
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, "<html lang=\"ja\"><head><meta charset=\"utf-8\"><title>Case {}</title>", case)?;
+    writeln!(out, "<style>body{{font-family:sans-serif}}table{{border-collapse:collapse;margin-bottom:1em}}td,th{{border:1px solid #ccc;padding:2px 8px;text-align:left}}</style>")?;
+    writeln!(out, "</head><body>")?;
+    writeln!(out, "<h1>Case {}</h1>", case)?;
+    writeln!(
+        out,
+        "<p>generated {}, model {}, feature schema {}</p>",
+        format_time(report.generated_at),
+        report.versions.model_version,
+        report.versions.feature_schema_version
+    )?;
+    let count = |n: Option<usize>| n.map_or_else(|| "-".to_string(), |n| n.to_string());
+    writeln!(
+        out,
+        "<p>reference corpus: {} texts; baseline: {} different-author pairs</p>",
+        count(report.corpus.reference_samples),
+        count(report.corpus.baseline_pairs)
+    )?;
+
+    writeln!(out, "<h2>Profiles</h2>")?;
+    writeln!(out, "<table><tr><th>author</th><th>samples</th><th>tokens per sample</th></tr>")?;
+    for profile in &report.profiles {
+        writeln!(out, "<tr><td>{}</td><td>{}</td><td>{}</td></tr>", escape_html(&profile.author), profile.sample_count, profile.token_count)?;
+    }
+    writeln!(out, "</table>")?;
+
+    writeln!(out, "<h2>Comparisons</h2>")?;
+    writeln!(out, "<table><tr><th>id</th><th>served</th><th>verdict</th><th>confidence</th><th>backend</th><th>text1 SHA-256</th><th>text2 SHA-256</th><th>signed</th></tr>")?;
+    for stored in &report.comparisons {
+        writeln!(
+            out,
+            "<tr><td><a href=\"#c{id}\">{id}</a></td><td>{}</td><td>{}</td><td>{:.3}</td><td>{}</td><td><code>{}</code></td><td><code>{}</code></td><td>{}</td></tr>",
+            format_time(stored.created_at),
+            name_of(stored.analysis.verdict),
+            stored.analysis.confidence,
+            stored.analysis.scoring.name(),
+            stored.input_sha256[0],
+            stored.input_sha256[1],
+            if stored.analysis.signature.is_some() { "yes" } else { "no" },
+            id = stored.id
+        )?;
+    }
+    writeln!(out, "</table>")?;
+
+    for stored in &report.comparisons {
+        writeln!(out, "<h3 id=\"c{}\">Comparison {}</h3>", stored.id, stored.id)?;
+        writeln!(out, "<table><tr><th>aspect</th><th>difference</th><th>weight</th></tr>")?;
+        for detail in &stored.analysis.detailed_analysis {
+            let difference = detail.difference.map_or_else(|| "-".to_string(), |d| format!("{:.3}", d));
+            writeln!(out, "<tr><td>{}</td><td>{}</td><td>{:.2}</td></tr>", escape_html(&detail.aspect), difference, detail.weight)?;
+        }
+        writeln!(out, "</table>")?;
+        for warning in &stored.analysis.warnings {
+            writeln!(out, "<p>warning: {}</p>", escape_html(&warning.message))?;
+        }
+    }
+    writeln!(out, "</body></html>")
+}
+
+#[cfg(feature = "history")]
+fn write_zip(files: &[(&str, &[u8])]) -> io::Result<Vec<u8>> {
+    let mut zip = zip::ZipWriter::new(io::Cursor::new(Vec::new()));
+    for (name, bytes) in files {
+        zip.start_file(*name, zip::write::SimpleFileOptions::default()).map_err(io::Error::other)?;
+        zip.write_all(bytes)?;
+    }
+    Ok(zip.finish().map_err(io::Error::other)?.into_inner())
+}
+
+// Unreachable: without the feature there is no History to build a report from
+#[cfg(not(feature = "history"))]
+fn write_zip(_files: &[(&str, &[u8])]) -> io::Result<Vec<u8>> {
+    Err(io::Error::other("built without the `history` feature"))
+}
+
+/// `report.json` and `report.html`, with `manifest.json` hashing both and,
+/// when the server signs results, `manifest.sig` holding the hex Ed25519
+/// signature of the manifest.
+pub fn bundle(report: &CaseReport, signer: Option<&ResultSigner>) -> io::Result<Vec<u8>> {
+    let json = serde_json::to_vec_pretty(report).map_err(io::Error::other)?;
+    let mut html = Vec::new();
+    write_html(report, &mut html)?;
+
+    let manifest = Manifest {
+        case: &report.case,
+        generated_at: report.generated_at,
+        files: BTreeMap::from([
+            ("report.html", to_hex(&Sha256::digest(&html))),
+            ("report.json", to_hex(&Sha256::digest(&json))),
+        ]),
+        signature: signer.map(|signer| ManifestSignature {
+            algorithm: ALGORITHM,
+            public_key: signer.public_key(),
+        }),
+    };
+    let manifest = serde_json::to_vec_pretty(&manifest).map_err(io::Error::other)?;
+    let signature = signer.map(|signer| signer.sign_bytes(&manifest));
+
+    let mut files: Vec<(&str, &[u8])> = vec![("report.json", &json), ("report.html", &html), ("manifest.json", &manifest)];
+    if let Some(signature) = &signature {
+        files.push(("manifest.sig", signature.as_bytes()));
+    }
+    write_zip(&files)
+}
+
+/// Every comparison filed under the case, bundled with what they were
+/// scored against, as `<case>.zip`.
+#[get("/cases/{id}/report")]
+pub async fn case_report(
+    case: web::Path<String>,
+    history: Option<web::Data<History>>,
+    config: web::Data<Reloadable<Config>>,
+    scorers: web::Data<Reloadable<Scorers>>,
+    snapshots: Option<web::Data<Snapshots>>,
+    baseline: Option<web::Data<Reloadable<Baseline>>>,
+    signer: Option<web::Data<ResultSigner>>,
+) -> Result<HttpResponse> {
+    let case = case.into_inner();
+    validate_case(&case).map_err(error::ErrorBadRequest)?;
+    let history = history_of(history)?;
+    let filed = case.clone();
+    let comparisons = web::block(move || history.case(&filed)).await?.map_err(error::ErrorInternalServerError)?;
+    if comparisons.is_empty() {
+        return Err(error::ErrorNotFound(format!("no comparisons filed under case {}", case)));
+    }
+    let store = ProfileStore::new(&config.current().profiles.directory);
+    // Every profile is read from disk
+    let profiles = web::block(move || {
+        store
+            .authors()?
+            .iter()
+            .map(|author| store.load(author).map(|profile| RegisteredAuthor::new(author, &profile)))
+            .collect::<Result<Vec<_>, _>>()
+    })
+    .await?
+    .map_err(store_error)?;
+
+    let mut scoring_backends = Vec::new();
+    let mut snapshot_ids = Vec::new();
+    for stored in &comparisons {
+        if !scoring_backends.contains(&stored.analysis.scoring) {
+            scoring_backends.push(stored.analysis.scoring);
+        }
+        if let Some(id) = &stored.analysis.config_snapshot {
+            if !snapshot_ids.contains(id) {
+                snapshot_ids.push(id.clone());
+            }
+        }
+    }
+    let config_snapshots = snapshot_ids
+        .iter()
+        .filter_map(|id| snapshots.as_ref().and_then(|snapshots| snapshots.get(id)))
+        .collect();
+    let report = CaseReport {
+        case,
+        generated_at: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64),
+        versions: ModelVersions {
+            model_version: MODEL_VERSION,
+            feature_schema_version: FEATURE_SCHEMA_VERSION,
+            scoring_backends,
+            config_snapshots,
+        },
+        corpus: CorpusStats {
+            reference_samples: scorers.current().reference_model().map(|model| model.sample_count()),
+            baseline_pairs: baseline.map(|baseline| baseline.current().pair_count()),
+        },
+        profiles,
+        comparisons,
+    };
+
+    let archive = bundle(&report, signer.as_ref().map(|signer| signer.get_ref())).map_err(error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok()
+        .content_type("application/zip")
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(format!("{}.zip", report.case))],
+        })
+        .body(archive))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_case() {
+        assert!(validate_case("2024-017_a.b").is_ok());
+        for invalid in ["", ".hidden", "a/b", "a b", "事件", &"a".repeat(MAX_CASE_LEN + 1)] {
+            assert!(validate_case(invalid).is_err(), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!(format_time(0), "1970-01-01 00:00:00 UTC");
+        assert_eq!(format_time(951_827_696), "2000-02-29 12:34:56 UTC");
+    }
+}
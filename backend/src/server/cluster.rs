@@ -0,0 +1,49 @@
+use super::default_tokenizer;
+use super::reload::Reloadable;
+use crate::clustering::{cluster, comparison_distances, Dendrogram, DendrogramFormat};
+use crate::config::Config;
+use crate::datasets::LabeledText;
+use crate::features::{extract_features, TextFeatures};
+use crate::scoring::Scorers;
+use crate::tokenizer::TokenizerPool;
+use actix_web::{error, post, web, HttpResponse, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ClusterQuery {
+    pub texts: Vec<LabeledText>,
+    #[serde(default)]
+    pub format: DendrogramFormat,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ClusterTree {
+    pub tree: Dendrogram,
+}
+
+#[post("/cluster")]
+pub async fn cluster_texts(
+    body: web::Json<ClusterQuery>,
+    config: web::Data<Reloadable<Config>>,
+    scorers: web::Data<Reloadable<Scorers>>,
+    tokenizers: web::Data<TokenizerPool>,
+) -> Result<HttpResponse> {
+    let (config, scorers) = (config.current(), scorers.current());
+    let tokenizer = default_tokenizer(&tokenizers)?;
+
+    let features = body
+        .texts
+        .iter()
+        .map(|t| extract_features(&t.text, tokenizer))
+        .collect::<Result<Vec<TextFeatures>, _>>()
+        .map_err(error::ErrorInternalServerError)?;
+    let distances = comparison_distances(&features, &config, scorers.default_scorer());
+    let labels: Vec<String> = body.texts.iter().map(|t| t.author.clone()).collect();
+    let tree = cluster(&distances, &labels).ok_or_else(|| error::ErrorBadRequest("no texts to cluster"))?;
+
+    Ok(match body.format {
+        DendrogramFormat::Json => HttpResponse::Ok().json(ClusterTree { tree }),
+        DendrogramFormat::Newick => HttpResponse::Ok().content_type("text/plain; charset=utf-8").body(tree.to_newick()),
+    })
+}
@@ -0,0 +1,10 @@
+use actix_web::{get, HttpResponse};
+
+const DEMO_PAGE: &str = include_str!("demo.html");
+
+#[get("/demo")]
+pub async fn demo() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(DEMO_PAGE)
+}
@@ -0,0 +1,185 @@
+use super::telemetry::VerdictCounts;
+use crate::comparison::{decide, Analysis, ExperimentAssignment, Verdict};
+use crate::config::{DecisionProfile, ExperimentConfig};
+use actix_web::{error, get, web, Result};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::hash_map::{DefaultHasher, RandomState};
+use std::collections::BTreeMap;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::sync::Mutex;
+
+const CONTROL: &str = "control";
+/// Identifies the caller; requests without it are assigned at random.
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Outcomes of the requests one arm decided.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct ArmStats {
+    /// Configured share of requests.
+    pub fraction: f64,
+    pub decision: DecisionProfile,
+    pub requests: u64,
+    /// Requests assigned from their API key rather than at random.
+    pub by_api_key: u64,
+    pub verdicts: VerdictCounts,
+    /// None before the first request.
+    pub mean_confidence: Option<f64>,
+    #[serde(skip)]
+    confidence_sum: f64,
+}
+
+impl ArmStats {
+    fn new(fraction: f64, decision: DecisionProfile) -> ArmStats {
+        ArmStats {
+            fraction,
+            decision,
+            requests: 0,
+            by_api_key: 0,
+            verdicts: VerdictCounts::default(),
+            mean_confidence: None,
+            confidence_sum: 0.0,
+        }
+    }
+
+    fn record(&mut self, analysis: &Analysis, by_api_key: bool) {
+        self.requests += 1;
+        self.by_api_key += u64::from(by_api_key);
+        match analysis.verdict {
+            Verdict::SameAuthor => self.verdicts.same_author += 1,
+            Verdict::DifferentAuthor => self.verdicts.different_author += 1,
+            Verdict::InsufficientData => self.verdicts.insufficient_data += 1,
+            Verdict::IdenticalText => self.verdicts.identical_text += 1,
+            Verdict::Inconclusive => self.verdicts.inconclusive += 1,
+        }
+        self.confidence_sum += analysis.confidence;
+        self.mean_confidence = Some(self.confidence_sum / self.requests as f64);
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ExperimentReport {
+    /// Every experiment by name, plus `control` for the requests outside them.
+    pub arms: BTreeMap<String, ArmStats>,
+}
+
+/// Alternative decision profiles, each deciding a share of `/compare`
+/// requests, with the outcomes of every arm counted since startup.
+/// Only registered when experiments are configured.
+#[derive(Debug)]
+pub struct Experiments {
+    // Consecutive slices of [0, 1) by upper bound; buckets past the last
+    // slice are the control
+    arms: Vec<(String, f64, DecisionProfile)>,
+    stats: Mutex<BTreeMap<String, ArmStats>>,
+}
+
+impl Experiments {
+    /// Returns `None` when no experiment is configured; `control` is the
+    /// configured decision profile, reported for the requests outside every
+    /// experiment.
+    pub fn from_config(configs: &BTreeMap<String, ExperimentConfig>, control: DecisionProfile) -> Option<Experiments> {
+        if configs.is_empty() {
+            return None;
+        }
+        let mut arms = Vec::new();
+        let mut stats = BTreeMap::new();
+        let mut upper = 0.0;
+        for (name, experiment) in configs {
+            upper += experiment.fraction;
+            arms.push((name.clone(), upper, experiment.decision));
+            stats.insert(name.clone(), ArmStats::new(experiment.fraction, experiment.decision));
+        }
+        stats.insert(CONTROL.to_string(), ArmStats::new((1.0 - upper).max(0.0), control));
+        Some(Experiments {
+            arms,
+            stats: Mutex::new(stats),
+        })
+    }
+
+    // The same API key always lands in the same bucket; the control gets no
+    // profile, so it keeps the verdict the request's own config gave
+    fn assign(&self, api_key: Option<&str>) -> (ExperimentAssignment, Option<DecisionProfile>) {
+        let hash = match api_key {
+            Some(key) => {
+                let mut hasher = DefaultHasher::new();
+                key.hash(&mut hasher);
+                hasher.finish()
+            }
+            None => RandomState::new().build_hasher().finish(),
+        };
+        let bucket = hash as f64 / u64::MAX as f64;
+        let (arm, decision) = self
+            .arms
+            .iter()
+            .find(|(_, upper, _)| bucket < *upper)
+            .map_or((CONTROL, None), |(name, _, decision)| (name.as_str(), Some(*decision)));
+        let assignment = ExperimentAssignment {
+            arm: arm.to_string(),
+            by_api_key: api_key.is_some(),
+        };
+        (assignment, decision)
+    }
+
+    /// Re-decides the verdict with the request's arm, records the
+    /// assignment in the result and counts its outcome.
+    pub fn apply(&self, analysis: &mut Analysis, api_key: Option<&str>) {
+        let (assignment, decision) = self.assign(api_key);
+        if let Some(decision) = decision {
+            decide(analysis, &decision);
+        }
+        let mut stats = self.stats.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(arm) = stats.get_mut(&assignment.arm) {
+            arm.record(analysis, assignment.by_api_key);
+        }
+        analysis.experiment = Some(assignment);
+    }
+
+    pub fn report(&self) -> ExperimentReport {
+        ExperimentReport {
+            arms: self.stats.lock().unwrap_or_else(|e| e.into_inner()).clone(),
+        }
+    }
+}
+
+#[get("/experiments")]
+pub async fn experiments(experiments: Option<web::Data<Experiments>>) -> Result<web::Json<ExperimentReport>> {
+    let experiments = experiments.ok_or_else(|| error::ErrorNotFound("no experiments are configured"))?;
+    Ok(web::Json(experiments.report()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn experiment(fraction: f64, same_author_above: f64) -> ExperimentConfig {
+        ExperimentConfig {
+            fraction,
+            decision: DecisionProfile { same_author_above },
+        }
+    }
+
+    #[test]
+    fn test_assignment() {
+        let config = BTreeMap::from([("lenient".to_string(), experiment(0.5, 0.4)), ("strict".to_string(), experiment(0.5, 0.8))]);
+        let arms = Experiments::from_config(&config, DecisionProfile::default()).unwrap();
+        assert_eq!(arms.report().arms["control"].fraction, 0.0);
+
+        // A key keeps its arm, and with no control share every request is in one
+        for key in ["a", "b", "c", "d"] {
+            let (first, decision) = arms.assign(Some(key));
+            let (second, _) = arms.assign(Some(key));
+            assert_eq!(first, second);
+            assert!(first.by_api_key);
+            assert_eq!(decision, Some(config[&first.arm].decision));
+        }
+        let (random, _) = arms.assign(None);
+        assert!(!random.by_api_key && random.arm != CONTROL);
+
+        assert!(Experiments::from_config(&BTreeMap::new(), DecisionProfile::default()).is_none());
+        let control = DecisionProfile { same_author_above: 0.7 };
+        let none = Experiments::from_config(&BTreeMap::from([("off".to_string(), experiment(0.0, 0.9))]), control).unwrap();
+        assert_eq!(none.assign(Some("a")), (ExperimentAssignment { arm: CONTROL.to_string(), by_api_key: true }, None));
+        assert_eq!(none.report().arms[CONTROL].decision, control);
+    }
+}
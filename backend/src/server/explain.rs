@@ -0,0 +1,37 @@
+use super::reload::Reloadable;
+use super::{default_tokenizer, request_pipeline};
+use crate::config::Config;
+use crate::explain::{explain, Explanation};
+use crate::features::extract_features_filtered;
+use crate::tokenizer::TokenizerPool;
+use actix_web::{error, post, web, Result};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ExplainQuery {
+    pub text1: String,
+    pub text2: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preprocessing: Option<Vec<String>>,
+}
+
+/// Per-aspect decomposition of the weighted confidence, with counterfactuals.
+#[post("/compare/explain")]
+pub async fn explain_comparison(
+    body: web::Json<ExplainQuery>,
+    config: web::Data<Reloadable<Config>>,
+    tokenizers: web::Data<TokenizerPool>,
+) -> Result<web::Json<Explanation>> {
+    let config = config.current();
+    let pipeline = request_pipeline(body.preprocessing.as_deref(), &config)?;
+    let tokenizer = default_tokenizer(&tokenizers)?;
+
+    let filters = config.token_filters().unwrap_or_default();
+
+    let extract = |text: &str| {
+        extract_features_filtered(&pipeline.apply(text), tokenizer, &filters).map_err(error::ErrorInternalServerError)
+    };
+    let features1 = extract(&body.text1)?;
+    let features2 = extract(&body.text2)?;
+    Ok(web::Json(explain(&features1, &features2, &config)))
+}
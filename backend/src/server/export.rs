@@ -0,0 +1,31 @@
+use crate::datasets::LabeledText;
+use crate::export::{feature_table, ExportFormat};
+use super::default_tokenizer;
+use crate::tokenizer::TokenizerPool;
+use actix_web::{error, post, web, HttpResponse, Result};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ExportQuery {
+    pub texts: Vec<LabeledText>,
+    pub format: ExportFormat,
+    #[serde(default = "default_vocabulary")]
+    pub vocabulary: usize,
+}
+
+fn default_vocabulary() -> usize {
+    100
+}
+
+#[post("/export")]
+pub async fn export(body: web::Json<ExportQuery>, tokenizers: web::Data<TokenizerPool>) -> Result<HttpResponse> {
+    let tokenizer = default_tokenizer(&tokenizers)?;
+
+    let table = feature_table(&body.texts, body.vocabulary, tokenizer).map_err(error::ErrorInternalServerError)?;
+    let mut out = Vec::new();
+    table.write(body.format, &mut out).map_err(|e| match e.kind() {
+        std::io::ErrorKind::Unsupported => error::ErrorBadRequest(e),
+        _ => error::ErrorInternalServerError(e),
+    })?;
+    Ok(HttpResponse::Ok().content_type(body.format.content_type()).body(out))
+}
@@ -0,0 +1,66 @@
+use super::reload::Reloadable;
+use crate::config::Config;
+use crate::explain::{explain_details, Explanation};
+use crate::history::{ComparisonSummary, History, StoredComparison};
+use actix_web::{error, get, web, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_LIMIT: usize = 50;
+const MAX_LIMIT: usize = 500;
+
+#[derive(Debug, Deserialize)]
+pub struct HistoryQuery {
+    /// At most this many results, 50 by default.
+    pub limit: Option<usize>,
+    /// Only results with a lower id, for paging back from the last one seen.
+    pub before: Option<i64>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ComparisonList {
+    /// Newest first.
+    pub comparisons: Vec<ComparisonSummary>,
+}
+
+pub(super) fn history_of(history: Option<web::Data<History>>) -> Result<web::Data<History>> {
+    history.ok_or_else(|| error::ErrorNotFound("comparison history is not enabled"))
+}
+
+#[get("/comparisons")]
+pub async fn comparisons(
+    query: web::Query<HistoryQuery>,
+    history: Option<web::Data<History>>,
+) -> Result<web::Json<ComparisonList>> {
+    let history = history_of(history)?;
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+    let before = query.before;
+    let comparisons = web::block(move || history.list(limit, before)).await?.map_err(error::ErrorInternalServerError)?;
+    Ok(web::Json(ComparisonList { comparisons }))
+}
+
+async fn stored(id: i64, history: Option<web::Data<History>>) -> Result<StoredComparison> {
+    let history = history_of(history)?;
+    web::block(move || history.get(id))
+        .await?
+        .map_err(error::ErrorInternalServerError)?
+        .ok_or_else(|| error::ErrorNotFound(format!("no stored comparison {}", id)))
+}
+
+#[get("/comparisons/{id}")]
+pub async fn comparison(id: web::Path<i64>, history: Option<web::Data<History>>) -> Result<web::Json<StoredComparison>> {
+    Ok(web::Json(stored(id.into_inner(), history).await?))
+}
+
+/// Per-aspect decomposition of a stored result, from the differences and
+/// weights it was scored with. Normalized differences follow the current
+/// transforms.
+#[get("/comparisons/{id}/explain")]
+pub async fn explain_comparison(
+    id: web::Path<i64>,
+    history: Option<web::Data<History>>,
+    config: web::Data<Reloadable<Config>>,
+) -> Result<web::Json<Explanation>> {
+    let stored = stored(id.into_inner(), history).await?;
+    Ok(web::Json(explain_details(&stored.analysis.detailed_analysis, &config.current())))
+}
@@ -0,0 +1,380 @@
+use super::queue::{JobQueue, QueuedRun};
+use super::reload::Reloadable;
+use super::webhooks::Webhooks;
+use crate::baseline::Baseline;
+use crate::calibration::{scored_pairs, Calibration};
+use crate::config::{Config, ConfigError, JobConfig, JobTask, WebhookEvent};
+use crate::datasets::{load_author_dirs, Dataset};
+use crate::history::History;
+use crate::scoring::Scorers;
+use crate::tokenizer::{TokenizerPool, TokenizerSpec};
+use actix_web::{get, web};
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::json;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// The scheduler never sleeps longer than this, so a clock change is noticed
+const MAX_SLEEP: Duration = Duration::from_secs(60);
+// Length buckets a refit uses when the job sets none, as `calibrate` does
+const DEFAULT_BUCKETS: [usize; 3] = [100, 300, 1000];
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct JobRun {
+    /// Unix time the run started at, in seconds.
+    pub started_at: u64,
+    pub duration_ms: u64,
+    pub succeeded: bool,
+    /// What the run did, or why it failed.
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct JobStatus {
+    pub name: String,
+    pub task: &'static str,
+    pub every_minutes: u64,
+    pub running: bool,
+    /// Runs since the server started.
+    pub runs: u64,
+    /// Unix time of the next scheduled run, in seconds.
+    pub next_run_at: u64,
+    pub last_run: Option<JobRun>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct JobList {
+    pub jobs: Vec<JobStatus>,
+}
+
+/// What the tasks maintain: the same shared state the handlers read, so a
+/// refreshed baseline or refitted calibration applies to the next request.
+#[derive(Clone)]
+pub struct JobContext {
+    pub config: web::Data<Reloadable<Config>>,
+    pub scorers: web::Data<Reloadable<Scorers>>,
+    pub tokenizers: web::Data<TokenizerPool>,
+    pub baseline: Option<web::Data<Reloadable<Baseline>>>,
+    pub history: Option<web::Data<History>>,
+    pub webhooks: Option<web::Data<Webhooks>>,
+}
+
+struct Job {
+    config: JobConfig,
+    status: Mutex<JobStatus>,
+}
+
+/// The configured `[[jobs]]`, run one at a time by a scheduler thread.
+/// Runs of shared jobs go through the queue, so that of several instances
+/// sharing it only one performs each.
+pub struct Jobs {
+    jobs: Vec<Job>,
+    queue: JobQueue,
+    context: JobContext,
+}
+
+impl std::fmt::Debug for Jobs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Jobs").field("jobs", &self.list()).finish()
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+// The first multiple of the period since the Unix epoch after `time`, the
+// same on every instance whenever it started
+fn next_due(time: u64, every_minutes: u64) -> u64 {
+    let period = every_minutes * 60;
+    (time / period + 1) * period
+}
+
+impl Jobs {
+    /// Opens the queue and schedules each job, or returns `None` when none
+    /// is configured. The queue connects from the jobs thread, on first use.
+    pub fn from_config(config: &Config, context: JobContext) -> Result<Option<Jobs>, ConfigError> {
+        if config.jobs.is_empty() {
+            return Ok(None);
+        }
+        let queue = JobQueue::from_config(&config.queue)?;
+        let now = now();
+        let jobs = config
+            .jobs
+            .iter()
+            .map(|config| Job {
+                status: Mutex::new(JobStatus {
+                    name: config.name.clone(),
+                    task: config.task.name(),
+                    every_minutes: config.every_minutes,
+                    running: false,
+                    runs: 0,
+                    next_run_at: next_due(now, config.every_minutes),
+                    last_run: None,
+                }),
+                config: config.clone(),
+            })
+            .collect();
+        Ok(Some(Jobs { jobs, queue, context }))
+    }
+
+    /// Starts the thread running the jobs as they come due.
+    pub fn start(jobs: web::Data<Jobs>) -> std::io::Result<()> {
+        std::thread::Builder::new().name("jobs".to_string()).spawn(move || loop {
+            jobs.tick(now());
+            let next = jobs.list().iter().map(|status| status.next_run_at).min().unwrap_or(u64::MAX);
+            std::thread::sleep(Duration::from_secs(next.saturating_sub(now())).clamp(Duration::from_secs(1), MAX_SLEEP));
+        })?;
+        Ok(())
+    }
+
+    // Runs the local jobs due at `time` and queues the shared ones, then runs
+    // whatever this instance or another has queued
+    fn tick(&self, time: u64) {
+        for job in &self.jobs {
+            let due_at = {
+                let mut status = job.status.lock().unwrap_or_else(|e| e.into_inner());
+                if status.next_run_at > time {
+                    continue;
+                }
+                std::mem::replace(&mut status.next_run_at, next_due(time, job.config.every_minutes))
+            };
+            if !job.config.task.shared() {
+                self.run(job);
+                continue;
+            }
+            let run = QueuedRun {
+                job: job.config.name.clone(),
+                due_at,
+            };
+            // Every instance queues the run; the queue keeps one
+            if let Err(e) = self.queue.push(&run) {
+                eprintln!("Skipping the run of job {} due at {}: {}", run.job, run.due_at, e);
+            }
+        }
+        loop {
+            match self.queue.pop() {
+                Ok(Some(run)) => match self.jobs.iter().find(|job| job.config.name == run.job) {
+                    Some(job) => {
+                        self.run(job);
+                    }
+                    None => eprintln!("No job named {} to run; every instance sharing the queue needs the same [[jobs]]", run.job),
+                },
+                Ok(None) => break,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    break;
+                }
+            }
+        }
+    }
+
+    pub fn list(&self) -> Vec<JobStatus> {
+        self.jobs.iter().map(|job| job.status.lock().unwrap_or_else(|e| e.into_inner()).clone()).collect()
+    }
+
+    /// Runs the named job right away, off schedule; `None` if there is no
+    /// such job.
+    #[cfg(test)]
+    fn run_now(&self, name: &str) -> Option<JobRun> {
+        self.jobs.iter().find(|job| job.config.name == name).map(|job| self.run(job))
+    }
+
+    fn run(&self, job: &Job) -> JobRun {
+        job.status.lock().unwrap_or_else(|e| e.into_inner()).running = true;
+        let (started_at, started) = (now(), Instant::now());
+        let result = self.perform(&job.config);
+        let run = JobRun {
+            started_at,
+            duration_ms: started.elapsed().as_millis() as u64,
+            succeeded: result.is_ok(),
+            message: result.unwrap_or_else(|e| e),
+        };
+        if !run.succeeded {
+            eprintln!("Job {} failed: {}", job.config.name, run.message);
+        }
+        {
+            let mut status = job.status.lock().unwrap_or_else(|e| e.into_inner());
+            status.running = false;
+            status.runs += 1;
+            status.last_run = Some(run.clone());
+        }
+        if let Some(webhooks) = &self.context.webhooks {
+            let data = json!({"job": job.config.name, "task": job.config.task.name(), "run": run});
+            webhooks.notify(WebhookEvent::JobCompleted, data);
+        }
+        run
+    }
+
+    // Ok with a summary of what was done
+    fn perform(&self, job: &JobConfig) -> Result<String, String> {
+        let context = &self.context;
+        let config = context.config.current();
+        match &job.task {
+            JobTask::RetentionPurge { keep_days } => {
+                let history = context.history.as_ref().ok_or("the history isn't enabled")?;
+                let before = now().saturating_sub(u64::from(*keep_days) * 86_400);
+                let deleted = history.purge(before as i64).map_err(|e| e.to_string())?;
+                Ok(format!("deleted {} comparisons past the {}-day retention", deleted, keep_days))
+            }
+            JobTask::BaselineRefresh => {
+                let baseline = context.baseline.as_ref().ok_or("no baseline was loaded at startup")?;
+                let tokenizer = context.tokenizers.get(&TokenizerSpec::default()).map_err(|e| e.to_string())?;
+                let refreshed = Baseline::from_config(&config, tokenizer)
+                    .map_err(|e| e.to_string())?
+                    .ok_or("baseline.corpus is no longer set")?;
+                let pairs = refreshed.pair_count();
+                baseline.replace(refreshed);
+                Ok(format!("rebuilt the baseline from {} pairs", pairs))
+            }
+            JobTask::CalibrationRefit { corpus, buckets } => {
+                let path = config.scoring.calibration.clone().ok_or("scoring.calibration is no longer set")?;
+                let tokenizer = context.tokenizers.get(&TokenizerSpec::default()).map_err(|e| e.to_string())?;
+                // Fitted on the raw scores, not on those of the current calibration
+                let mut uncalibrated = (*config).clone();
+                uncalibrated.scoring.calibration = None;
+                let raw = Scorers::from_config(&uncalibrated, tokenizer).map_err(|e| e.to_string())?;
+                let dataset = Dataset::Texts(load_author_dirs(corpus).map_err(|e| format!("{}: {}", corpus.display(), e))?);
+                let scorer = raw.default_scorer();
+                let scored = scored_pairs(&dataset, tokenizer, &uncalibrated, scorer).map_err(|e| e.to_string())?;
+                let calibration = Calibration::fit(scorer.backend(), &scored, buckets.as_deref().unwrap_or(&DEFAULT_BUCKETS));
+                write_calibration(&path, &calibration)?;
+                context.scorers.replace(Scorers::from_config(&config, tokenizer).map_err(|e| e.to_string())?);
+
+                if let Some(webhooks) = &context.webhooks {
+                    let data = json!({
+                        "job": job.name,
+                        "model": "calibration",
+                        "scoring": calibration.backend,
+                        "pairs": scored.len(),
+                        "buckets": calibration.buckets.len(),
+                    });
+                    webhooks.notify(WebhookEvent::TrainingCompleted, data);
+                }
+                Ok(format!(
+                    "fitted {} length buckets on {} pairs for the {} backend",
+                    calibration.buckets.len(),
+                    scored.len(),
+                    calibration.backend.name()
+                ))
+            }
+        }
+    }
+}
+
+// Written beside the file and renamed over it, so a config reload never
+// reads it half-written
+fn write_calibration(path: &Path, calibration: &Calibration) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(calibration).map_err(|e| e.to_string())?;
+    let partial = path.with_extension("json.partial");
+    std::fs::write(&partial, json)
+        .and_then(|()| std::fs::rename(&partial, path))
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// The scheduled jobs and how their last runs went.
+#[get("/jobs")]
+pub async fn list_jobs(jobs: Option<web::Data<Jobs>>) -> web::Json<JobList> {
+    web::Json(JobList {
+        jobs: jobs.map(|jobs| jobs.list()).unwrap_or_default(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> JobContext {
+        JobContext {
+            config: web::Data::new(Reloadable::new(Config::default())),
+            scorers: web::Data::new(Reloadable::new(Scorers::default())),
+            tokenizers: web::Data::new(TokenizerPool::default()),
+            baseline: None,
+            history: None,
+            webhooks: None,
+        }
+    }
+
+    fn job(name: &str, task: JobTask) -> JobConfig {
+        JobConfig {
+            name: name.to_string(),
+            every_minutes: 60,
+            task,
+        }
+    }
+
+    fn scheduled(jobs: Vec<JobConfig>, context: JobContext) -> Jobs {
+        let config = Config { jobs, ..Config::default() };
+        Jobs::from_config(&config, context).unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_failed_run() {
+        assert!(Jobs::from_config(&Config::default(), context()).unwrap().is_none());
+        let jobs = scheduled(vec![job("refresh", JobTask::BaselineRefresh)], context());
+
+        let run = jobs.run_now("refresh").unwrap();
+        assert!(!run.succeeded);
+        assert!(run.message.contains("baseline"), "{}", run.message);
+        let status = &jobs.list()[0];
+        assert_eq!((status.runs, status.running, status.task), (1, false, "baseline_refresh"));
+        assert!(status.last_run.is_some());
+        assert!(jobs.run_now("missing").is_none());
+    }
+
+    #[test]
+    fn test_schedule() {
+        assert_eq!(next_due(3599, 60), 3600);
+        assert_eq!(next_due(3600, 60), 7200);
+
+        let jobs = scheduled(
+            vec![job("purge", JobTask::RetentionPurge { keep_days: 1 }), job("refresh", JobTask::BaselineRefresh)],
+            context(),
+        );
+        let due_at = jobs.list()[0].next_run_at;
+        assert_eq!(due_at % 3600, 0);
+        jobs.tick(due_at - 1);
+        assert!(jobs.list().iter().all(|status| status.runs == 0));
+
+        // The shared purge goes through the queue, the refresh runs here
+        jobs.tick(due_at);
+        let statuses: Vec<(u64, u64)> = jobs.list().iter().map(|status| (status.runs, status.next_run_at)).collect();
+        assert_eq!(statuses, [(1, due_at + 3600), (1, due_at + 3600)]);
+        let purge = QueuedRun {
+            job: "purge".to_string(),
+            due_at,
+        };
+        assert!(!jobs.queue.push(&purge).unwrap());
+        jobs.tick(due_at + 60);
+        assert_eq!(jobs.list()[0].runs, 1);
+    }
+
+    #[cfg(feature = "history")]
+    #[test]
+    fn test_retention_purge() {
+        use crate::comparison::Analysis;
+
+        let history = History::open(":memory:").unwrap();
+        let analysis: Analysis = serde_json::from_value(json!({
+            "verdict": "same_author",
+            "same_author": true,
+            "confidence": 0.8,
+            "detailed_analysis": [],
+        }))
+        .unwrap();
+        history.insert(&history.entry(&analysis, [b"a", b"b"], Some("case-1"))).unwrap();
+        let context = JobContext {
+            history: Some(web::Data::new(history)),
+            ..context()
+        };
+        let jobs = scheduled(vec![job("purge", JobTask::RetentionPurge { keep_days: 1 })], context);
+
+        // Stored just now, so kept
+        let run = jobs.run_now("purge").unwrap();
+        assert!(run.succeeded, "{}", run.message);
+        assert_eq!(run.message, "deleted 0 comparisons past the 1-day retention");
+        assert_eq!(jobs.context.history.as_ref().unwrap().case("case-1").unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,67 @@
+use std::io;
+use std::net::TcpListener;
+#[cfg(unix)]
+use std::os::unix::net::UnixListener;
+use std::path::Path;
+
+pub enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener),
+}
+
+// First descriptor systemd passes (SD_LISTEN_FDS_START)
+#[cfg(unix)]
+const LISTEN_FDS_START: i32 = 3;
+
+/// Sockets passed by systemd socket activation, empty when the process wasn't
+/// started that way.
+#[cfg(unix)]
+pub fn inherited() -> Vec<Listener> {
+    use std::os::fd::{FromRawFd, IntoRawFd};
+
+    let env = |name: &str| std::env::var(name).ok().and_then(|v| v.parse::<u32>().ok());
+    if env("LISTEN_PID") != Some(std::process::id()) {
+        return Vec::new();
+    }
+    let count = env("LISTEN_FDS").unwrap_or(0) as i32;
+    // Not meant for any child processes
+    for name in ["LISTEN_PID", "LISTEN_FDS", "LISTEN_FDNAMES"] {
+        std::env::remove_var(name);
+    }
+
+    (LISTEN_FDS_START..LISTEN_FDS_START + count)
+        .map(|fd| {
+            // SAFETY: systemd hands these descriptors to this process, open and owned by no one else
+            let tcp = unsafe { TcpListener::from_raw_fd(fd) };
+            // Only internet sockets have an address std can represent
+            if tcp.local_addr().is_ok() {
+                Listener::Tcp(tcp)
+            } else {
+                Listener::Unix(unsafe { UnixListener::from_raw_fd(tcp.into_raw_fd()) })
+            }
+        })
+        .collect()
+}
+
+#[cfg(not(unix))]
+pub fn inherited() -> Vec<Listener> {
+    Vec::new()
+}
+
+/// Removes a socket file left behind by an earlier run, which would make
+/// binding fail. Anything other than a socket is left alone.
+#[cfg(unix)]
+pub fn remove_stale_socket(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => std::fs::remove_file(path),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(not(unix))]
+pub fn remove_stale_socket(_path: &Path) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "Unix sockets are not supported on this platform"))
+}
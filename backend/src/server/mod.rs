@@ -0,0 +1,1810 @@
+use crate::baseline::Baseline;
+use crate::comparison::{compare_extracted, compare_with_options, Analysis, CompareOptions};
+use crate::config::{validate_weights, Config, CONFIG_ENV};
+use crate::embedding::Embedder;
+use crate::features::{TextFeatures, FEATURE_SCHEMA_VERSION};
+use crate::history::History;
+use crate::hooks::{HookContext, Hooks};
+use crate::plugins::Plugins;
+use crate::preprocess::Pipeline;
+use crate::schema::{upgrade_features, SchemaError};
+use crate::scoring::{Backend, Scorers};
+use crate::signing::ResultSigner;
+use crate::token_cache;
+use crate::tokenizer::{self, Tokenizer, TokenizerPool, TokenizerSpec};
+use experiments::{Experiments, API_KEY_HEADER};
+use jobs::{JobContext, Jobs};
+use listen::Listener;
+use rate_limit::RateLimiter;
+use ready::Readiness;
+use reload::Reloadable;
+use shadow::Shadow;
+use snapshots::Snapshots;
+use telemetry::Telemetry;
+use tenants::Tenants;
+use webhooks::Webhooks;
+use actix_cors::Cors;
+use actix_web::{error, middleware, post, web, App, HttpRequest, HttpServer, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use std::time::Duration;
+
+mod anomaly;
+mod anonymize;
+mod authors;
+#[cfg(feature = "embed-frontend")]
+mod assets;
+mod batch;
+mod capabilities;
+mod cases;
+mod cluster;
+mod demo;
+mod experiments;
+mod explain;
+mod export;
+mod history;
+mod jobs;
+mod listen;
+mod overlap;
+mod plugins;
+mod preprocess;
+mod projection;
+mod record;
+mod queue;
+mod rate_limit;
+mod ready;
+mod reload;
+mod schemas;
+mod shadow;
+mod snapshots;
+mod telemetry;
+mod tenants;
+mod verify;
+mod webhooks;
+
+#[cfg(feature = "embed-frontend")]
+use assets::configure as configure_frontend;
+
+#[cfg(not(feature = "embed-frontend"))]
+fn configure_frontend(_cfg: &mut web::ServiceConfig) {}
+
+pub const MAX_PAYLOAD_BYTES: usize = 2 * 1024 * 1024;
+// Dictionary load plus the first tokenization; going over is logged, not fatal
+const STARTUP_BUDGET: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct ComparisonQuery {
+    /// May be omitted when `features` is given.
+    #[serde(default)]
+    pub text1: String,
+    #[serde(default)]
+    pub text2: String,
+    /// Precomputed feature vectors, compared instead of the texts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub features: Option<ImportedFeatures>,
+    /// Overrides the configured preprocessing steps for this request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preprocessing: Option<Vec<String>>,
+    /// Tokenize with this configured or built-in dictionary instead of IPADIC.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dictionary: Option<String>,
+    /// Attach per-token POS details and feature buckets to the response.
+    #[serde(default)]
+    pub debug: bool,
+    /// Attach the most similar and most different sentence pairs and the
+    /// per-sentence style timeline.
+    #[serde(default)]
+    pub verbose: bool,
+    /// Attach this many top shared and exclusive content words per list.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vocabulary: Option<usize>,
+    /// Report how far meaning-preserving edits to text2 move the verdict.
+    #[serde(default)]
+    pub robustness: bool,
+    /// Compare word usage over this many most frequent words instead of the configured number.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mfw: Option<NonZeroUsize>,
+    /// Scoring backend for this request; the configured default when omitted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scoring: Option<Backend>,
+    /// Genres of text1 and text2, if known; differing ones are warned about.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub genres: Option<[String; 2]>,
+    /// Aspect weights for this request, over the configured ones.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weights: Option<BTreeMap<String, f64>>,
+    /// Same-author threshold for this request; such requests are left out
+    /// of decision-profile experiments.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub same_author_above: Option<f64>,
+    /// Files the stored result under this case, for `/cases/{id}/report`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub case: Option<String>,
+}
+
+/// Feature vectors extracted elsewhere, e.g. offline with `extract_features`.
+/// Older schema versions are migrated on arrival.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(try_from = "DeclaredFeatures")]
+pub struct ImportedFeatures {
+    pub feature_schema_version: u32,
+    pub text1: TextFeatures,
+    pub text2: TextFeatures,
+}
+
+#[derive(Deserialize)]
+struct DeclaredFeatures {
+    feature_schema_version: u32,
+    text1: serde_json::Value,
+    text2: serde_json::Value,
+}
+
+impl TryFrom<DeclaredFeatures> for ImportedFeatures {
+    type Error = SchemaError;
+
+    fn try_from(declared: DeclaredFeatures) -> std::result::Result<Self, SchemaError> {
+        Ok(ImportedFeatures {
+            feature_schema_version: FEATURE_SCHEMA_VERSION,
+            text1: upgrade_features(declared.text1, declared.feature_schema_version)?,
+            text2: upgrade_features(declared.text2, declared.feature_schema_version)?,
+        })
+    }
+}
+
+fn request_pipeline(steps: Option<&[String]>, config: &Config) -> Result<Pipeline> {
+    Pipeline::from_names(steps.unwrap_or(&config.preprocessing)).map_err(error::ErrorBadRequest)
+}
+
+// Tokenizers come from the app state's pool, so picking a dictionary only
+// costs a load the first time it is used
+fn request_tokenizer(name: Option<&str>, config: &Config, tokenizers: &TokenizerPool) -> Result<&'static Tokenizer> {
+    let spec = match name {
+        Some(name) => config
+            .tokenizer_spec(name)
+            .ok_or_else(|| error::ErrorBadRequest(format!("unknown dictionary '{}'", name)))?,
+        None => TokenizerSpec::default(),
+    };
+    tokenizers.get(&spec).map_err(error::ErrorInternalServerError)
+}
+
+// For the endpoints without a `dictionary` choice
+fn default_tokenizer(tokenizers: &TokenizerPool) -> Result<&'static Tokenizer> {
+    tokenizers.get(&TokenizerSpec::default()).map_err(error::ErrorInternalServerError)
+}
+
+fn request_scorer(scorers: &Scorers, backend: Option<Backend>) -> Result<&dyn crate::scoring::Scorer> {
+    scorers.get(backend).ok_or_else(|| {
+        let backend = backend.unwrap_or_default();
+        error::ErrorBadRequest(format!("scoring backend '{}' is not configured", backend.name()))
+    })
+}
+
+// The server config with the request's overrides of it applied
+fn request_config(body: &ComparisonQuery, mut config: Arc<Config>) -> Result<Arc<Config>> {
+    if body.mfw.is_some() {
+        // Part of the effective config, so the snapshot records it too
+        Arc::make_mut(&mut config).word_usage.mfw = body.mfw;
+    }
+    if let Some(weights) = &body.weights {
+        validate_weights(weights).map_err(error::ErrorBadRequest)?;
+        Arc::make_mut(&mut config).weights.extend(weights.clone());
+    }
+    if let Some(threshold) = body.same_author_above {
+        if !(0.0..=1.0).contains(&threshold) {
+            return Err(error::ErrorBadRequest("same_author_above must be between 0 and 1"));
+        }
+        Arc::make_mut(&mut config).decision.same_author_above = threshold;
+    }
+    Ok(config)
+}
+
+#[post("/compare")]
+// One extractor per optional piece of shared state
+#[allow(clippy::too_many_arguments)]
+async fn compare_texts(
+    request: HttpRequest,
+    body: web::Json<ComparisonQuery>,
+    config: web::Data<Reloadable<Config>>,
+    scorers: web::Data<Reloadable<Scorers>>,
+    tokenizers: web::Data<TokenizerPool>,
+    embedder: Option<web::Data<Embedder>>,
+    baseline: Option<web::Data<Reloadable<Baseline>>>,
+    signer: Option<web::Data<ResultSigner>>,
+    snapshots: Option<web::Data<Snapshots>>,
+    telemetry: Option<web::Data<Telemetry>>,
+    history: Option<web::Data<History>>,
+    shadow: Option<web::Data<Shadow>>,
+    experiments: Option<web::Data<Experiments>>,
+    plugins: Option<web::Data<Plugins>>,
+    tenants: Option<web::Data<Tenants>>,
+    hooks: Option<web::Data<Hooks>>,
+) -> Result<web::Json<Analysis>> {
+    let scorers = scorers.current();
+    let scorer = request_scorer(&scorers, body.scoring)?;
+    let config = request_config(&body, config.current())?;
+    let baseline = baseline.map(|baseline| baseline.current());
+    let tenant = tenants.as_ref().and_then(|tenants| tenants.of_request(&request));
+    if let Some(case) = &body.case {
+        cases::validate_case(case).map_err(error::ErrorBadRequest)?;
+    }
+
+    let (mut analysis, inputs) = if let Some(imported) = &body.features {
+        if body.debug || body.verbose || body.robustness || body.vocabulary.is_some() || body.dictionary.is_some() {
+            return Err(error::ErrorBadRequest(
+                "debug, verbose, vocabulary, robustness and dictionary need texts, not feature vectors",
+            ));
+        }
+        let mut analysis = compare_extracted(&imported.text1, &imported.text2, &config, scorer);
+        if let Some(baseline) = &baseline {
+            baseline.annotate(&mut analysis.detailed_analysis);
+        }
+        let inputs = [&imported.text1, &imported.text2].map(|f| serde_json::to_vec(f).unwrap_or_default());
+        (analysis, inputs)
+    } else {
+        let pipeline = request_pipeline(body.preprocessing.as_deref(), &config)?;
+        let tokenizer = request_tokenizer(body.dictionary.as_deref(), &config, &tokenizers)?;
+
+        let texts = [pipeline.apply(&body.text1), pipeline.apply(&body.text2)];
+        let tenant_plugins = plugins.as_ref().zip(tenant).and_then(|(plugins, tenant)| plugins.for_tenant(tenant));
+        let (backend, genres) = (scorer.backend(), body.genres.clone());
+        let (debug, verbose, vocabulary, robustness) = (body.debug, body.verbose, body.vocabulary, body.robustness);
+        // Extraction, plugins and the embedding model all keep a worker busy
+        let (analysis, texts) = web::block({
+            let (scorers, config, embedder) = (scorers.clone(), config.clone(), embedder.clone());
+            move || {
+                let steps = pipeline.names();
+                let options = CompareOptions {
+                    // The scorer checked above
+                    scorer: scorers.get(Some(backend)).unwrap_or(scorers.default_scorer()),
+                    debug,
+                    verbose,
+                    vocabulary,
+                    robustness,
+                    embedder: embedder.as_ref().map(|e| e.get_ref()),
+                    plugins: tenant_plugins.as_deref(),
+                    baseline: baseline.as_deref(),
+                    genres: genres.as_ref().map(|[genre1, genre2]| [genre1.as_str(), genre2.as_str()]),
+                    preprocessing: &steps,
+                };
+                compare_with_options(&texts[0], &texts[1], tokenizer, &config, options).map(|analysis| (analysis, texts))
+            }
+        })
+        .await?
+        .map_err(error::ErrorInternalServerError)?;
+        // Requests sending feature vectors aren't shadowed: the candidate may extract differently
+        if let Some(shadow) = shadow.filter(|shadow| shadow.sampled(&texts[0], &texts[1])) {
+            shadow::spawn(shadow, texts, tokenizer, embedder.clone(), &analysis);
+        }
+        (analysis, [body.text1.as_bytes().to_vec(), body.text2.as_bytes().to_vec()])
+    };
+
+    // A request's own threshold is never overridden by an experiment arm
+    if let Some(experiments) = experiments.filter(|_| body.same_author_above.is_none()) {
+        let api_key = request.headers().get(API_KEY_HEADER).and_then(|value| value.to_str().ok());
+        experiments.apply(&mut analysis, api_key);
+    }
+    // Last, so the hook sees the verdict the client would have got
+    if let Some(hooks) = hooks {
+        let tenant = tenant.map(str::to_string);
+        let lengths = body.features.is_none().then(|| [body.text1.chars().count(), body.text2.chars().count()]);
+        let genres = body.genres.clone();
+        // A script may use its whole operation budget
+        analysis = web::block(move || {
+            let context = HookContext {
+                tenant: tenant.as_deref(),
+                lengths,
+                genres: genres.as_ref().map(|[genre1, genre2]| [genre1.as_str(), genre2.as_str()]),
+            };
+            hooks.apply(&mut analysis, &context);
+            analysis
+        })
+        .await?;
+    }
+    if let Some(telemetry) = &telemetry {
+        let lengths = body.features.is_none().then(|| [body.text1.chars().count(), body.text2.chars().count()]);
+        telemetry.record(&analysis, lengths);
+    }
+    if let Some(snapshots) = &snapshots {
+        analysis.config_snapshot = Some(snapshots.record(&config, body.preprocessing.as_deref(), scorer.backend()));
+    }
+    if let Some(signer) = &signer {
+        signer.sign(&mut analysis, [&inputs[0], &inputs[1]]);
+    }
+    if let Some(history) = history {
+        // Stored as returned, signature included; a failed write doesn't fail the comparison
+        let entry = history.entry(&analysis, [&inputs[0], &inputs[1]], body.case.as_deref());
+        if let Err(e) = web::block(move || history.insert(&entry)).await? {
+            eprintln!("Failed to store a comparison: {}", e);
+        }
+    }
+    Ok(web::Json(analysis))
+}
+
+/// Everything the handlers read, built once from the configuration and
+/// shared by every worker.
+#[derive(Clone)]
+struct AppState {
+    config: web::Data<Reloadable<Config>>,
+    scorers: web::Data<Reloadable<Scorers>>,
+    tokenizers: web::Data<TokenizerPool>,
+    readiness: web::Data<Readiness>,
+    rate_limiter: web::Data<RateLimiter>,
+    snapshots: web::Data<Snapshots>,
+    embedder: Option<web::Data<Embedder>>,
+    baseline: Option<web::Data<Reloadable<Baseline>>>,
+    signer: Option<web::Data<ResultSigner>>,
+    telemetry: Option<web::Data<Telemetry>>,
+    history: Option<web::Data<History>>,
+    shadow: Option<web::Data<Shadow>>,
+    experiments: Option<web::Data<Experiments>>,
+    plugins: Option<web::Data<Plugins>>,
+    tenants: Option<web::Data<Tenants>>,
+    hooks: Option<web::Data<Hooks>>,
+    webhooks: Option<web::Data<Webhooks>>,
+    jobs: Option<web::Data<Jobs>>,
+}
+
+impl AppState {
+    fn new(config: Config, tokenizers: TokenizerPool) -> std::io::Result<AppState> {
+        let readiness = Readiness::default();
+        readiness.mark_ready(tokenizer::warm_up(&tokenizers).map_err(|e| std::io::Error::other(e.to_string()))?);
+        // The reference corpus is tokenized once here rather than per request
+        let tokenizer = tokenizers.get(&TokenizerSpec::default()).map_err(|e| std::io::Error::other(e.to_string()))?;
+        let scorers = Scorers::from_config(&config, tokenizer)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+        let embedder = Embedder::from_config(&config.embedding)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?
+            .map(web::Data::new);
+        let baseline = Baseline::from_config(&config, tokenizer)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?
+            .map(|baseline| web::Data::new(Reloadable::new(baseline)));
+        let signer = ResultSigner::from_config(&config.signing)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?
+            .map(web::Data::new);
+        let telemetry = Telemetry::from_config(&config.telemetry)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?
+            .map(web::Data::new);
+        let history = History::from_config(&config.history)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?
+            .map(web::Data::new);
+        let shadow = Shadow::from_config(&config.shadow, tokenizer)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?
+            .map(web::Data::new);
+        let experiments = Experiments::from_config(&config.experiments, config.decision).map(web::Data::new);
+        let plugins = Plugins::from_config(&config.plugins, &config.tenants)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?
+            .map(web::Data::new);
+        let tenants = Tenants::from_config(&config.tenants).map(web::Data::new);
+        let hooks = Hooks::from_config(&config.hooks)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?
+            .map(web::Data::new);
+        let webhooks = Webhooks::from_config(&config.webhooks)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?
+            .map(web::Data::new);
+        let config = web::Data::new(Reloadable::new(config));
+        let scorers = web::Data::new(Reloadable::new(scorers));
+        let tokenizers = web::Data::new(tokenizers);
+        let context = JobContext {
+            config: config.clone(),
+            scorers: scorers.clone(),
+            tokenizers: tokenizers.clone(),
+            baseline: baseline.clone(),
+            history: history.clone(),
+            webhooks: webhooks.clone(),
+        };
+        let jobs = Jobs::from_config(&config.current(), context)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?
+            .map(web::Data::new);
+        if let Some(jobs) = &jobs {
+            Jobs::start(jobs.clone())?;
+        }
+        Ok(AppState {
+            config,
+            scorers,
+            tokenizers,
+            readiness: web::Data::new(readiness),
+            rate_limiter: web::Data::new(RateLimiter::default()),
+            snapshots: web::Data::new(Snapshots::default()),
+            embedder,
+            baseline,
+            signer,
+            telemetry,
+            history,
+            shadow,
+            experiments,
+            plugins,
+            tenants,
+            hooks,
+            webhooks,
+            jobs,
+        })
+    }
+
+    fn configure(&self, cfg: &mut web::ServiceConfig) {
+        cfg.app_data(self.config.clone())
+            .app_data(self.scorers.clone())
+            .app_data(self.tokenizers.clone())
+            .app_data(self.readiness.clone())
+            .app_data(self.rate_limiter.clone())
+            .app_data(self.snapshots.clone())
+            .app_data(web::JsonConfig::default().limit(MAX_PAYLOAD_BYTES));
+        // Left out entirely when unconfigured: no Semantic Style aspect, no
+        // percentiles, nothing stored, no signatures, no events
+        if let Some(embedder) = &self.embedder {
+            cfg.app_data(embedder.clone());
+        }
+        if let Some(baseline) = &self.baseline {
+            cfg.app_data(baseline.clone());
+        }
+        if let Some(signer) = &self.signer {
+            cfg.app_data(signer.clone());
+        }
+        if let Some(telemetry) = &self.telemetry {
+            cfg.app_data(telemetry.clone());
+        }
+        if let Some(history) = &self.history {
+            cfg.app_data(history.clone());
+        }
+        if let Some(shadow) = &self.shadow {
+            cfg.app_data(shadow.clone());
+        }
+        if let Some(experiments) = &self.experiments {
+            cfg.app_data(experiments.clone());
+        }
+        if let Some(plugins) = &self.plugins {
+            // Uploads are raw module bytes, not JSON
+            cfg.app_data(plugins.clone()).app_data(web::PayloadConfig::new(MAX_PAYLOAD_BYTES));
+        }
+        if let Some(tenants) = &self.tenants {
+            cfg.app_data(tenants.clone());
+        }
+        if let Some(hooks) = &self.hooks {
+            cfg.app_data(hooks.clone());
+        }
+        if let Some(webhooks) = &self.webhooks {
+            cfg.app_data(webhooks.clone());
+        }
+        if let Some(jobs) = &self.jobs {
+            cfg.app_data(jobs.clone());
+        }
+        cfg.service(compare_texts)
+            .service(batch::compare_batch)
+            .service(explain::explain_comparison)
+            .service(record::record_comparison)
+            .service(preprocess::preprocess)
+            .service(overlap::overlap)
+            .service(anonymize::anonymize)
+            .service(anomaly::anomaly_score)
+            .service(authors::register_author)
+            .service(authors::list_authors)
+            .service(authors::identify)
+            .service(export::export)
+            .service(projection::projection)
+            .service(cluster::cluster_texts)
+            .service(verify::verify)
+            .service(snapshots::snapshot)
+            .service(history::comparisons)
+            .service(history::comparison)
+            .service(history::explain_comparison)
+            .service(cases::case_report)
+            .service(jobs::list_jobs)
+            .service(capabilities::capabilities)
+            .service(ready::ready)
+            .service(schemas::schema)
+            .service(telemetry::telemetry)
+            .service(experiments::experiments)
+            .service(plugins::upload_plugin)
+            .service(plugins::list_plugins)
+            .service(demo::demo);
+        configure_frontend(cfg);
+    }
+}
+
+/// Serves the API on an already bound listener, without config file watching
+/// or systemd sockets; for running the server inside another crate's tests.
+pub fn serve(config: Config, listener: std::net::TcpListener) -> std::io::Result<actix_web::dev::Server> {
+    let state = AppState::new(config, TokenizerPool::default())?;
+    let server = HttpServer::new(move || {
+        App::new()
+            .wrap(middleware::from_fn(rate_limit::limit_rate))
+            .wrap(Cors::permissive())
+            .wrap(middleware::Compress::default())
+            .configure(|cfg| state.configure(cfg))
+    });
+    Ok(server.listen(listener)?.run())
+}
+
+pub async fn run() -> std::io::Result<()> {
+    let config = Config::from_env().map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+    if config.token_cache.capacity > 0 {
+        token_cache::configure(config.token_cache.capacity);
+    }
+    // Nothing is bound until the dictionary is loaded and has tokenized once,
+    // so no request waits on (or fails during) warm-up, and `/ready` answers
+    // only once it is done
+    let addresses = config.listen.clone();
+    let state = AppState::new(config, TokenizerPool::default())?;
+    let warm_up = state.readiness.warm_up().expect("warmed up by AppState::new");
+    println!(
+        "Loaded IPADIC in {} ms, first tokenization took {} ms",
+        warm_up.dictionary_load.as_millis(),
+        warm_up.first_tokenization.as_millis()
+    );
+    if warm_up.dictionary_load + warm_up.first_tokenization > STARTUP_BUDGET {
+        eprintln!("Tokenizer warm-up exceeded the {} s startup budget", STARTUP_BUDGET.as_secs());
+    }
+    if let Some(path) = std::env::var_os(CONFIG_ENV) {
+        let path: std::path::PathBuf = path.into();
+        #[cfg(unix)]
+        reload::on_hangup(path.clone(), state.config.clone(), state.scorers.clone(), state.tokenizers.clone());
+        reload::watch(path, state.config.clone(), state.scorers.clone(), state.tokenizers.clone());
+    }
+
+    let mut server = HttpServer::new(move || {
+        let cors = Cors::permissive(); // For development only
+
+        App::new()
+            // Innermost, so CORS headers and compression apply to 429s too
+            .wrap(middleware::from_fn(rate_limit::limit_rate))
+            .wrap(cors)
+            // Gzip, Brotli or Zstandard per Accept-Encoding; verbose analyses are large
+            .wrap(middleware::Compress::default())
+            .configure(|cfg| state.configure(cfg))
+    });
+
+    let inherited = listen::inherited();
+    if !inherited.is_empty() {
+        println!("Listening on {} socket(s) passed by systemd", inherited.len());
+        for listener in inherited {
+            server = match listener {
+                Listener::Tcp(listener) => server.listen(listener)?,
+                #[cfg(unix)]
+                Listener::Unix(listener) => server.listen_uds(listener)?,
+            };
+        }
+    } else {
+        if let Some(path) = &addresses.unix_socket {
+            listen::remove_stale_socket(path)?;
+            #[cfg(unix)]
+            {
+                server = server.bind_uds(path)?;
+            }
+            println!("Listening on {}", path.display());
+        }
+        if let Some(address) = addresses.tcp_address() {
+            server = server.bind(address)?;
+            println!("Listening on http://{}", address);
+        }
+    }
+    server.run().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comparison::Verdict;
+    use crate::tokenizer::new_tokenizer;
+    use actix_web::{test, App};
+    use std::sync::OnceLock;
+
+    // One pool for every test, so IPADIC is loaded once
+    fn tokenizers() -> web::Data<TokenizerPool> {
+        static TOKENIZERS: OnceLock<web::Data<TokenizerPool>> = OnceLock::new();
+        TOKENIZERS.get_or_init(|| web::Data::new(TokenizerPool::default())).clone()
+    }
+
+    // The fixtures are a sentence or two, which the default config compares in
+    // short-text mode; these tests are about the usual aspects
+    fn weighted_config() -> Config {
+        let mut config = Config::default();
+        config.short_text.max_chars = 0;
+        config
+    }
+
+    // Pairs of a sentence or two, with whether the usual aspects take them for
+    // the same author and the confidence they should exceed
+    const COMPARE_CASES: &[(&str, &str, bool, f64)] = &[
+        // Test case 1: Similar casual writing style
+        (
+            "私は今日公園に行きました。とても楽しかったです。",
+            "私は昨日公園で遊びました。本当に楽しかったです。",
+            true,  // same author
+            0.5    // minimum confidence
+        ),
+        
+        // Test case 2: Different formality levels
+        (
+            "本日の会議にて、以下の事項が決定致しました。ご確認ください。",
+            "やっほー！今日めっちゃ楽しかった！またあそぼーね！",
+            false,
+            0.2
+        ),
+
+        // Test case 3: Similar formal business style
+        (
+            "第三四半期の売上実績について報告いたします。前年比110%となっております。",
+            "本年度の業績見通しについてご報告申し上げます。予想を上回る結果となっております。",
+            true,
+            0.5
+        ),
+
+        // Test case 4: Similar academic style
+        (
+            "本研究では、言語処理における形態素解析の重要性について考察する。",
+            "自然言語処理において、形態素解析は基礎的かつ重要な要素である。",
+            true,
+            0.5
+        ),
+
+        // Test case 5: Different context but similar casual style
+        (
+            "昨日の映画はとても面白かった！また見に行きたいな。",
+            "今日のライブ最高だった！また行きたいな！",
+            true,
+            0.5
+        ),
+
+        // Test case 6: Mixed styles
+        (
+            "明日の天気予報によると、関東地方は晴れるでしょう。",
+            "あしたは晴れるみたい！外で遊べるね！",
+            false,
+            0.2
+        ),
+
+        // Test case 7: Short vs Long text
+        (
+            "はい、そうですね。そのとおりです。",
+            "申し訳ございませんが、その件については改めて詳しくご説明させていただく必要があるかと存じます。",
+            false,
+            0.1
+        ),
+
+        // Test case 8: Similar technical style
+        (
+            "システムの実装にはRustを使用し、非同期処理を実現しました。",
+            "バックエンドの開発ではRustを採用し、並行処理を実装しています。",
+            true,
+            0.5
+        ),
+
+        // Test case 9: Different emotional expression
+        (
+            "今日は最悪な一日だった...もう嫌になっちゃう...",
+            "今日は最高の一日！とっても楽しかった！",
+            false,
+            0.3
+        ),
+
+        // Test case 10: Similar poetic style
+        (
+            "桜舞い散る春の日に、心が躍る。",
+            "紅葉舞う秋の夕べ、心が癒される。",
+            true,
+            0.5
+        ),
+    ];
+
+    async fn assert_compare_cases(config: Config, cases: &[(&str, &str, bool, f64)]) {
+        // Initialize the app
+        let app = test::init_service(
+            App::new()
+                .app_data(tokenizers())
+                .app_data(web::Data::new(Reloadable::new(config)))
+                .app_data(web::Data::new(Reloadable::new(Scorers::default())))
+                .service(compare_texts),
+        )
+        .await;
+
+        for (i, (text1, text2, expected_same_author, min_confidence)) in cases.iter().enumerate() {
+            let payload = ComparisonQuery {
+                text1: text1.to_string(),
+                text2: text2.to_string(),
+                ..Default::default()
+            };
+
+            let req = test::TestRequest::post()
+                .uri("/compare")
+                .set_json(&payload)
+                .to_request();
+        
+            let resp: Analysis = test::call_and_read_body_json(&app, req).await;
+        
+            assert_eq!(
+                resp.same_author, 
+                *expected_same_author,
+                "Test case {} failed: expected same_author={}, got={}", 
+                i + 1, 
+                expected_same_author, 
+                resp.same_author
+            );
+
+            assert!(
+                resp.confidence > *min_confidence,
+                "Test case {} failed: confidence {} is not > {}", 
+                i + 1, 
+                resp.confidence, 
+                min_confidence
+            );
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_compare_texts() {
+        // Every pair is under 80 characters, so the default config scores them
+        // in short-text mode. Every pair's n-gram difference reaches its cap and
+        // none uses emoji, so only sentence endings and script shares set them
+        // apart: too little to call 2, 6, 7 and 9 different.
+        let cases: Vec<_> = COMPARE_CASES
+            .iter()
+            .enumerate()
+            .map(|(i, &(text1, text2, same_author, min_confidence))| {
+                (text1, text2, same_author || [2, 6, 7, 9].contains(&(i + 1)), min_confidence)
+            })
+            .collect();
+        assert_compare_cases(Config::default(), &cases).await;
+    }
+
+    #[actix_rt::test]
+    async fn test_compare_texts_weighted() {
+        assert_compare_cases(weighted_config(), COMPARE_CASES).await;
+    }
+
+    #[actix_rt::test]
+    async fn test_rate_limit() {
+        let mut config = Config::default();
+        config.rate_limit = crate::config::RateLimitConfig {
+            requests_per_minute: 60,
+            burst: 1,
+        };
+        let config = web::Data::new(Reloadable::new(config));
+        let app = test::init_service(
+            App::new()
+                .wrap(middleware::from_fn(rate_limit::limit_rate))
+                .app_data(config.clone())
+                .app_data(web::Data::new(RateLimiter::default()))
+                .service(capabilities::capabilities)
+                .service(ready::ready),
+        )
+        .await;
+        let get = |uri: &str| test::TestRequest::get().uri(uri).to_request();
+
+        assert!(test::call_service(&app, get("/capabilities")).await.status().is_success());
+        let resp = test::call_service(&app, get("/capabilities")).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(resp.headers().get("retry-after").unwrap(), "1");
+        // Readiness probes are never limited
+        let resp = test::call_service(&app, get("/ready")).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::SERVICE_UNAVAILABLE);
+
+        // Limits follow the reloaded config
+        config.replace(Config::default());
+        assert!(test::call_service(&app, get("/capabilities")).await.status().is_success());
+    }
+
+    #[actix_rt::test]
+    async fn test_ready() {
+        let app = test::init_service(App::new().service(ready::ready)).await;
+        let req = test::TestRequest::get().uri("/ready").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::SERVICE_UNAVAILABLE);
+
+        let readiness = Readiness::default();
+        readiness.mark_ready(tokenizer::warm_up(&TokenizerPool::default()).unwrap());
+        let app = test::init_service(App::new().app_data(web::Data::new(readiness)).service(ready::ready)).await;
+        let req = test::TestRequest::get().uri("/ready").to_request();
+        let report: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_matches_schema("GET /ready", &report);
+        assert_eq!(report["ready"], true);
+        assert!(report["dictionary_load_ms"].is_u64());
+    }
+
+    // Building the state inside the actix runtime must not start the
+    // Postgres client's own runtime there; an unreachable server is an error
+    #[cfg(feature = "history")]
+    #[actix_rt::test]
+    async fn test_postgres_history_in_runtime() {
+        let mut config = Config::default();
+        config.history.database = Some("postgres://author-comparer@127.0.0.1:1/history".to_string());
+        let error = AppState::new(config, TokenizerPool::default()).err().expect("nothing listens on port 1");
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[actix_rt::test]
+    async fn test_demo_page() {
+        let app = test::init_service(App::new().service(demo::demo)).await;
+
+        let req = test::TestRequest::get().uri("/demo").to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        let body = std::str::from_utf8(&body).unwrap();
+
+        assert!(body.contains("<textarea"));
+        assert!(body.contains("/compare"));
+    }
+
+    #[actix_rt::test]
+    async fn test_capabilities() {
+        let app = test::init_service(App::new().service(capabilities::capabilities)).await;
+
+        let req = test::TestRequest::get().uri("/capabilities").to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+        let aspects = resp["aspects"].as_array().unwrap();
+        assert!(aspects.iter().any(|a| a["name"] == "Word Usage" && a["default_weight"] == 3.0));
+        assert_eq!(resp["model_version"], crate::MODEL_VERSION);
+        assert_eq!(resp["feature_schema_version"], FEATURE_SCHEMA_VERSION);
+        assert_eq!(resp["limits"]["max_payload_bytes"], MAX_PAYLOAD_BYTES);
+    }
+
+    #[actix_rt::test]
+    async fn test_insufficient_data() {
+        let app = test::init_service(
+            App::new()
+                .app_data(tokenizers())
+                .app_data(web::Data::new(Reloadable::new(weighted_config())))
+                .app_data(web::Data::new(Reloadable::new(Scorers::default())))
+                .service(compare_texts),
+        )
+        .await;
+
+        let payload = ComparisonQuery {
+            text1: "はい".to_string(),
+            text2: "私は今日公園に行きました。とても楽しかったです。".to_string(),
+            ..Default::default()
+        };
+        let req = test::TestRequest::post()
+            .uri("/compare")
+            .set_json(&payload)
+            .to_request();
+        let resp: Analysis = test::call_and_read_body_json(&app, req).await;
+
+        assert_eq!(resp.verdict, Verdict::InsufficientData);
+        assert!(!resp.same_author);
+        assert!(resp.detailed_analysis.is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn test_request_preprocessing() {
+        let config = Config {
+            preprocessing: vec!["remove_quotes".to_string()],
+            ..weighted_config()
+        };
+        let app = test::init_service(
+            App::new()
+                .app_data(tokenizers())
+                .app_data(web::Data::new(Reloadable::new(config)))
+                .app_data(web::Data::new(Reloadable::new(Scorers::default())))
+                .service(compare_texts),
+        )
+        .await;
+
+        // Without quote removal the reply is only a quote plus a short acknowledgement
+        let quoted = "> 私は今日公園に行きました。とても楽しかったです。\nはい";
+        let payload = ComparisonQuery {
+            text1: quoted.to_string(),
+            text2: "私は今日公園に行きました。とても楽しかったです。".to_string(),
+            ..Default::default()
+        };
+        let req = test::TestRequest::post().uri("/compare").set_json(&payload).to_request();
+        let resp: Analysis = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp.verdict, Verdict::InsufficientData);
+
+        let payload = ComparisonQuery {
+            preprocessing: Some(Vec::new()),
+            ..payload
+        };
+        let req = test::TestRequest::post().uri("/compare").set_json(&payload).to_request();
+        let resp: Analysis = test::call_and_read_body_json(&app, req).await;
+        assert_ne!(resp.verdict, Verdict::InsufficientData);
+
+        let payload = ComparisonQuery {
+            preprocessing: Some(vec!["stem".to_string()]),
+            ..payload
+        };
+        let req = test::TestRequest::post().uri("/compare").set_json(&payload).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+        // Dictionaries are picked by name, from the config or the built-in ones
+        let payload = ComparisonQuery {
+            preprocessing: None,
+            dictionary: Some("ipadic".to_string()),
+            ..payload
+        };
+        let req = test::TestRequest::post().uri("/compare").set_json(&payload).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+
+        let payload = ComparisonQuery {
+            dictionary: Some("jumandic".to_string()),
+            ..payload
+        };
+        let req = test::TestRequest::post().uri("/compare").set_json(&payload).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_rt::test]
+    async fn test_imported_features() {
+        let app = test::init_service(
+            App::new()
+                .app_data(tokenizers())
+                .app_data(web::Data::new(Reloadable::new(weighted_config())))
+                .app_data(web::Data::new(Reloadable::new(Scorers::default())))
+                .service(compare_texts),
+        )
+        .await;
+        let tokenizer = new_tokenizer().unwrap();
+        let text1 = "私は今日公園に行きました。とても楽しかったです。";
+        let text2 = "明日は図書館で本を読むつもりです。静かで落ち着きます。";
+
+        let by_text = ComparisonQuery {
+            text1: text1.to_string(),
+            text2: text2.to_string(),
+            ..Default::default()
+        };
+        let req = test::TestRequest::post().uri("/compare").set_json(&by_text).to_request();
+        let expected: Analysis = test::call_and_read_body_json(&app, req).await;
+
+        let mut payload = serde_json::json!({
+            "features": {
+                "feature_schema_version": FEATURE_SCHEMA_VERSION,
+                "text1": crate::extract_features(text1, &tokenizer).unwrap(),
+                "text2": crate::extract_features(text2, &tokenizer).unwrap(),
+            }
+        });
+        let req = test::TestRequest::post().uri("/compare").set_json(&payload).to_request();
+        let resp: Analysis = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp.verdict, expected.verdict);
+        assert!((resp.confidence - expected.confidence).abs() < 1e-9);
+
+        payload["features"]["feature_schema_version"] = serde_json::json!(FEATURE_SCHEMA_VERSION + 1);
+        let req = test::TestRequest::post().uri("/compare").set_json(&payload).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_rt::test]
+    async fn test_preprocess() {
+        let app = test::init_service(
+            App::new()
+                .app_data(tokenizers())
+                .app_data(web::Data::new(Reloadable::new(Config::default())))
+                .service(preprocess::preprocess),
+        )
+        .await;
+
+        let payload = preprocess::PreprocessQuery {
+            text: "> 引用です。\n公園に行きました。".to_string(),
+            preprocessing: Some(vec!["remove_quotes".to_string()]),
+        };
+        let req = test::TestRequest::post().uri("/preprocess").set_json(&payload).to_request();
+        let resp: preprocess::PreprocessOutput = test::call_and_read_body_json(&app, req).await;
+
+        assert_eq!(resp.steps, vec!["remove_quotes"]);
+        assert_eq!(resp.text, "公園に行きました。");
+        assert!(resp.tokens.iter().any(|t| t.text == "公園" && !t.pos.is_empty()));
+    }
+
+    #[actix_rt::test]
+    async fn test_debug_tokens() {
+        let app = test::init_service(
+            App::new()
+                .app_data(tokenizers())
+                .app_data(web::Data::new(Reloadable::new(weighted_config())))
+                .app_data(web::Data::new(Reloadable::new(Scorers::default())))
+                .service(compare_texts),
+        )
+        .await;
+
+        let mut payload = ComparisonQuery {
+            text1: "私は今日公園に行きました。".to_string(),
+            text2: "私は昨日公園で遊びました。".to_string(),
+            ..Default::default()
+        };
+        let req = test::TestRequest::post().uri("/compare").set_json(&payload).to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert!(resp.get("debug").is_none());
+
+        payload.debug = true;
+        let req = test::TestRequest::post().uri("/compare").set_json(&payload).to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let tokens = resp["debug"]["text1"].as_array().unwrap();
+        let park = tokens.iter().find(|t| t["text"] == "公園").unwrap();
+        assert_eq!(park["pos"][0], "名詞");
+        assert_eq!(park["buckets"], serde_json::json!(["word"]));
+        assert_eq!(resp["scoring"], "weighted");
+
+        // Only the weighted backend is configured here
+        payload.scoring = Some(Backend::Neural);
+        let req = test::TestRequest::post().uri("/compare").set_json(&payload).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_rt::test]
+    async fn test_overlap() {
+        let app = test::init_service(App::new().service(overlap::overlap)).await;
+
+        let req = test::TestRequest::post()
+            .uri("/overlap")
+            .set_json(serde_json::json!({
+                "text1": "本日の会議にて、以下の事項が決定致しました。",
+                "text2": "議事録：本日の会議にて以下の事項が決定致しました",
+            }))
+            .to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+        let spans = resp["spans"].as_array().unwrap();
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0]["text1"]["start"], 0);
+        assert_eq!(spans[0]["text2"]["start"], 4);
+        assert_eq!(resp["coverage1"], 1.0);
+    }
+
+    #[actix_rt::test]
+    async fn test_anonymize() {
+        let app = test::init_service(App::new().app_data(tokenizers()).service(anonymize::anonymize)).await;
+        let tokenizer = new_tokenizer().unwrap();
+        let profile = crate::build_profile(&["とても楽しかった。とても笑った。"], &tokenizer).unwrap();
+
+        let req = test::TestRequest::post()
+            .uri("/anonymize")
+            .set_json(serde_json::json!({ "text": "とても眠い。", "profile": profile }))
+            .to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+        let features = resp["identifying_features"].as_array().unwrap();
+        assert!(features.iter().any(|f| f["feature"] == "とても" && f["kind"] == "pet_word"));
+
+        // The same profile, read from the profile store by author
+        let mut config = weighted_config();
+        config.profiles.directory = std::env::temp_dir().join(format!("author-comparer-anonymize-{}", std::process::id()));
+        crate::profile_store::ProfileStore::new(&config.profiles.directory)
+            .save("natsume", &profile)
+            .unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(tokenizers())
+                .app_data(web::Data::new(Reloadable::new(config.clone())))
+                .service(anonymize::anonymize),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri("/anonymize")
+            .set_json(serde_json::json!({ "text": "とても眠い。", "author": "natsume" }))
+            .to_request();
+        let stored: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(stored, resp);
+
+        let req = test::TestRequest::post()
+            .uri("/anonymize")
+            .set_json(serde_json::json!({ "text": "とても眠い。", "author": "mori" }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), actix_web::http::StatusCode::NOT_FOUND);
+        std::fs::remove_dir_all(&config.profiles.directory).unwrap();
+    }
+
+    #[actix_rt::test]
+    async fn test_export() {
+        let app = test::init_service(App::new().app_data(tokenizers()).service(export::export)).await;
+
+        let req = test::TestRequest::post()
+            .uri("/export")
+            .set_json(serde_json::json!({
+                "texts": [
+                    { "author": "a", "text": "今日は晴れです。" },
+                    { "author": "b", "text": "明日は雨だろう。" },
+                ],
+                "format": "libsvm",
+                "vocabulary": 5,
+            }))
+            .to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        let lines: Vec<&str> = std::str::from_utf8(&body).unwrap().lines().collect();
+
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("1 ") && lines[1].starts_with("2 "));
+    }
+
+    #[actix_rt::test]
+    async fn test_projection() {
+        let app = test::init_service(App::new().app_data(tokenizers()).service(projection::projection)).await;
+
+        let req = test::TestRequest::post()
+            .uri("/projection")
+            .set_json(serde_json::json!({
+                "texts": [
+                    { "author": "a", "text": "今日は晴れです。とても暖かいです。" },
+                    { "author": "a", "text": "明日も晴れです。とても嬉しいです。" },
+                    { "author": "b", "text": "雨だろう。傘を持って行け！" },
+                ],
+            }))
+            .to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+        let points = resp["points"].as_array().unwrap();
+        assert_eq!(points.len(), 3);
+        assert_eq!(points[2]["label"], "b");
+        assert!(resp["explained_variance"][0].as_f64().unwrap() > 0.0);
+    }
+
+    #[actix_rt::test]
+    async fn test_cluster() {
+        let app = test::init_service(
+            App::new()
+                .app_data(tokenizers())
+                .app_data(web::Data::new(Reloadable::new(weighted_config())))
+                .app_data(web::Data::new(Reloadable::new(Scorers::default())))
+                .service(cluster::cluster_texts),
+        )
+        .await;
+        let texts = serde_json::json!([
+            { "author": "a", "text": "私は今日公園に行きました。とても楽しかったです。" },
+            { "author": "a", "text": "私は昨日公園で遊びました。とても楽しかったです。" },
+            { "author": "b", "text": "マジで最高！！まじでヤバいって！！" },
+        ]);
+
+        let req = test::TestRequest::post()
+            .uri("/cluster")
+            .set_json(serde_json::json!({ "texts": texts }))
+            .to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["tree"]["size"], 3);
+        assert!(resp["tree"]["distance"].as_f64().unwrap() > 0.0);
+
+        let req = test::TestRequest::post()
+            .uri("/cluster")
+            .set_json(serde_json::json!({ "texts": texts, "format": "newick" }))
+            .to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        let newick = std::str::from_utf8(&body).unwrap();
+        assert!(newick.starts_with('(') && newick.ends_with(';'));
+    }
+
+    #[cfg(feature = "history")]
+    #[actix_rt::test]
+    async fn test_case_report() {
+        use sha2::{Digest, Sha256};
+        use std::io::Read;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(tokenizers())
+                .app_data(web::Data::new(Reloadable::new(weighted_config())))
+                .app_data(web::Data::new(Reloadable::new(Scorers::default())))
+                .app_data(web::Data::new(History::open(":memory:").unwrap()))
+                .service(compare_texts)
+                .service(cases::case_report),
+        )
+        .await;
+        for case in [Some("2024-017"), None, Some("2024-017")] {
+            let req = test::TestRequest::post()
+                .uri("/compare")
+                .set_json(serde_json::json!({
+                    "text1": "私は毎朝コーヒーを飲みます。それから新聞を読んで、散歩に出かけます。",
+                    "text2": "彼は夜にお茶を飲む。そして本を読み、早く寝てしまう。",
+                    "case": case,
+                }))
+                .to_request();
+            assert!(test::call_service(&app, req).await.status().is_success());
+        }
+
+        let req = test::TestRequest::get().uri("/cases/2024-017/report").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+        assert_eq!(resp.headers().get("content-type").unwrap(), "application/zip");
+        let body = test::read_body(resp).await;
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(body.to_vec())).unwrap();
+        let mut read = |name: &str| {
+            let mut bytes = Vec::new();
+            archive.by_name(name).unwrap().read_to_end(&mut bytes).unwrap();
+            bytes
+        };
+        let (json, html, manifest) = (read("report.json"), read("report.html"), read("manifest.json"));
+        let report: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(report["case"], "2024-017");
+        assert_eq!(report["comparisons"].as_array().unwrap().len(), 2);
+        assert!(report["comparisons"].as_array().unwrap().iter().all(|c| c["case"] == "2024-017"));
+        assert_eq!(report["versions"]["model_version"], crate::MODEL_VERSION);
+        let manifest: serde_json::Value = serde_json::from_slice(&manifest).unwrap();
+        assert_eq!(manifest["files"]["report.json"], format!("{:x}", Sha256::digest(&json)));
+        assert_eq!(manifest["files"]["report.html"], format!("{:x}", Sha256::digest(&html)));
+        assert!(manifest.get("signature").is_none());
+        assert!(archive.by_name("manifest.sig").is_err());
+
+        let req = test::TestRequest::get().uri("/cases/2024-018/report").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), actix_web::http::StatusCode::NOT_FOUND);
+        let req = test::TestRequest::get().uri("/cases/a%20b/report").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), actix_web::http::StatusCode::BAD_REQUEST);
+        let req = test::TestRequest::post()
+            .uri("/compare")
+            .set_json(serde_json::json!({"text1": "今日は晴れ。", "text2": "明日は雨。", "case": "../etc"}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), actix_web::http::StatusCode::BAD_REQUEST);
+    }
+
+    #[cfg(feature = "history")]
+    #[actix_rt::test]
+    async fn test_explain_stored() {
+        let history = web::Data::new(History::open(":memory:").unwrap());
+        let app = test::init_service(
+            App::new()
+                .app_data(tokenizers())
+                .app_data(web::Data::new(Reloadable::new(weighted_config())))
+                .app_data(web::Data::new(Reloadable::new(Scorers::default())))
+                .app_data(history.clone())
+                .service(compare_texts)
+                .service(history::explain_comparison),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri("/compare")
+            .set_json(serde_json::json!({
+                "text1": "私は毎朝コーヒーを飲みます。それから新聞を読んで、散歩に出かけます。",
+                "text2": "彼は夜にお茶を飲む。そして本を読み、早く寝てしまう。",
+                "case": "2024-017",
+            }))
+            .to_request();
+        let served: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let id = history.case("2024-017").unwrap()[0].id;
+
+        let req = test::TestRequest::get().uri(&format!("/comparisons/{}/explain", id)).to_request();
+        let explanation: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_matches_schema("GET /comparisons/{id}/explain", &explanation);
+        let aspects = explanation["aspects"].as_array().unwrap();
+        assert_eq!(aspects.len(), served["detailed_analysis"].as_array().unwrap().len());
+        assert!(aspects.iter().all(|a| a["values"].is_null()));
+        let total: f64 = aspects.iter().map(|a| a["contribution"].as_f64().unwrap()).sum();
+        assert!((total - explanation["confidence"].as_f64().unwrap()).abs() < 1e-9);
+
+        let req = test::TestRequest::get().uri(&format!("/comparisons/{}/explain", id + 1)).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), actix_web::http::StatusCode::NOT_FOUND);
+    }
+
+    #[actix_rt::test]
+    async fn test_verify_unconfigured() {
+        let app = test::init_service(App::new().service(verify::verify)).await;
+
+        let req = test::TestRequest::post()
+            .uri("/verify")
+            .set_json(serde_json::json!({ "confidence": 0.5 }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+    }
+
+    #[cfg(feature = "history")]
+    #[actix_rt::test]
+    async fn test_history() {
+        let app = test::init_service(
+            App::new()
+                .app_data(tokenizers())
+                .app_data(web::Data::new(Reloadable::new(weighted_config())))
+                .app_data(web::Data::new(Reloadable::new(Scorers::default())))
+                .app_data(web::Data::new(History::open(":memory:").unwrap()))
+                .service(compare_texts)
+                .service(history::comparisons)
+                .service(history::comparison),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/compare")
+            .set_json(serde_json::json!({
+                "text1": "私は毎朝コーヒーを飲みます。それから新聞を読んで、散歩に出かけます。",
+                "text2": "彼は夜にお茶を飲む。そして本を読み、早く寝てしまう。",
+            }))
+            .to_request();
+        let served: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+        let req = test::TestRequest::get().uri("/comparisons").to_request();
+        let list: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_matches_schema("GET /comparisons", &list);
+        assert_eq!(list["comparisons"].as_array().unwrap().len(), 1);
+        assert_eq!(list["comparisons"][0]["verdict"], served["verdict"]);
+        let id = list["comparisons"][0]["id"].as_i64().unwrap();
+
+        let req = test::TestRequest::get().uri(&format!("/comparisons/{}", id)).to_request();
+        let stored: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_matches_schema("GET /comparisons/{id}", &stored);
+        assert_eq!(stored["analysis"], served);
+
+        let req = test::TestRequest::get().uri(&format!("/comparisons/{}", id + 1)).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::NOT_FOUND);
+    }
+
+    #[actix_rt::test]
+    async fn test_request_overrides() {
+        let app = test::init_service(
+            App::new()
+                .app_data(tokenizers())
+                .app_data(web::Data::new(Reloadable::new(weighted_config())))
+                .app_data(web::Data::new(Reloadable::new(Scorers::default())))
+                .service(compare_texts),
+        )
+        .await;
+        let texts = serde_json::json!({
+            "text1": "私は今日公園に行きました。とても楽しかったです。友達とたくさん話しました。",
+            "text2": "本日の会議にて、以下の事項が決定致しました。詳細は追って連絡致します。",
+        });
+        let with = |extra: serde_json::Value| {
+            let mut body = texts.clone();
+            body.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+            test::TestRequest::post().uri("/compare").set_json(body).to_request()
+        };
+
+        let lenient: Analysis = test::call_and_read_body_json(&app, with(serde_json::json!({ "same_author_above": 0.0 }))).await;
+        assert!(lenient.same_author);
+        let strict: Analysis = test::call_and_read_body_json(&app, with(serde_json::json!({ "same_author_above": 1.0 }))).await;
+        assert!(!strict.same_author);
+
+        let weighted: Analysis =
+            test::call_and_read_body_json(&app, with(serde_json::json!({ "weights": { "Word Usage": 0.0 } }))).await;
+        let word_usage = weighted.detailed_analysis.iter().find(|d| d.aspect == "Word Usage").unwrap();
+        assert_eq!(word_usage.weight, 0.0);
+
+        for invalid in [serde_json::json!({ "weights": { "Handwriting": 1.0 } }), serde_json::json!({ "same_author_above": 1.5 })] {
+            let resp = test::call_service(&app, with(invalid)).await;
+            assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_compare_batch() {
+        let app = test::init_service(
+            App::new()
+                .app_data(tokenizers())
+                .app_data(web::Data::new(Reloadable::new(weighted_config())))
+                .app_data(web::Data::new(Reloadable::new(Scorers::default())))
+                .service(batch::compare_batch),
+        )
+        .await;
+        let pairs = serde_json::json!([
+            { "id": "p1", "text1": "私は今日公園に行きました。", "text2": "私は昨日公園で遊びました。" },
+            { "text1": "今日は晴れです。", "text2": "明日は雨だろう。" },
+            { "text1": "はい", "text2": "いいえ" },
+        ]);
+
+        // A zero budget still makes progress, one pair at a time
+        let req = test::TestRequest::post()
+            .uri("/compare/batch")
+            .set_json(serde_json::json!({ "pairs": pairs, "max_duration_ms": 0 }))
+            .to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["results"].as_array().unwrap().len(), 1);
+        assert_eq!(resp["results"][0]["id"], "p1");
+        assert_eq!(resp["remaining"], 2);
+        let token = resp["continuation"].as_str().unwrap().to_string();
+
+        let req = test::TestRequest::post()
+            .uri("/compare/batch")
+            .set_json(serde_json::json!({ "pairs": pairs, "continuation": token }))
+            .to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let results = resp["results"].as_array().unwrap();
+        assert_eq!(results.iter().map(|r| r["index"].clone()).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(results[1]["analysis"]["verdict"], "insufficient_data");
+        assert!(resp.get("continuation").is_none());
+
+        let req = test::TestRequest::post()
+            .uri("/compare/batch")
+            .set_json(serde_json::json!({ "pairs": &pairs.as_array().unwrap()[..2], "continuation": token }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+        // Every two texts, with the confidence matrix
+        let texts = serde_json::json!([
+            { "id": "a", "text": "私は今日公園に行きました。とても楽しかったです。" },
+            { "id": "b", "text": "私は昨日公園で遊びました。とても面白かったです。" },
+            { "id": "c", "text": "猫が好きだ！本当に可愛いから、毎日見ている。" },
+        ]);
+        let req = test::TestRequest::post()
+            .uri("/compare/batch")
+            .set_json(serde_json::json!({ "texts": texts }))
+            .to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let results = resp["results"].as_array().unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[1]["id"], "a|c");
+        assert_eq!(results[1]["texts"], serde_json::json!([0, 2]));
+        let matrix = resp["matrix"].as_array().unwrap();
+        assert_eq!(matrix[0][0], 1.0);
+        assert_eq!(matrix[2][1], results[2]["analysis"]["confidence"]);
+        assert_matches_schema("POST /compare/batch", &resp);
+
+        let req = test::TestRequest::post()
+            .uri("/compare/batch")
+            .set_json(serde_json::json!({ "pairs": pairs, "texts": texts }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_rt::test]
+    async fn test_compression() {
+        let app = test::init_service(
+            App::new()
+                .app_data(tokenizers())
+                .wrap(middleware::Compress::default())
+                .service(capabilities::capabilities),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/capabilities")
+            .insert_header(("accept-encoding", "gzip"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.headers().get("content-encoding").unwrap(), "gzip");
+    }
+
+    #[actix_rt::test]
+    async fn test_config_snapshot() {
+        let mut config = weighted_config();
+        config.history.database = Some("comparisons.db".to_string());
+        let app = test::init_service(
+            App::new()
+                .app_data(tokenizers())
+                .app_data(web::Data::new(Reloadable::new(config)))
+                .app_data(web::Data::new(Reloadable::new(Scorers::default())))
+                .app_data(web::Data::new(Snapshots::default()))
+                .service(compare_texts)
+                .service(snapshots::snapshot),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/compare")
+            .set_json(serde_json::json!({
+                "text1": "私は今日公園に行きました。",
+                "text2": "私は昨日公園で遊びました。",
+                "preprocessing": ["normalize"],
+            }))
+            .to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let id = resp["config_snapshot"].as_str().unwrap();
+
+        let req = test::TestRequest::get().uri(&format!("/config/snapshots/{}", id)).to_request();
+        let snapshot: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(snapshot["config"]["preprocessing"], serde_json::json!(["normalize"]));
+        assert_eq!(snapshot["model_version"], crate::MODEL_VERSION);
+        // Database URLs, webhook secrets and paths stay out of the unauthenticated response
+        for secret in ["history", "signing", "webhooks", "embedding"] {
+            assert!(snapshot["config"].get(secret).is_none(), "{}", secret);
+        }
+
+        let req = test::TestRequest::get().uri("/config/snapshots/unknown").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::NOT_FOUND);
+    }
+
+    #[actix_rt::test]
+    async fn test_compare_record() {
+        let mut config = weighted_config();
+        config.records.inputs = crate::record::InputPolicy::Raw;
+        let app = test::init_service(
+            App::new()
+                .app_data(tokenizers())
+                .app_data(web::Data::new(Reloadable::new(config)))
+                .app_data(web::Data::new(Reloadable::new(Scorers::default())))
+                .service(record::record_comparison),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/compare/record")
+            .set_json(serde_json::json!({
+                "text1": "私は今日公園に行きました。",
+                "text2": "猫がとても好きだ！本当に可愛いから。",
+                "preprocessing": ["normalize"],
+            }))
+            .to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        let record = crate::record::ExperimentRecord::from_json(std::str::from_utf8(&body).unwrap()).unwrap();
+        assert_eq!(record.inputs[0].text.as_deref(), Some("私は今日公園に行きました。"));
+        assert_eq!(record.config.preprocessing, ["normalize"]);
+        let tokenizer = tokenizers().get(&TokenizerSpec::default()).unwrap();
+        let replayed = record.replay(tokenizer, &record.config, &crate::scoring::WeightedScorer, None).unwrap();
+        assert_eq!(replayed.confidence, record.analysis.confidence);
+    }
+
+    #[actix_rt::test]
+    async fn test_baseline_percentiles() {
+        let texts = [("a", "私は今日公園に行きました。"), ("b", "猫がとても好きだ！本当に可愛いから。")]
+            .map(|(author, sentence)| crate::datasets::LabeledText {
+                author: author.to_string(),
+                text: sentence.repeat(30),
+                genre: None,
+            });
+        let baseline = Baseline::build(&texts, &Config::default(), &new_tokenizer().unwrap()).unwrap();
+        assert!(baseline.pair_count() > 0);
+
+        let app = test::init_service(
+            App::new()
+                .app_data(tokenizers())
+                .app_data(web::Data::new(Reloadable::new(weighted_config())))
+                .app_data(web::Data::new(Reloadable::new(Scorers::default())))
+                .app_data(web::Data::new(Reloadable::new(baseline)))
+                .service(compare_texts),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/compare")
+            .set_json(serde_json::json!({
+                "text1": "私は今日公園に行きました。",
+                "text2": "私は昨日公園で遊びました。",
+            }))
+            .to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let word_usage = &resp["detailed_analysis"][0];
+        assert_eq!(word_usage["aspect"], "Word Usage");
+        let percentile = word_usage["percentile"].as_f64().unwrap();
+        assert!((0.0..=1.0).contains(&percentile));
+    }
+
+    #[actix_rt::test]
+    async fn test_explain() {
+        let app = test::init_service(
+            App::new()
+                .app_data(tokenizers())
+                .app_data(web::Data::new(Reloadable::new(weighted_config())))
+                .service(explain::explain_comparison),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/compare/explain")
+            .set_json(serde_json::json!({
+                "text1": "私は今日公園に行きました。とても楽しかったです。",
+                "text2": "猫が好きだ！本当に可愛いから、毎日見ている。",
+            }))
+            .to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let aspects = resp["aspects"].as_array().unwrap();
+        assert_eq!(aspects[0]["aspect"], "Word Usage");
+        let total: f64 = aspects.iter().map(|a| a["contribution"].as_f64().unwrap()).sum();
+        assert!((total - resp["confidence"].as_f64().unwrap()).abs() < 1e-9);
+        assert!(aspects[1]["counterfactual"].as_str().unwrap().starts_with("if sentence length matched"));
+    }
+
+    #[actix_rt::test]
+    async fn test_telemetry() {
+        let app = test::init_service(
+            App::new()
+                .app_data(tokenizers())
+                .app_data(web::Data::new(Reloadable::new(weighted_config())))
+                .app_data(web::Data::new(Reloadable::new(Scorers::default())))
+                .app_data(web::Data::new(Telemetry::new(3600, 24, Some("secret".to_string()))))
+                .service(compare_texts)
+                .service(telemetry::telemetry),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/compare")
+            .set_json(serde_json::json!({ "text1": "私は今日公園に行きました。", "text2": "私は昨日公園で遊びました。" }))
+            .to_request();
+        let analysis: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+        let req = test::TestRequest::get().uri("/admin/telemetry").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::UNAUTHORIZED);
+
+        let req = test::TestRequest::get()
+            .uri("/admin/telemetry")
+            .insert_header(("Authorization", "Bearer secret"))
+            .to_request();
+        let report: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(report["total"]["comparisons"], 1);
+        assert_eq!(report["total"]["mean_confidence"], analysis["confidence"]);
+        assert_eq!(report["total"]["text_length"]["counts"][0], 2);
+        assert!(!report.to_string().contains("公園"));
+        assert_matches_schema("GET /admin/telemetry", &report);
+    }
+
+    #[actix_rt::test]
+    async fn test_anomaly() {
+        let app = test::init_service(
+            App::new()
+                .app_data(tokenizers())
+                .app_data(web::Data::new(Reloadable::new(weighted_config())))
+                .app_data(web::Data::new(Reloadable::new(Scorers::default())))
+                .service(anomaly::anomaly_score),
+        )
+        .await;
+        let text = "私は今日公園に行きました。とても楽しかったです。";
+
+        // No reference corpus is configured
+        let req = test::TestRequest::post().uri("/anomaly").set_json(serde_json::json!({ "text": text })).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+        let samples = ["私は昨日公園で遊びました。とても楽しかったです。", "私は毎朝公園を歩きます。空気がおいしいです。", "私は週末に海へ行きました。波が高かったです。"];
+        let tokenizer = crate::tokenizer::new_tokenizer().unwrap();
+        let profile = crate::profile::build_profile(&samples, &tokenizer).unwrap().with_samples(&samples);
+        let req = test::TestRequest::post()
+            .uri("/anomaly")
+            .set_json(serde_json::json!({ "text": text, "profile": profile }))
+            .to_request();
+        let report: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(report["reference_samples"], 3);
+        assert_eq!(report["features"].as_array().unwrap().len(), crate::scoring::MAHALANOBIS_FEATURES.len());
+        assert_matches_schema("POST /anomaly", &report);
+    }
+
+    #[actix_rt::test]
+    async fn test_authors() {
+        let mut config = weighted_config();
+        config.profiles.directory = std::env::temp_dir().join(format!("author-comparer-authors-{}", std::process::id()));
+        let app = test::init_service(
+            App::new()
+                .app_data(tokenizers())
+                .app_data(web::Data::new(Reloadable::new(config.clone())))
+                .app_data(web::Data::new(Reloadable::new(Scorers::default())))
+                .service(authors::register_author)
+                .service(authors::list_authors)
+                .service(authors::identify),
+        )
+        .await;
+
+        // Nobody registered yet
+        let text = "私は今日公園に行きました。とても楽しかったです。";
+        let req = test::TestRequest::post().uri("/identify").set_json(serde_json::json!({ "text": text })).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+        let known = [
+            ("walker", ["私は昨日公園で遊びました。とても楽しかったです。", "私は毎朝公園を歩きます。とても気持ちがいいです。"]),
+            ("cat", ["猫が好きだ！本当に可愛いから、毎日見ている。", "犬もいいけど、やっぱり猫だ！撫でると落ち着く。"]),
+        ];
+        for (author, texts) in known {
+            let req = test::TestRequest::post()
+                .uri("/authors")
+                .set_json(serde_json::json!({ "author": author, "texts": texts }))
+                .to_request();
+            let registered: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+            assert_eq!(registered["sample_count"], 2);
+        }
+        let req = test::TestRequest::get().uri("/authors").to_request();
+        let listed: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(listed["authors"].as_array().unwrap().len(), 2);
+        assert_matches_schema("GET /authors", &listed);
+
+        let req = test::TestRequest::post().uri("/identify").set_json(serde_json::json!({ "text": text })).to_request();
+        let identification: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let candidates = identification["candidates"].as_array().unwrap();
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(candidates[0]["author"], "walker");
+        assert!(candidates[0]["confidence"].as_f64() >= candidates[1]["confidence"].as_f64());
+        let margin = candidates[0]["confidence"].as_f64().unwrap() - candidates[1]["confidence"].as_f64().unwrap();
+        assert!((identification["rank_margin"]["margin"].as_f64().unwrap() - margin).abs() < 1e-9);
+        assert!(identification["rank_margin"]["decisiveness"].is_string());
+        assert_matches_schema("POST /identify", &identification);
+        assert_eq!(identification["mode"], "open");
+
+        // Nobody reaches a threshold of 1 in an open set; a closed set still names the top candidate
+        for (mode, best_match) in [("open", serde_json::Value::Null), ("closed", serde_json::json!("walker"))] {
+            let req = test::TestRequest::post()
+                .uri("/identify")
+                .set_json(serde_json::json!({ "text": text, "mode": mode, "reject_below": 1.0 }))
+                .to_request();
+            let identification: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+            assert_eq!(identification["best_match"], best_match);
+        }
+
+        let req = test::TestRequest::post()
+            .uri("/identify")
+            .set_json(serde_json::json!({ "text": text, "authors": ["nobody"] }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), actix_web::http::StatusCode::NOT_FOUND);
+        std::fs::remove_dir_all(&config.profiles.directory).unwrap();
+    }
+
+    #[actix_rt::test]
+                .app_data(tokenizers())
+    async fn test_experiments() {
+        let config: Config = toml::from_str("[experiments.lenient]\nfraction = 1.0\nsame_author_above = 0.0\n").unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(tokenizers())
+                .app_data(web::Data::new(Reloadable::new(weighted_config())))
+                .app_data(web::Data::new(Reloadable::new(Scorers::default())))
+                .app_data(web::Data::new(Experiments::from_config(&config.experiments, config.decision).unwrap()))
+                .service(compare_texts)
+                .service(experiments::experiments),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/compare")
+            .insert_header((API_KEY_HEADER, "team-a"))
+            .set_json(serde_json::json!({ "text1": "私は今日公園に行きました。", "text2": "猫が好きだ！本当に可愛いから、毎日見ている。" }))
+            .to_request();
+        let analysis: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(analysis["experiment"], serde_json::json!({ "arm": "lenient", "by_api_key": true }));
+        assert_eq!(analysis["verdict"], "same_author");
+
+        let req = test::TestRequest::get().uri("/experiments").to_request();
+        let report: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(report["arms"]["lenient"]["requests"], 1);
+        assert_eq!(report["arms"]["lenient"]["verdicts"]["same_author"], 1);
+        assert_eq!(report["arms"]["control"]["requests"], 0);
+        assert_matches_schema("GET /experiments", &report);
+    }
+
+    // Standalone schema for one route, with the shared definitions its refs point to
+    fn route_schema(route: &str) -> serde_json::Value {
+        let schemas = serde_json::to_value(schemas::response_schemas()).unwrap();
+        serde_json::json!({
+            "allOf": [schemas["responses"][route]],
+            "definitions": schemas["definitions"],
+        })
+    }
+
+    fn assert_matches_schema(route: &str, response: &serde_json::Value) {
+        let schema = route_schema(route);
+        let validator = jsonschema::JSONSchema::compile(&schema).expect("invalid schema");
+        let errors: Vec<String> = match validator.validate(response) {
+            Ok(()) => return,
+            Err(errors) => errors.map(|e| e.to_string()).collect(),
+        };
+        panic!("{} response does not match its schema: {:?}", route, errors);
+    }
+
+    #[actix_rt::test]
+    async fn test_response_schemas() {
+        let app = test::init_service(
+            App::new()
+                .app_data(tokenizers())
+                .app_data(web::Data::new(Reloadable::new(weighted_config())))
+                .app_data(web::Data::new(Reloadable::new(Scorers::default())))
+                .service(compare_texts)
+                .service(overlap::overlap)
+                .service(capabilities::capabilities)
+                .service(schemas::schema),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/schema").to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert!(resp["responses"]["POST /compare"].is_object());
+        assert!(resp["definitions"]["Analysis"]["properties"]["verdict"].is_object());
+
+        let req = test::TestRequest::post()
+            .uri("/compare")
+            .set_json(serde_json::json!({
+                "text1": "私は今日公園に行きました。とても楽しかったです。",
+                "text2": "猫が好きだ！本当に可愛いから、毎日見ている。",
+                "debug": true,
+                "verbose": true,
+                "vocabulary": 3,
+                "robustness": true,
+            }))
+            .to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_matches_schema("POST /compare", &resp);
+
+        let req = test::TestRequest::post()
+            .uri("/overlap")
+            .set_json(serde_json::json!({ "text1": "本日の会議にて決定致しました。", "text2": "本日の会議にて決定致しました" }))
+            .to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_matches_schema("POST /overlap", &resp);
+
+        let req = test::TestRequest::get().uri("/capabilities").to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_matches_schema("GET /capabilities", &resp);
+
+        // A response missing a required field is caught
+        let mut broken = resp.clone();
+        broken.as_object_mut().unwrap().remove("model_version");
+        assert!(!jsonschema::is_valid(&route_schema("GET /capabilities"), &broken));
+    }
+}
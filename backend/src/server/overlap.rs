@@ -0,0 +1,20 @@
+use crate::overlap::{find_overlaps, Overlap, DEFAULT_MIN_LENGTH};
+use actix_web::{post, web};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct OverlapQuery {
+    pub text1: String,
+    pub text2: String,
+    #[serde(default = "default_min_length")]
+    pub min_length: usize,
+}
+
+fn default_min_length() -> usize {
+    DEFAULT_MIN_LENGTH
+}
+
+#[post("/overlap")]
+pub async fn overlap(body: web::Json<OverlapQuery>) -> web::Json<Overlap> {
+    web::Json(find_overlaps(&body.text1, &body.text2, body.min_length))
+}
@@ -0,0 +1,51 @@
+use super::tenants::Tenants;
+use crate::plugins::{PluginError, PluginInfo, Plugins};
+use actix_web::{error, get, post, web, HttpRequest, Result};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct PluginList {
+    pub plugins: Vec<PluginInfo>,
+}
+
+fn plugins_of(plugins: Option<web::Data<Plugins>>) -> Result<web::Data<Plugins>> {
+    plugins.ok_or_else(|| error::ErrorNotFound("plugins are not enabled"))
+}
+
+fn tenant_of(request: &HttpRequest, tenants: Option<web::Data<Tenants>>) -> Result<String> {
+    tenants.ok_or_else(|| error::ErrorUnauthorized("no tenants are configured"))?.require(request)
+}
+
+#[post("/plugins/{name}")]
+pub async fn upload_plugin(
+    request: HttpRequest,
+    name: web::Path<String>,
+    body: web::Bytes,
+    plugins: Option<web::Data<Plugins>>,
+    tenants: Option<web::Data<Tenants>>,
+) -> Result<web::Json<PluginInfo>> {
+    let plugins = plugins_of(plugins)?;
+    let tenant = tenant_of(&request, tenants)?;
+    let name = name.into_inner();
+    // Compiling a module takes a while
+    let uploaded = web::block(move || plugins.upload(&tenant, &name, &body)).await?;
+    uploaded.map(web::Json).map_err(|e| match e {
+        PluginError::TooLarge { .. } => error::ErrorPayloadTooLarge(e),
+        PluginError::Io(_) => error::ErrorInternalServerError(e),
+        PluginError::InvalidName(_) | PluginError::Invalid(_) => error::ErrorBadRequest(e),
+    })
+}
+
+#[get("/plugins")]
+pub async fn list_plugins(
+    request: HttpRequest,
+    plugins: Option<web::Data<Plugins>>,
+    tenants: Option<web::Data<Tenants>>,
+) -> Result<web::Json<PluginList>> {
+    let plugins = plugins_of(plugins)?;
+    let tenant = tenant_of(&request, tenants)?;
+    Ok(web::Json(PluginList {
+        plugins: plugins.list(&tenant),
+    }))
+}
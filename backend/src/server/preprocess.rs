@@ -0,0 +1,42 @@
+use super::reload::Reloadable;
+use super::{default_tokenizer, request_pipeline};
+use crate::config::Config;
+use crate::tokenizer::{tokenize_with_pos, TokenInfo, TokenizerPool};
+use actix_web::{error, post, web, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PreprocessQuery {
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preprocessing: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct PreprocessOutput {
+    // Steps that were applied, in order
+    pub steps: Vec<String>,
+    pub text: String,
+    pub tokens: Vec<TokenInfo>,
+}
+
+/// Dry run of what `/compare` feeds into feature extraction for a single text.
+#[post("/preprocess")]
+pub async fn preprocess(
+    body: web::Json<PreprocessQuery>,
+    config: web::Data<Reloadable<Config>>,
+    tokenizers: web::Data<TokenizerPool>,
+) -> Result<web::Json<PreprocessOutput>> {
+    let config = config.current();
+    let pipeline = request_pipeline(body.preprocessing.as_deref(), &config)?;
+    let tokenizer = default_tokenizer(&tokenizers)?;
+
+    let text = pipeline.apply(&body.text);
+    let tokens = tokenize_with_pos(&text, tokenizer).map_err(error::ErrorInternalServerError)?;
+    Ok(web::Json(PreprocessOutput {
+        steps: pipeline.names().into_iter().map(String::from).collect(),
+        text,
+        tokens,
+    }))
+}
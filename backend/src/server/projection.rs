@@ -0,0 +1,29 @@
+use crate::datasets::LabeledText;
+use crate::export::feature_table;
+use crate::projection::{project, Projection};
+use super::default_tokenizer;
+use crate::tokenizer::TokenizerPool;
+use actix_web::{error, post, web, Result};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ProjectionQuery {
+    pub texts: Vec<LabeledText>,
+    #[serde(default = "default_vocabulary")]
+    pub vocabulary: usize,
+}
+
+fn default_vocabulary() -> usize {
+    100
+}
+
+#[post("/projection")]
+pub async fn projection(
+    body: web::Json<ProjectionQuery>,
+    tokenizers: web::Data<TokenizerPool>,
+) -> Result<web::Json<Projection>> {
+    let tokenizer = default_tokenizer(&tokenizers)?;
+
+    let table = feature_table(&body.texts, body.vocabulary, tokenizer).map_err(error::ErrorInternalServerError)?;
+    Ok(web::Json(project(&table)))
+}
@@ -0,0 +1,291 @@
+use crate::config::{ConfigError, QueueConfig};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::{Mutex, MutexGuard};
+
+#[cfg(feature = "queue")]
+const POSTGRES_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS job_runs (
+    job TEXT NOT NULL,
+    due_at BIGINT NOT NULL,
+    taken_at BIGINT,
+    PRIMARY KEY (job, due_at)
+)";
+
+#[cfg(feature = "queue")]
+const REDIS_QUEUE: &str = "author-comparer:job-runs";
+// Followed by `<job>:<due_at>`, set while a run is queued or recently taken
+#[cfg(feature = "queue")]
+const REDIS_QUEUED: &str = "author-comparer:job-runs:queued:";
+// Long enough for every instance to have queued the run, whatever its clock
+#[cfg(feature = "queue")]
+const REDIS_QUEUED_SECONDS: u64 = 86_400;
+
+/// One scheduled run of a job: its name and the Unix time it came due, in
+/// seconds. Instances with the same `[[jobs]]` compute the same times, so a
+/// run queued by several of them is the same run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueuedRun {
+    pub job: String,
+    pub due_at: u64,
+}
+
+#[derive(Debug)]
+pub struct QueueError(String);
+
+impl fmt::Display for QueueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "job queue: {}", self.0)
+    }
+}
+
+impl std::error::Error for QueueError {}
+
+enum Backend {
+    Memory {
+        queued: VecDeque<QueuedRun>,
+        // The last run queued per job
+        latest: HashMap<String, u64>,
+    },
+    // Connected on first use, from the jobs thread rather than the async
+    // one the queue is opened on
+    #[cfg(feature = "queue")]
+    Unconnected(String),
+    #[cfg(feature = "queue")]
+    Postgres(crate::sync_postgres::Client),
+    #[cfg(feature = "queue")]
+    Redis(redis::Connection),
+}
+
+/// Runs of shared jobs waiting for an instance to take them: in memory for a
+/// single instance, or in Postgres or Redis for several.
+pub struct JobQueue {
+    backend: Mutex<Backend>,
+}
+
+impl fmt::Debug for JobQueue {
+    // URLs may hold a password
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("JobQueue")
+    }
+}
+
+impl JobQueue {
+    /// Opens the configured queue, or queues in memory when none is.
+    pub fn from_config(config: &QueueConfig) -> Result<JobQueue, ConfigError> {
+        match &config.url {
+            Some(url) => JobQueue::open(url),
+            None => Ok(JobQueue::memory()),
+        }
+    }
+
+    pub fn memory() -> JobQueue {
+        JobQueue::with(Backend::Memory {
+            queued: VecDeque::new(),
+            latest: HashMap::new(),
+        })
+    }
+
+    fn with(backend: Backend) -> JobQueue {
+        JobQueue {
+            backend: Mutex::new(backend),
+        }
+    }
+
+    /// `postgres://` and `postgresql://` URLs queue in a `job_runs` table,
+    /// `redis://` and `rediss://` ones in a list. Only the URL is checked
+    /// here: the queue connects when first used, and a failed connection is
+    /// retried on the next use.
+    #[cfg(feature = "queue")]
+    pub fn open(url: &str) -> Result<JobQueue, ConfigError> {
+        if url.starts_with("postgres://") || url.starts_with("postgresql://") {
+            url.parse::<postgres::Config>().map_err(|e| ConfigError::Queue(e.to_string()))?;
+        } else if url.starts_with("redis://") || url.starts_with("rediss://") {
+            redis::Client::open(url).map_err(|e| ConfigError::Queue(e.to_string()))?;
+        } else {
+            return Err(ConfigError::Queue("the url must be a postgres:// or redis:// URL".to_string()));
+        }
+        Ok(JobQueue::with(Backend::Unconnected(url.to_string())))
+    }
+
+    #[cfg(not(feature = "queue"))]
+    pub fn open(_url: &str) -> Result<JobQueue, ConfigError> {
+        Err(ConfigError::Queue("built without the `queue` feature".to_string()))
+    }
+
+    // The backend, connected if it wasn't yet
+    fn backend(&self) -> Result<MutexGuard<'_, Backend>, QueueError> {
+        #[cfg_attr(not(feature = "queue"), allow(unused_mut))]
+        let mut backend = self.backend.lock().unwrap_or_else(|e| e.into_inner());
+        #[cfg(feature = "queue")]
+        if let Backend::Unconnected(url) = &*backend {
+            let connected = if url.starts_with("redis://") || url.starts_with("rediss://") {
+                let connection = redis::Client::open(url.as_str())
+                    .and_then(|client| client.get_connection())
+                    .map_err(|e| QueueError(e.to_string()))?;
+                Backend::Redis(connection)
+            } else {
+                let mut client = crate::sync_postgres::Client::connect(url).map_err(|e| QueueError(e.to_string()))?;
+                client.batch_execute(POSTGRES_SCHEMA).map_err(|e| QueueError(e.to_string()))?;
+                Backend::Postgres(client)
+            };
+            *backend = connected;
+        }
+        Ok(backend)
+    }
+
+    /// Queues `run` unless it was queued before, by this instance or another
+    /// sharing the queue; `false` if it was.
+    pub fn push(&self, run: &QueuedRun) -> Result<bool, QueueError> {
+        let mut backend = self.backend()?;
+        match &mut *backend {
+            Backend::Memory { queued, latest } => {
+                if latest.get(&run.job).is_some_and(|due_at| *due_at >= run.due_at) {
+                    return Ok(false);
+                }
+                latest.insert(run.job.clone(), run.due_at);
+                queued.push_back(run.clone());
+                Ok(true)
+            }
+            #[cfg(feature = "queue")]
+            Backend::Unconnected(_) => unreachable!("connected by backend()"),
+            #[cfg(feature = "queue")]
+            Backend::Postgres(client) => {
+                let due_at = run.due_at as i64;
+                let inserted = client
+                    .execute("INSERT INTO job_runs (job, due_at) VALUES ($1, $2) ON CONFLICT DO NOTHING", &[&run.job, &due_at])
+                    .map_err(|e| QueueError(e.to_string()))?;
+                // Taken runs are only kept to turn away the other instances' copies
+                client
+                    .execute("DELETE FROM job_runs WHERE job = $1 AND due_at < $2 AND taken_at IS NOT NULL", &[&run.job, &due_at])
+                    .map_err(|e| QueueError(e.to_string()))?;
+                Ok(inserted == 1)
+            }
+            #[cfg(feature = "queue")]
+            Backend::Redis(connection) => {
+                let first: Option<String> = redis::cmd("SET")
+                    .arg(format!("{}{}:{}", REDIS_QUEUED, run.job, run.due_at))
+                    .arg(1)
+                    .arg("NX")
+                    .arg("EX")
+                    .arg(REDIS_QUEUED_SECONDS)
+                    .query(connection)
+                    .map_err(|e| QueueError(e.to_string()))?;
+                if first.is_none() {
+                    return Ok(false);
+                }
+                let run = serde_json::to_string(run).map_err(|e| QueueError(e.to_string()))?;
+                redis::cmd("RPUSH").arg(REDIS_QUEUE).arg(run).query::<()>(connection).map_err(|e| QueueError(e.to_string()))?;
+                Ok(true)
+            }
+        }
+    }
+
+    /// Takes the oldest run no instance has taken yet. A taken run is
+    /// never handed out again, even if the instance dies running it.
+    pub fn pop(&self) -> Result<Option<QueuedRun>, QueueError> {
+        let mut backend = self.backend()?;
+        match &mut *backend {
+            Backend::Memory { queued, .. } => Ok(queued.pop_front()),
+            #[cfg(feature = "queue")]
+            Backend::Unconnected(_) => unreachable!("connected by backend()"),
+            #[cfg(feature = "queue")]
+            Backend::Postgres(client) => {
+                use std::time::{SystemTime, UNIX_EPOCH};
+
+                let taken_at = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64);
+                // SKIP LOCKED lets instances polling at once each take a different run
+                let rows = client
+                    .query(
+                        "UPDATE job_runs SET taken_at = $1 WHERE (job, due_at) = (
+                             SELECT job, due_at FROM job_runs WHERE taken_at IS NULL
+                             ORDER BY due_at LIMIT 1 FOR UPDATE SKIP LOCKED
+                         ) RETURNING job, due_at",
+                        &[&taken_at],
+                    )
+                    .map_err(|e| QueueError(e.to_string()))?;
+                rows.first()
+                    .map(|row| {
+                        Ok(QueuedRun {
+                            job: row.try_get(0)?,
+                            due_at: row.try_get::<_, i64>(1)? as u64,
+                        })
+                    })
+                    .transpose()
+                    .map_err(|e: postgres::Error| QueueError(e.to_string()))
+            }
+            #[cfg(feature = "queue")]
+            Backend::Redis(connection) => {
+                let run: Option<String> = redis::cmd("LPOP").arg(REDIS_QUEUE).query(connection).map_err(|e| QueueError(e.to_string()))?;
+                run.map(|run| serde_json::from_str(&run).map_err(|e| QueueError(e.to_string()))).transpose()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(job: &str, due_at: u64) -> QueuedRun {
+        QueuedRun {
+            job: job.to_string(),
+            due_at,
+        }
+    }
+
+    #[test]
+    fn test_memory_queue() {
+        let queue = JobQueue::memory();
+        assert!(queue.push(&run("purge", 3600)).unwrap());
+        assert!(queue.push(&run("other", 60)).unwrap());
+        // Already queued, or older than what was
+        assert!(!queue.push(&run("purge", 3600)).unwrap());
+        assert!(!queue.push(&run("purge", 0)).unwrap());
+
+        assert_eq!(queue.pop().unwrap(), Some(run("purge", 3600)));
+        assert_eq!(queue.pop().unwrap(), Some(run("other", 60)));
+        assert_eq!(queue.pop().unwrap(), None);
+        // Taken, and still not queued again
+        assert!(!queue.push(&run("purge", 3600)).unwrap());
+        assert!(queue.push(&run("purge", 7200)).unwrap());
+    }
+
+    #[test]
+    fn test_queue_url() {
+        assert!(JobQueue::from_config(&QueueConfig::default()).is_ok());
+        assert!(JobQueue::open("mysql://localhost/jobs").is_err());
+    }
+
+    // Opening doesn't connect, so nothing has to listen until the queue is used
+    #[cfg(feature = "queue")]
+    #[test]
+    fn test_queue_connects_on_use() {
+        let queue = JobQueue::open("postgres://author-comparer@127.0.0.1:1/jobs").unwrap();
+        assert!(queue.push(&run("purge", 3600)).is_err());
+        assert!(queue.pop().is_err());
+    }
+
+    // Against a real queue when TEST_QUEUE_URL names one, e.g.
+    // `TEST_QUEUE_URL=redis://127.0.0.1/ cargo test --features queue`. The
+    // test takes every run waiting there, so don't point it at a live queue.
+    #[cfg(feature = "queue")]
+    #[test]
+    fn test_shared_queue() {
+        let Ok(url) = std::env::var("TEST_QUEUE_URL") else {
+            return;
+        };
+        let first = JobQueue::open(&url).unwrap();
+        let second = JobQueue::open(&url).unwrap();
+        while first.pop().unwrap().is_some() {}
+
+        // Runs outlive the test, so each run of it queues a job of its own
+        let job = format!("test-{}", std::process::id());
+        let due_at = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        assert!(first.push(&run(&job, due_at)).unwrap());
+        assert!(!second.push(&run(&job, due_at)).unwrap());
+        assert_eq!(second.pop().unwrap(), Some(run(&job, due_at)));
+        assert_eq!(first.pop().unwrap(), None);
+        assert!(!first.push(&run(&job, due_at)).unwrap());
+    }
+}
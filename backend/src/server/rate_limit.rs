@@ -0,0 +1,107 @@
+use super::experiments::API_KEY_HEADER;
+use super::reload::Reloadable;
+use crate::config::{Config, RateLimitConfig};
+use crate::signing::to_hex;
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::RETRY_AFTER;
+use actix_web::middleware::Next;
+use actix_web::{web, Error, HttpResponse};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Past this many tracked clients, the ones back to a full bucket are dropped
+const MAX_CLIENTS: usize = 10_000;
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// A token bucket per client. The limits are read from the current config on
+/// every request, so a reload changes them without a restart.
+#[derive(Debug, Default)]
+pub struct RateLimiter(Mutex<HashMap<String, Bucket>>);
+
+impl RateLimiter {
+    /// Takes one request from the client's bucket, or returns how long until
+    /// it holds one again.
+    pub fn check(&self, client: &str, limits: &RateLimitConfig, now: Instant) -> Result<(), Duration> {
+        let capacity = limits.capacity() as f64;
+        let per_second = limits.requests_per_minute as f64 / 60.0;
+        let refill = |bucket: &Bucket| capacity.min(bucket.tokens + now.duration_since(bucket.updated).as_secs_f64() * per_second);
+
+        let mut buckets = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if buckets.len() >= MAX_CLIENTS && !buckets.contains_key(client) {
+            buckets.retain(|_, bucket| refill(bucket) < capacity);
+        }
+        let bucket = buckets.entry(client.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+        });
+        bucket.tokens = refill(bucket);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / per_second))
+        }
+    }
+}
+
+// API keys are hashed like tenants' so none is kept in memory
+fn client_of(request: &ServiceRequest) -> String {
+    match request.headers().get(API_KEY_HEADER) {
+        Some(key) => format!("key:{}", to_hex(&Sha256::digest(key.as_bytes()))),
+        None => format!("addr:{}", request.peer_addr().map_or_else(|| "local".to_string(), |addr| addr.ip().to_string())),
+    }
+}
+
+/// Answers 429 with `Retry-After` once a client is over its limit. Probes of
+/// `/ready` are never limited.
+pub async fn limit_rate<B: MessageBody>(
+    request: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, Error> {
+    let limits = request.app_data::<web::Data<Reloadable<Config>>>().map(|config| config.current().rate_limit);
+    let limiter = request.app_data::<web::Data<RateLimiter>>();
+    if let (Some(limits), Some(limiter)) = (limits.filter(RateLimitConfig::is_enabled), limiter) {
+        if request.path() != "/ready" {
+            if let Err(wait) = limiter.check(&client_of(&request), &limits, Instant::now()) {
+                let response = HttpResponse::TooManyRequests()
+                    .insert_header((RETRY_AFTER, wait.as_secs().max(1)))
+                    .body("rate limit exceeded");
+                return Ok(request.into_response(response).map_into_right_body());
+            }
+        }
+    }
+    next.call(request).await.map(ServiceResponse::map_into_left_body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket() {
+        let limiter = RateLimiter::default();
+        let limits = RateLimitConfig {
+            requests_per_minute: 60,
+            burst: 2,
+        };
+        let start = Instant::now();
+        assert!(limiter.check("a", &limits, start).is_ok());
+        assert!(limiter.check("a", &limits, start).is_ok());
+        let wait = limiter.check("a", &limits, start).unwrap_err();
+        assert!((wait.as_secs_f64() - 1.0).abs() < 1e-6);
+        // Other clients have buckets of their own
+        assert!(limiter.check("b", &limits, start).is_ok());
+        // One request a second refills
+        assert!(limiter.check("a", &limits, start + Duration::from_secs(1)).is_ok());
+        assert!(limiter.check("a", &limits, start + Duration::from_secs(1)).is_err());
+    }
+}
@@ -0,0 +1,43 @@
+use crate::tokenizer::WarmUp;
+use actix_web::{get, web, HttpResponse};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::sync::OnceLock;
+
+/// Set once the IPADIC tokenizer has loaded and tokenized its first sentence.
+#[derive(Debug, Default)]
+pub struct Readiness(OnceLock<WarmUp>);
+
+impl Readiness {
+    pub fn mark_ready(&self, warm_up: WarmUp) {
+        // Warm-up runs once per server; a second one would report the same
+        let _ = self.0.set(warm_up);
+    }
+
+    pub fn warm_up(&self) -> Option<WarmUp> {
+        self.0.get().copied()
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ReadinessReport {
+    pub ready: bool,
+    pub dictionary_load_ms: Option<u64>,
+    pub first_tokenization_ms: Option<u64>,
+}
+
+/// For orchestrators' readiness probes: 200 once warm-up is done, 503 before.
+#[get("/ready")]
+pub async fn ready(readiness: Option<web::Data<Readiness>>) -> HttpResponse {
+    let warm_up = readiness.and_then(|readiness| readiness.warm_up());
+    let report = ReadinessReport {
+        ready: warm_up.is_some(),
+        dictionary_load_ms: warm_up.map(|w| w.dictionary_load.as_millis() as u64),
+        first_tokenization_ms: warm_up.map(|w| w.first_tokenization.as_millis() as u64),
+    };
+    if report.ready {
+        HttpResponse::Ok().json(report)
+    } else {
+        HttpResponse::ServiceUnavailable().json(report)
+    }
+}
@@ -0,0 +1,92 @@
+use super::reload::Reloadable;
+use super::{request_config, request_pipeline, request_scorer, request_tokenizer, ComparisonQuery};
+use crate::comparison::{compare_extracted, compare_with_options, CompareOptions};
+use crate::config::Config;
+use crate::embedding::Embedder;
+use crate::features::{extract_features_filtered, FEATURE_SCHEMA_VERSION};
+use crate::record::{ExperimentRecord, InputPolicy, RecordedInput, RECORD_FORMAT_VERSION};
+use crate::scoring::Scorers;
+use crate::signing::to_hex;
+use crate::tokenizer::TokenizerPool;
+use crate::MODEL_VERSION;
+use actix_web::{error, post, web, Result};
+use sha2::{Digest, Sha256};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Compares like `/compare` and returns the comparison as an experiment
+/// record for `author-comparer replay`. Only what a replay can reproduce
+/// is scored: no plugins, hooks, experiments or baseline percentiles.
+#[post("/compare/record")]
+pub async fn record_comparison(
+    body: web::Json<ComparisonQuery>,
+    config: web::Data<Reloadable<Config>>,
+    scorers: web::Data<Reloadable<Scorers>>,
+    tokenizers: web::Data<TokenizerPool>,
+    embedder: Option<web::Data<Embedder>>,
+) -> Result<web::Json<ExperimentRecord>> {
+    let scorers = scorers.current();
+    let scorer = request_scorer(&scorers, body.scoring)?;
+    let mut config = request_config(&body, config.current())?.as_ref().clone();
+    let genres = body.genres.as_ref().map(|[genre1, genre2]| [genre1.as_str(), genre2.as_str()]);
+
+    let (features, analysis, inputs) = if let Some(imported) = &body.features {
+        if body.debug || body.verbose || body.robustness || body.vocabulary.is_some() || body.dictionary.is_some() {
+            return Err(error::ErrorBadRequest(
+                "debug, verbose, vocabulary, robustness and dictionary need texts, not feature vectors",
+            ));
+        }
+        let analysis = compare_extracted(&imported.text1, &imported.text2, &config, scorer);
+        let inputs = [&imported.text1, &imported.text2].map(|features| RecordedInput {
+            sha256: to_hex(&Sha256::digest(serde_json::to_vec(features).unwrap_or_default())),
+            text: None,
+        });
+        ([imported.text1.clone(), imported.text2.clone()], analysis, inputs)
+    } else {
+        let pipeline = request_pipeline(body.preprocessing.as_deref(), &config)?;
+        let tokenizer = request_tokenizer(body.dictionary.as_deref(), &config, &tokenizers)?;
+        let (text1, text2) = (pipeline.apply(&body.text1), pipeline.apply(&body.text2));
+        let filters = config.token_filters().unwrap_or_default();
+        let extract = |text: &str| extract_features_filtered(text, tokenizer, &filters).map_err(error::ErrorInternalServerError);
+        let features = [extract(&text1)?, extract(&text2)?];
+        let steps = pipeline.names();
+        let options = CompareOptions {
+            scorer,
+            debug: body.debug,
+            verbose: body.verbose,
+            vocabulary: body.vocabulary,
+            robustness: body.robustness,
+            embedder: embedder.as_ref().map(|e| e.get_ref()),
+            genres,
+            preprocessing: &steps,
+            ..CompareOptions::default()
+        };
+        let analysis =
+            compare_with_options(&text1, &text2, tokenizer, &config, options).map_err(error::ErrorInternalServerError)?;
+        let keep = config.records.inputs == InputPolicy::Raw;
+        let inputs = [&body.text1, &body.text2].map(|text| RecordedInput {
+            sha256: to_hex(&Sha256::digest(text.as_bytes())),
+            text: keep.then(|| text.clone()),
+        });
+        (features, analysis, inputs)
+    };
+
+    // Recorded as in effect, like a configuration snapshot, but without
+    // the tenants' API key hashes
+    if let Some(steps) = &body.preprocessing {
+        config.preprocessing = steps.clone();
+    }
+    config.scoring.backend = scorer.backend();
+    config.tenants.clear();
+    Ok(web::Json(ExperimentRecord {
+        format_version: RECORD_FORMAT_VERSION,
+        created_at: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64),
+        model_version: MODEL_VERSION.to_string(),
+        feature_schema_version: FEATURE_SCHEMA_VERSION,
+        inputs,
+        dictionary: body.dictionary.clone(),
+        genres: body.genres.clone(),
+        features,
+        config,
+        analysis,
+    }))
+}
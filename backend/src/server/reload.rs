@@ -0,0 +1,128 @@
+use crate::config::Config;
+use crate::scoring::Scorers;
+use crate::tokenizer::{TokenizerPool, TokenizerSpec};
+use actix_web::web;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+
+// How often the config file's modification time is checked
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+// Sections read once at startup; changing them needs a restart
+const RESTART_ONLY: &[&str] = &["embedding.", "signing.", "listen.", "baseline.", "token_cache.", "telemetry.", "history.", "shadow.", "experiments.", "tenants.", "plugins.", "hooks.", "webhooks:", "jobs:", "queue."];
+
+/// Shared state that can be swapped while the server runs. Each request
+/// takes the current value once, so a reload never changes it mid-request.
+#[derive(Debug)]
+pub struct Reloadable<T>(RwLock<Arc<T>>);
+
+impl<T> Reloadable<T> {
+    pub fn new(value: T) -> Self {
+        Reloadable(RwLock::new(Arc::new(value)))
+    }
+
+    pub fn current(&self) -> Arc<T> {
+        // A panicked writer leaves the previous value in place, which is still usable
+        self.0.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    pub fn replace(&self, value: T) {
+        *self.0.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(value);
+    }
+}
+
+fn load(path: &Path, tokenizers: &TokenizerPool) -> Result<(Config, Scorers), String> {
+    let config = Config::load(path).map_err(|e| e.to_string())?;
+    let tokenizer = tokenizers.get(&TokenizerSpec::default()).map_err(|e| e.to_string())?;
+    let scorers = Scorers::from_config(&config, tokenizer).map_err(|e| e.to_string())?;
+    Ok((config, scorers))
+}
+
+// Loads the file and, when it differs from the running config, swaps the
+// config and the scorers built from it and logs what changed. Ok(false)
+// when nothing did
+fn reload(
+    path: &Path,
+    config: &Reloadable<Config>,
+    scorers: &Reloadable<Scorers>,
+    tokenizers: &TokenizerPool,
+) -> Result<bool, String> {
+    let (new_config, new_scorers) = load(path, tokenizers)?;
+    let changes = config.current().diff(&new_config);
+    if changes.is_empty() {
+        return Ok(false);
+    }
+    println!("Reloaded {}:", path.display());
+    for change in changes {
+        let note = if RESTART_ONLY.iter().any(|section| change.starts_with(section)) {
+            " (takes effect after a restart)"
+        } else {
+            ""
+        };
+        println!("  {}{}", change, note);
+    }
+    scorers.replace(new_scorers);
+    config.replace(new_config);
+    Ok(true)
+}
+
+fn report_failure(path: &Path, e: &str) {
+    eprintln!("Keeping the current config; {} failed to load: {}", path.display(), e);
+}
+
+/// Reloads the config file whenever it changes and rebuilds the scorers from
+/// it. A file that fails to load is reported and the running config kept.
+pub fn watch(
+    path: PathBuf,
+    config: web::Data<Reloadable<Config>>,
+    scorers: web::Data<Reloadable<Scorers>>,
+    tokenizers: web::Data<TokenizerPool>,
+) {
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    std::thread::spawn(move || {
+        let mut seen: Option<SystemTime> = modified(&path);
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+            let current = modified(&path);
+            if current == seen {
+                continue;
+            }
+            seen = current;
+            if let Err(e) = reload(&path, &config, &scorers, &tokenizers) {
+                report_failure(&path, &e);
+            }
+        }
+    });
+}
+
+/// Reloads the config file on SIGHUP too, for when the file's modification
+/// time doesn't change (such as a Kubernetes ConfigMap swapped by symlink)
+/// or the reload shouldn't wait for the next poll. Needs a running actix
+/// system.
+#[cfg(unix)]
+pub fn on_hangup(
+    path: PathBuf,
+    config: web::Data<Reloadable<Config>>,
+    scorers: web::Data<Reloadable<Scorers>>,
+    tokenizers: web::Data<TokenizerPool>,
+) {
+    use actix_web::rt::signal::unix::{signal, SignalKind};
+
+    actix_web::rt::spawn(async move {
+        let mut hangups = match signal(SignalKind::hangup()) {
+            Ok(hangups) => hangups,
+            Err(e) => return eprintln!("Can't reload on SIGHUP: {}", e),
+        };
+        while hangups.recv().await.is_some() {
+            let (path, config, scorers, tokenizers) = (path.clone(), config.clone(), scorers.clone(), tokenizers.clone());
+            let reloaded = web::block(move || match reload(&path, &config, &scorers, &tokenizers) {
+                Ok(true) => {}
+                Ok(false) => println!("SIGHUP: {} is unchanged", path.display()),
+                Err(e) => report_failure(&path, &e),
+            });
+            if let Err(e) = reloaded.await {
+                eprintln!("SIGHUP reload failed: {}", e);
+            }
+        }
+    });
+}
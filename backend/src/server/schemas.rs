@@ -0,0 +1,79 @@
+use super::authors::{Identification, RegisteredAuthor, RegisteredAuthors};
+use super::batch::BatchResponse;
+use super::capabilities::Capabilities;
+use super::cluster::ClusterTree;
+use super::experiments::ExperimentReport;
+use super::history::ComparisonList;
+use super::jobs::JobList;
+use super::plugins::PluginList;
+use super::preprocess::PreprocessOutput;
+use super::ready::ReadinessReport;
+use super::snapshots::ConfigSnapshot;
+use super::telemetry::TelemetryReport;
+use super::verify::Verification;
+use crate::anomaly::AnomalyReport;
+use crate::anonymize::AnonymizationReport;
+use crate::comparison::Analysis;
+use crate::explain::Explanation;
+use crate::history::StoredComparison;
+use crate::overlap::Overlap;
+use crate::plugins::PluginInfo;
+use crate::projection::Projection;
+use crate::record::ExperimentRecord;
+use actix_web::{get, web};
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
+use schemars::Map;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// JSON Schemas of every JSON response, keyed by route. Nested types are
+/// shared in `definitions` and referenced as `#/definitions/<name>`.
+#[derive(Debug, Serialize)]
+pub struct ResponseSchemas {
+    #[serde(rename = "$schema", skip_serializing_if = "Option::is_none")]
+    pub meta_schema: Option<String>,
+    pub responses: BTreeMap<&'static str, Schema>,
+    pub definitions: Map<String, Schema>,
+}
+
+pub fn response_schemas() -> ResponseSchemas {
+    let mut generator = SchemaGenerator::default();
+    let responses = BTreeMap::from([
+        ("POST /compare", generator.subschema_for::<Analysis>()),
+        ("POST /compare/batch", generator.subschema_for::<BatchResponse>()),
+        ("POST /compare/explain", generator.subschema_for::<Explanation>()),
+        ("POST /compare/record", generator.subschema_for::<ExperimentRecord>()),
+        ("POST /preprocess", generator.subschema_for::<PreprocessOutput>()),
+        ("POST /overlap", generator.subschema_for::<Overlap>()),
+        ("POST /anonymize", generator.subschema_for::<AnonymizationReport>()),
+        ("POST /anomaly", generator.subschema_for::<AnomalyReport>()),
+        ("POST /authors", generator.subschema_for::<RegisteredAuthor>()),
+        ("GET /authors", generator.subschema_for::<RegisteredAuthors>()),
+        ("POST /identify", generator.subschema_for::<Identification>()),
+        ("POST /projection", generator.subschema_for::<Projection>()),
+        ("POST /cluster", generator.subschema_for::<ClusterTree>()),
+        ("POST /verify", generator.subschema_for::<Verification>()),
+        ("GET /config/snapshots/{id}", generator.subschema_for::<ConfigSnapshot>()),
+        ("GET /comparisons", generator.subschema_for::<ComparisonList>()),
+        ("GET /comparisons/{id}", generator.subschema_for::<StoredComparison>()),
+        ("GET /comparisons/{id}/explain", generator.subschema_for::<Explanation>()),
+        ("GET /capabilities", generator.subschema_for::<Capabilities>()),
+        ("GET /ready", generator.subschema_for::<ReadinessReport>()),
+        ("GET /admin/telemetry", generator.subschema_for::<TelemetryReport>()),
+        ("GET /experiments", generator.subschema_for::<ExperimentReport>()),
+        ("POST /plugins/{name}", generator.subschema_for::<PluginInfo>()),
+        ("GET /plugins", generator.subschema_for::<PluginList>()),
+        ("GET /jobs", generator.subschema_for::<JobList>()),
+    ]);
+    ResponseSchemas {
+        meta_schema: generator.settings().meta_schema.clone(),
+        responses,
+        definitions: generator.take_definitions(),
+    }
+}
+
+#[get("/schema")]
+pub async fn schema() -> web::Json<ResponseSchemas> {
+    web::Json(response_schemas())
+}
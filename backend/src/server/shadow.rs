@@ -0,0 +1,197 @@
+use crate::comparison::{compare_with_options, Analysis, CompareOptions, DetailedResult, Verdict};
+use crate::config::{Config, ConfigError, ShadowConfig};
+use crate::delta::{aspect_deltas, AspectDelta};
+use crate::embedding::Embedder;
+use crate::scoring::{Backend, Scorers};
+use crate::tokenizer::{LinderaResult, Tokenizer};
+use actix_web::web;
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Aspects the two models put closer together than this aren't logged
+const ASPECT_THRESHOLD: f64 = 0.05;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Outcome {
+    pub backend: Backend,
+    pub verdict: Verdict,
+    pub confidence: f64,
+}
+
+impl Outcome {
+    fn of(analysis: &Analysis) -> Outcome {
+        Outcome {
+            backend: analysis.scoring,
+            verdict: analysis.verdict,
+            confidence: analysis.confidence,
+        }
+    }
+}
+
+/// One request scored by both models, as logged. Holds no text.
+#[derive(Debug, Clone, Serialize)]
+pub struct ShadowComparison {
+    /// Unix time, in seconds.
+    pub time: u64,
+    pub active: Outcome,
+    pub shadow: Outcome,
+    pub agree: bool,
+    /// Shadow confidence minus active confidence.
+    pub confidence_change: f64,
+    /// Aspects whose difference the models disagree on, largest gap first.
+    pub aspects: Vec<AspectDelta>,
+}
+
+/// A candidate model, loaded from its own config file, that scores a sample
+/// of `/compare` requests next to the active one. Its results are only
+/// logged, never returned.
+pub struct Shadow {
+    config: Config,
+    scorers: Scorers,
+    sample_rate: f64,
+    log: Mutex<Box<dyn Write + Send>>,
+}
+
+impl Shadow {
+    /// Loads the candidate's config and scorers, or returns `None` when no
+    /// shadow is configured.
+    pub fn from_config(config: &ShadowConfig, tokenizer: &Tokenizer) -> Result<Option<Shadow>, ConfigError> {
+        let Some(path) = &config.config else {
+            return Ok(None);
+        };
+        let shadow_config = Config::load(path)?;
+        let scorers = Scorers::from_config(&shadow_config, tokenizer)?;
+        let log: Box<dyn Write + Send> = match &config.log {
+            Some(log) => Box::new(
+                std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(log)
+                    .map_err(ConfigError::Io)?,
+            ),
+            None => Box::new(std::io::stdout()),
+        };
+        Ok(Some(Shadow::new(shadow_config, scorers, config.sample_rate, log)))
+    }
+
+    pub fn new(config: Config, scorers: Scorers, sample_rate: f64, log: Box<dyn Write + Send>) -> Shadow {
+        Shadow {
+            config,
+            scorers,
+            sample_rate,
+            log: Mutex::new(log),
+        }
+    }
+
+    /// Decided by a hash of the texts rather than at random, so a resent
+    /// pair is sampled the same way every time.
+    pub fn sampled(&self, text1: &str, text2: &str) -> bool {
+        let mut hasher = DefaultHasher::new();
+        (text1, text2).hash(&mut hasher);
+        (hasher.finish() as f64 / u64::MAX as f64) < self.sample_rate
+    }
+
+    /// Scores already preprocessed texts with the candidate's default
+    /// backend and compares the result with the active model's.
+    pub fn compare(
+        &self,
+        texts: [&str; 2],
+        tokenizer: &Tokenizer,
+        embedder: Option<&Embedder>,
+        active: &Outcome,
+        active_aspects: &[DetailedResult],
+    ) -> LinderaResult<ShadowComparison> {
+        let options = CompareOptions {
+            scorer: self.scorers.default_scorer(),
+            embedder,
+            ..CompareOptions::default()
+        };
+        let analysis = compare_with_options(texts[0], texts[1], tokenizer, &self.config, options)?;
+        let shadow = Outcome::of(&analysis);
+        Ok(ShadowComparison {
+            time: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()),
+            agree: shadow.verdict == active.verdict,
+            confidence_change: shadow.confidence - active.confidence,
+            aspects: aspect_deltas(active_aspects, &analysis.detailed_analysis, ASPECT_THRESHOLD),
+            active: active.clone(),
+            shadow,
+        })
+    }
+
+    /// Appends the comparison to the log as one JSON line.
+    pub fn log(&self, comparison: &ShadowComparison) {
+        let Ok(line) = serde_json::to_string(comparison) else {
+            return;
+        };
+        let mut log = self.log.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(e) = writeln!(log, "{}", line) {
+            eprintln!("Failed to log a shadow comparison: {}", e);
+        }
+    }
+}
+
+/// Scores the pair with the shadow model on the blocking pool, so the
+/// response doesn't wait for it, and logs the comparison.
+pub fn spawn(
+    shadow: web::Data<Shadow>,
+    texts: [String; 2],
+    tokenizer: &'static Tokenizer,
+    embedder: Option<web::Data<Embedder>>,
+    active: &Analysis,
+) {
+    let outcome = Outcome::of(active);
+    let aspects = active.detailed_analysis.clone();
+    actix_web::rt::task::spawn_blocking(move || {
+        match shadow.compare([&texts[0], &texts[1]], tokenizer, embedder.as_ref().map(|e| e.get_ref()), &outcome, &aspects) {
+            Ok(comparison) => shadow.log(&comparison),
+            Err(e) => eprintln!("Failed to run a shadow comparison: {}", e),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::new_tokenizer;
+    use std::sync::Arc;
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(bytes)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_shadow_comparison() {
+        let tokenizer = new_tokenizer().unwrap();
+        let (text1, text2) = ("私は今日公園に行きました。とても楽しかったです。", "猫が好きだ！本当に可愛いから、毎日見ている。");
+        let active = crate::comparison::compare(text1, text2, &tokenizer).unwrap();
+        let buffer = Buffer::default();
+        let shadow = Shadow::new(Config::default(), Scorers::default(), 1.0, Box::new(buffer.clone()));
+        assert!(shadow.sampled(text1, text2));
+        assert!(!Shadow::new(Config::default(), Scorers::default(), 0.0, Box::new(Buffer::default())).sampled(text1, text2));
+
+        // The same model as the active one agrees everywhere
+        let comparison = shadow.compare([text1, text2], &tokenizer, None, &Outcome::of(&active), &active.detailed_analysis).unwrap();
+        assert!(comparison.agree);
+        assert!(comparison.confidence_change.abs() < 1e-9);
+        assert!(comparison.aspects.is_empty());
+
+        shadow.log(&comparison);
+        let log = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(log.lines().count(), 1);
+        assert!(log.contains("\"agree\":true"));
+        assert!(!log.contains("公園"));
+    }
+}
@@ -0,0 +1,82 @@
+use crate::aspects::Transform;
+use crate::config::{Config, DecisionProfile, WordUsageConfig};
+use crate::scoring::Backend;
+use crate::MODEL_VERSION;
+use actix_web::{error, get, web, Result};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct ConfigSnapshot {
+    pub id: String,
+    pub model_version: &'static str,
+    pub config: ScoringSettings,
+}
+
+/// The part of the effective config a result's scores depend on. The rest
+/// (database URLs, key hashes, file paths) is never served, since the
+/// endpoint is unauthenticated.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct ScoringSettings {
+    pub preprocessing: Vec<String>,
+    pub backend: Backend,
+    pub transforms: BTreeMap<String, Transform>,
+    pub weights: BTreeMap<String, f64>,
+    pub decision: DecisionProfile,
+    pub word_usage: WordUsageConfig,
+}
+
+impl From<Config> for ScoringSettings {
+    fn from(config: Config) -> Self {
+        ScoringSettings {
+            preprocessing: config.preprocessing,
+            backend: config.scoring.backend,
+            transforms: config.transforms,
+            weights: config.weights,
+            decision: config.decision,
+            word_usage: config.word_usage,
+        }
+    }
+}
+
+/// Every effective configuration a result was produced under since startup.
+/// Ids are content hashes, so a result from before a restart resolves again
+/// once the same settings are in use.
+#[derive(Debug, Default)]
+pub struct Snapshots(Mutex<HashMap<String, ConfigSnapshot>>);
+
+impl Snapshots {
+    /// The server config with this request's overrides applied, recorded
+    /// under its id.
+    pub fn record(&self, config: &Config, preprocessing: Option<&[String]>, backend: Backend) -> String {
+        let mut effective = config.clone();
+        if let Some(steps) = preprocessing {
+            effective.preprocessing = steps.to_vec();
+        }
+        effective.scoring.backend = backend;
+
+        let id = effective.snapshot_id();
+        let mut snapshots = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        snapshots.entry(id.clone()).or_insert_with(|| ConfigSnapshot {
+            id: id.clone(),
+            model_version: MODEL_VERSION,
+            config: effective.into(),
+        });
+        id
+    }
+
+    pub fn get(&self, id: &str) -> Option<ConfigSnapshot> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).get(id).cloned()
+    }
+}
+
+#[get("/config/snapshots/{id}")]
+pub async fn snapshot(id: web::Path<String>, snapshots: web::Data<Snapshots>) -> Result<web::Json<ConfigSnapshot>> {
+    let id = id.into_inner();
+    snapshots
+        .get(&id)
+        .map(web::Json)
+        .ok_or_else(|| error::ErrorNotFound(format!("no configuration snapshot '{}'", id)))
+}
@@ -0,0 +1,235 @@
+use crate::comparison::{Analysis, Verdict};
+use crate::config::{ConfigError, TelemetryConfig};
+use actix_web::{error, get, web, HttpRequest, Result};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Upper bounds of the text length buckets, in characters; the last bucket is open
+const LENGTH_BOUNDS: &[f64] = &[50.0, 100.0, 200.0, 500.0, 1000.0, 2000.0, 5000.0, 10000.0];
+const CONFIDENCE_BUCKETS: usize = 10;
+
+/// Counts per bucket; bucket `i` holds values below `upper_bounds[i]` and
+/// not below the bound before it, and the last bucket everything above.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct Histogram {
+    pub upper_bounds: Vec<f64>,
+    pub counts: Vec<u64>,
+}
+
+impl Histogram {
+    fn new(upper_bounds: Vec<f64>) -> Histogram {
+        let counts = vec![0; upper_bounds.len() + 1];
+        Histogram { upper_bounds, counts }
+    }
+
+    fn add(&mut self, value: f64) {
+        let bucket = self.upper_bounds.iter().position(|&bound| value < bound).unwrap_or(self.upper_bounds.len());
+        self.counts[bucket] += 1;
+    }
+
+    fn merge(&mut self, other: &Histogram) {
+        for (count, other) in self.counts.iter_mut().zip(&other.counts) {
+            *count += other;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, JsonSchema)]
+pub struct VerdictCounts {
+    pub same_author: u64,
+    pub different_author: u64,
+    pub insufficient_data: u64,
+    pub identical_text: u64,
+    pub inconclusive: u64,
+}
+
+/// What was served during one window. Only these aggregates are kept;
+/// no text, feature or individual result ever is.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct TelemetryWindow {
+    /// Unix time the window starts at, in seconds.
+    pub start: u64,
+    pub comparisons: u64,
+    pub verdicts: VerdictCounts,
+    /// None before the first comparison.
+    pub mean_confidence: Option<f64>,
+    pub confidence: Histogram,
+    /// Lengths of both compared texts, in characters; requests sending
+    /// feature vectors instead of texts aren't counted here.
+    pub text_length: Histogram,
+    #[serde(skip)]
+    confidence_sum: f64,
+}
+
+impl TelemetryWindow {
+    fn new(start: u64) -> TelemetryWindow {
+        let confidence_bounds = (1..CONFIDENCE_BUCKETS).map(|i| i as f64 / CONFIDENCE_BUCKETS as f64).collect();
+        TelemetryWindow {
+            start,
+            comparisons: 0,
+            verdicts: VerdictCounts::default(),
+            mean_confidence: None,
+            confidence: Histogram::new(confidence_bounds),
+            text_length: Histogram::new(LENGTH_BOUNDS.to_vec()),
+            confidence_sum: 0.0,
+        }
+    }
+
+    fn record(&mut self, analysis: &Analysis, lengths: Option<[usize; 2]>) {
+        self.comparisons += 1;
+        match analysis.verdict {
+            Verdict::SameAuthor => self.verdicts.same_author += 1,
+            Verdict::DifferentAuthor => self.verdicts.different_author += 1,
+            Verdict::InsufficientData => self.verdicts.insufficient_data += 1,
+            Verdict::IdenticalText => self.verdicts.identical_text += 1,
+            Verdict::Inconclusive => self.verdicts.inconclusive += 1,
+        }
+        self.confidence.add(analysis.confidence);
+        self.confidence_sum += analysis.confidence;
+        self.mean_confidence = Some(self.confidence_sum / self.comparisons as f64);
+        for length in lengths.into_iter().flatten() {
+            self.text_length.add(length as f64);
+        }
+    }
+
+    fn merge(&mut self, other: &TelemetryWindow) {
+        self.comparisons += other.comparisons;
+        self.verdicts.same_author += other.verdicts.same_author;
+        self.verdicts.different_author += other.verdicts.different_author;
+        self.verdicts.insufficient_data += other.verdicts.insufficient_data;
+        self.verdicts.identical_text += other.verdicts.identical_text;
+        self.confidence.merge(&other.confidence);
+        self.text_length.merge(&other.text_length);
+        self.confidence_sum += other.confidence_sum;
+        self.mean_confidence = (self.comparisons > 0).then(|| self.confidence_sum / self.comparisons as f64);
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct TelemetryReport {
+    pub window_seconds: u64,
+    /// Oldest first; windows without comparisons are left out.
+    pub windows: Vec<TelemetryWindow>,
+    /// All kept windows together.
+    pub total: TelemetryWindow,
+}
+
+/// Score distributions, verdict rates and text lengths of the comparisons
+/// served, aggregated per time window. Only registered when enabled.
+#[derive(Debug)]
+pub struct Telemetry {
+    window_seconds: u64,
+    retention: usize,
+    token: Option<String>,
+    windows: Mutex<VecDeque<TelemetryWindow>>,
+}
+
+impl Telemetry {
+    /// Reads the admin token file, or returns `None` when telemetry is off.
+    pub fn from_config(config: &TelemetryConfig) -> Result<Option<Telemetry>, ConfigError> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        let token = match &config.token_file {
+            Some(path) => Some(std::fs::read_to_string(path).map_err(ConfigError::Io)?.trim().to_string()),
+            None => None,
+        };
+        Ok(Some(Telemetry::new(config.window_seconds, config.retention, token)))
+    }
+
+    pub fn new(window_seconds: u64, retention: usize, token: Option<String>) -> Telemetry {
+        Telemetry {
+            window_seconds: window_seconds.max(1),
+            retention: retention.max(1),
+            token,
+            windows: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Counts one served comparison; `lengths` are the texts' lengths in characters.
+    pub fn record(&self, analysis: &Analysis, lengths: Option<[usize; 2]>) {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        self.record_at(now, analysis, lengths);
+    }
+
+    fn record_at(&self, now: u64, analysis: &Analysis, lengths: Option<[usize; 2]>) {
+        let start = now - now % self.window_seconds;
+        let mut windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+        if windows.back().is_none_or(|window| window.start != start) {
+            windows.push_back(TelemetryWindow::new(start));
+            while windows.len() > self.retention {
+                windows.pop_front();
+            }
+        }
+        if let Some(window) = windows.back_mut() {
+            window.record(analysis, lengths);
+        }
+    }
+
+    pub fn report(&self) -> TelemetryReport {
+        let windows: Vec<TelemetryWindow> = self.windows.lock().unwrap_or_else(|e| e.into_inner()).iter().cloned().collect();
+        let mut total = TelemetryWindow::new(windows.first().map_or(0, |window| window.start));
+        for window in &windows {
+            total.merge(window);
+        }
+        TelemetryReport {
+            window_seconds: self.window_seconds,
+            windows,
+            total,
+        }
+    }
+
+    fn authorized(&self, request: &HttpRequest) -> bool {
+        let Some(token) = &self.token else {
+            return true;
+        };
+        request
+            .headers()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|given| given == token)
+    }
+}
+
+#[get("/admin/telemetry")]
+pub async fn telemetry(request: HttpRequest, telemetry: Option<web::Data<Telemetry>>) -> Result<web::Json<TelemetryReport>> {
+    let telemetry = telemetry.ok_or_else(|| error::ErrorNotFound("telemetry is not enabled"))?;
+    if !telemetry.authorized(&request) {
+        return Err(error::ErrorUnauthorized("a valid admin bearer token is required"));
+    }
+    Ok(web::Json(telemetry.report()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comparison::compare;
+    use crate::tokenizer::new_tokenizer;
+
+    #[test]
+    fn test_telemetry_windows() {
+        let tokenizer = new_tokenizer().unwrap();
+        let analysis = compare("私は今日公園に行きました。", "私は昨日公園で遊びました。", &tokenizer).unwrap();
+        let windows = Telemetry::new(60, 2, None);
+
+        windows.record_at(0, &analysis, Some([13, 120]));
+        windows.record_at(59, &analysis, None);
+        windows.record_at(61, &analysis, Some([13, 13]));
+        let report = windows.report();
+        assert_eq!(report.windows.len(), 2);
+        assert_eq!((report.windows[0].start, report.windows[0].comparisons), (0, 2));
+        assert_eq!(report.windows[0].text_length.counts[..3], [1, 0, 1]);
+        assert_eq!(report.total.comparisons, 3);
+        assert_eq!(report.total.confidence.counts.iter().sum::<u64>(), 3);
+        assert!((report.total.mean_confidence.unwrap() - analysis.confidence).abs() < 1e-9);
+
+        // Only `retention` windows are kept
+        windows.record_at(130, &analysis, None);
+        let starts: Vec<u64> = windows.report().windows.iter().map(|w| w.start).collect();
+        assert_eq!(starts, [60, 120]);
+    }
+}
@@ -0,0 +1,53 @@
+use super::experiments::API_KEY_HEADER;
+use crate::signing::to_hex;
+use actix_web::{error, HttpRequest, Result};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+/// The configured tenants, found by the SHA-256 of their API key so no key
+/// is kept, even in memory.
+pub struct Tenants {
+    by_key_hash: BTreeMap<String, String>,
+}
+
+impl Tenants {
+    /// Returns `None` when no tenant is configured. Names and hashes are
+    /// checked when the config loads.
+    pub fn from_config(tenants: &BTreeMap<String, String>) -> Option<Tenants> {
+        if tenants.is_empty() {
+            return None;
+        }
+        let by_key_hash = tenants.iter().map(|(tenant, hash)| (hash.to_ascii_lowercase(), tenant.clone())).collect();
+        Some(Tenants { by_key_hash })
+    }
+
+    pub fn tenant(&self, api_key: &str) -> Option<&str> {
+        self.by_key_hash.get(&to_hex(&Sha256::digest(api_key.as_bytes()))).map(String::as_str)
+    }
+
+    /// The tenant the request's API key belongs to.
+    pub fn of_request(&self, request: &HttpRequest) -> Option<&str> {
+        let api_key = request.headers().get(API_KEY_HEADER)?.to_str().ok()?;
+        self.tenant(api_key)
+    }
+
+    /// Like `of_request`, but refuses requests from no tenant.
+    pub fn require(&self, request: &HttpRequest) -> Result<String> {
+        self.of_request(request).map(str::to_string).ok_or_else(|| error::ErrorUnauthorized("unknown API key"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tenant_by_key_hash() {
+        // SHA-256 of "test"
+        let hash = "9F86D081884C7D659A2FEAA0C55AD015A3BF4F1B2B0B822CD15D6C15B0F00A08";
+        let tenants = Tenants::from_config(&BTreeMap::from([("acme".to_string(), hash.to_string())])).unwrap();
+        assert_eq!(tenants.tenant("test"), Some("acme"));
+        assert_eq!(tenants.tenant("other"), None);
+        assert!(Tenants::from_config(&BTreeMap::new()).is_none());
+    }
+}
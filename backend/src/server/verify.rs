@@ -0,0 +1,22 @@
+use crate::signing::ResultSigner;
+use actix_web::{error, post, web, Result};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct Verification {
+    pub valid: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// Checks a stored `/compare` response, sent back verbatim, against this server's key.
+#[post("/verify")]
+pub async fn verify(body: web::Json<serde_json::Value>, signer: Option<web::Data<ResultSigner>>) -> Result<web::Json<Verification>> {
+    let signer = signer.ok_or_else(|| error::ErrorBadRequest("result signing is not configured"))?;
+    let result = signer.verify(&body);
+    Ok(web::Json(Verification {
+        valid: result.is_ok(),
+        reason: result.err().map(|e| e.to_string()),
+    }))
+}
@@ -0,0 +1,261 @@
+use crate::config::{ConfigError, WebhookConfig, WebhookEvent};
+use crate::scoring::Backend;
+use serde_json::{json, Value};
+
+#[cfg(feature = "webhooks")]
+pub const EVENT_HEADER: &str = "X-Webhook-Event";
+/// `sha256=` and the hex HMAC-SHA256 of the body under the webhook's secret.
+#[cfg(feature = "webhooks")]
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+
+/// The body of every delivery.
+#[cfg(feature = "webhooks")]
+#[derive(Debug, serde::Serialize)]
+pub struct Delivery<'a> {
+    /// Unique per event, so a receiver can drop retried deliveries it already has.
+    pub id: String,
+    pub event: WebhookEvent,
+    /// Unix time the event happened, in seconds.
+    pub sent_at: u64,
+    pub data: &'a Value,
+}
+
+#[cfg(feature = "webhooks")]
+struct Message {
+    event: WebhookEvent,
+    body: std::sync::Arc<Vec<u8>>,
+}
+
+#[cfg(feature = "webhooks")]
+struct Hook {
+    config: WebhookConfig,
+    queue: std::sync::mpsc::Sender<Message>,
+}
+
+/// The configured webhooks, each with a thread of its own delivering its
+/// events in order, so a slow or failing endpoint only holds up itself.
+#[cfg(feature = "webhooks")]
+pub struct Webhooks {
+    hooks: Vec<Hook>,
+    sequence: std::sync::atomic::AtomicU64,
+}
+
+/// Uninhabited without the `webhooks` feature, so none can be configured.
+#[cfg(not(feature = "webhooks"))]
+pub enum Webhooks {}
+
+impl std::fmt::Debug for Webhooks {
+    // Never print the secrets
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Webhooks")
+    }
+}
+
+#[cfg(feature = "webhooks")]
+pub fn signature(secret: &[u8], body: &[u8]) -> String {
+    use hmac::{Hmac, Mac};
+
+    let mut mac = Hmac::<sha2::Sha256>::new_from_slice(secret).expect("HMAC takes keys of any length");
+    mac.update(body);
+    format!("sha256={}", crate::signing::to_hex(&mac.finalize().into_bytes()))
+}
+
+// Ok once delivered, or refused in a way retrying won't change; Err when
+// worth another try
+#[cfg(feature = "webhooks")]
+fn post(agent: &ureq::Agent, url: &str, secret: Option<&[u8]>, message: &Message) -> Result<(), String> {
+    let event = serde_json::to_value(message.event).ok().and_then(|v| v.as_str().map(str::to_string)).unwrap_or_default();
+    let mut request = agent.post(url).set("Content-Type", "application/json").set(EVENT_HEADER, &event);
+    if let Some(secret) = secret {
+        request = request.set(SIGNATURE_HEADER, &signature(secret, &message.body));
+    }
+    match request.send_bytes(&message.body) {
+        Ok(_) => Ok(()),
+        // Busy or broken on the receiving side, which may pass
+        Err(ureq::Error::Status(status, _)) if status == 429 || status >= 500 => Err(format!("HTTP {}", status)),
+        Err(ureq::Error::Status(status, _)) => {
+            eprintln!("Webhook {} refused a {} event with HTTP {}", url, event, status);
+            Ok(())
+        }
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[cfg(feature = "webhooks")]
+fn deliver(agent: &ureq::Agent, config: &WebhookConfig, secret: Option<&[u8]>, message: &Message, first_retry: std::time::Duration) {
+    let mut wait = first_retry;
+    for attempt in 1..=config.attempts {
+        match post(agent, &config.url, secret, message) {
+            Ok(()) => return,
+            Err(e) if attempt == config.attempts => {
+                eprintln!("Giving up on webhook {} after {} attempts: {}", config.url, attempt, e);
+            }
+            Err(_) => {
+                std::thread::sleep(wait);
+                wait *= 2;
+            }
+        }
+    }
+}
+
+impl Webhooks {
+    /// Reads the secrets and starts a delivery thread per webhook, or
+    /// returns `None` when none is configured.
+    pub fn from_config(webhooks: &[WebhookConfig]) -> Result<Option<Webhooks>, ConfigError> {
+        if webhooks.is_empty() {
+            return Ok(None);
+        }
+        let mut hooks = Vec::new();
+        for config in webhooks {
+            let secret = match &config.secret_file {
+                Some(path) => Some(std::fs::read_to_string(path).map_err(ConfigError::Io)?.trim().as_bytes().to_vec()),
+                None => None,
+            };
+            hooks.push((config.clone(), secret));
+        }
+        Webhooks::start(hooks, std::time::Duration::from_secs(1)).map(Some)
+    }
+
+    #[cfg(feature = "webhooks")]
+    fn start(hooks: Vec<(WebhookConfig, Option<Vec<u8>>)>, first_retry: std::time::Duration) -> Result<Webhooks, ConfigError> {
+        let agent = ureq::AgentBuilder::new().timeout(std::time::Duration::from_secs(10)).build();
+        let hooks = hooks
+            .into_iter()
+            .map(|(config, secret)| {
+                let (queue, messages) = std::sync::mpsc::channel::<Message>();
+                let (agent, delivered) = (agent.clone(), config.clone());
+                std::thread::Builder::new()
+                    .name("webhook".to_string())
+                    .spawn(move || {
+                        for message in messages {
+                            deliver(&agent, &delivered, secret.as_deref(), &message, first_retry);
+                        }
+                    })
+                    .map_err(|e| ConfigError::Webhooks(e.to_string()))?;
+                Ok(Hook { config, queue })
+            })
+            .collect::<Result<_, ConfigError>>()?;
+        Ok(Webhooks {
+            hooks,
+            sequence: std::sync::atomic::AtomicU64::new(0),
+        })
+    }
+
+    #[cfg(not(feature = "webhooks"))]
+    fn start(_hooks: Vec<(WebhookConfig, Option<Vec<u8>>)>, _first_retry: std::time::Duration) -> Result<Webhooks, ConfigError> {
+        Err(ConfigError::Webhooks("built without the `webhooks` feature".to_string()))
+    }
+
+    /// Queues the event for every webhook that wants it and `accepts`;
+    /// never waits for a delivery.
+    #[cfg(feature = "webhooks")]
+    fn send(&self, event: WebhookEvent, data: Value, accepts: impl Fn(&WebhookConfig) -> bool) {
+        let recipients: Vec<&Hook> = self.hooks.iter().filter(|hook| hook.config.wants(event) && accepts(&hook.config)).collect();
+        if recipients.is_empty() {
+            return;
+        }
+        let sent_at = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        let sequence = self.sequence.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let delivery = Delivery {
+            id: format!("{}-{}", sent_at, sequence),
+            event,
+            sent_at,
+            data: &data,
+        };
+        // A Delivery serializes without fallible parts
+        let body = std::sync::Arc::new(serde_json::to_vec(&delivery).unwrap_or_default());
+        for hook in recipients {
+            // The thread only stops with the server
+            let _ = hook.queue.send(Message { event, body: body.clone() });
+        }
+    }
+
+    #[cfg(not(feature = "webhooks"))]
+    fn send(&self, _event: WebhookEvent, _data: Value, _accepts: impl Fn(&WebhookConfig) -> bool) {
+        match *self {}
+    }
+
+    /// Tells every webhook that wants `event` about it.
+    pub fn notify(&self, event: WebhookEvent, data: Value) {
+        self.send(event, data, |_| true);
+    }
+
+    /// Tells the webhooks whose `match_above` the confidence reaches that an
+    /// identification named `author`.
+    pub fn profile_match(&self, author: &str, confidence: f64, scoring: Backend) {
+        let data = json!({"author": author, "confidence": confidence, "scoring": scoring});
+        self.send(WebhookEvent::ProfileMatch, data, |config| confidence >= config.match_above);
+    }
+}
+
+#[cfg(all(test, feature = "webhooks"))]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_signature() {
+        // RFC 4231, test case 2
+        assert_eq!(
+            signature(b"Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    // Lowercased header lines and the body
+    type Request = (Vec<String>, Vec<u8>);
+
+    // Answers each request with the next status, passing on its headers and body
+    fn receiver(statuses: &'static [u16]) -> (String, mpsc::Receiver<Request>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let (requests, received) = mpsc::channel();
+        std::thread::spawn(move || {
+            for (stream, status) in listener.incoming().zip(statuses) {
+                let mut stream = BufReader::new(stream.unwrap());
+                let mut headers = Vec::new();
+                loop {
+                    let mut line = String::new();
+                    stream.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    headers.push(line.trim().to_ascii_lowercase());
+                }
+                let length = headers.iter().find_map(|h| h.strip_prefix("content-length: ")).map_or(0, |l| l.parse().unwrap());
+                let mut body = vec![0; length];
+                stream.read_exact(&mut body).unwrap();
+                write!(stream.get_mut(), "HTTP/1.1 {} X\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status).unwrap();
+                requests.send((headers, body)).unwrap();
+            }
+        });
+        (url, received)
+    }
+
+    #[test]
+    fn test_delivery() {
+        let (url, received) = receiver(&[503, 200]);
+        let config = WebhookConfig {
+            url,
+            attempts: 3,
+            ..WebhookConfig::default()
+        };
+        let webhooks = Webhooks::start(vec![(config, Some(b"secret".to_vec()))], Duration::from_millis(10)).unwrap();
+        // Below match_above, so not sent
+        webhooks.profile_match("natsume", 0.5, Backend::Weighted);
+        webhooks.profile_match("natsume", 0.95, Backend::Weighted);
+
+        let (first, body) = received.recv_timeout(Duration::from_secs(10)).unwrap();
+        let (retried, retried_body) = received.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(body, retried_body);
+        assert!(first.contains(&"x-webhook-event: profile.match".to_string()));
+        assert!(retried.contains(&format!("x-webhook-signature: {}", signature(b"secret", &body))));
+        let delivery: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(delivery["event"], "profile.match");
+        assert_eq!(delivery["data"]["author"], "natsume");
+        assert_eq!(delivery["data"]["confidence"], 0.95);
+    }
+}
@@ -0,0 +1,126 @@
+use crate::comparison::DetailedResult;
+use crate::config::ShortTextConfig;
+use crate::features::char_ngrams;
+use crate::numerics::{clamp, js_divergence, safe_div};
+use crate::punctuation::is_punctuation;
+use crate::symbols::{is_emoji, is_symbol};
+use std::collections::{BTreeSet, HashMap};
+
+// Sentence-final particles and their usual combinations, longest first so
+// that よね isn't read as ね
+const ENDINGS: &[&str] = &["じゃん", "かな", "かね", "よね", "けど", "っす", "のよ", "わよ", "ぞ", "ぜ", "よ", "ね", "な", "わ", "さ", "か", "の"];
+
+/// Whether a pair is too short for the usual features and gets compared in
+/// short-text mode.
+pub fn is_short(text1: &str, text2: &str, config: &ShortTextConfig) -> bool {
+    text1.chars().count().min(text2.chars().count()) < config.max_chars
+}
+
+fn missing(has1: bool, has2: bool, what: &str) -> Result<(), String> {
+    match (has1, has2) {
+        (true, true) => Ok(()),
+        (false, true) => Err(format!("no {} in text1", what)),
+        (true, false) => Err(format!("no {} in text2", what)),
+        (false, false) => Err(format!("no {} in either text", what)),
+    }
+}
+
+/// 1 minus the cosine similarity of the texts' character bigram and trigram
+/// frequencies, which carry spelling, kana choice and favourite phrases even
+/// in a handful of words.
+pub fn ngram_difference(text1: &str, text2: &str) -> Result<f64, String> {
+    ngram_distance(&char_ngrams(text1), &char_ngrams(text2))
+}
+
+/// `ngram_difference` of already-counted n-grams.
+pub fn ngram_distance(grams1: &HashMap<String, f64>, grams2: &HashMap<String, f64>) -> Result<f64, String> {
+    missing(!grams1.is_empty(), !grams2.is_empty(), "character n-grams")?;
+    let dot: f64 = grams1.iter().filter_map(|(gram, f1)| grams2.get(gram).map(|f2| f1 * f2)).sum();
+    let norm = |grams: &HashMap<String, f64>| grams.values().map(|f| f * f).sum::<f64>().sqrt();
+    Ok(1.0 - clamp(safe_div(dot, norm(grams1) * norm(grams2), 0.0), 0.0, 1.0))
+}
+
+// How often each sentence-final particle ends a sentence, with one last
+// slot for sentences ending any other way
+fn ending_counts(text: &str) -> Vec<f64> {
+    let mut counts = vec![0.0; ENDINGS.len() + 1];
+    let boundary = |c: char| matches!(c, '。' | '！' | '？' | '!' | '?' | '\n') || is_emoji(c);
+    for sentence in text.split(boundary) {
+        let sentence = sentence.trim_end_matches(|c: char| {
+            c.is_whitespace() || is_punctuation(c) || is_symbol(c) || matches!(c, 'ー' | '〜' | '～' | 'w' | 'ｗ' | '笑')
+        });
+        if sentence.is_empty() {
+            continue;
+        }
+        let slot = ENDINGS.iter().position(|ending| sentence.ends_with(ending)).unwrap_or(ENDINGS.len());
+        counts[slot] += 1.0;
+    }
+    counts
+}
+
+/// Jensen-Shannon divergence between how the texts' sentences end: with
+/// which particle (よ, ね, じゃん...) or without one.
+pub fn ending_difference(text1: &str, text2: &str) -> Result<f64, String> {
+    let (counts1, counts2) = (ending_counts(text1), ending_counts(text2));
+    let has = |counts: &[f64]| counts.iter().sum::<f64>() > 0.0;
+    missing(has(&counts1), has(&counts2), "sentences")?;
+    Ok(js_divergence(&counts1, &counts2))
+}
+
+/// 0 when neither text uses emoji, 1 when only one does, and otherwise the
+/// Jaccard distance between the sets of emoji used.
+pub fn emoji_difference(text1: &str, text2: &str) -> f64 {
+    // Selectors and joiners are parts of sequences, not emoji of their own
+    let emoji = |text: &str| -> BTreeSet<char> {
+        text.chars().filter(|c| is_emoji(*c) && !matches!(c, '\u{FE0F}' | '\u{200D}')).collect()
+    };
+    let (emoji1, emoji2) = (emoji(text1), emoji(text2));
+    match (emoji1.is_empty(), emoji2.is_empty()) {
+        (true, true) => 0.0,
+        (true, false) | (false, true) => 1.0,
+        (false, false) => {
+            let shared = emoji1.intersection(&emoji2).count() as f64;
+            1.0 - shared / emoji1.union(&emoji2).count() as f64
+        }
+    }
+}
+
+/// Short-text calibration: the weighted similarity of the computed aspects
+/// through a logistic curve, so a pair at `midpoint` gets 0.5.
+pub fn confidence(details: &[DetailedResult], config: &ShortTextConfig) -> f64 {
+    let computed = details.iter().filter_map(|detail| detail.difference.map(|d| (detail.weight, d)));
+    let (weighted, total) = computed.fold((0.0, 0.0), |(weighted, total), (weight, difference)| {
+        (weighted + weight * (1.0 - clamp(difference, 0.0, 1.0)), total + weight)
+    });
+    if total == 0.0 {
+        return 0.0;
+    }
+    let similarity = weighted / total;
+    1.0 / (1.0 + (-config.steepness * (similarity - config.midpoint)).exp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_short_text_aspects() {
+        let config = ShortTextConfig::default();
+        assert!(is_short("今日も雨じゃん😢", "明日は晴れるかな", &config));
+        assert!(!is_short(&"あ".repeat(80), &"い".repeat(80), &config));
+        let off = ShortTextConfig { max_chars: 0, ..config };
+        assert!(!is_short("雨", "晴れ", &off));
+
+        assert!(ngram_difference("今日も雨じゃん", "今日も雨じゃん").unwrap() < 1e-9);
+        assert!(ngram_difference("今日も雨じゃん", "明日は晴れるかな").unwrap() > 0.9);
+        assert_eq!(ngram_difference("雨", "明日は晴れ").unwrap_err(), "no character n-grams in text1");
+
+        assert_eq!(ending_counts("行くよね！まじかー。草www\nそうだね😊")[3], 1.0);
+        assert!(ending_difference("雨じゃん。暑いじゃん！", "寒いじゃん…").unwrap() < 1e-9);
+        assert!(ending_difference("雨だよ。", "晴れだね。").unwrap() > 0.9);
+
+        assert_eq!(emoji_difference("雨", "晴れ"), 0.0);
+        assert_eq!(emoji_difference("雨😢", "晴れ"), 1.0);
+        assert!((emoji_difference("雨😢☔️", "晴れ😢") - 0.5).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,215 @@
+use crate::comparison::Analysis;
+use crate::config::{ConfigError, SigningConfig};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::path::Path;
+
+pub const ALGORITHM: &str = "ed25519";
+
+/// Attached to a signed analysis. The signature covers the whole response
+/// with `signature.value` left out.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ResultSignature {
+    pub algorithm: String,
+    /// Hex-encoded Ed25519 public key of the signing server.
+    pub public_key: String,
+    /// SHA-256 of both inputs exactly as submitted, in hex.
+    pub input_sha256: [String; 2],
+    pub model_version: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub value: String,
+}
+
+/// Signs results with the server's Ed25519 key.
+#[cfg(feature = "signing")]
+pub struct ResultSigner {
+    key: ed25519_dalek::SigningKey,
+}
+
+/// Uninhabited without the `signing` feature, so no key can be configured.
+#[cfg(not(feature = "signing"))]
+pub enum ResultSigner {}
+
+impl fmt::Debug for ResultSigner {
+    // Never print the key
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ResultSigner")
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum VerifyError {
+    Unsigned,
+    /// Signed, but not with this server's key.
+    OtherKey,
+    Malformed(String),
+    /// The result or its signature was altered.
+    Mismatch,
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyError::Unsigned => write!(f, "the result carries no signature"),
+            VerifyError::OtherKey => write!(f, "the result was signed with a different key"),
+            VerifyError::Malformed(e) => write!(f, "malformed signature: {}", e),
+            VerifyError::Mismatch => write!(f, "the signature does not match the result"),
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    let digit = |c: u8| (c as char).to_digit(16).map(|d| d as u8);
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            [high, low] => Some((digit(*high)? << 4) | digit(*low)?),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The bytes a signature covers: the analysis as canonical JSON (sorted keys)
+/// without the signature value.
+#[cfg(any(feature = "signing", test))]
+fn signed_message(analysis: &Value) -> Vec<u8> {
+    let mut analysis = analysis.clone();
+    if let Some(Value::Object(signature)) = analysis.get_mut("signature") {
+        signature.remove("value");
+    }
+    analysis.to_string().into_bytes()
+}
+
+impl ResultSigner {
+    /// Loads the configured key, or returns `None` when signing is off.
+    pub fn from_config(config: &SigningConfig) -> Result<Option<ResultSigner>, ConfigError> {
+        match &config.key {
+            Some(key) => ResultSigner::load(key).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Reads a 32-byte Ed25519 secret key stored as hex.
+    #[cfg(feature = "signing")]
+    fn load(path: &Path) -> Result<ResultSigner, ConfigError> {
+        let hex = std::fs::read_to_string(path).map_err(ConfigError::Io)?;
+        let seed: [u8; 32] = from_hex(hex.trim())
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| ConfigError::Signing(format!("{}: expected 64 hex digits", path.display())))?;
+        Ok(ResultSigner {
+            key: ed25519_dalek::SigningKey::from_bytes(&seed),
+        })
+    }
+
+    #[cfg(not(feature = "signing"))]
+    fn load(_path: &Path) -> Result<ResultSigner, ConfigError> {
+        Err(ConfigError::Signing("built without the `signing` feature".to_string()))
+    }
+
+    #[cfg(feature = "signing")]
+    pub fn public_key(&self) -> String {
+        to_hex(self.key.verifying_key().as_bytes())
+    }
+
+    #[cfg(not(feature = "signing"))]
+    pub fn public_key(&self) -> String {
+        match *self {}
+    }
+
+    /// Attaches a signature over the analysis, the hashes of both inputs and
+    /// the model version.
+    #[cfg(feature = "signing")]
+    pub fn sign(&self, analysis: &mut Analysis, inputs: [&[u8]; 2]) {
+        use ed25519_dalek::Signer;
+        use sha2::{Digest, Sha256};
+
+        analysis.signature = Some(ResultSignature {
+            algorithm: ALGORITHM.to_string(),
+            public_key: self.public_key(),
+            input_sha256: inputs.map(|input| to_hex(&Sha256::digest(input))),
+            model_version: crate::MODEL_VERSION.to_string(),
+            value: String::new(),
+        });
+        // Analysis serializes without fallible parts, so this can't fail
+        let message = signed_message(&serde_json::to_value(&*analysis).unwrap_or_default());
+        let signature = self.key.sign(&message);
+        if let Some(attached) = &mut analysis.signature {
+            attached.value = to_hex(&signature.to_bytes());
+        }
+    }
+
+    #[cfg(not(feature = "signing"))]
+    pub fn sign(&self, _analysis: &mut Analysis, _inputs: [&[u8]; 2]) {
+        match *self {}
+    }
+
+    /// Signs arbitrary bytes, such as a case report's manifest; the
+    /// signature is returned in hex.
+    #[cfg(feature = "signing")]
+    pub fn sign_bytes(&self, message: &[u8]) -> String {
+        use ed25519_dalek::Signer;
+
+        to_hex(&self.key.sign(message).to_bytes())
+    }
+
+    #[cfg(not(feature = "signing"))]
+    pub fn sign_bytes(&self, _message: &[u8]) -> String {
+        match *self {}
+    }
+
+    /// Checks that a stored analysis, as JSON, is unaltered output signed by this server.
+    #[cfg(feature = "signing")]
+    pub fn verify(&self, analysis: &Value) -> Result<(), VerifyError> {
+        use ed25519_dalek::Verifier;
+
+        let signature: ResultSignature = match analysis.get("signature") {
+            Some(signature) => serde_json::from_value(signature.clone()).map_err(|e| VerifyError::Malformed(e.to_string()))?,
+            None => return Err(VerifyError::Unsigned),
+        };
+        if signature.public_key != self.public_key() {
+            return Err(VerifyError::OtherKey);
+        }
+        let bytes: [u8; 64] = from_hex(&signature.value)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| VerifyError::Malformed("expected 128 hex digits".to_string()))?;
+        self.key
+            .verifying_key()
+            .verify(&signed_message(analysis), &ed25519_dalek::Signature::from_bytes(&bytes))
+            .map_err(|_| VerifyError::Mismatch)
+    }
+
+    #[cfg(not(feature = "signing"))]
+    pub fn verify(&self, _analysis: &Value) -> Result<(), VerifyError> {
+        match *self {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(to_hex(&[0x00, 0xab, 0x7f]), "00ab7f");
+        assert_eq!(from_hex("00ab7F"), Some(vec![0x00, 0xab, 0x7f]));
+        assert_eq!(from_hex("abc"), None);
+        assert_eq!(from_hex("zz"), None);
+    }
+
+    #[test]
+    fn test_signed_message() {
+        let analysis = serde_json::json!({ "confidence": 0.5, "signature": { "public_key": "ab", "value": "cd" } });
+        assert_eq!(
+            String::from_utf8(signed_message(&analysis)).unwrap(),
+            r#"{"confidence":0.5,"signature":{"public_key":"ab"}}"#
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextClass {
+    Prose,
+    EmojiHeavy,
+    AsciiArt,
+    SymbolHeavy,
+}
+
+// Share of non-whitespace characters above which ratios stop describing prose
+const SYMBOL_HEAVY_RATIO: f64 = 0.3;
+const ASCII_ART_MIN_CHARS: usize = 5;
+const ASCII_ART_LINE_RATIO: f64 = 0.6;
+
+pub fn is_emoji(c: char) -> bool {
+    matches!(c,
+        '\u{1F000}'..='\u{1FAFF}' // Emoticons, pictographs, transport, etc.
+        | '\u{2600}'..='\u{27BF}' // Miscellaneous symbols and dingbats
+        | '\u{2B00}'..='\u{2BFF}' // Miscellaneous symbols and arrows
+        | '\u{FE0F}' // Emoji presentation selector
+        | '\u{200D}' // Zero-width joiner inside emoji sequences
+    )
+}
+
+/// Characters that decorate text rather than carry language: emoji, arrows,
+/// math operators, box drawing and the full-width strokes kaomoji are built from.
+pub fn is_symbol(c: char) -> bool {
+    is_emoji(c)
+        || matches!(c,
+            '\u{2190}'..='\u{23FF}' // Arrows, math operators, misc technical
+            | '\u{2500}'..='\u{25FF}' // Box drawing, blocks, geometric shapes
+            | '＼' | '／' | '＾' | '＿' | '｀' | '｜' | '～' | '￣'
+        )
+}
+
+fn is_decorative(c: char) -> bool {
+    is_symbol(c) || c.is_ascii_punctuation()
+}
+
+/// A token made mostly of symbols, such as kaomoji like "＼(^o^)／" or emoji
+/// sequences. Pure punctuation is not a symbol run.
+pub fn is_symbol_run(token: &str) -> bool {
+    let chars: Vec<char> = token.chars().filter(|c| !c.is_whitespace()).collect();
+    let decorative = chars.iter().filter(|c| is_decorative(**c)).count();
+    chars.iter().any(|c| is_symbol(*c)) && decorative * 2 > chars.len()
+}
+
+fn is_ascii_art_line(line: &str) -> bool {
+    let chars: Vec<char> = line.chars().filter(|c| !c.is_whitespace()).collect();
+    if chars.len() < ASCII_ART_MIN_CHARS {
+        return false;
+    }
+    // Emoji decorate prose rather than draw, so they don't make a line art
+    let decorative = chars
+        .iter()
+        .filter(|c| is_decorative(**c) && !is_emoji(**c))
+        .count();
+    decorative as f64 / chars.len() as f64 >= ASCII_ART_LINE_RATIO
+}
+
+/// Drops lines that are drawings rather than text, keeping the input borrowed
+/// when there are none.
+pub fn strip_ascii_art(text: &str) -> Cow<'_, str> {
+    if !text.lines().any(is_ascii_art_line) {
+        return Cow::Borrowed(text);
+    }
+    Cow::Owned(
+        text.split_inclusive('\n')
+            .filter(|line| !is_ascii_art_line(line))
+            .collect(),
+    )
+}
+
+/// Share of non-whitespace characters that are symbols.
+pub fn symbol_ratio(text: &str) -> f64 {
+    let mut total = 0;
+    let mut symbols = 0;
+    for c in text.chars().filter(|c| !c.is_whitespace()) {
+        total += 1;
+        if is_symbol(c) {
+            symbols += 1;
+        }
+    }
+    if total == 0 {
+        0.0
+    } else {
+        symbols as f64 / total as f64
+    }
+}
+
+pub fn classify(text: &str) -> TextClass {
+    let lines = text.lines().filter(|l| !l.trim().is_empty()).count();
+    let art_lines = text.lines().filter(|l| is_ascii_art_line(l)).count();
+    if art_lines > 0 && art_lines * 2 >= lines {
+        return TextClass::AsciiArt;
+    }
+
+    if symbol_ratio(text) < SYMBOL_HEAVY_RATIO {
+        return TextClass::Prose;
+    }
+    let symbols: Vec<char> = text.chars().filter(|c| is_symbol(*c)).collect();
+    let emoji = symbols.iter().filter(|c| is_emoji(**c)).count();
+    if emoji * 2 >= symbols.len() {
+        TextClass::EmojiHeavy
+    } else {
+        TextClass::SymbolHeavy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(classify("私は今日公園に行きました。"), TextClass::Prose);
+        assert_eq!(classify("今日も😀😀😀🎉🎉"), TextClass::EmojiHeavy);
+        assert_eq!(classify("＼(^o^)／ ＼(^o^)／ ＼(^o^)／"), TextClass::AsciiArt);
+        assert_eq!(classify("了解■■です→→よろしく"), TextClass::SymbolHeavy);
+    }
+
+    #[test]
+    fn test_strip_ascii_art() {
+        let text = "おはよう。\n(*^▽^*)(*^▽^*)\nまたね。\n";
+
+        assert_eq!(strip_ascii_art(text), "おはよう。\nまたね。\n");
+        assert!(matches!(strip_ascii_art("おはよう。"), Cow::Borrowed(_)));
+        assert!(is_symbol_run("＼(^o^)／"));
+        assert!(!is_symbol_run("..."));
+        assert!(!is_symbol_run("公園"));
+    }
+}
@@ -0,0 +1,40 @@
+use std::ops::{Deref, DerefMut};
+
+/// A blocking `postgres::Client` that connects and disconnects on a thread
+/// of its own. The client runs a Tokio runtime, and Tokio panics when one is
+/// started or dropped on a thread already driving another, such as the actix
+/// one the server is set up on. Queries in between block as usual, so callers
+/// still keep them off the async workers.
+pub struct Client(Option<postgres::Client>);
+
+impl Client {
+    pub fn connect(url: &str) -> Result<Client, postgres::Error> {
+        outside_runtime(|| postgres::Client::connect(url, postgres::NoTls)).map(|client| Client(Some(client)))
+    }
+}
+
+impl Deref for Client {
+    type Target = postgres::Client;
+
+    fn deref(&self) -> &postgres::Client {
+        self.0.as_ref().expect("only taken on drop")
+    }
+}
+
+impl DerefMut for Client {
+    fn deref_mut(&mut self) -> &mut postgres::Client {
+        self.0.as_mut().expect("only taken on drop")
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        if let Some(client) = self.0.take() {
+            outside_runtime(move || drop(client));
+        }
+    }
+}
+
+fn outside_runtime<T: Send>(f: impl FnOnce() -> T + Send) -> T {
+    std::thread::scope(|scope| scope.spawn(f).join().unwrap_or_else(|panic| std::panic::resume_unwind(panic)))
+}
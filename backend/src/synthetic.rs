@@ -0,0 +1,169 @@
+use crate::datasets::{LabeledText, VerificationPair};
+use serde::{Deserialize, Serialize};
+
+// Samples drawn per requested pair before giving up on it
+const MAX_ATTEMPTS: usize = 20;
+// Excerpts start at the next sentence boundary within this share of their length
+const BOUNDARY_SEARCH: usize = 4;
+const SENTENCE_ENDS: &[char] = &['。', '！', '？', '!', '?', '\n'];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyntheticOptions {
+    pub pairs: usize,
+    /// Excerpt lengths, in characters, are drawn uniformly from this range.
+    pub min_length: usize,
+    pub max_length: usize,
+    pub same_author_share: f64,
+    /// Whether texts of different genres may be paired.
+    pub mix_genres: bool,
+    pub seed: u64,
+}
+
+impl Default for SyntheticOptions {
+    fn default() -> Self {
+        Self {
+            pairs: 100,
+            min_length: 200,
+            max_length: 2000,
+            same_author_share: 0.5,
+            mix_genres: false,
+            seed: 0,
+        }
+    }
+}
+
+/// SplitMix64, so a seed reproduces the same pairs on every platform.
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n.max(1) as u64) as usize
+    }
+
+    fn between(&mut self, low: usize, high: usize) -> usize {
+        low + self.below(high.saturating_sub(low) + 1)
+    }
+
+    fn chance(&mut self, p: f64) -> bool {
+        ((self.next_u64() >> 11) as f64 / (1u64 << 53) as f64) < p
+    }
+
+    fn pick<T: Copy>(&mut self, items: &[T]) -> Option<T> {
+        (!items.is_empty()).then(|| items[self.below(items.len())])
+    }
+}
+
+/// Cuts up to `length` characters out of `chars`, starting at a sentence
+/// boundary when one is close to the random start.
+fn excerpt(chars: &[char], length: usize, rng: &mut Rng) -> String {
+    let length = length.min(chars.len());
+    let mut start = rng.below(chars.len() - length + 1);
+    let search_end = (start + length / BOUNDARY_SEARCH).min(chars.len());
+    if start > 0 {
+        if let Some(offset) = chars[start..search_end].iter().position(|c| SENTENCE_ENDS.contains(c)) {
+            start += offset + 1;
+        }
+    }
+    let end = (start + length).min(chars.len());
+    chars[start..end].iter().collect::<String>().trim().to_string()
+}
+
+/// Samples same-author and different-author pairs of excerpts from `texts`.
+/// Same-author pairs come from two texts of the author, or from both halves
+/// of one text when the author has no other. Texts shorter than `min_length`
+/// are never sampled, so fewer pairs than requested may be returned.
+pub fn generate(texts: &[LabeledText], options: &SyntheticOptions) -> Vec<VerificationPair> {
+    let min_length = options.min_length.max(1);
+    let max_length = options.max_length.max(min_length);
+    let chars: Vec<Vec<char>> = texts.iter().map(|t| t.text.chars().collect()).collect();
+    let eligible: Vec<usize> = (0..texts.len()).filter(|&i| chars[i].len() >= min_length).collect();
+    let compatible = |i: usize, j: usize| options.mix_genres || texts[i].genre == texts[j].genre;
+
+    let mut rng = Rng(options.seed);
+    let mut pairs = Vec::with_capacity(options.pairs);
+    for n in 0..options.pairs {
+        for _ in 0..MAX_ATTEMPTS {
+            let Some(i) = rng.pick(&eligible) else {
+                return pairs;
+            };
+            let same_author = rng.chance(options.same_author_share);
+            let partners: Vec<usize> = eligible
+                .iter()
+                .copied()
+                .filter(|&j| j != i && (texts[j].author == texts[i].author) == same_author && compatible(i, j))
+                .collect();
+            let (length1, length2) = (rng.between(min_length, max_length), rng.between(min_length, max_length));
+
+            let (text1, text2) = match rng.pick(&partners) {
+                Some(j) => (excerpt(&chars[i], length1, &mut rng), excerpt(&chars[j], length2, &mut rng)),
+                None if same_author && chars[i].len() >= 2 * min_length => {
+                    let (first, second) = chars[i].split_at(chars[i].len() / 2);
+                    (excerpt(first, length1, &mut rng), excerpt(second, length2, &mut rng))
+                }
+                None => continue,
+            };
+            pairs.push(VerificationPair {
+                id: format!("synthetic-{:05}", n),
+                text1,
+                text2,
+                same_author,
+            });
+            break;
+        }
+    }
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(author: &str, sentence: &str, genre: &str) -> LabeledText {
+        LabeledText {
+            author: author.to_string(),
+            text: sentence.repeat(40),
+            genre: Some(genre.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_generate() {
+        let texts = vec![
+            text("a", "ああ。", "blog"),
+            text("b", "いい。", "blog"),
+            text("c", "うう。", "novel"),
+        ];
+        let options = SyntheticOptions {
+            pairs: 30,
+            min_length: 20,
+            max_length: 50,
+            ..Default::default()
+        };
+
+        let pairs = generate(&texts, &options);
+        assert_eq!(pairs.len(), 30);
+        assert!(pairs.iter().any(|p| p.same_author) && pairs.iter().any(|p| !p.same_author));
+        for pair in &pairs {
+            let first = |s: &str| s.chars().next().unwrap();
+            assert_eq!(first(&pair.text1) == first(&pair.text2), pair.same_author);
+            // Genres stay apart unless mixing is asked for
+            assert!(!pair.text1.contains('う') || pair.same_author);
+            for excerpt in [&pair.text1, &pair.text2] {
+                assert!(excerpt.chars().count() <= 50);
+            }
+        }
+
+        let again = generate(&texts, &options);
+        assert!(pairs.iter().zip(&again).all(|(a, b)| a.text1 == b.text1 && a.text2 == b.text2));
+        assert!(generate(&texts, &SyntheticOptions { min_length: 1000, ..options }).is_empty());
+    }
+}
@@ -0,0 +1,102 @@
+use crate::language;
+use crate::script::is_kanji;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+// Checked longest first, so "ました" isn't read as plain "た"
+const POLITE_ENDINGS: &[&str] = &["ましょう", "ください", "でした", "ました", "ません", "です", "ます"];
+const PLAIN_ENDINGS: &[&str] = &["だった", "である", "だ", "た", "ない", "る", "う", "い"];
+// Closing quotes and brackets that can follow the real end of a sentence
+const CLOSERS: &[char] = &['」', '』', '）', ')', '"', '”'];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Ending {
+    Polite,
+    Plain,
+    Question,
+    Exclamation,
+    /// Noun endings, fragments and anything else.
+    Other,
+}
+
+/// One value per sentence, in document order.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct SentenceSeries {
+    /// Characters, without surrounding whitespace.
+    pub lengths: Vec<usize>,
+    pub endings: Vec<Ending>,
+    /// Share of kanji among the sentence's letters.
+    pub kanji_ratios: Vec<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct StyleTimeline {
+    pub text1: SentenceSeries,
+    pub text2: SentenceSeries,
+}
+
+fn ending(sentence: &str) -> Ending {
+    let body = sentence.trim_end_matches(CLOSERS);
+    if body.ends_with(['？', '?']) {
+        return Ending::Question;
+    }
+    if body.ends_with(['！', '!']) {
+        return Ending::Exclamation;
+    }
+    let body = body.trim_end_matches(['。', '.', '…', '、']);
+    if POLITE_ENDINGS.iter().any(|e| body.ends_with(e)) {
+        Ending::Polite
+    } else if PLAIN_ENDINGS.iter().any(|e| body.ends_with(e)) {
+        Ending::Plain
+    } else {
+        Ending::Other
+    }
+}
+
+pub fn sentence_series(text: &str) -> SentenceSeries {
+    let mut series = SentenceSeries::default();
+    for segment in language::segment(text) {
+        let sentence = segment.text.trim();
+        if sentence.is_empty() {
+            continue;
+        }
+        let letters = sentence.chars().filter(|c| c.is_alphanumeric()).count();
+        let kanji = sentence.chars().filter(|c| is_kanji(*c)).count();
+        series.lengths.push(sentence.chars().count());
+        series.endings.push(ending(sentence));
+        series
+            .kanji_ratios
+            .push(if letters > 0 { kanji as f64 / letters as f64 } else { 0.0 });
+    }
+    series
+}
+
+pub fn style_timeline(text1: &str, text2: &str) -> StyleTimeline {
+    StyleTimeline {
+        text1: sentence_series(text1),
+        text2: sentence_series(text2),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sentence_series() {
+        let series = sentence_series("今日は公園に行きました。楽しかった！\n本当？猫。");
+
+        assert_eq!(series.lengths, vec![12, 6, 3, 2]);
+        assert_eq!(
+            series.endings,
+            vec![Ending::Polite, Ending::Exclamation, Ending::Question, Ending::Other]
+        );
+        // 今日公園行 out of 今日は公園に行きました
+        assert!((series.kanji_ratios[0] - 5.0 / 11.0).abs() < 1e-9);
+        assert_eq!(series.kanji_ratios[3], 1.0);
+        assert_eq!(ending("雨が降っている。"), Ending::Plain);
+        assert_eq!(ending("「行きますか？」"), Ending::Question);
+        assert_eq!(ending("「そうです。」"), Ending::Polite);
+    }
+}
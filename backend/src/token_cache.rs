@@ -0,0 +1,202 @@
+use crate::pos::Pos;
+use crate::tokenizer::{LinderaResult, Tokenizer};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedToken {
+    pub text: String,
+    // IPADIC details, part of speech first
+    pub details: Vec<String>,
+    pub pos: Pos,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct CacheStats {
+    pub capacity: usize,
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    tokenizer: usize,
+    sentence: String,
+    tokens: Arc<Vec<CachedToken>>,
+    last_used: u64,
+}
+
+#[derive(Debug, Default)]
+struct Lru {
+    entries: HashMap<u64, Entry>,
+    // last_used tick -> key, oldest first
+    recency: BTreeMap<u64, u64>,
+    tick: u64,
+}
+
+/// Least-recently-used cache of tokenized sentences, keyed by a hash of the
+/// tokenizer and sentence, so boilerplate and re-submitted texts skip the tokenizer.
+#[derive(Debug)]
+pub struct TokenCache {
+    capacity: usize,
+    lru: Mutex<Lru>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+// Tokenizers with different dictionaries split the same sentence differently.
+// The server's tokenizers live as long as the process, so an address
+// identifies one.
+fn tokenizer_id(tokenizer: &Tokenizer) -> usize {
+    tokenizer as *const Tokenizer as usize
+}
+
+fn sentence_key(tokenizer: usize, sentence: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    tokenizer.hash(&mut hasher);
+    sentence.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(feature = "ipadic")]
+pub(crate) fn tokenize_uncached(sentence: &str, tokenizer: &Tokenizer) -> LinderaResult<Vec<CachedToken>> {
+    Ok(tokenizer
+        .tokenize(sentence)?
+        .into_iter()
+        .map(|mut token| {
+            let details: Vec<String> = token
+                .get_details()
+                .map(|details| details.iter().map(|d| d.to_string()).collect())
+                .unwrap_or_default();
+            CachedToken {
+                text: token.text.to_string(),
+                pos: Pos::of(&details),
+                details,
+            }
+        })
+        .collect())
+}
+
+#[cfg(not(feature = "ipadic"))]
+pub(crate) fn tokenize_uncached(_sentence: &str, tokenizer: &Tokenizer) -> LinderaResult<Vec<CachedToken>> {
+    match *tokenizer {}
+}
+
+impl TokenCache {
+    pub fn new(capacity: usize) -> TokenCache {
+        TokenCache {
+            capacity,
+            lru: Mutex::new(Lru::default()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub fn tokenize(&self, sentence: &str, tokenizer: &Tokenizer) -> LinderaResult<Arc<Vec<CachedToken>>> {
+        let id = tokenizer_id(tokenizer);
+        let key = sentence_key(id, sentence);
+        {
+            let mut lru = self.lru.lock().unwrap_or_else(|e| e.into_inner());
+            lru.tick += 1;
+            let tick = lru.tick;
+            if let Some(entry) = lru.entries.get_mut(&key).filter(|e| e.tokenizer == id && e.sentence == sentence) {
+                let previous = std::mem::replace(&mut entry.last_used, tick);
+                let tokens = entry.tokens.clone();
+                lru.recency.remove(&previous);
+                lru.recency.insert(tick, key);
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return Ok(tokens);
+            }
+        }
+
+        // Tokenize outside the lock so concurrent misses don't queue up
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let tokens = Arc::new(tokenize_uncached(sentence, tokenizer)?);
+        if self.capacity == 0 {
+            return Ok(tokens);
+        }
+        let mut lru = self.lru.lock().unwrap_or_else(|e| e.into_inner());
+        lru.tick += 1;
+        let tick = lru.tick;
+        if let Some(old) = lru.entries.remove(&key) {
+            lru.recency.remove(&old.last_used);
+        }
+        while lru.entries.len() >= self.capacity {
+            let Some((_, oldest)) = lru.recency.pop_first() else {
+                break;
+            };
+            lru.entries.remove(&oldest);
+        }
+        lru.entries.insert(
+            key,
+            Entry {
+                tokenizer: id,
+                sentence: sentence.to_string(),
+                tokens: tokens.clone(),
+                last_used: tick,
+            },
+        );
+        lru.recency.insert(tick, key);
+        Ok(tokens)
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            capacity: self.capacity,
+            entries: self.lru.lock().unwrap_or_else(|e| e.into_inner()).entries.len(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+static TOKEN_CACHE: OnceLock<TokenCache> = OnceLock::new();
+
+/// Turns on the process-wide cache; only the first call has an effect.
+pub fn configure(capacity: usize) -> &'static TokenCache {
+    TOKEN_CACHE.get_or_init(|| TokenCache::new(capacity))
+}
+
+pub fn global() -> Option<&'static TokenCache> {
+    TOKEN_CACHE.get()
+}
+
+/// Tokenizes one sentence through the process-wide cache when configured.
+pub fn tokenize_sentence(sentence: &str, tokenizer: &Tokenizer) -> LinderaResult<Arc<Vec<CachedToken>>> {
+    match global() {
+        Some(cache) => cache.tokenize(sentence, tokenizer),
+        None => tokenize_uncached(sentence, tokenizer).map(Arc::new),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::new_tokenizer;
+
+    #[test]
+    fn test_token_cache() {
+        let tokenizer = new_tokenizer().unwrap();
+        let cache = TokenCache::new(2);
+
+        let first = cache.tokenize("今日は晴れ。", &tokenizer).unwrap();
+        assert_eq!(first.first().map(|t| t.text.as_str()), Some("今日"));
+        assert!(Arc::ptr_eq(&first, &cache.tokenize("今日は晴れ。", &tokenizer).unwrap()));
+        cache.tokenize("猫がいる。", &tokenizer).unwrap();
+        // The third sentence evicts the least recently used one
+        cache.tokenize("犬もいる。", &tokenizer).unwrap();
+        cache.tokenize("猫がいる。", &tokenizer).unwrap();
+        let again = cache.tokenize("今日は晴れ。", &tokenizer).unwrap();
+        assert!(!Arc::ptr_eq(&first, &again));
+        assert_eq!(*first, *again);
+
+        let stats = cache.stats();
+        assert_eq!((stats.entries, stats.hits, stats.misses), (2, 2, 4));
+    }
+}
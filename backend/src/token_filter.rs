@@ -0,0 +1,151 @@
+use std::fmt;
+
+/// Rewrites a token before it is counted, so superficial variants of a word
+/// share one entry in the word frequencies.
+pub trait TokenFilter: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn apply(&self, token: &str) -> String;
+}
+
+pub const FILTER_NAMES: &[&str] = &["lowercase", "fold_digits", "unify_symbols"];
+
+// Same placeholder mask_entities leaves for numbers
+const DIGIT_PLACEHOLDER: &str = "0";
+
+pub fn filter(name: &str) -> Option<Box<dyn TokenFilter>> {
+    match name {
+        "lowercase" => Some(Box::new(Lowercase)),
+        "fold_digits" => Some(Box::new(FoldDigits)),
+        "unify_symbols" => Some(Box::new(UnifySymbols)),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownFilter(pub String);
+
+impl fmt::Display for UnknownFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown token filter \"{}\" (expected one of: {})",
+            self.0,
+            FILTER_NAMES.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for UnknownFilter {}
+
+#[derive(Default)]
+pub struct TokenFilters {
+    filters: Vec<Box<dyn TokenFilter>>,
+}
+
+impl TokenFilters {
+    pub fn from_names<S: AsRef<str>>(names: &[S]) -> Result<TokenFilters, UnknownFilter> {
+        let filters = names
+            .iter()
+            .map(|name| filter(name.as_ref()).ok_or_else(|| UnknownFilter(name.as_ref().to_string())))
+            .collect::<Result<_, _>>()?;
+        Ok(TokenFilters { filters })
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.filters.iter().map(|f| f.name()).collect()
+    }
+
+    pub fn apply(&self, token: &str) -> String {
+        self.filters
+            .iter()
+            .fold(token.to_string(), |token, filter| filter.apply(&token))
+    }
+}
+
+/// Folds Latin letters, half- and full-width, to lowercase.
+pub struct Lowercase;
+
+impl TokenFilter for Lowercase {
+    fn name(&self) -> &'static str {
+        "lowercase"
+    }
+
+    fn apply(&self, token: &str) -> String {
+        token.to_lowercase()
+    }
+}
+
+/// Replaces every run of digits, in any script, with a single placeholder,
+/// so "3月" and "12月" count as the same word.
+pub struct FoldDigits;
+
+impl TokenFilter for FoldDigits {
+    fn name(&self) -> &'static str {
+        "fold_digits"
+    }
+
+    fn apply(&self, token: &str) -> String {
+        let mut out = String::with_capacity(token.len());
+        let mut in_digits = false;
+        for c in token.chars() {
+            if c.is_numeric() {
+                if !in_digits {
+                    out.push_str(DIGIT_PLACEHOLDER);
+                }
+                in_digits = true;
+            } else {
+                out.push(c);
+                in_digits = false;
+            }
+        }
+        out
+    }
+}
+
+/// Maps each class of interchangeable symbols (brackets, quotes, dots, dashes,
+/// tildes, ellipses) to one representative.
+pub struct UnifySymbols;
+
+impl TokenFilter for UnifySymbols {
+    fn name(&self) -> &'static str {
+        "unify_symbols"
+    }
+
+    fn apply(&self, token: &str) -> String {
+        token
+            .chars()
+            .map(|c| match c {
+                '『' | '【' | '〔' | '［' | '〈' | '《' | '（' | '(' | '[' => '「',
+                '』' | '】' | '〕' | '］' | '〉' | '》' | '）' | ')' | ']' => '」',
+                '“' | '”' | '‘' | '’' | '＂' | '\'' => '"',
+                '·' | '•' | '･' => '・',
+                '—' | '―' | '–' | '‐' | '－' => '-',
+                '〜' | '~' => '～',
+                '‥' => '…',
+                _ => c,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_filters() {
+        let filters = TokenFilters::from_names(&["lowercase", "fold_digits", "unify_symbols"]).unwrap();
+        assert_eq!(filters.names(), FILTER_NAMES);
+
+        assert_eq!(filters.apply("Ｔｏｋｙｏ"), "ｔｏｋｙｏ");
+        assert_eq!(filters.apply("12月"), "0月");
+        assert_eq!(filters.apply("３日"), "0日");
+        assert_eq!(filters.apply("『"), "「");
+        assert_eq!(filters.apply("公園"), "公園");
+        assert_eq!(TokenFilters::default().apply("ABC"), "ABC");
+        assert_eq!(
+            TokenFilters::from_names(&["stem"]).err(),
+            Some(UnknownFilter("stem".to_string()))
+        );
+    }
+}
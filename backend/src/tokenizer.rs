@@ -0,0 +1,228 @@
+use crate::language::{self, Language};
+use crate::token_cache;
+#[cfg(feature = "ipadic")]
+use lindera_core::error::LinderaErrorKind;
+#[cfg(feature = "ipadic")]
+use lindera_core::mode::Mode;
+#[cfg(feature = "ipadic")]
+use lindera_dictionary::{DictionaryConfig, DictionaryKind, UserDictionaryConfig};
+#[cfg(feature = "ipadic")]
+use lindera_tokenizer::tokenizer::TokenizerConfig;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "ipadic")]
+pub use lindera_core::LinderaResult;
+#[cfg(feature = "ipadic")]
+pub use lindera_tokenizer::tokenizer::Tokenizer;
+
+/// Built without the `ipadic` feature there is no dictionary, so no tokenizer
+/// can be built and only precomputed feature vectors can be compared.
+#[cfg(not(feature = "ipadic"))]
+pub enum Tokenizer {}
+
+#[cfg(not(feature = "ipadic"))]
+pub type LinderaResult<T> = Result<T, NoDictionary>;
+
+/// Why a tokenizer couldn't be built without the `ipadic` feature.
+#[cfg(not(feature = "ipadic"))]
+#[derive(Debug)]
+pub struct NoDictionary;
+
+#[cfg(not(feature = "ipadic"))]
+impl std::fmt::Display for NoDictionary {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("built without the `ipadic` feature")
+    }
+}
+
+#[cfg(not(feature = "ipadic"))]
+impl std::error::Error for NoDictionary {}
+
+/// Dictionary a tokenizer is built on. UniDic needs the `unidic` feature.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Dictionary {
+    #[default]
+    Ipadic,
+    Unidic,
+}
+
+impl Dictionary {
+    pub const ALL: [Dictionary; 2] = [Dictionary::Ipadic, Dictionary::Unidic];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Dictionary::Ipadic => "ipadic",
+            Dictionary::Unidic => "unidic",
+        }
+    }
+
+    #[cfg(feature = "ipadic")]
+    fn kind(self) -> Option<DictionaryKind> {
+        match self {
+            Dictionary::Ipadic => Some(DictionaryKind::IPADIC),
+            #[cfg(feature = "unidic")]
+            Dictionary::Unidic => Some(DictionaryKind::UniDic),
+            #[cfg(not(feature = "unidic"))]
+            Dictionary::Unidic => None,
+        }
+    }
+}
+
+
+/// Everything that makes two tokenizers differ.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct TokenizerSpec {
+    pub dictionary: Dictionary,
+    /// Lindera user dictionary CSV, in the system dictionary's format.
+    pub user_dictionary: Option<PathBuf>,
+}
+
+#[cfg(feature = "ipadic")]
+pub fn build_tokenizer(spec: &TokenizerSpec) -> LinderaResult<Tokenizer> {
+    let kind = spec.dictionary.kind().ok_or_else(|| {
+        let message = format!("built without the `{}` feature", spec.dictionary.name());
+        LinderaErrorKind::Dictionary.with_error(std::io::Error::new(std::io::ErrorKind::Unsupported, message))
+    })?;
+    let config = TokenizerConfig {
+        dictionary: DictionaryConfig {
+            kind: Some(kind.clone()),
+            path: None,
+        },
+        user_dictionary: spec.user_dictionary.clone().map(|path| UserDictionaryConfig {
+            kind: Some(kind),
+            path,
+        }),
+        mode: Mode::Normal,
+    };
+
+    Tokenizer::from_config(config)
+}
+
+#[cfg(not(feature = "ipadic"))]
+pub fn build_tokenizer(_spec: &TokenizerSpec) -> LinderaResult<Tokenizer> {
+    Err(NoDictionary)
+}
+
+pub fn new_tokenizer() -> LinderaResult<Tokenizer> {
+    build_tokenizer(&TokenizerSpec::default())
+}
+
+/// Tokenizers built so far, one per spec. Loading a dictionary is expensive,
+/// so each is built on first use and kept for the life of the process.
+#[derive(Default)]
+pub struct TokenizerPool {
+    tokenizers: Mutex<HashMap<TokenizerSpec, &'static Tokenizer>>,
+}
+
+impl TokenizerPool {
+    pub fn get(&self, spec: &TokenizerSpec) -> LinderaResult<&'static Tokenizer> {
+        // Held while building, so concurrent first requests load a dictionary once
+        let mut tokenizers = self.tokenizers.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(tokenizer) = tokenizers.get(spec) {
+            return Ok(tokenizer);
+        }
+        let tokenizer: &'static Tokenizer = Box::leak(Box::new(build_tokenizer(spec)?));
+        tokenizers.insert(spec.clone(), tokenizer);
+        Ok(tokenizer)
+    }
+
+    /// Specs of the tokenizers built so far.
+    pub fn loaded(&self) -> Vec<TokenizerSpec> {
+        self.tokenizers.lock().unwrap_or_else(|e| e.into_inner()).keys().cloned().collect()
+    }
+}
+
+static SHARED_POOL: OnceLock<TokenizerPool> = OnceLock::new();
+
+/// Process-wide IPADIC tokenizer, for the language bindings and the CLI,
+/// which have no server state to hang a pool off. The server keeps its own
+/// pool in its app state instead.
+pub fn shared_tokenizer() -> LinderaResult<&'static Tokenizer> {
+    SHARED_POOL.get_or_init(TokenizerPool::default).get(&TokenizerSpec::default())
+}
+
+/// Time spent getting a pool's IPADIC tokenizer ready.
+#[derive(Debug, Clone, Copy)]
+pub struct WarmUp {
+    pub dictionary_load: Duration,
+    pub first_tokenization: Duration,
+}
+
+/// Loads the pool's IPADIC tokenizer and tokenizes one sentence with it, so
+/// the first request doesn't pay for either. IPADIC is embedded in the
+/// binary, so there is no dictionary file to memory-map, and lindera 0.24
+/// copies user dictionaries into buffers it owns, so those aren't mapped
+/// either.
+pub fn warm_up(pool: &TokenizerPool) -> LinderaResult<WarmUp> {
+    let start = Instant::now();
+    let tokenizer = pool.get(&TokenizerSpec::default())?;
+    let dictionary_load = start.elapsed();
+    let start = Instant::now();
+    token_cache::tokenize_uncached("辞書の読み込みを確認します。", tokenizer)?;
+    Ok(WarmUp {
+        dictionary_load,
+        first_tokenization: start.elapsed(),
+    })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TokenInfo {
+    pub text: String,
+    pub language: Language,
+    // IPADIC details (part of speech first); empty for English words
+    pub pos: Vec<String>,
+}
+
+/// Tokenizes text the way feature extraction sees it: English segments are
+/// split on whitespace and everything else goes through lindera.
+pub fn tokenize_with_pos(text: &str, tokenizer: &Tokenizer) -> LinderaResult<Vec<TokenInfo>> {
+    let mut tokens = Vec::new();
+    for segment in language::segment(text) {
+        if segment.language == Language::English {
+            tokens.extend(segment.text.split_whitespace().map(|word| TokenInfo {
+                text: word.to_string(),
+                language: Language::English,
+                pos: Vec::new(),
+            }));
+            continue;
+        }
+
+        for token in token_cache::tokenize_sentence(segment.text, tokenizer)?.iter() {
+            tokens.push(TokenInfo {
+                text: token.text.clone(),
+                language: segment.language,
+                pos: token.details.clone(),
+            });
+        }
+    }
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize_with_pos() {
+        let tokenizer = new_tokenizer().unwrap();
+        let tokens = tokenize_with_pos("公園に行きました。See you soon.", &tokenizer).unwrap();
+
+        let park = tokens.iter().find(|t| t.text == "公園").unwrap();
+        assert_eq!(park.pos.first().map(String::as_str), Some("名詞"));
+        assert_eq!(park.language, Language::Japanese);
+
+        let english: Vec<&str> = tokens
+            .iter()
+            .filter(|t| t.language == Language::English)
+            .map(|t| t.text.as_str())
+            .collect();
+        assert_eq!(english, vec!["See", "you", "soon."]);
+    }
+}
@@ -0,0 +1,121 @@
+use crate::language::Language;
+use crate::pos::Pos;
+use crate::punctuation;
+use crate::symbols;
+use crate::tokenizer::{LinderaResult, Tokenizer, tokenize_with_pos};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Frequent words the part-of-speech filter lets through but that say nothing
+// about an author's vocabulary
+const STOPWORDS: &[&str] = &[
+    "する", "ある", "いる", "なる", "れる", "られる", "できる", "いう", "こと", "もの", "よう", "ため", "これ",
+    "それ", "あれ", "この", "その", "あの", "ここ", "そこ", "the", "a", "an", "and", "or", "of", "to", "in", "is",
+    "are", "was", "it", "i", "you", "that", "this",
+];
+// Placeholders left by the mask_entities preprocessing step
+const MASKS: &[&str] = &["URL", "EMAIL", "0"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct VocabularyWord {
+    pub word: String,
+    /// Occurrences in text1 and text2.
+    pub counts: [usize; 2],
+    /// Occurrences per content word of each text.
+    pub frequencies: [f64; 2],
+}
+
+/// Most frequent content words used by both texts and by only one of them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct VocabularyDiff {
+    pub shared: Vec<VocabularyWord>,
+    pub only_text1: Vec<VocabularyWord>,
+    pub only_text2: Vec<VocabularyWord>,
+}
+
+fn content_words(text: &str, tokenizer: &Tokenizer) -> LinderaResult<HashMap<String, usize>> {
+    let mut counts = HashMap::new();
+    for token in tokenize_with_pos(text, tokenizer)? {
+        let word = match token.language {
+            Language::English => punctuation::trim_punctuation(&token.text).to_lowercase(),
+            _ if Pos::of(&token.pos).is_function_word() => continue,
+            _ => token.text,
+        };
+        if word.is_empty()
+            || punctuation::is_punctuation_token(&word)
+            || symbols::is_symbol_run(&word)
+            || STOPWORDS.contains(&word.as_str())
+            || MASKS.iter().any(|mask| mask.eq_ignore_ascii_case(&word))
+        {
+            continue;
+        }
+        *counts.entry(word).or_default() += 1;
+    }
+    Ok(counts)
+}
+
+/// Compares the content vocabularies of two texts, keeping the `top` most
+/// frequent words of each list.
+pub fn vocabulary_diff(text1: &str, text2: &str, tokenizer: &Tokenizer, top: usize) -> LinderaResult<VocabularyDiff> {
+    let counts1 = content_words(text1, tokenizer)?;
+    let counts2 = content_words(text2, tokenizer)?;
+    let totals = [counts1.values().sum::<usize>(), counts2.values().sum::<usize>()];
+    let entry = |word: &str| {
+        let counts = [*counts1.get(word).unwrap_or(&0), *counts2.get(word).unwrap_or(&0)];
+        VocabularyWord {
+            word: word.to_string(),
+            counts,
+            frequencies: [0, 1].map(|i| if totals[i] > 0 { counts[i] as f64 / totals[i] as f64 } else { 0.0 }),
+        }
+    };
+
+    let mut diff = VocabularyDiff::default();
+    for word in counts1.keys().chain(counts2.keys().filter(|w| !counts1.contains_key(*w))) {
+        let word = entry(word);
+        match word.counts {
+            [_, 0] => diff.only_text1.push(word),
+            [0, _] => diff.only_text2.push(word),
+            _ => diff.shared.push(word),
+        }
+    }
+    for list in [&mut diff.shared, &mut diff.only_text1, &mut diff.only_text2] {
+        list.sort_by(|a, b| {
+            let total = |w: &VocabularyWord| w.frequencies[0] + w.frequencies[1];
+            total(b).total_cmp(&total(a)).then_with(|| a.word.cmp(&b.word))
+        });
+        list.truncate(top);
+    }
+    Ok(diff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::new_tokenizer;
+
+    #[test]
+    fn test_vocabulary_diff() {
+        let tokenizer = new_tokenizer().unwrap();
+        let diff = vocabulary_diff(
+            "公園で猫を見た。公園は広い。URL",
+            "公園で犬と遊んだ。犬は元気だ。",
+            &tokenizer,
+            10,
+        )
+        .unwrap();
+
+        let words = |list: &[VocabularyWord]| list.iter().map(|w| w.word.clone()).collect::<Vec<_>>();
+        assert_eq!(words(&diff.shared), vec!["公園"]);
+        assert_eq!(diff.shared[0].counts, [2, 1]);
+        assert_eq!(diff.only_text2[0].word, "犬");
+        assert!(words(&diff.only_text1).contains(&"猫".to_string()));
+        // Particles and mask placeholders are filtered out
+        for word in words(&diff.only_text1).iter().chain(&words(&diff.only_text2)) {
+            assert!(!["で", "を", "は", "と", "URL"].contains(&word.as_str()), "{}", word);
+        }
+
+        let top = vocabulary_diff("猫と犬と鳥。", "魚。", &tokenizer, 1).unwrap();
+        assert_eq!(top.only_text1.len(), 1);
+    }
+}
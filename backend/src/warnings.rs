@@ -0,0 +1,49 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Machine-readable reason a result should be read with care.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum WarningCode {
+    /// A text is below the evidence thresholds.
+    ShortText,
+    /// The texts are mostly in different languages.
+    LanguageMismatch,
+    /// The texts were declared to be of different genres.
+    GenreMismatch,
+    /// Much of the text is shared verbatim.
+    HighOverlap,
+    /// The texts are the same, or nearly so, and weren't compared.
+    IdenticalText,
+    /// A text is mostly emoji, symbols or ASCII art, so its ratios say little.
+    DegenerateFeatures,
+    /// Reliable aspects strongly disagree about the verdict.
+    ConflictingAspects,
+    /// The requested scoring backend failed and the weighted one was used.
+    ScoringFallback,
+    /// A verdict hook failed, so the result is as scored.
+    HookFailed,
+}
+
+/// A warning code with a human-readable explanation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Warning {
+    pub code: WarningCode,
+    pub message: String,
+}
+
+impl Warning {
+    pub fn new(code: WarningCode, message: impl Into<String>) -> Warning {
+        Warning {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
@@ -0,0 +1,10 @@
+use crate::tokenizer::shared_tokenizer;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+pub fn compare(text1: &str, text2: &str) -> Result<JsValue, JsError> {
+    let tokenizer = shared_tokenizer().map_err(|e| JsError::new(&e.to_string()))?;
+    let analysis = crate::compare(text1, text2, tokenizer).map_err(|e| JsError::new(&e.to_string()))?;
+
+    serde_wasm_bindgen::to_value(&analysis).map_err(|e| JsError::new(&e.to_string()))
+}
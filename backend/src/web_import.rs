@@ -0,0 +1,466 @@
+use crate::datasets::DatasetError;
+use crate::language::{detect_language, Language};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Write};
+use std::path::Path;
+
+// Elements whose content is never the main text
+const SKIPPED: &[&str] = &["head", "noscript", "nav", "header", "footer", "aside", "form", "iframe", "button", "select"];
+// Elements skipped without looking into their content for tags
+const RAW_TEXT: &[&str] = &["script", "style", "template", "svg"];
+// Elements that end the running block of text
+const BLOCKS: &[&str] = &[
+    "p", "div", "li", "ul", "ol", "dl", "dt", "dd", "h1", "h2", "h3", "h4", "h5", "h6", "br", "hr", "table", "tr",
+    "td", "th", "section", "article", "main", "blockquote", "pre", "figcaption", "body",
+];
+// Blocks shorter than this, in characters, are menus, bylines and buttons
+const MIN_BLOCK_CHARS: usize = 15;
+// Blocks with more of their text inside links are navigation
+const MAX_LINK_DENSITY: f64 = 0.4;
+
+/// A web page or dump record reduced to its main text.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Document {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub text: String,
+    pub language: Language,
+}
+
+impl Document {
+    pub fn new(url: Option<String>, title: Option<String>, text: String) -> Document {
+        let language = detect_language(&text);
+        Document {
+            url,
+            title,
+            text,
+            language,
+        }
+    }
+
+    /// Keeps only the page's main content, see `extract_main_content`.
+    pub fn from_html(url: Option<String>, html: &str) -> Document {
+        let (title, text) = extract_main_content(html);
+        Document::new(url, title, text)
+    }
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+    match entity {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some(' '),
+        _ => {
+            let code = match entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X")) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => entity.strip_prefix('#')?.parse().ok()?,
+            };
+            char::from_u32(code)
+        }
+    }
+}
+
+#[derive(Default)]
+struct Block {
+    text: String,
+    chars: usize,
+    link_chars: usize,
+}
+
+// Byte range of the first `<name ...>...</name>`, if the page has one
+fn element(html: &str, lower: &str, name: &str) -> Option<(usize, usize)> {
+    let start = lower.find(&format!("<{}", name))?;
+    let end = lower[start..].find(&format!("</{}", name)).map_or(html.len(), |end| start + end);
+    Some((start, end))
+}
+
+/// Extracts the title and main text of an HTML page: scripts, navigation,
+/// headers, footers and forms are dropped, the page is narrowed to its
+/// `<article>` or `<main>` when it has one, and of the remaining blocks
+/// only those long enough and not made up mostly of links are kept, one per line.
+pub fn extract_main_content(html: &str) -> (Option<String>, String) {
+    // ASCII lowercasing keeps byte offsets valid in the original
+    let lower = html.to_ascii_lowercase();
+    let title = element(html, &lower, "title")
+        .and_then(|(start, end)| html[start..end].split_once('>').map(|(_, title)| title))
+        .map(|title| extract_blocks(title).into_iter().map(|b| b.text).collect::<Vec<_>>().join(" "))
+        .filter(|title| !title.is_empty());
+    let (start, end) = element(html, &lower, "article")
+        .or_else(|| element(html, &lower, "main"))
+        .unwrap_or((0, html.len()));
+
+    let text = extract_blocks(&html[start..end])
+        .into_iter()
+        .filter(|block| block.chars >= MIN_BLOCK_CHARS && block.link_chars as f64 <= block.chars as f64 * MAX_LINK_DENSITY)
+        .map(|block| block.text)
+        .collect::<Vec<_>>()
+        .join("\n");
+    (title, text)
+}
+
+fn extract_blocks(html: &str) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut block = Block::default();
+    let mut skipped = 0usize;
+    let mut links = 0usize;
+    let mut rest = html;
+
+    let mut flush = |block: &mut Block| {
+        let taken = std::mem::take(block);
+        let text = taken.text.trim();
+        if !text.is_empty() {
+            blocks.push(Block {
+                text: text.to_string(),
+                ..taken
+            });
+        }
+    };
+    let push = |block: &mut Block, c: char, in_link: bool| {
+        let c = if c.is_whitespace() { ' ' } else { c };
+        if c == ' ' && (block.text.is_empty() || block.text.ends_with(' ')) {
+            return;
+        }
+        block.text.push(c);
+        block.chars += 1;
+        if in_link {
+            block.link_chars += 1;
+        }
+    };
+
+    while let Some(c) = rest.chars().next() {
+        if c == '<' {
+            if let Some(comment) = rest.strip_prefix("<!--") {
+                rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+                continue;
+            }
+            let Some(end) = rest.find('>') else {
+                break;
+            };
+            let tag = &rest[1..end];
+            rest = &rest[end + 1..];
+            let closing = tag.starts_with('/');
+            let name = tag
+                .trim_start_matches('/')
+                .split(|c: char| !c.is_ascii_alphanumeric())
+                .next()
+                .unwrap_or_default()
+                .to_ascii_lowercase();
+
+            if !closing && RAW_TEXT.contains(&name.as_str()) {
+                let close = format!("</{}", name);
+                rest = rest.to_ascii_lowercase().find(&close).map_or("", |end| &rest[end..]);
+                continue;
+            }
+            if SKIPPED.contains(&name.as_str()) {
+                if closing {
+                    skipped = skipped.saturating_sub(1);
+                } else if !tag.ends_with('/') {
+                    skipped += 1;
+                }
+            } else if name == "a" {
+                links = if closing { links.saturating_sub(1) } else { links + 1 };
+            }
+            if BLOCKS.contains(&name.as_str()) {
+                flush(&mut block);
+            }
+            continue;
+        }
+
+        rest = &rest[c.len_utf8()..];
+        if skipped > 0 {
+            continue;
+        }
+        let c = match c {
+            '&' => match rest.find(';').filter(|&end| end <= 10).and_then(|end| Some((decode_entity(&rest[..end])?, end))) {
+                Some((decoded, end)) => {
+                    rest = &rest[end + 1..];
+                    decoded
+                }
+                None => c,
+            },
+            _ => c,
+        };
+        push(&mut block, c, links > 0);
+    }
+    flush(&mut block);
+    blocks
+}
+
+/// A record of a WARC file holding a fetched page.
+#[derive(Debug, Clone)]
+pub struct WarcRecord {
+    pub target_uri: Option<String>,
+    pub content_type: Option<String>,
+    pub payload: Vec<u8>,
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+// Header lines up to the first blank line, and where the body after it starts
+fn headers(data: &[u8]) -> Option<(Vec<(String, String)>, usize)> {
+    let end = find(data, b"\r\n\r\n")?;
+    let headers = String::from_utf8_lossy(&data[..end])
+        .lines()
+        .filter_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            Some((name.trim().to_ascii_lowercase(), value.trim().to_string()))
+        })
+        .collect();
+    Some((headers, end + 4))
+}
+
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers.iter().find(|(n, _)| n == name).map(|(_, value)| value.as_str())
+}
+
+/// Splits an uncompressed WARC file into its `response` and `resource`
+/// records; requests, metadata and other records are skipped. The HTTP
+/// headers of responses are parsed off, leaving the page as the payload.
+pub fn parse_warc(data: &[u8]) -> io::Result<Vec<WarcRecord>> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, format!("invalid WARC: {}", message));
+    let mut records = Vec::new();
+    let mut rest = data;
+    loop {
+        while let [b'\r' | b'\n', tail @ ..] = rest {
+            rest = tail;
+        }
+        if rest.is_empty() {
+            return Ok(records);
+        }
+        if !rest.starts_with(b"WARC/") {
+            return Err(invalid("expected a WARC/ version line"));
+        }
+        let (warc_headers, body_start) = headers(rest).ok_or_else(|| invalid("unterminated record header"))?;
+        let length: usize = header(&warc_headers, "content-length")
+            .and_then(|length| length.parse().ok())
+            .ok_or_else(|| invalid("record without a Content-Length"))?;
+        let body = rest.get(body_start..body_start + length).ok_or_else(|| invalid("truncated record"))?;
+        rest = &rest[body_start + length..];
+
+        let target_uri = header(&warc_headers, "warc-target-uri").map(str::to_string);
+        match header(&warc_headers, "warc-type") {
+            Some("response") => {
+                let Some((http_headers, payload_start)) = headers(body) else {
+                    continue;
+                };
+                records.push(WarcRecord {
+                    target_uri,
+                    content_type: header(&http_headers, "content-type").map(str::to_string),
+                    payload: body[payload_start..].to_vec(),
+                });
+            }
+            Some("resource") => records.push(WarcRecord {
+                target_uri,
+                content_type: header(&warc_headers, "content-type").map(str::to_string),
+                payload: body.to_vec(),
+            }),
+            _ => {}
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct JsonlDocument {
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(default)]
+    html: Option<String>,
+}
+
+/// Reads a JSONL dump with one `{"url", "title", "text"}` object per line;
+/// lines giving `html` instead of `text` have their main content extracted.
+pub fn parse_jsonl(input: &str) -> Result<Vec<Document>, DatasetError> {
+    let mut documents = Vec::new();
+    for (i, line) in input.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+        let parse_error = |message: String| DatasetError::Parse { line: i + 1, message };
+        let record: JsonlDocument = serde_json::from_str(line).map_err(|e| parse_error(e.to_string()))?;
+        let document = match (record.text, record.html) {
+            (Some(text), _) => Document::new(record.url, record.title, text),
+            (None, Some(html)) => {
+                let document = Document::from_html(record.url, &html);
+                Document {
+                    title: record.title.or(document.title),
+                    ..document
+                }
+            }
+            (None, None) => return Err(parse_error("expected a \"text\" or \"html\" field".to_string())),
+        };
+        documents.push(document);
+    }
+    Ok(documents)
+}
+
+#[derive(Serialize)]
+struct IndexEntry<'a> {
+    file: String,
+    url: Option<&'a str>,
+    title: Option<&'a str>,
+    language: Language,
+    chars: usize,
+}
+
+/// Writes each document as `<hash of its text>.txt` into `output`, a flat
+/// folder `scoring.reference_corpus` can point at, and appends its URL,
+/// title and language to `index.jsonl` there. Documents already in the
+/// folder are skipped, so re-importing a dump adds nothing twice.
+/// Returns how many documents were new.
+pub fn write_documents(documents: &[Document], output: &Path) -> io::Result<usize> {
+    std::fs::create_dir_all(output)?;
+    let mut index = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(output.join("index.jsonl"))?;
+    let mut written = 0;
+    for document in documents {
+        let mut hasher = DefaultHasher::new();
+        document.text.hash(&mut hasher);
+        let file = format!("{:016x}.txt", hasher.finish());
+        let path = output.join(&file);
+        if path.exists() {
+            continue;
+        }
+        std::fs::write(&path, &document.text)?;
+        let entry = IndexEntry {
+            file,
+            url: document.url.as_deref(),
+            title: document.title.as_deref(),
+            language: document.language,
+            chars: document.text.chars().count(),
+        };
+        let entry = serde_json::to_string(&entry).map_err(io::Error::other)?;
+        writeln!(index, "{}", entry)?;
+        written += 1;
+    }
+    Ok(written)
+}
+
+/// Decodes a page by the charset its `Content-Type` or `<meta>` tags
+/// declare, falling back to UTF-8.
+#[cfg(feature = "web-import")]
+pub fn decode_html(bytes: &[u8], content_type: Option<&str>) -> String {
+    let charset = |declaration: &str| {
+        let start = declaration.find("charset=")? + "charset=".len();
+        let label: String = declaration[start..]
+            .trim_start_matches(['"', '\''])
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+            .collect();
+        encoding_rs::Encoding::for_label(label.as_bytes())
+    };
+    // Declarations sit in the head, near the start of the page
+    let head = String::from_utf8_lossy(&bytes[..bytes.len().min(2048)]).to_ascii_lowercase();
+    let encoding = content_type.map(str::to_ascii_lowercase).as_deref().and_then(charset).or_else(|| charset(&head));
+    match encoding {
+        Some(encoding) => encoding.decode(bytes).0.into_owned(),
+        None => String::from_utf8_lossy(bytes).into_owned(),
+    }
+}
+
+/// Downloads a page and keeps its main content.
+#[cfg(feature = "web-import")]
+pub fn fetch(url: &str) -> io::Result<Document> {
+    use std::io::Read;
+
+    let response = ureq::get(url).call().map_err(io::Error::other)?;
+    let content_type = response.header("content-type").map(str::to_string);
+    let mut bytes = Vec::new();
+    response.into_reader().read_to_end(&mut bytes)?;
+    Ok(Document::from_html(Some(url.to_string()), &decode_html(&bytes, content_type.as_deref())))
+}
+
+/// Reads the HTML pages of a WARC file, gzipped when it ends in `.gz`.
+#[cfg(feature = "web-import")]
+pub fn read_warc(path: &Path) -> io::Result<Vec<Document>> {
+    use std::io::Read;
+
+    let file = std::fs::File::open(path)?;
+    let mut data = Vec::new();
+    if path.extension().is_some_and(|ext| ext == "gz") {
+        flate2::read::MultiGzDecoder::new(file).read_to_end(&mut data)?;
+    } else {
+        io::BufReader::new(file).read_to_end(&mut data)?;
+    }
+    Ok(parse_warc(&data)?
+        .into_iter()
+        .filter(|record| record.content_type.as_deref().is_some_and(|t| t.starts_with("text/html")))
+        .map(|record| {
+            let html = decode_html(&record.payload, record.content_type.as_deref());
+            Document::from_html(record.target_uri, &html)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = "<!DOCTYPE html><html><head><title>公園の話 &amp; 猫</title><style>p { color: red; }</style>\n<script>if (a < b) { document.write('<p>広告です広告です広告です広告です</p>'); }</script></head>\n<body><header><p>サイト名のヘッダーがここに入りますよ。</p></header>\n<nav><a href=\"/\">ホーム</a> <a href=\"/about\">このサイトについて</a></nav>\n<div><a href=\"/1\">関連記事その一のタイトルです</a>を読む</div>\n<p>今日は公園に行きました。\nとても楽しかったです。</p><!-- <p>コメントアウトされた段落です。</p> -->\n<p>短い</p><p>猫が&#x597D;きだ！本当に可愛いから毎日見ている&#12290;</p>\n<footer>Copyright 2024 サイト名 All rights reserved.</footer></body></html>";
+
+    #[test]
+    fn test_extract_main_content() {
+        let (title, text) = extract_main_content(PAGE);
+        assert_eq!(title.as_deref(), Some("公園の話 & 猫"));
+        assert_eq!(text, "今日は公園に行きました。 とても楽しかったです。\n猫が好きだ！本当に可愛いから毎日見ている。");
+
+        let article = "<p>記事の外にある長い段落なのでこれは捨てられます。</p><article><h1>見出し</h1><p>記事の本文はここにあります。とても長い。</p></article>";
+        assert_eq!(extract_main_content(article), (None, "記事の本文はここにあります。とても長い。".to_string()));
+
+        let document = Document::from_html(None, PAGE);
+        assert_eq!(document.language, Language::Japanese);
+    }
+
+    #[test]
+    fn test_parse_warc() {
+        let http = format!("HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\n\r\n{}", PAGE);
+        let request = "GET / HTTP/1.1\r\nHost: example.com\r\n\r\n";
+        let record = |kind: &str, body: &str| {
+            format!(
+                "WARC/1.0\r\nWARC-Type: {}\r\nWARC-Target-URI: https://example.com/\r\nContent-Length: {}\r\n\r\n{}\r\n\r\n",
+                kind,
+                body.len(),
+                body
+            )
+        };
+        let warc = format!("{}{}", record("request", request), record("response", &http));
+
+        let records = parse_warc(warc.as_bytes()).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].target_uri.as_deref(), Some("https://example.com/"));
+        assert_eq!(records[0].content_type.as_deref(), Some("text/html; charset=utf-8"));
+        assert_eq!(records[0].payload, PAGE.as_bytes());
+        assert!(parse_warc(&warc.as_bytes()[..warc.len() / 2]).is_err());
+    }
+
+    #[test]
+    fn test_parse_jsonl() {
+        let jsonl = "{\"url\": \"https://example.com/a\", \"text\": \"今日は公園に行きました。\"}\n\n{\"html\": \"<title>題</title><p>This page is written in English.</p>\"}\n";
+        let documents = parse_jsonl(jsonl).unwrap();
+        assert_eq!(documents.len(), 2);
+        assert_eq!(documents[0].language, Language::Japanese);
+        assert_eq!(documents[1].title.as_deref(), Some("題"));
+        assert_eq!(documents[1].text, "This page is written in English.");
+        assert_eq!(documents[1].language, Language::English);
+        assert!(matches!(parse_jsonl("{\"url\": \"x\"}"), Err(DatasetError::Parse { line: 1, .. })));
+
+        let dir = std::env::temp_dir().join(format!("author-comparer-web-{}", std::process::id()));
+        assert_eq!(write_documents(&documents, &dir).unwrap(), 2);
+        assert_eq!(write_documents(&documents, &dir).unwrap(), 0);
+        let index = std::fs::read_to_string(dir.join("index.jsonl")).unwrap();
+        assert_eq!(index.lines().count(), 2);
+        assert!(index.contains("\"url\":\"https://example.com/a\""));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
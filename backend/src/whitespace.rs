@@ -0,0 +1,76 @@
+use crate::numerics::safe_div;
+use crate::symbols::is_emoji;
+use std::borrow::Cow;
+
+/// Spaces other than the ASCII ones, which render like a space.
+fn is_unusual_space(c: char) -> bool {
+    matches!(c,
+        '\u{00A0}' // No-break space
+        | '\u{2000}'..='\u{200A}' // En, em, thin and other typographic spaces
+        | '\u{202F}' // Narrow no-break space
+        | '\u{205F}' // Medium mathematical space
+        | '\u{3000}' // Ideographic (full-width) space
+    )
+}
+
+/// Invisible characters that don't render at all.
+fn is_zero_width(c: char) -> bool {
+    matches!(c, '\u{200B}'..='\u{200D}' | '\u{2060}' | '\u{FEFF}')
+}
+
+/// Whitespace a keyboard doesn't produce by default: typographic and
+/// full-width spaces, zero-width characters and byte order marks.
+pub fn is_unusual_whitespace(c: char) -> bool {
+    is_unusual_space(c) || is_zero_width(c)
+}
+
+// A zero-width joiner between two emoji is part of the emoji, not stray whitespace
+fn is_stray(chars: &[char], i: usize) -> bool {
+    let joins_emoji = chars[i] == '\u{200D}'
+        && i > 0
+        && is_emoji(chars[i - 1])
+        && chars.get(i + 1).is_some_and(|c| is_emoji(*c));
+    is_unusual_whitespace(chars[i]) && !joins_emoji
+}
+
+/// Share of characters that are unusual whitespace.
+pub fn unusual_whitespace_ratio(text: &str) -> f64 {
+    let chars: Vec<char> = text.chars().collect();
+    let unusual = (0..chars.len()).filter(|&i| is_stray(&chars, i)).count();
+    safe_div(unusual as f64, chars.len() as f64, 0.0)
+}
+
+/// Turns unusual spaces into ASCII ones and drops zero-width characters, so
+/// neither ends up inside a word.
+pub fn sanitize(text: &str) -> Cow<'_, str> {
+    if !text.chars().any(is_unusual_whitespace) {
+        return Cow::Borrowed(text);
+    }
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    for (i, &c) in chars.iter().enumerate() {
+        if !is_stray(&chars, i) {
+            out.push(c);
+        } else if is_unusual_space(c) {
+            out.push(' ');
+        }
+    }
+    Cow::Owned(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize() {
+        assert!(matches!(sanitize("公園に行った。"), Cow::Borrowed(_)));
+        assert_eq!(sanitize("\u{FEFF}公園\u{3000}に\u{200B}行った\u{00A0}。"), "公園 に行った 。");
+        // The joiner inside an emoji sequence is part of the emoji
+        assert_eq!(sanitize("👨\u{200D}👩"), "👨\u{200D}👩");
+
+        assert_eq!(unusual_whitespace_ratio("あい"), 0.0);
+        assert_eq!(unusual_whitespace_ratio("あ\u{3000}い\u{200B}"), 0.5);
+        assert_eq!(unusual_whitespace_ratio(""), 0.0);
+    }
+}
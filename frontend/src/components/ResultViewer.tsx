@@ -2,19 +2,87 @@ import { css } from '../../styled-system/css'
 
 interface ResultViewerProps {
 	result: {
+		verdict: 'same_author' | 'different_author' | 'insufficient_data'
 		same_author: boolean
 		confidence: number
 		detailed_analysis: Array<{
 			aspect: string
-			difference: number
+			difference: number | null
 			explanation: string
+			weight: number
+			capped: boolean
+			reliability: number
+			missing_reason?: string
+			percentile?: number
 		}>
+		dimensions?: Array<{
+			dimension: string
+			score: number | null
+			weight: number
+			aspects: string[]
+		}>
+		warnings: Array<{
+			code: string
+			message: string
+		}>
+		vocabulary?: {
+			shared: VocabularyWord[]
+			only_text1: VocabularyWord[]
+			only_text2: VocabularyWord[]
+		}
 	}
 }
 
+interface VocabularyWord {
+	word: string
+	counts: [number, number]
+	frequencies: [number, number]
+}
+
+function WordList({ title, words }: { title: string; words: VocabularyWord[] }) {
+	return (
+		<div>
+			<h4
+				class={css({
+					fontSize: '16px',
+					fontWeight: 'bold',
+					marginBottom: '8px',
+					color: '#4A5568',
+				})}
+			>
+				{title}
+			</h4>
+			<ul class={css({ fontSize: '14px', color: '#4A5568' })}>
+				{words.map((word) => (
+					<li key={word.word}>
+						{word.word} ({word.counts[0]} / {word.counts[1]})
+					</li>
+				))}
+			</ul>
+		</div>
+	)
+}
+
 export default function ResultViewer({ result }: ResultViewerProps) {
 	console.log(result)
 
+	if (result.verdict === 'insufficient_data') {
+		return (
+			<div
+				class={css({
+					backgroundColor: '#FEFCBF',
+					borderRadius: '12px',
+					padding: '24px',
+					margin: '24px 0',
+					color: '#744210',
+					textAlign: 'center',
+				})}
+			>
+				Not enough text to compare. Please enter longer samples.
+			</div>
+		)
+	}
+
 	return (
 		<div
 			class={css({
@@ -36,6 +104,21 @@ export default function ResultViewer({ result }: ResultViewerProps) {
 			>
 				Analysis Result
 			</h2>
+			{result.warnings.map((warning) => (
+				<p
+					key={warning.message}
+					class={css({
+						backgroundColor: '#FEFCBF',
+						borderRadius: '8px',
+						padding: '8px 12px',
+						marginBottom: '16px',
+						fontSize: '14px',
+						color: '#744210',
+					})}
+				>
+					{warning.message}
+				</p>
+			))}
 			<div
 				class={css({
 					display: 'flex',
@@ -138,6 +221,32 @@ export default function ResultViewer({ result }: ResultViewerProps) {
 				</div>
 			</div>
 
+			{result.dimensions && result.dimensions.length > 0 && (
+				<>
+					<h3
+						class={css({
+							fontSize: '20px',
+							fontWeight: 'bold',
+							marginBottom: '16px',
+							color: '#2D3748',
+						})}
+					>
+						Style Dimensions
+					</h3>
+					<ul class={css({ marginBottom: '24px', color: '#4A5568' })}>
+						{result.dimensions.map((group) => (
+							<li key={group.dimension} class={css({ marginBottom: '4px' })}>
+								<strong>{group.dimension}</strong>:{' '}
+								{group.score === null
+									? 'not computed'
+									: `${(group.score * 100).toFixed(0)}% similar`}{' '}
+								({group.aspects.join(', ')})
+							</li>
+						))}
+					</ul>
+				</>
+			)}
+
 			<h3
 				class={css({
 					fontSize: '20px',
@@ -185,7 +294,25 @@ export default function ResultViewer({ result }: ResultViewerProps) {
 								marginBottom: '8px',
 							})}
 						>
-							Difference: {(detail.difference * 100).toFixed(2)}%
+							{detail.difference === null
+								? `Not computed: ${detail.missing_reason}`
+								: `Difference: ${(detail.difference * 100).toFixed(2)}%`}
+							{detail.capped && ' (capped)'}
+						</p>
+						<p
+							class={css({
+								fontSize: '12px',
+								color: '#718096',
+								marginBottom: '8px',
+							})}
+						>
+							Weight ×{detail.weight} · Reliability{' '}
+							{(detail.reliability * 100).toFixed(0)}%
+							{detail.percentile !== undefined &&
+								` · Smaller than in ${(
+									(1 - detail.percentile) *
+									100
+								).toFixed(0)}% of different-author pairs`}
 						</p>
 						<p
 							class={css({
@@ -199,6 +326,36 @@ export default function ResultViewer({ result }: ResultViewerProps) {
 					</div>
 				))}
 			</div>
+
+			{result.vocabulary && (
+				<>
+					<h3
+						class={css({
+							fontSize: '20px',
+							fontWeight: 'bold',
+							marginTop: '24px',
+							marginBottom: '16px',
+							color: '#2D3748',
+						})}
+					>
+						Vocabulary
+					</h3>
+					<div
+						class={css({
+							display: 'grid',
+							gap: '16px',
+							gridTemplateColumns: {
+								base: '1fr',
+								md: 'repeat(3, 1fr)',
+							},
+						})}
+					>
+						<WordList title="Shared" words={result.vocabulary.shared} />
+						<WordList title="Only in text 1" words={result.vocabulary.only_text1} />
+						<WordList title="Only in text 2" words={result.vocabulary.only_text2} />
+					</div>
+				</>
+			)}
 		</div>
 	)
 }
@@ -5,13 +5,25 @@ import { useState } from 'preact/hooks'
 
 import ResultViewer from './components/ResultViewer'
 
+// Empty when the UI is embedded in the backend binary, so requests stay same-origin
+const API_URL = import.meta.env.VITE_API_URL ?? 'http://localhost:8000'
+
 interface CompareResult {
+	verdict: 'same_author' | 'different_author' | 'insufficient_data'
 	same_author: boolean
 	confidence: number
 	detailed_analysis: Array<{
 		aspect: string
-		difference: number
+		difference: number | null
 		explanation: string
+		weight: number
+		capped: boolean
+		reliability: number
+		missing_reason?: string
+	}>
+	warnings: Array<{
+		code: string
+		message: string
 	}>
 }
 
@@ -87,7 +99,7 @@ export function App() {
 	const handleSubmit = async () => {
 		setLoading(true)
 		try {
-			const response = await fetch('http://localhost:8000/compare', {
+			const response = await fetch(`${API_URL}/compare`, {
 				method: 'POST',
 				headers: {
 					'Content-Type': 'application/json',
@@ -95,6 +107,7 @@ export function App() {
 				body: JSON.stringify({
 					text1,
 					text2,
+					vocabulary: 10,
 				}),
 			})
 			const data = await response.json()